@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Fuzz `parse_spec_content` and `lower` with arbitrary documents
+//!
+//! Run with `cargo +nightly fuzz run parse_and_lower` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rescript_openapi::{ir, parser};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let limits = parser::Limits {
+        max_spec_bytes: 1024 * 1024,
+        ..Default::default()
+    };
+
+    if let Ok(spec) = parser::parse_spec_content(text, &limits) {
+        let _ = ir::lower(&spec);
+    }
+});
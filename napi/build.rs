@@ -0,0 +1,7 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+fn main() {
+    // Link flags for loading the library as a Node addon
+    napi_build::setup();
+}
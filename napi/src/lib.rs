@@ -0,0 +1,269 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Node bindings for rescript-openapi
+//!
+//! Exposes `generate` and `validate` to JavaScript so build plugins can call
+//! the generator in-process instead of shelling out to the CLI. Options are
+//! passed as JSON with camelCase keys mirroring the CLI flags:
+//!
+//! ```js
+//! const { generate } = require("./rescript-openapi.node");
+//! const files = generate(specText, JSON.stringify({ module: "Api", withSpy: true }));
+//! ```
+
+use rescript_openapi::build::{self, Input, Options};
+use rescript_openapi::codegen::{BodySerializer, ClientSurface, ResponseStyle};
+use rescript_openapi::ir::{
+    BinaryResponses, Coercion, ExtraProperties, FieldCase, FormatMapping, Int64Mode, RequestTypes,
+    WildcardResponses,
+};
+use rescript_openapi::parser::{self, ErrorStrategy, Limits, Severity};
+use rescript_openapi::{filter, ir};
+use napi_derive::napi;
+use serde::Deserialize;
+
+/// A generated file returned to JavaScript
+#[napi(object)]
+pub struct File {
+    pub filename: String,
+    pub content: String,
+}
+
+/// A validation or lowering finding returned to JavaScript
+#[napi(object)]
+pub struct Diagnostic {
+    /// `"error"` or `"warning"`
+    pub severity: String,
+    pub message: String,
+    pub path: Option<String>,
+}
+
+impl From<parser::Diagnostic> for Diagnostic {
+    fn from(diagnostic: parser::Diagnostic) -> Self {
+        let severity = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        Self {
+            severity: severity.to_string(),
+            message: diagnostic.message,
+            path: diagnostic.path,
+        }
+    }
+}
+
+/// Generation options accepted as JSON; omitted keys use the CLI defaults
+#[derive(Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+struct NodeOptions {
+    module: String,
+    with_schema: bool,
+    with_schema_registry: bool,
+    describe_errors: bool,
+    with_client: bool,
+    with_spy: bool,
+    with_exn: bool,
+    with_retry: bool,
+    no_pagination_helpers: bool,
+    client_surface: ClientSurface,
+    response_style: ResponseStyle,
+    body_serializer: BodySerializer,
+    field_case: FieldCase,
+    int64: Int64Mode,
+    compat_nullable: bool,
+    coerce: Vec<String>,
+    formats: Vec<String>,
+    wildcard_responses: WildcardResponses,
+    binary_responses: BinaryResponses,
+    promote_enums: bool,
+    negotiate_responses: bool,
+    extra_properties: ExtraProperties,
+    request_types: RequestTypes,
+    include_tags: Vec<String>,
+    include_paths: Vec<String>,
+    error_strategy: ErrorStrategy,
+    allow_remote_refs: bool,
+}
+
+impl Default for NodeOptions {
+    fn default() -> Self {
+        Self {
+            module: "Api".to_string(),
+            with_schema: true,
+            with_schema_registry: false,
+            describe_errors: false,
+            with_client: true,
+            with_spy: false,
+            with_exn: false,
+            with_retry: false,
+            no_pagination_helpers: false,
+            client_surface: ClientSurface::default(),
+            response_style: ResponseStyle::default(),
+            body_serializer: BodySerializer::default(),
+            field_case: FieldCase::default(),
+            int64: Int64Mode::default(),
+            compat_nullable: false,
+            coerce: Vec::new(),
+            formats: Vec::new(),
+            wildcard_responses: WildcardResponses::default(),
+            binary_responses: BinaryResponses::default(),
+            promote_enums: false,
+            negotiate_responses: false,
+            extra_properties: ExtraProperties::default(),
+            request_types: RequestTypes::default(),
+            include_tags: Vec::new(),
+            include_paths: Vec::new(),
+            error_strategy: ErrorStrategy::default(),
+            allow_remote_refs: false,
+        }
+    }
+}
+
+impl NodeOptions {
+    fn into_options(self, spec_text: String) -> napi::Result<Options> {
+        let coercions = self
+            .coerce
+            .iter()
+            .map(|c| c.parse::<Coercion>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(napi::Error::from_reason)?;
+        let formats = self
+            .formats
+            .iter()
+            .map(|f| f.parse::<FormatMapping>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(napi::Error::from_reason)?;
+
+        let mut options = Options::new(Input::Text(spec_text));
+        options.config.module_prefix = self.module;
+        options.config.generate_schema = self.with_schema;
+        options.config.generate_schema_registry = self.with_schema_registry;
+        options.config.describe_errors = self.describe_errors;
+        options.config.generate_client = self.with_client;
+        options.config.generate_spy = self.with_spy;
+        options.config.client_surface = self.client_surface;
+        options.config.response_style = self.response_style;
+        options.config.body_serializer = self.body_serializer;
+        options.config.generate_exn = self.with_exn;
+        options.config.generate_retry = self.with_retry;
+        options.config.generate_pagination = !self.no_pagination_helpers;
+        options.lower = ir::LowerOptions {
+            field_case: self.field_case,
+            int64: self.int64,
+            error_strategy: self.error_strategy,
+            compat_nullable: self.compat_nullable,
+            coercions,
+            formats,
+            wildcard_responses: self.wildcard_responses,
+            binary_responses: self.binary_responses,
+            promote_enums: self.promote_enums,
+            extra_properties: self.extra_properties,
+            request_types: self.request_types,
+            negotiate_responses: self.negotiate_responses,
+        };
+        options.filter = filter::Filter {
+            tags: self.include_tags,
+            paths: self.include_paths,
+        };
+        options.limits.allow_remote_refs = self.allow_remote_refs;
+        Ok(options)
+    }
+}
+
+/// Generate ReScript files from an OpenAPI document (JSON or YAML text)
+#[napi]
+pub fn generate(spec_text: String, options_json: Option<String>) -> napi::Result<Vec<File>> {
+    let node_options: NodeOptions = match options_json.as_deref() {
+        Some(json) => serde_json::from_str(json).map_err(to_js_error)?,
+        None => NodeOptions::default(),
+    };
+    let options = node_options.into_options(spec_text)?;
+
+    let generated = build::generate(&options).map_err(|error| match &error {
+        build::Error::Invalid { diagnostics } => napi::Error::from_reason(
+            std::iter::once(error.to_string())
+                .chain(diagnostics.iter().map(|d| d.to_string()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        _ => to_js_error(error),
+    })?;
+
+    Ok(generated
+        .files
+        .into_iter()
+        .map(|file| File {
+            filename: file.filename,
+            content: file.content,
+        })
+        .collect())
+}
+
+/// Validate an OpenAPI document, reporting every problem found
+///
+/// Throws only if the text isn't an OpenAPI document at all.
+#[napi]
+pub fn validate(spec_text: String) -> napi::Result<Vec<Diagnostic>> {
+    let spec = parser::parse_spec_content(&spec_text, &Limits::default()).map_err(to_js_error)?;
+    let diagnostics = ir::diagnostics(&spec, ErrorStrategy::Collect).map_err(to_js_error)?;
+    Ok(diagnostics.into_iter().map(Diagnostic::from).collect())
+}
+
+/// Convert an error and its causes into a JavaScript `Error`
+fn to_js_error(error: impl Into<anyhow::Error>) -> napi::Error {
+    napi::Error::from_reason(format!("{:#}", error.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = r#"
+openapi: 3.0.3
+info: { title: T, version: "1" }
+paths: {}
+components:
+  schemas:
+    Counter:
+      type: object
+      properties:
+        big: { type: integer, format: int64 }
+"#;
+
+    #[test]
+    fn test_generate() {
+        let names = |files: Vec<File>| files.into_iter().map(|f| f.filename).collect::<Vec<_>>();
+
+        let files = generate(SPEC.to_string(), None).unwrap();
+        assert_eq!(names(files), ["ApiTypes.res", "ApiMeta.res", "ApiSchema.res", "ApiClient.res"]);
+
+        let options = r#"{"module": "Shop", "withClient": false, "withSpy": true, "int64": "string"}"#;
+        let files = generate(SPEC.to_string(), Some(options.to_string())).unwrap();
+        let types = files.iter().find(|f| f.filename == "ShopTypes.res").unwrap();
+        assert!(types.content.contains("  big: option<string>,\n"));
+        assert_eq!(names(files), ["ShopTypes.res", "ShopMeta.res", "ShopSchema.res", "ShopClientSpy.res"]);
+    }
+
+    #[test]
+    fn test_generate_errors() {
+        let error = |options: &str| {
+            generate(SPEC.to_string(), Some(options.to_string())).err().unwrap().reason
+        };
+        assert!(error(r#"{"withSpies": true}"#).contains("unknown field `withSpies`"));
+        assert!(error(r#"{"coerce": ["Counter.big"]}"#).contains("Counter.big"));
+
+        let error = generate("openapi: 3.0.3".to_string(), None).err().unwrap();
+        assert!(error.reason.contains("info"), "{}", error.reason);
+    }
+
+    #[test]
+    fn test_validate() {
+        let diagnostics = validate(SPEC.to_string()).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, "warning");
+        assert_eq!(diagnostics[0].path.as_deref(), Some("components.schemas.Counter.properties.big"));
+
+        assert!(validate("not: [an, openapi, document]".to_string()).is_err());
+    }
+}
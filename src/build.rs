@@ -0,0 +1,506 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Stable entry point for embedding the generator
+//!
+//! Intended for `build.rs` scripts and native wrappers (e.g. napi-rs): it never
+//! prints or exits the process, reports failures as a structured [`Error`],
+//! and can return generated files in memory instead of writing them.
+//!
+//! ```no_run
+//! use rescript_openapi::build::{self, Input, Options, Output};
+//!
+//! let mut options = Options::new(Input::Path("openapi.yaml".into()));
+//! options.config.output_dir = "src/api".into();
+//! options.output = Output::Directory;
+//!
+//! let generated = build::generate(&options)?;
+//! for diagnostic in &generated.diagnostics {
+//!     println!("cargo:warning={}", diagnostic);
+//! }
+//! # Ok::<(), build::Error>(())
+//! ```
+//!
+//! Types in this module only gain fields and variants in minor releases; they
+//! are `#[non_exhaustive]`, so start from [`Options::new`] and set fields.
+
+use crate::bundle::sha256_hex;
+use crate::manifest::{self, InputHash, Manifest, MANIFEST_FILE};
+use crate::parser::{Diagnostic, ErrorStrategy, Limits};
+use crate::{codegen, filter, ir, parser, remote};
+use openapiv3::OpenAPI;
+use std::path::PathBuf;
+
+/// Where the OpenAPI document comes from
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Input {
+    /// JSON or YAML file on disk
+    Path(PathBuf),
+    /// JSON or YAML document fetched from an `http(s)://` URL
+    Url(String),
+    /// JSON or YAML document already in memory
+    Text(String),
+    /// Document already parsed into a JSON value, e.g. fetched from a registry
+    Value(serde_json::Value),
+}
+
+/// What to do with generated files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Output {
+    /// Only return them in [`Generated::files`]
+    #[default]
+    InMemory,
+    /// Also write them to `config.output_dir`, keeping customization regions
+    Directory,
+}
+
+/// Everything that determines a generation run
+#[non_exhaustive]
+pub struct Options {
+    pub input: Input,
+    pub config: codegen::Config,
+    pub lower: ir::LowerOptions,
+    pub filter: filter::Filter,
+    /// Headers, such as credentials, sent when fetching an [`Input::Url`]
+    pub auth: remote::Auth,
+    /// Where an [`Input::Text`] document was fetched from; its relative `$ref`s
+    /// resolve against this URL and the manifest names it
+    pub source_url: Option<String>,
+    /// Size and depth limits applied while parsing the input
+    pub limits: Limits,
+    /// Also write the filtered spec here
+    pub emit_spec: Option<PathBuf>,
+    pub output: Output,
+    /// Add `manifest.json` (inputs, options and output hashes) to the generated files
+    pub manifest: bool,
+}
+
+impl Options {
+    /// Default options for an input: all files, in memory
+    pub fn new(input: Input) -> Self {
+        Self {
+            input,
+            config: codegen::Config::default(),
+            lower: ir::LowerOptions::default(),
+            filter: filter::Filter::default(),
+            auth: remote::Auth::default(),
+            source_url: None,
+            limits: Limits::default(),
+            emit_spec: None,
+            output: Output::InMemory,
+            manifest: false,
+        }
+    }
+
+    /// Fetch an [`Input::Url`] into [`Input::Text`], so later runs reuse the
+    /// document instead of downloading it again
+    pub fn fetch(&mut self) -> Result<(), Error> {
+        if let Input::Url(url) = &self.input {
+            let text = fetch(url, self)?;
+            self.source_url = Some(url.clone());
+            self.input = Input::Text(text);
+        }
+        Ok(())
+    }
+}
+
+/// A generated ReScript file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GeneratedFile {
+    /// File name relative to the output directory, e.g. `ApiTypes.res`
+    pub filename: String,
+    pub content: String,
+}
+
+/// Result of a successful run
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Generated {
+    pub files: Vec<GeneratedFile>,
+    /// SHA-256 of the normalized spec, as embedded in the Meta module
+    pub spec_hash: String,
+    /// Warnings from validation and lowering
+    pub diagnostics: Vec<Diagnostic>,
+    /// Type counts and how much of the spec was degraded or skipped
+    pub report: ir::LoweringReport,
+    /// The parsed and filtered spec the files were generated from
+    pub spec: OpenAPI,
+}
+
+/// Why a run failed
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The input couldn't be read or isn't a valid OpenAPI document
+    #[error("Failed to parse OpenAPI spec: {input}")]
+    Parse {
+        /// Input path or URL, or `<text>` / `<value>` for in-memory input
+        input: String,
+        #[source]
+        source: anyhow::Error,
+    },
+    /// Lowering stopped at the first error (`ErrorStrategy::FailFast`)
+    #[error("Failed to lower OpenAPI spec to IR")]
+    Lower(#[source] anyhow::Error),
+    /// The spec has errors; every problem found is listed (`ErrorStrategy::Collect`)
+    #[error("Failed to lower OpenAPI spec: {}", parser::summarize(diagnostics))]
+    Invalid { diagnostics: Vec<Diagnostic> },
+    /// A generator failed for one file
+    #[error("Failed to generate {file}")]
+    Generate {
+        file: String,
+        #[source]
+        source: anyhow::Error,
+    },
+    /// Two runs over the same input produced different output
+    #[error("Generation is not deterministic; output differs in {}", files.join(", "))]
+    Nondeterministic { files: Vec<String> },
+    /// A file couldn't be written
+    #[error("Failed to write {path:?}")]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+/// Parse, filter and lower the input without generating code
+///
+/// Returns the IR; its `diagnostics` also include validation findings when
+/// `options.lower.error_strategy` is `Collect`.
+pub fn lower(options: &Options) -> Result<ir::ApiSpec, Error> {
+    let (spec, _) = read(options)?;
+    lower_spec(&spec, options)
+}
+
+/// Lower a spec with its schemas and paths declared in reverse order
+///
+/// The endpoints are put back in declaration order and the spec hash is that
+/// of the document as given, so only names chosen by declaration order
+/// (e.g. collision suffixes) can make the output differ from [`lower_spec`].
+fn lower_permuted(spec: &OpenAPI, options: &Options) -> Result<ir::ApiSpec, Error> {
+    let mut permuted = spec.clone();
+    if let Some(components) = &mut permuted.components {
+        components.schemas.reverse();
+    }
+    permuted.paths.paths.reverse();
+
+    let mut api_spec = lower_spec(&permuted, options)?;
+    api_spec
+        .endpoints
+        .sort_by_key(|endpoint| spec.paths.paths.get_index_of(&endpoint.path));
+    api_spec.spec_hash = ir::spec_hash(spec);
+    Ok(api_spec)
+}
+
+fn lower_spec(spec: &OpenAPI, options: &Options) -> Result<ir::ApiSpec, Error> {
+    // Collect mode reports validation findings together with lowering problems
+    let mut diagnostics = match options.lower.error_strategy {
+        ErrorStrategy::Collect => parser::validate(spec),
+        ErrorStrategy::FailFast => Vec::new(),
+    };
+
+    let mut api_spec = match ir::lower_with_options(spec, &options.lower) {
+        Ok(api_spec) => api_spec,
+        Err(error) => match error.downcast::<ir::LowerError>() {
+            Ok(lower_error) => {
+                diagnostics.extend(lower_error.diagnostics);
+                return Err(Error::Invalid { diagnostics });
+            }
+            Err(error) => return Err(Error::Lower(error)),
+        },
+    };
+
+    diagnostics.append(&mut api_spec.diagnostics);
+    api_spec.diagnostics = diagnostics;
+    Ok(api_spec)
+}
+
+/// Run the generator
+pub fn generate(options: &Options) -> Result<Generated, Error> {
+    let generated = generate_in_memory(options)?;
+    if options.output == Output::Directory {
+        write(&options.config, &generated.files)?;
+    }
+    Ok(generated)
+}
+
+/// Run the generator twice and fail if the two outputs differ
+///
+/// The second run lowers the spec with its schemas and paths in reverse
+/// order, and hash maps used while lowering and sorting types are seeded
+/// afresh, so output that depends on declaration or iteration order (such as
+/// which of two colliding names gets the suffix) shows up as a difference.
+/// Files are only written once both runs agree.
+pub fn generate_deterministic(options: &Options) -> Result<Generated, Error> {
+    let (spec, inputs) = read(options)?;
+    let (api_spec, permuted) = (lower_spec(&spec, options)?, lower_permuted(&spec, options)?);
+    let second = generate_files(options, &inputs, spec.clone(), permuted)?;
+    let first = generate_files(options, &inputs, spec, api_spec)?;
+
+    let differing = differing_files(&first.files, &second.files);
+    if !differing.is_empty() {
+        return Err(Error::Nondeterministic { files: differing });
+    }
+
+    if options.output == Output::Directory {
+        write(&options.config, &first.files)?;
+    }
+    Ok(first)
+}
+
+/// Names of files that differ between two runs or are missing from either
+fn differing_files(first: &[GeneratedFile], second: &[GeneratedFile]) -> Vec<String> {
+    let mut differing: Vec<String> = Vec::new();
+    let changed = first
+        .iter()
+        .filter(|file| !second.contains(file))
+        .chain(second.iter().filter(|file| !first.contains(file)));
+    for file in changed {
+        if !differing.contains(&file.filename) {
+            differing.push(file.filename.clone());
+        }
+    }
+    differing
+}
+
+fn generate_in_memory(options: &Options) -> Result<Generated, Error> {
+    let (spec, inputs) = read(options)?;
+    let api_spec = lower_spec(&spec, options)?;
+    generate_files(options, &inputs, spec, api_spec)
+}
+
+fn generate_files(
+    options: &Options,
+    inputs: &[InputHash],
+    spec: OpenAPI,
+    api_spec: ir::ApiSpec,
+) -> Result<Generated, Error> {
+    let config = &options.config;
+
+    type Generator = fn(&ir::ApiSpec, &codegen::Config) -> anyhow::Result<String>;
+    let generators: [(&str, bool, Generator); 6] = [
+        ("Types", true, codegen::types::generate),
+        ("Meta", true, codegen::meta::generate),
+        ("Auth", api_spec.has_oauth2(), codegen::auth::generate),
+        ("Schema", config.generate_schema, codegen::schema::generate),
+        ("Client", config.generate_client, codegen::client::generate),
+        ("ClientSpy", config.generate_spy, codegen::spy::generate),
+    ];
+
+    let mut files = Vec::new();
+    for (name, enabled, generator) in generators {
+        if !enabled {
+            continue;
+        }
+        let filename = format!("{}{}.res", config.module_prefix, name);
+        match generator(&api_spec, config) {
+            Ok(content) => files.push(GeneratedFile { filename, content }),
+            Err(source) => return Err(Error::Generate { file: filename, source }),
+        }
+    }
+
+    if options.manifest {
+        let manifest = manifest(options, inputs, &api_spec, &files);
+        files.push(GeneratedFile {
+            filename: MANIFEST_FILE.to_string(),
+            content: manifest.to_json(),
+        });
+    }
+
+    Ok(Generated {
+        files,
+        spec_hash: api_spec.spec_hash,
+        diagnostics: api_spec.diagnostics,
+        report: api_spec.report,
+        spec,
+    })
+}
+
+fn manifest(
+    options: &Options,
+    inputs: &[InputHash],
+    api_spec: &ir::ApiSpec,
+    files: &[GeneratedFile],
+) -> Manifest {
+    let settings = serde_json::json!({
+        "config": options.config,
+        "lower": options.lower,
+        "filter": options.filter,
+    });
+    let outputs: Vec<(String, String)> = files
+        .iter()
+        .map(|f| (f.filename.clone(), f.content.clone()))
+        .collect();
+    Manifest::new(inputs.to_vec(), &api_spec.spec_hash, &settings, &outputs)
+}
+
+/// Parse and filter the input, writing it to `emit_spec` if set
+///
+/// An [`Input::Url`] is fetched once, and the document read is what the
+/// manifest hashes; the hashes are only computed when a manifest is wanted.
+fn read(options: &Options) -> Result<(OpenAPI, Vec<InputHash>), Error> {
+    let text_hash = |file: &str, text: &str| InputHash {
+        file: file.to_string(),
+        sha256: sha256_hex(text.as_bytes()),
+    };
+    let fetched;
+    let (name, text, source_url) = match &options.input {
+        Input::Url(url) => {
+            fetched = fetch(url, options)?;
+            (url.clone(), &fetched, Some(url.as_str()))
+        }
+        Input::Text(text) => {
+            let name = options.source_url.clone().unwrap_or_else(|| "<text>".to_string());
+            (name, text, options.source_url.as_deref())
+        }
+        Input::Path(path) => {
+            let spec = parser::parse_spec_with_limits(path, &options.limits).map_err(|source| {
+                Error::Parse {
+                    input: path.display().to_string(),
+                    source,
+                }
+            })?;
+            let inputs = match options.manifest {
+                true => manifest::hash_inputs(path),
+                false => Vec::new(),
+            };
+            return filtered(spec, inputs, options);
+        }
+        Input::Value(value) => {
+            let spec = parser::parse_spec_value_with_limits(value.clone(), &options.limits)
+                .map_err(|source| Error::Parse {
+                    input: "<value>".to_string(),
+                    source,
+                })?;
+            let inputs = match options.manifest {
+                true => vec![text_hash("<value>", &value.to_string())],
+                false => Vec::new(),
+            };
+            return filtered(spec, inputs, options);
+        }
+    };
+
+    let spec = match source_url {
+        Some(url) => parser::parse_fetched_spec(url, text, &options.auth, &options.limits),
+        None => parser::parse_spec_content(text, &options.limits),
+    }
+    .map_err(|source| Error::Parse {
+        input: name.clone(),
+        source,
+    })?;
+    let inputs = match options.manifest {
+        true => vec![text_hash(&name, text)],
+        false => Vec::new(),
+    };
+    filtered(spec, inputs, options)
+}
+
+/// Apply the filter to a parsed spec and write it to `emit_spec` if set
+fn filtered(
+    spec: OpenAPI,
+    inputs: Vec<InputHash>,
+    options: &Options,
+) -> Result<(OpenAPI, Vec<InputHash>), Error> {
+    let spec = filter::apply(&spec, &options.filter);
+    if let Some(emit_path) = &options.emit_spec {
+        parser::write_spec(&spec, emit_path).map_err(|source| Error::Write {
+            path: emit_path.clone(),
+            source,
+        })?;
+    }
+    Ok((spec, inputs))
+}
+
+/// Download a spec URL within the size limit
+fn fetch(url: &str, options: &Options) -> Result<String, Error> {
+    remote::fetch(url, options.limits.max_spec_bytes, &options.auth).map_err(|source| {
+        Error::Parse {
+            input: url.to_string(),
+            source: source.into(),
+        }
+    })
+}
+
+fn write(config: &codegen::Config, files: &[GeneratedFile]) -> Result<(), Error> {
+    std::fs::create_dir_all(&config.output_dir).map_err(|source| Error::Write {
+        path: config.output_dir.clone(),
+        source: source.into(),
+    })?;
+
+    for file in files {
+        let path = config.output_dir.join(&file.filename);
+        codegen::write_file(&path, &file.content)
+            .map_err(|source| Error::Write { path, source })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(filename: &str, content: &str) -> GeneratedFile {
+        GeneratedFile {
+            filename: filename.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_differing_files() {
+        let first = vec![file("ApiTypes.res", "a"), file("ApiSchema.res", "b")];
+        assert!(differing_files(&first, &first.clone()).is_empty());
+
+        // Changed content, and files only one run produced, in either direction
+        let second = vec![file("ApiTypes.res", "a2"), file("ApiClient.res", "c")];
+        assert_eq!(
+            differing_files(&first, &second),
+            vec!["ApiTypes.res", "ApiSchema.res", "ApiClient.res"]
+        );
+        assert_eq!(differing_files(&first[..1], &first), vec!["ApiSchema.res"]);
+    }
+
+    #[test]
+    #[cfg(feature = "remote")]
+    fn test_url_fetched_once() {
+        // The server answers a single request, so a second download would fail
+        let url = crate::remote::tests::serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 58\r\n\r\nopenapi: 3.0.3\ninfo: { title: T, version: '1' }\npaths: {}\n",
+        );
+        let mut options = Options::new(Input::Url(url.clone()));
+        options.manifest = true;
+        let generated = generate_deterministic(&options).unwrap();
+
+        let manifest: Manifest = serde_json::from_str(&generated.files.last().unwrap().content).unwrap();
+        assert_eq!(manifest.inputs.len(), 1);
+        assert_eq!(manifest.inputs[0].file, url);
+        assert_eq!(
+            manifest.inputs[0].sha256,
+            sha256_hex(b"openapi: 3.0.3\ninfo: { title: T, version: '1' }\npaths: {}\n")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "remote")]
+    fn test_fetch_options() {
+        let url = crate::remote::tests::serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 58\r\n\r\nopenapi: 3.0.3\ninfo: { title: T, version: '1' }\npaths: {}\n",
+        );
+        let mut options = Options::new(Input::Url(url.clone()));
+        options.fetch().unwrap();
+        assert!(matches!(&options.input, Input::Text(text) if text.starts_with("openapi: 3.0.3")));
+        assert_eq!(options.source_url.as_deref(), Some(url.as_str()));
+        // Later runs reuse the text
+        generate(&options).unwrap();
+        generate(&options).unwrap();
+
+        // A failed download is a parse error naming the URL, not an empty input
+        let mut options = Options::new(Input::Url(url.clone()));
+        let error = options.fetch().unwrap_err();
+        assert!(matches!(error, Error::Parse { ref input, .. } if *input == url));
+    }
+}
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Reproducible codegen bundles
+//!
+//! A bundle records everything needed to audit or reproduce a generation run:
+//! the spec as written and as the generator saw it (after filtering), the
+//! options used, the tool version, and a hash of every generated file. It
+//! contains no timestamps, so identical inputs produce identical bundles.
+
+use anyhow::{Context, Result};
+use openapiv3::OpenAPI;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Bump when the bundle layout changes incompatibly
+pub const BUNDLE_FORMAT: u32 = 1;
+
+/// Hex-encoded SHA-256 of some bytes
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct Bundle {
+    pub format: u32,
+    pub tool: Tool,
+    pub spec: SpecSource,
+    /// Generation options, as passed on the command line
+    pub config: serde_json::Value,
+    /// Generated files in generation order
+    pub outputs: Vec<OutputHash>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Tool {
+    pub name: String,
+    pub version: String,
+}
+
+impl Tool {
+    /// This build of rescript-openapi
+    pub fn current() -> Self {
+        Tool {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SpecSource {
+    pub path: String,
+    /// SHA-256 of the spec file as written
+    pub sha256: String,
+    /// Spec file contents as written
+    pub source: String,
+    /// Parsed spec after filtering, as used for generation
+    pub effective: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OutputHash {
+    pub file: String,
+    pub sha256: String,
+}
+
+impl Bundle {
+    /// Describe a run from its spec, options and `(filename, content)` outputs
+    pub fn new(
+        spec_path: &str,
+        source: &str,
+        effective: &OpenAPI,
+        config: serde_json::Value,
+        outputs: &[(String, String)],
+    ) -> Result<Self> {
+        Ok(Bundle {
+            format: BUNDLE_FORMAT,
+            tool: Tool::current(),
+            spec: SpecSource {
+                path: spec_path.to_string(),
+                sha256: sha256_hex(source.as_bytes()),
+                source: source.to_string(),
+                effective: serde_json::to_value(effective)
+                    .context("Failed to serialize effective spec")?,
+            },
+            config,
+            outputs: outputs
+                .iter()
+                .map(|(file, content)| OutputHash {
+                    file: file.clone(),
+                    sha256: sha256_hex(content.as_bytes()),
+                })
+                .collect(),
+        })
+    }
+
+    /// Write the bundle as pretty-printed JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut json = serde_json::to_string_pretty(self)?;
+        json.push('\n');
+        std::fs::write(path, json).with_context(|| format!("Failed to write bundle: {:?}", path))
+    }
+}
@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! OAuth2 helper module generation
+//!
+//! `ApiAuth.res` is generated for specs with `oauth2` security schemes. Each
+//! scheme gets a module with typed scope constants and its flows' endpoint
+//! URLs; a shared `tokenStore` refreshes access tokens, which the client asks
+//! for before every request that needs one.
+
+use crate::ir::{ApiSpec, OAuthFlow, SecuritySchemeKind, RESERVED_KEYWORDS};
+use super::{doc_comment, region, string_literal, Config};
+use anyhow::Result;
+use heck::{ToLowerCamelCase, ToPascalCase};
+
+/// Names taken by the helpers in each scheme module
+const SCHEME_HELPERS: &[&str] = &["allScopes", "scopeParam"];
+
+pub fn generate(spec: &ApiSpec, config: &Config) -> Result<String> {
+    let mut output = String::new();
+
+    // Header
+    output.push_str("// SPDX-License-Identifier: AGPL-3.0-or-later\n");
+    output.push_str("// Generated by rescript-openapi - DO NOT EDIT\n");
+    output.push_str(&format!("// Source: {} v{}\n\n", spec.title, spec.version));
+
+    output.push_str(&config.opens(true, &[]));
+
+    output.push_str(r#"/** An access token and when it expires */
+type token = {
+  accessToken: string,
+  /** Expiry as epoch milliseconds; `None` if the token doesn't expire */
+  expiresAt: option<float>,
+}
+
+/** Keeps an access token fresh for the client
+ *
+ * Pass it to `makeConfig(~tokenStore)`: before each request that needs a
+ * token, `refresh` runs if there is none yet or it expires within `leewayMs`.
+ * Concurrent requests wait for the same refresh.
+ */
+type tokenStore = {
+  mutable current: option<token>,
+  mutable refreshing: option<promise<option<token>>>,
+  refresh: option<token> => promise<option<token>>,
+  leewayMs: float,
+}
+
+/** Create a token store; `refresh` receives the expiring token, if any */
+let makeTokenStore = (
+  ~refresh: option<token> => promise<option<token>>,
+  ~initial: option<token>=?,
+  ~leewayMs: float=30_000.,
+  (),
+): tokenStore => {current: initial, refreshing: None, refresh, leewayMs}
+
+/** Whether a token is still usable `leewayMs` from now */
+let isFresh = (token: token, ~leewayMs: float): bool =>
+  switch token.expiresAt {
+  | Some(expiresAt) => expiresAt -. leewayMs > Date.now()
+  | None => true
+  }
+
+/** The current access token, refreshed first when missing or about to expire */
+let getToken = async (store: tokenStore): option<string> => {
+  switch store.current {
+  | Some(token) if token->isFresh(~leewayMs=store.leewayMs) => Some(token.accessToken)
+  | current =>
+    let refreshing = switch store.refreshing {
+    | Some(refreshing) => refreshing
+    | None =>
+      let refreshing = store.refresh(current)
+      store.refreshing = Some(refreshing)
+      refreshing
+    }
+    switch await refreshing {
+    | token =>
+      store.refreshing = None
+      store.current = token
+      token->Option.map(t => t.accessToken)
+    | exception e =>
+      store.refreshing = None
+      raise(e)
+    }
+  }
+}
+
+/** Drop the current token, e.g. after a 401, so the next request refreshes it */
+let invalidate = (store: tokenStore): unit => store.current = None
+
+"#);
+
+    let mut modules: Vec<String> = Vec::new();
+    for scheme in &spec.security_schemes {
+        if let SecuritySchemeKind::OAuth2 { flows, scopes } = &scheme.kind {
+            let module = module_name(&scheme.name, &modules);
+            output.push_str(&generate_scheme(&scheme.name, &module, flows, scopes));
+            modules.push(module);
+        }
+    }
+
+    region::append(&mut output);
+
+    Ok(output)
+}
+
+/// Module for one `oauth2` scheme: its scopes and flow endpoints
+fn generate_scheme(name: &str, module: &str, flows: &[OAuthFlow], scopes: &[(String, String)]) -> String {
+    let mut output = format!("/** OAuth2 security scheme `{}` */\nmodule {} = {{\n", name, module);
+
+    if !scopes.is_empty() {
+        let tags: Vec<String> = scopes.iter().map(|(scope, _)| format!("#{}", string_literal(scope))).collect();
+        output.push_str("  /** Scopes the scheme grants */\n");
+        output.push_str(&format!("  type scope = [{}]\n\n", tags.join(" | ")));
+
+        let mut names: Vec<String> = Vec::new();
+        for ((scope, description), tag) in scopes.iter().zip(&tags) {
+            let name = scope_name(scope, &names);
+            if !description.is_empty() {
+                output.push_str(&doc_comment(description, "  "));
+            }
+            output.push_str(&format!("  let {}: scope = {}\n", name, tag));
+            names.push(name);
+        }
+
+        output.push_str("\n  /** Every scope the scheme defines */\n");
+        output.push_str(&format!("  let allScopes: array<scope> = [{}]\n\n", names.join(", ")));
+        output.push_str("  /** Space-separated `scope` parameter for token requests */\n");
+        output.push_str("  let scopeParam = (scopes: array<scope>): string =>\n");
+        output.push_str("    scopes->Array.map(s => (s :> string))->Array.join(\" \")\n");
+    }
+
+    for flow in flows {
+        output.push_str(&format!(
+            "\n  /** `{}` flow */\n  module {} = {{\n",
+            flow.flow,
+            flow.flow.to_pascal_case()
+        ));
+        let urls = [
+            ("authorizationUrl", &flow.authorization_url),
+            ("tokenUrl", &flow.token_url),
+            ("refreshUrl", &flow.refresh_url),
+        ];
+        for (field, url) in urls {
+            if let Some(url) = url {
+                output.push_str(&format!("    let {} = {}\n", field, string_literal(url)));
+            }
+        }
+        output.push_str("  }\n");
+    }
+
+    output.push_str("}\n\n");
+    output
+}
+
+/// Module name for a scheme key, e.g. `petstore_auth` -> `PetstoreAuth`, unique among `taken`
+fn module_name(name: &str, taken: &[String]) -> String {
+    let mut name = name.to_pascal_case();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name = format!("Scheme{}", name);
+    }
+    if !taken.contains(&name) {
+        return name;
+    }
+    (2..)
+        .map(|i| format!("{}{}", name, i))
+        .find(|n| !taken.contains(n))
+        .expect("some suffix is free")
+}
+
+/// Constant name for a scope, e.g. `write:pets` -> `writePets`, unique among `taken`
+fn scope_name(scope: &str, taken: &[String]) -> String {
+    let mut name = scope.to_lower_camel_case();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name = format!("scope{}", name.to_pascal_case());
+    }
+    if RESERVED_KEYWORDS.contains(&name.as_str()) {
+        name.push('_');
+    }
+    let is_taken = |n: &str| taken.iter().any(|t| t == n) || SCHEME_HELPERS.contains(&n);
+    if !is_taken(&name) {
+        return name;
+    }
+    (2..)
+        .map(|i| format!("{}{}", name, i))
+        .find(|n| !is_taken(n))
+        .expect("some suffix is free")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_names() {
+        assert_eq!(scope_name("write:pets", &[]), "writePets");
+        assert_eq!(scope_name("https://example.com/auth/drive", &[]), "httpsExampleComAuthDrive");
+        assert_eq!(scope_name("2fa", &[]), "scope2fa");
+        assert_eq!(scope_name("open", &[]), "open_");
+        assert_eq!(scope_name("all-scopes", &[]), "allScopes2");
+        assert_eq!(scope_name("read_pets", &["readPets".to_string()]), "readPets2");
+    }
+
+    #[test]
+    fn test_module_names() {
+        assert_eq!(module_name("petstore_auth", &[]), "PetstoreAuth");
+        assert_eq!(module_name("2legged", &[]), "Scheme2legged");
+        assert_eq!(module_name("petstoreAuth", &["PetstoreAuth".to_string()]), "PetstoreAuth2");
+    }
+}
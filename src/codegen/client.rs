@@ -3,12 +3,23 @@
 
 //! HTTP client generation with pluggable HTTP backend
 
-use crate::ir::{ApiSpec, Endpoint, HttpMethod, Parameter, ParameterLocation, RsType};
-use super::Config;
-use anyhow::Result;
+use crate::ir::{
+    sanitize_field_name, ApiSpec, Endpoint, FieldCase, FormField, FormFieldKind, HttpMethod, MediaKind, Pagination,
+    PaginationKind, Parameter, ParameterLocation, ParameterStyle, Response,
+    ResponseStatus, RsType, SecurityScheme, SecuritySchemeKind, TypeDef,
+};
+use super::{
+    allow_deprecated, deprecated_attribute, doc_comment, region, string_literal, BodySerializer,
+    ClientSurface, Config, ResponseStyle,
+};
+use anyhow::{bail, Result};
 use heck::ToPascalCase;
 
 pub fn generate(spec: &ApiSpec, config: &Config) -> Result<String> {
+    if config.generate_exn && config.response_style == ResponseStyle::TypedErrors {
+        bail!("`*Exn` wrappers raise `ApiError(apiError)`, which typed errors replace");
+    }
+
     let mut output = String::new();
 
     // Header
@@ -17,39 +28,225 @@ pub fn generate(spec: &ApiSpec, config: &Config) -> Result<String> {
     output.push_str(&format!("// Source: {} v{}\n\n", spec.title, spec.version));
 
     // Import core library and types
-    output.push_str("open RescriptCore\n");
-    output.push_str(&format!("open {}Types\n", config.module_prefix));
-    output.push_str(&format!("open {}Schema\n\n", config.module_prefix));
+    output.push_str(&config.opens(true, &["Types", "Schema"]));
+    output.push_str(allow_deprecated(spec));
+    output.push_str(&format!("module Meta = {}\n", config.module_ref("Meta")));
+
+    let runtime = Runtime::for_spec(spec);
+    if runtime.oauth2 {
+        output.push_str(&format!("module Auth = {}\n", config.module_ref("Auth")));
+    }
+    output.push('\n');
 
     // HTTP abstraction layer
-    output.push_str(r#"/** API error type */
+    output.push_str(r#"/** Failures detected by the client itself rather than reported by the server */
+type errorKind =
+  /** Body exceeded the configured size limit (sizes in bytes) */
+  | PayloadTooLarge({limit: int, size: int})
+  /** Cancelled through `RequestScope.abortAll` */
+  | Aborted
+  /** No response within the config's or the operation's timeout */
+  | Timeout({timeoutMs: int})
+
+/** API error type */
 type apiError = {
   status: int,
   message: string,
   body: option<Js.Json.t>,
-}
+  kind?: errorKind,
+"#);
+    if runtime.problem_details {
+        output.push_str("  /** RFC 7807 details, when the body is `application/problem+json` */\n");
+        output.push_str("  problem?: problemDetails,\n");
+    }
+    output.push_str(r#"}
 
 /** HTTP method (polymorphic variant for Fetch API) */
 type httpMethod = [#GET | #POST | #PUT | #PATCH | #DELETE | #HEAD | #OPTIONS]
 
+"#);
+
+    if config.generate_exn {
+        output.push_str(r#"/** Raised by the `*Exn` client functions with the error of a failed call */
+exception ApiError(apiError)
+
+"#);
+    }
+
+    if runtime.raw_body {
+        output.push_str(r#"/** Unparsed body of a wildcard (e.g. `*/*`) or binary (e.g. `image/png`) response */
+type rawBody<'body> = {
+  contentType: option<string>,
+  body: 'body,
+}
+
+"#);
+    }
+
+    if runtime.negotiation {
+        output.push_str(r#"/** Body of a response offered in several media types, read per its `Content-Type` */
+type negotiated<'json> =
+  | Json('json)
+  | Text(rawBody<string>)
+  | Blob(rawBody<Fetch.Blob.t>)
+
+"#);
+    }
+
+    output.push_str(r#"/** Browser `AbortController`, bound here so transports don't depend on a Fetch binding */
+type abortController
+type abortSignal
+@new external makeAbortController: unit => abortController = "AbortController"
+@get external abortSignal: abortController => abortSignal = "signal"
+@send external abort: abortController => unit = "abort"
+@get external aborted: abortSignal => bool = "aborted"
+@send external onAbort: (abortSignal, @as("abort") _, unit => unit) => unit = "addEventListener"
+
+/** Fetch `credentials` mode: when cookies and HTTP auth are sent */
+type credentialsMode = [#omit | #"same-origin" | #include]
+"#);
+    if runtime.multipart {
+        output.push_str(r#"
+/** Browser `FormData`, the body of `multipart/form-data` requests */
+type formData
+@new external makeFormData: unit => formData = "FormData"
+@send external appendFormValue: (formData, string, string) => unit = "append"
+@send external appendFormFile: (formData, string, Fetch.Blob.t) => unit = "append"
+"#);
+    }
+    output.push_str(r#"
 /** HTTP request configuration */
 type httpRequest = {
   method: httpMethod,
   url: string,
   headers: Dict.t<string>,
   body: option<Js.Json.t>,
+  /** Reject responses larger than this many bytes, if set */
+  maxResponseBytes: option<int>,
+  /** How to read a successful body: parsed JSON (an empty one as `null`), text as a JSON string, or a Blob */
+  responseType: [#json | #text | #blob],
+  /** Hand back the body's unread `ReadableStream` instead of reading it; transports
+   * that don't may read it as `responseType` says */
+  stream?: bool,
+  /** Abort signal passed by the caller, or of the config's `RequestScope` */
+  signal: option<abortSignal>,
+  /** Whether to send cookies with cross-origin requests; the transport's default if `None` */
+  credentials: option<credentialsMode>,
+"#);
+    if runtime.multipart {
+        output.push_str("  /** Multipart body, sent instead of `body` */\n");
+        output.push_str("  form?: formData,\n");
+    }
+    output.push_str(r#"}
+
+/** Successful HTTP response */
+type httpResponse = {
+  status: int,
+  headers: Dict.t<string>,
+  body: Js.Json.t,
 }
 
-/** HTTP client module signature - implement this to use any HTTP library */
+/** Look up a header case-insensitively */
+let headerValue = (headers: Dict.t<string>, name: string): option<string> => {
+  let wanted = name->String.toLowerCase
+  headers
+  ->Dict.toArray
+  ->Array.find(((key, _)) => key->String.toLowerCase == wanted)
+  ->Option.map(((_, value)) => value)
+}
+"#);
+    if runtime.auth || runtime.cookies {
+        output.push_str(r#"
+/** Add `name=value` to a request's `Cookie` header
+ *
+ * Browsers don't let scripts set `Cookie`; there, send the browser's own cookies
+ * with `~credentials=#include` instead.
+ */
+let addCookie = (headers: Dict.t<string>, name: string, value: string): unit => {
+  let cookie = `${name}=${encodeURIComponent(value)}`
+  headers->Dict.set("Cookie", switch headers->headerValue("Cookie") {
+  | Some(cookies) => `${cookies}; ${cookie}`
+  | None => cookie
+  })
+}
+"#);
+    }
+    output.push_str(r#"
+/** Declared `Content-Length` of a response, when present */
+let contentLength = (headers: Dict.t<string>): option<int> => {
+  headers->headerValue("Content-Length")->Option.flatMap(v => v->Int.fromString)
+}
+
+type textEncoder
+@new external makeTextEncoder: unit => textEncoder = "TextEncoder"
+@send external encode: (textEncoder, string) => Uint8Array.t = "encode"
+
+/** UTF-8 size of a string in bytes */
+let byteLength = (s: string): int => makeTextEncoder()->encode(s)->TypedArray.length
+
+/** Client-side error for a body over its size limit */
+let payloadTooLarge = (~what: string, ~limit: int, ~size: int): apiError => {
+  status: 0,
+  message: `${what} of ${size->Int.toString} bytes exceeds the ${limit->Int.toString} byte limit`,
+  body: None,
+  kind: PayloadTooLarge({limit, size}),
+}
+
+"#);
+
+    if runtime.negotiation {
+        output.push_str(r#"/** Media type of a response without its parameters, e.g. `text/csv` */
+let mediaType = (headers: Dict.t<string>): option<string> =>
+  headers
+  ->headerValue("Content-Type")
+  ->Option.map(t => t->String.split(";")->Array.get(0)->Option.getOr("")->String.trim->String.toLowerCase)
+
+"#);
+    }
+
+    if runtime.problem_details {
+        output.push_str(r#"/** RFC 7807 details of an error body served as `application/problem+json`
+ *
+ * Custom backends set `problem` with this for their error results.
+ */
+let decodeProblem = (headers: Dict.t<string>, body: option<Js.Json.t>): option<problemDetails> =>
+  switch (headers->headerValue("Content-Type"), body) {
+  | (Some(contentType), Some(json)) if contentType->String.startsWith("application/problem+json") =>
+    try Some(parseProblemDetails(json)) catch {
+    | Exn.Error(_) => None
+    }
+  | _ => None
+  }
+
+"#);
+    }
+
+    output.push_str(r#"/** HTTP client module signature - implement this to use any HTTP library
+ *
+ * Returns only the parsed body; wrap it with `FromHttpClient` to pass it to `Make`.
+ */
 module type HttpClient = {
   let request: httpRequest => promise<result<Js.Json.t, apiError>>
 }
 
-/** Default fetch-based HTTP client using @glennsl/rescript-fetch */
-module FetchClient: HttpClient = {
+/** HTTP transport module signature: like `HttpClient`, with the response status and headers */
+module type HttpTransport = {
+  let request: httpRequest => promise<result<httpResponse, apiError>>
+}
+
+/** Transport for an `HttpClient`, reporting every success as a `200` without headers */
+module FromHttpClient = (Http: HttpClient): HttpTransport => {
+  let request = async (req: httpRequest): result<httpResponse, apiError> =>
+    (await Http.request(req))->Result.map(body => {status: 200, headers: Dict.make(), body})
+}
+
+/** Default fetch-based HTTP transport using @glennsl/rescript-fetch */
+module FetchClient: HttpTransport = {
   open Fetch
 
-  let request = async (req: httpRequest): result<Js.Json.t, apiError> => {
+  @val external headerEntries: Headers.t => array<(string, string)> = "Array.from"
+
+  let request = async (req: httpRequest): result<httpResponse, apiError> => {
     try {
       let init: Request.init = {
         method: (req.method :> Fetch.method),
@@ -59,22 +256,69 @@ module FetchClient: HttpClient = {
       | Some(b) => {...init, body: b->JSON.stringify->Body.string}
       | None => init
       }
+"#);
+    if runtime.multipart {
+        output.push_str(r#"      let init = switch req.form {
+      | Some(form) => {...init, body: form->Obj.magic}
+      | None => init
+      }
+"#);
+    }
+    output.push_str(r#"      let init = switch req.signal {
+      | Some(signal) => {...init, signal: signal->Obj.magic}
+      | None => init
+      }
+      let init = switch req.credentials {
+      | Some(credentials) => {...init, credentials: credentials->Obj.magic}
+      | None => init
+      }
       let response = await fetch(req.url, init)
 
+      let status = response->Response.status
       if response->Response.ok {
-        let json = await response->Response.json
-        Ok(json)
+        let headers = response->Response.headers->headerEntries->Dict.fromArray
+        switch (req.maxResponseBytes, contentLength(headers)) {
+        // No content, so nothing to read
+        | (_, _) if status == 204 => Ok({status, headers, body: JSON.Encode.null})
+        // Refuse before reading when the declared size is already too large
+        | (Some(limit), Some(size)) if size > limit =>
+          Error(payloadTooLarge(~what="Response body", ~limit, ~size))
+        | (_, _) if req.stream == Some(true) =>
+          Ok({status, headers, body: response->Response.body->Obj.magic})
+        | (_, _) if req.responseType == #blob =>
+          let blob = await response->Response.blob
+          Ok({status, headers, body: blob->Obj.magic})
+        | (maxBytes, _) =>
+          let text = await response->Response.text
+          // Encode the text once, and only when there is a limit to check
+          switch (maxBytes, maxBytes->Option.map(_ => byteLength(text))) {
+          | (Some(limit), Some(size)) if size > limit =>
+            Error(payloadTooLarge(~what="Response body", ~limit, ~size))
+          | _ if req.responseType == #text => Ok({status, headers, body: JSON.Encode.string(text)})
+          // e.g. a 204 from an operation that also declares a JSON response
+          | _ if text->String.trim == "" => Ok({status, headers, body: JSON.Encode.null})
+          | _ => Ok({status, headers, body: JSON.parseExn(text)})
+          }
+        }
       } else {
-        let status = response->Response.status
         let message = response->Response.statusText
         let body = try {
           Some(await response->Response.json)
         } catch {
         | _ => None
         }
-        Error({status, message, body})
-      }
+"#);
+    if runtime.problem_details {
+        output.push_str(r#"        let headers = response->Response.headers->headerEntries->Dict.fromArray
+        Error({status, message, body, ?problem: decodeProblem(headers, body)})
+"#);
+    } else {
+        output.push_str("        Error({status, message, body})\n");
+    }
+    output.push_str(r#"      }
     } catch {
+    | Exn.Error(e) if Exn.name(e) == Some("AbortError") =>
+      Error({status: 0, message: "Request aborted", body: None, kind: Aborted})
     | Exn.Error(e) => Error({
         status: 0,
         message: Exn.message(e)->Option.getOr("Network error"),
@@ -84,22 +328,199 @@ module FetchClient: HttpClient = {
   }
 }
 
-/** Authentication configuration */
-type authConfig =
-  | NoAuth
-  | BearerToken(string)
-  | ApiKey({key: string, headerName: string})
+"#);
+
+    if runtime.auth {
+        output.push_str(r#"/** Credentials for the API's security schemes
+ *
+ * Each operation sends only those its `security` requirements ask for.
+ */
+type authConfig = {
+  /** Supplies the token for bearer and OAuth2 schemes, asked before each request that needs one */
+  bearerToken: option<unit => promise<option<string>>>,
+  /** Keys for API key schemes, by security scheme name */
+  apiKeys: Dict.t<string>,
+  /** Key for API key schemes without an entry in `apiKeys` */
+  apiKey: option<string>,
+  /** Header that also carries `apiKey` on operations declaring no security requirements
+   * (the deprecated `~apiKeyHeader`) */
+  apiKeyHeader: option<string>,
+}
+
+/** A credential an operation's security requirement asks for */
+type authScheme =
+  | Bearer
+  /** The key of security scheme `scheme`, sent as the header, query parameter or cookie `name` */
+  | ApiKey({scheme: string, name: string, location: [#header | #query | #cookie]})
+
+"#);
+    }
+
+    output.push_str(r#"/** Transforms a request before it is sent, e.g. to add tracing headers */
+type requestInterceptor = httpRequest => promise<httpRequest>
+
+/** Transforms what the transport returned before it is decoded, e.g. to log or rewrite errors */
+type responseInterceptor = result<httpResponse, apiError> => promise<result<httpResponse, apiError>>
+
+/** A request about to be sent, for `onRequest` */
+type requestEvent = {
+  method: httpMethod,
+  url: string,
+}
+
+/** A finished request, for `onResponse` */
+type responseEvent = {
+  method: httpMethod,
+  url: string,
+  /** `0` when no response arrived, e.g. on network errors and timeouts */
+  status: int,
+  /** Milliseconds from sending to the transport's answer */
+  durationMs: float,
+}
+
+/** Run `interceptors` in order, each on the previous one's output */
+let intercept = async (value: 'a, interceptors: array<'a => promise<'a>>): 'a => {
+  let value = ref(value)
+  for i in 0 to interceptors->Array.length - 1 {
+    value := await (interceptors->Array.getUnsafe(i))(value.contents)
+  }
+  value.contents
+}
+
+/** In-flight requests that can be cancelled together, e.g. on a route change
+ *
+ * Every request sent with a config whose `scope` is set is tracked until it
+ * settles; `abortAll` cancels those still running, which then fail with
+ * `kind: Aborted`.
+ */
+module RequestScope = {
+  type t = {
+    name: string,
+    controllers: array<abortController>,
+  }
+
+  let make = (~name: string="default", ()): t => {name, controllers: []}
+
+  /** Run a request with a fresh abort signal, tracked until it settles
+   *
+   * Aborting the caller's own `signal` aborts the request too.
+   */
+  let track = async (
+    scope: t,
+    ~signal: option<abortSignal>=?,
+    run: abortSignal => promise<'a>,
+  ): 'a => {
+    let controller = makeAbortController()
+    switch signal {
+    | Some(signal) if signal->aborted => controller->abort
+    | Some(signal) => signal->onAbort(() => controller->abort)
+    | None => ()
+    }
+    scope.controllers->Array.push(controller)
+    let release = () =>
+      switch scope.controllers->Array.indexOfOpt(controller) {
+      | Some(index) => scope.controllers->Array.splice(~start=index, ~remove=1, ~insert=[])
+      | None => ()
+      }
+    switch await run(controller->abortSignal) {
+    | result =>
+      release()
+      result
+    | exception e =>
+      release()
+      raise(e)
+    }
+  }
+
+  /** Abort every request still in flight; later requests are unaffected */
+  let abortAll = (scope: t): unit => {
+    let running = scope.controllers->Array.copy
+    scope.controllers->Array.splice(~start=0, ~remove=running->Array.length, ~insert=[])
+    running->Array.forEach(abort)
+  }
+
+  /** Number of requests still in flight */
+  let pending = (scope: t): int => scope.controllers->Array.length
+}
+
+"#);
+    if config.generate_retry {
+        output.push_str(r#"/** When and how often failed requests are retried */
+type retryPolicy = {
+  /** Attempts in total, including the first; `1` disables retries */
+  maxAttempts: int,
+  /** Delay before the first retry, doubled for each further one */
+  initialDelayMs: int,
+  /** Upper bound for the delay between attempts */
+  maxDelayMs: int,
+  /** Error statuses worth retrying; network failures and timeouts always are */
+  retryStatuses: array<int>,
+  /** Methods that may be retried; others are sent once, as they may not be idempotent */
+  retryMethods: array<httpMethod>,
+}
+
+/** Three attempts for idempotent methods on 408, 429, 502, 503 and 504 */
+let defaultRetryPolicy: retryPolicy = {
+  maxAttempts: 3,
+  initialDelayMs: 200,
+  maxDelayMs: 5_000,
+  retryStatuses: [408, 429, 502, 503, 504],
+  retryMethods: [#GET, #HEAD, #OPTIONS, #PUT, #DELETE],
+}
 
-/** Client configuration */
+"#);
+    }
+    output.push_str(r#"/** Client configuration */
 type config = {
   baseUrl: string,
   headers: Dict.t<string>,
-  auth: authConfig,
-}
+"#);
+    if runtime.auth {
+        output.push_str("  auth: authConfig,\n");
+    }
+    output.push_str(r#"  /** Called on 401 responses, e.g. to trigger re-login */
+  on401: option<apiError => unit>,
+  /** Called on 403 responses */
+  on403: option<apiError => unit>,
+  /** Called on any 5xx response, e.g. to show a maintenance banner */
+  on5xx: option<apiError => unit>,
+  /** Response header carrying the server's API version */
+  versionHeader: string,
+  /** Called when the server reports a different API version than `Meta.version` */
+  onVersionMismatch: option<Meta.versionMismatch => unit>,
+  /** Reject request bodies larger than this many bytes before sending */
+  maxRequestBytes: option<int>,
+  /** Reject response bodies larger than this many bytes */
+  maxResponseBytes: option<int>,
+  /** Log a console warning when a deprecated parameter is passed (for development builds) */
+  warnDeprecated: bool,
+  /** Track requests so `RequestScope.abortAll` can cancel them */
+  scope: option<RequestScope.t>,
+  /** Give up on requests after this many milliseconds, unless the operation has its own */
+  timeoutMs: option<int>,
+  /** Fetch `credentials` mode for every request */
+  credentials: option<credentialsMode>,
+  /** Sends requests instead of the client's `HttpTransport`, e.g. a stub in tests */
+  transport: option<httpRequest => promise<result<httpResponse, apiError>>>,
+  /** Applied to every request before it is sent, in order */
+  requestInterceptors: array<requestInterceptor>,
+  /** Applied to every transport result before it is decoded, in order */
+  responseInterceptors: array<responseInterceptor>,
+  /** Called before every request is sent, e.g. for logging */
+  onRequest: option<requestEvent => unit>,
+  /** Called when every request finishes, with its status and duration, e.g. for metrics */
+  onResponse: option<responseEvent => unit>,
+"#);
+    if config.generate_retry {
+        output.push_str("  /** Retries for operations without `x-retries` */\n");
+        output.push_str("  retry: retryPolicy,\n");
+    }
+    output.push_str("}\n\n");
 
-/** Create client configuration with optional authentication
+    if runtime.auth {
+        output.push_str(r#"/** Create client configuration with optional authentication
  *
- * Bearer token auth:
+ * Bearer token auth, sent only to operations whose `security` requires it:
  * ```rescript
  * let config = makeConfig(
  *   ~baseUrl="https://api.example.com",
@@ -108,12 +529,123 @@ type config = {
  * )
  * ```
  *
- * API key auth:
+ * Or supply a fresh token before each request that needs one:
+ * ```rescript
+ * let config = makeConfig(
+ *   ~baseUrl="https://api.example.com",
+ *   ~getBearerToken=async () => Some(await Session.accessToken()),
+ *   ()
+ * )
+ * ```
+ *
+ * API key auth, sent in the header, query parameter or cookie the scheme declares:
  * ```rescript
  * let config = makeConfig(
  *   ~baseUrl="https://api.example.com",
  *   ~apiKey="my-api-key",
- *   ~apiKeyHeader="X-API-Key",
+ *   ()
+ * )
+ * ```
+ *
+ * Separate keys per security scheme name:
+ * ```rescript
+ * let config = makeConfig(
+ *   ~baseUrl="https://api.example.com",
+ *   ~apiKeys=Dict.fromArray([("publicKey", "pk-123"), ("adminKey", "ak-456")]),
+ *   ()
+ * )
+ * ```
+ *
+ * `~apiKeyHeader` is deprecated: header API key schemes take their header name
+ * from the spec. It still sends `~apiKey` in that header on operations that
+ * declare no security requirements.
+ *
+"#);
+        if runtime.oauth2 {
+            output.push_str(r#" * OAuth2 tokens, refreshed shortly before they expire:
+ * ```rescript
+ * let tokenStore = Auth.makeTokenStore(~refresh=_ => fetchAccessToken(), ())
+ * let config = makeConfig(~baseUrl="https://api.example.com", ~tokenStore, ())
+ * ```
+ *
+"#);
+        }
+    } else {
+        output.push_str("/** Create client configuration\n *\n");
+    }
+
+    output.push_str(r#" * Status handlers run before the error result reaches the caller:
+ * ```rescript
+ * let config = makeConfig(
+ *   ~baseUrl="https://api.example.com",
+ *   ~on401=_ => redirectToLogin(),
+ *   ()
+ * )
+ * ```
+ *
+ * Version drift between client and server:
+ * ```rescript
+ * let config = makeConfig(
+ *   ~baseUrl="https://api.example.com",
+ *   ~onVersionMismatch=m => Console.warn(`API ${m.actual}, client built for ${m.expected}`),
+ *   ()
+ * )
+ * ```
+ *
+ * Body size limits (errors carry `kind: PayloadTooLarge`):
+ * ```rescript
+ * let config = makeConfig(
+ *   ~baseUrl="https://api.example.com",
+ *   ~maxResponseBytes=1_000_000,
+ *   ()
+ * )
+ * ```
+ *
+ * Cancel outstanding calls on a route change (errors carry `kind: Aborted`):
+ * ```rescript
+ * let scope = RequestScope.make(~name="dashboard", ())
+ * let config = makeConfig(~baseUrl="https://api.example.com", ~scope, ())
+ * // later
+ * scope->RequestScope.abortAll
+ * ```
+ *
+ * Default timeout, cookies on cross-origin requests and a stubbed transport:
+ * ```rescript
+ * let config = makeConfig(
+ *   ~baseUrl="https://api.example.com",
+ *   ~timeoutMs=10_000,
+ *   ~credentials=#include,
+ *   ~transport=async _ => Ok({status: 200, headers: Dict.make(), body: JSON.Encode.null}),
+ *   ()
+ * )
+ * ```
+ *
+ * Interceptors, e.g. for tracing and logging:
+ * ```rescript
+ * let config = makeConfig(
+ *   ~baseUrl="https://api.example.com",
+ *   ~requestInterceptors=[
+ *     async req => {
+ *       let headers = req.headers->Dict.copy
+ *       headers->Dict.set("X-Trace-Id", newTraceId())
+ *       {...req, headers}
+ *     },
+ *   ],
+ *   ~responseInterceptors=[
+ *     async result => {
+ *       Console.log(result)
+ *       result
+ *     },
+ *   ],
+ *   ()
+ * )
+ * ```
+ *
+ * Request timings, e.g. for metrics:
+ * ```rescript
+ * let config = makeConfig(
+ *   ~baseUrl="https://api.example.com",
+ *   ~onResponse=e => Metrics.observe(e.url, e.status, e.durationMs),
  *   ()
  * )
  * ```
@@ -121,64 +653,808 @@ type config = {
 let makeConfig = (
   ~baseUrl: string,
   ~headers=Dict.make(),
-  ~bearerToken: option<string>=?,
+"#);
+    if runtime.auth {
+        output.push_str(r#"  ~bearerToken: option<string>=?,
+  ~getBearerToken: option<unit => promise<option<string>>>=?,
   ~apiKey: option<string>=?,
-  ~apiKeyHeader: string="X-API-Key",
-  ()
+  ~apiKeys=Dict.make(),
+  ~apiKeyHeader: option<string>=?,
+"#);
+    }
+    if runtime.oauth2 {
+        output.push_str("  ~tokenStore: option<Auth.tokenStore>=?,\n");
+    }
+    output.push_str(r#"  ~on401: option<apiError => unit>=?,
+  ~on403: option<apiError => unit>=?,
+  ~on5xx: option<apiError => unit>=?,
+  ~versionHeader: string=Meta.versionHeader,
+  ~onVersionMismatch: option<Meta.versionMismatch => unit>=?,
+  ~maxRequestBytes: option<int>=?,
+  ~maxResponseBytes: option<int>=?,
+  ~warnDeprecated: bool=false,
+  ~scope: option<RequestScope.t>=?,
+  ~timeoutMs: option<int>=?,
+  ~credentials: option<credentialsMode>=?,
+  ~transport: option<httpRequest => promise<result<httpResponse, apiError>>>=?,
+  ~requestInterceptors: array<requestInterceptor>=[],
+  ~responseInterceptors: array<responseInterceptor>=[],
+  ~onRequest: option<requestEvent => unit>=?,
+  ~onResponse: option<responseEvent => unit>=?,
+"#);
+    if config.generate_retry {
+        output.push_str("  ~retry: retryPolicy=defaultRetryPolicy,\n");
+    }
+    output.push_str(r#"  ()
 ): config => {
-  let auth = switch (bearerToken, apiKey) {
-  | (Some(token), _) => BearerToken(token)
-  | (_, Some(key)) => ApiKey({key, headerName: apiKeyHeader})
-  | (None, None) => NoAuth
+"#);
+    if runtime.oauth2 {
+        output.push_str(r#"  let getBearerToken = switch tokenStore {
+  | Some(store) => getBearerToken->Option.orElse(Some(() => store->Auth.getToken))
+  | None => getBearerToken
   }
-  {
-    baseUrl,
-    headers,
-    auth,
+"#);
+    }
+    if runtime.auth {
+        output.push_str(r#"  let auth = {
+    bearerToken: switch (getBearerToken, bearerToken) {
+    | (Some(getToken), _) => Some(getToken)
+    | (None, Some(token)) => Some(async () => Some(token))
+    | (None, None) => None
+    },
+    apiKeys,
+    apiKey,
+    apiKeyHeader,
   }
+"#);
+    }
+    output.push_str("  {\n    baseUrl,\n    headers,\n");
+    if runtime.auth {
+        output.push_str("    auth,\n");
+    }
+    output.push_str(r#"    on401,
+    on403,
+    on5xx,
+    versionHeader,
+    onVersionMismatch,
+    maxRequestBytes,
+    maxResponseBytes,
+    warnDeprecated,
+    scope,
+    timeoutMs,
+    credentials,
+    transport,
+    requestInterceptors,
+    responseInterceptors,
+    onRequest,
+    onResponse,
+"#);
+    if config.generate_retry {
+        output.push_str("    retry,\n");
+    }
+    output.push_str(r#"  }
 }
 
-/** Apply authentication headers to a headers dict */
-let applyAuth = (headers: Dict.t<string>, auth: authConfig): unit => {
-  switch auth {
-  | NoAuth => ()
-  | BearerToken(token) => headers->Dict.set("Authorization", `Bearer ${token}`)
-  | ApiKey({key, headerName}) => headers->Dict.set(headerName, key)
+"#);
+
+    if runtime.deprecated_params {
+        output.push_str(r#"/** Warn that a deprecated parameter was passed, when enabled in the config */
+let warnDeprecatedParam = (config: config, operationId: string, param: string): unit => {
+  if config.warnDeprecated {
+    Console.warn(`${operationId}: parameter ~${param} is deprecated`)
   }
 }
 
-/** Build URL with query parameters */
-let buildUrl = (baseUrl: string, path: string, query: Dict.t<string>): string => {
-  let url = baseUrl ++ path
-  let params = query
-    ->Dict.toArray
-    ->Array.map(((k, v)) => `${encodeURIComponent(k)}=${encodeURIComponent(v)}`)
-    ->Array.join("&")
+"#);
+    }
 
-  if params->String.length > 0 {
-    url ++ "?" ++ params
-  } else {
-    url
+    output.push_str(r#"/** Run the configured status handler for an error response, if any */
+let notifyStatus = (config: config, err: apiError): unit => {
+  let handler = switch err.status {
+  | 401 => config.on401
+  | 403 => config.on403
+  | status if status >= 500 && status < 600 => config.on5xx
+  | _ => None
+  }
+  switch handler {
+  | Some(f) => f(err)
+  | None => ()
+  }
+}
+
+/** Report a server API version that differs from the generated one */
+let checkServerVersion = (config: config, res: httpResponse): unit => {
+  switch config.onVersionMismatch {
+  | None => ()
+  | Some(onMismatch) =>
+    switch res.headers->headerValue(config.versionHeader) {
+    | Some(actual) =>
+      switch Meta.checkVersion(actual) {
+      | Ok() => ()
+      | Error(mismatch) => onMismatch(mismatch)
+      }
+    | None => ()
+    }
   }
 }
 
-/** API client functor - provide your own HttpClient implementation */
-module Make = (Http: HttpClient) => {
 "#);
 
-    // Generate endpoint functions inside the functor
-    for endpoint in &spec.endpoints {
-        output.push_str(&generate_endpoint(endpoint, config));
-        output.push('\n');
+    if runtime.auth {
+        output.push_str(r#"/** Add the credentials of the first security requirement the config can satisfy
+ *
+ * `requirements` are an operation's alternatives, each listing the schemes it
+ * needs together; an empty one means the operation also works without auth.
+ */
+let applyAuth = async (
+  headers: Dict.t<string>,
+  query: array<(string, string)>,
+  auth: authConfig,
+  requirements: array<array<authScheme>>,
+): unit => {
+  let needsToken = requirements->Array.some(r => r->Array.includes(Bearer))
+  let token = switch auth.bearerToken {
+  | Some(getToken) if needsToken => await getToken()
+  | _ => None
+  }
+  let keyFor = scheme => auth.apiKeys->Dict.get(scheme)->Option.orElse(auth.apiKey)
+  let available = scheme =>
+    switch scheme {
+    | Bearer => token->Option.isSome
+    | ApiKey({scheme}) => keyFor(scheme)->Option.isSome
     }
+  switch requirements->Array.find(r => r->Array.every(available)) {
+  | Some(requirement) =>
+    requirement->Array.forEach(scheme =>
+      switch scheme {
+      | Bearer =>
+        token->Option.forEach(token => headers->Dict.set("Authorization", `Bearer ${token}`))
+      | ApiKey({scheme, name, location}) =>
+        keyFor(scheme)->Option.forEach(key =>
+          switch location {
+          | #header => headers->Dict.set(name, key)
+          | #query => query->Array.push((name, key))
+          | #cookie => headers->addCookie(name, key)
+          }
+        )
+      }
+    )
+  | None => ()
+  }
+}
 
-    output.push_str("}\n\n");
+/** Add every credential the config has for `schemes`, for operations whose spec
+ * declares no security requirements at all
+ */
+let applyConfiguredAuth = async (
+  headers: Dict.t<string>,
+  query: array<(string, string)>,
+  auth: authConfig,
+  schemes: array<authScheme>,
+): unit => {
+  await schemes->Array.reduce(Promise.resolve(), async (previous, scheme) => {
+    await previous
+    await applyAuth(headers, query, auth, [[scheme]])
+  })
+  switch (auth.apiKeyHeader, auth.apiKey) {
+  | (Some(name), Some(key)) if headers->Dict.get(name)->Option.isNone => headers->Dict.set(name, key)
+  | _ => ()
+  }
+}
 
-    // Default client using FetchClient
-    output.push_str("/** Default client using fetch */\n");
-    output.push_str("module Client = Make(FetchClient)\n\n");
+"#);
+    }
 
-    // Generate aliases map (operationId -> path-based name)
+    if runtime.wire {
+        output.push_str(r#"/** Locale-independent formatting for path, query and header values
+ *
+ * Never use `toLocaleString` for wire values: a German locale would send
+ * `1.234,5` instead of `1234.5`.
+ */
+module Wire = {
+  /** Decimal integer without grouping separators */
+  let int = (v: int): string => v->Int.toString
+  /** Shortest round-trip decimal, always with `.` as separator */
+  let float = (v: float): string => v->Float.toString
+  let bool = (v: bool): string => v ? "true" : "false"
+  /** ISO 8601 timestamp in UTC, e.g. `2024-01-31T12:00:00.000Z` */
+  let dateTime = (v: Date.t): string => v->Date.toISOString
+  /** ISO 8601 calendar date in UTC, e.g. `2024-01-31` */
+  let date = (v: Date.t): string => v->Date.toISOString->String.slice(~start=0, ~end=10)
+  /** Decimal integer of any size */
+  let bigint = (v: bigint): string => v->BigInt.toString
+}
+
+"#);
+    }
+
+    if runtime.query_styles {
+        output.push_str(r#"/** OpenAPI `style` serialization of object and array query parameters */
+module QueryStyle = {
+  /** A scalar as sent on the wire; nested arrays and objects as JSON text */
+  let scalar = (json: JSON.t): string =>
+    switch json {
+    | String(s) => s
+    | Number(n) => n->Float.toString
+    | Boolean(b) => b ? "true" : "false"
+    | Null => ""
+    | Array(_) | Object(_) => json->JSON.stringify
+    }
+
+  /** Items of an array, or an object's property names and values in turn */
+  let parts = (json: JSON.t): array<string> =>
+    switch json {
+    | Array(items) => items->Array.map(scalar)
+    | Object(fields) => fields->Dict.toArray->Array.flatMap(((k, v)) => [k, scalar(v)])
+    | json => [scalar(json)]
+    }
+
+  /** `form`: `name=a,b`; exploded, `name=a&name=b` for arrays and each property as
+   * its own parameter for objects */
+  let form = (query: array<(string, string)>, name: string, json: JSON.t, ~explode: bool) =>
+    switch json {
+    | Object(fields) if explode =>
+      fields->Dict.forEachWithKey((v, k) => query->Array.push((k, scalar(v))))
+    | Array(items) if explode => items->Array.forEach(item => query->Array.push((name, scalar(item))))
+    | json => query->Array.push((name, json->parts->Array.join(",")))
+    }
+
+  /** `pipeDelimited` or `spaceDelimited`: `name=a|b` or `name=a%20b` */
+  let delimited = (query: array<(string, string)>, name: string, json: JSON.t, separator: string) =>
+    query->Array.push((name, json->parts->Array.join(separator)))
+
+  /** `deepObject`: `name[key]=value`, nested objects adding a bracket per level */
+  let rec deepObject = (query: array<(string, string)>, name: string, json: JSON.t): unit =>
+    switch json {
+    | Object(fields) =>
+      fields->Dict.forEachWithKey((v, k) => deepObject(query, `${name}[${k}]`, v))
+    | json => query->Array.push((name, json->parts->Array.join(",")))
+    }
+}
+
+"#);
+    }
+
+    output.push_str(r#"/** Build URL with query parameters, in order and keeping repeated names */
+let buildUrl = (baseUrl: string, path: string, query: array<(string, string)>): string => {
+  let url = baseUrl ++ path
+  let params = query
+    ->Array.map(((k, v)) => `${encodeURIComponent(k)}=${encodeURIComponent(v)}`)
+    ->Array.join("&")
+
+  if params->String.length > 0 {
+    url ++ "?" ++ params
+  } else {
+    url
+  }
+}
+
+"#);
+
+    if runtime.array_buffer {
+        output.push_str(r#"@send external blobArrayBuffer: Fetch.Blob.t => promise<ArrayBuffer.t> = "arrayBuffer"
+
+/** A response whose body was requested as a Blob, with the body read into an `ArrayBuffer` */
+let readArrayBuffer = async (response: promise<result<httpResponse, apiError>>) =>
+  switch await response {
+  | Ok(res) => Ok({...res, body: (await blobArrayBuffer(res.body->Obj.magic))->Obj.magic})
+  | Error(e) => Error(e)
+  }
+
+"#);
+    }
+
+    if runtime.stream {
+        output.push_str(r#"/** A response body left unread, see the `*Stream` functions */
+type readableStream
+type streamReader
+type streamChunk = {done: bool, value?: Uint8Array.t}
+@send external getReader: readableStream => streamReader = "getReader"
+@send external readChunk: streamReader => promise<streamChunk> = "read"
+
+/** The unread stream of a body, or of the Blob a transport ignoring `stream` read;
+ * `None` for a response without a body, such as a 204 */
+let bodyStream: Js.Json.t => option<readableStream> = %raw(`body => body == null ? undefined : body instanceof Blob ? body.stream() : body`)
+
+/** Call `onChunk` with each chunk of `stream` as it arrives, until the body ends
+ *
+ * Failing to read the body, e.g. when the connection drops, ends with an `Error`;
+ * exceptions raised by `onChunk` are not caught.
+ */
+let forEachChunk = async (
+  stream: option<readableStream>,
+  onChunk: Uint8Array.t => unit,
+): result<unit, apiError> =>
+  switch stream {
+  | None => Ok()
+  | Some(stream) =>
+    let reader = stream->getReader
+    let finished = ref(None)
+    while finished.contents->Option.isNone {
+      switch await reader->readChunk {
+      | chunk =>
+        chunk.value->Option.forEach(onChunk)
+        if chunk.done {
+          finished := Some(Ok())
+        }
+      | exception Exn.Error(e) =>
+        finished := Some(Error({status: 0, message: Exn.message(e)->Option.getOr("Network error"), body: None}))
+      }
+    }
+    finished.contents->Option.getOr(Ok())
+  }
+
+"#);
+    }
+
+    if runtime.events || runtime.ndjson {
+        output.push_str(r#"type textDecoder
+@new external makeTextDecoder: unit => textDecoder = "TextDecoder"
+@send external decodeChunk: (textDecoder, Uint8Array.t, {"stream": bool}) => string = "decode"
+
+/** Call `onLine` with each line of a UTF-8 body as it arrives, without its line break */
+let forEachLine = async (
+  stream: option<readableStream>,
+  onLine: string => unit,
+): result<unit, apiError> => {
+  let decoder = makeTextDecoder()
+  let pending = ref("")
+  let read = await stream->forEachChunk(chunk => {
+    let text = pending.contents ++ decoder->decodeChunk(chunk, {"stream": true})
+    let lines = text->String.split("\n")
+    pending := lines->Array.pop->Option.getOr("")
+    lines->Array.forEach(line =>
+      onLine(line->String.endsWith("\r") ? line->String.slice(~start=0, ~end=-1) : line)
+    )
+  })
+  if read->Result.isOk && pending.contents != "" {
+    onLine(pending.contents)
+  }
+  read
+}
+
+"#);
+    }
+
+    if runtime.events {
+        output.push_str(r#"/** A Server-Sent Event and its decoded `data`; `event` defaults to `message` */
+type serverEvent<'data> = {
+  event: string,
+  id: option<string>,
+  data: 'data,
+}
+
+/** Parse a `text/event-stream` body, calling `onEvent` with each event as it completes */
+let forEachServerEvent = async (
+  stream: option<readableStream>,
+  onEvent: serverEvent<string> => unit,
+): result<unit, apiError> => {
+  let data = []
+  let event = ref("message")
+  let id = ref(None)
+  let dispatch = () => {
+    if data->Array.length > 0 {
+      onEvent({event: event.contents, id: id.contents, data: data->Array.join("\n")})
+    }
+    data->Array.splice(~start=0, ~remove=data->Array.length, ~insert=[])
+    event := "message"
+  }
+  let read = await stream->forEachLine(line =>
+    if line == "" {
+      dispatch()
+    } else if !(line->String.startsWith(":")) {
+      let (field, value) = switch line->String.indexOf(":") {
+      | -1 => (line, "")
+      | i =>
+        let value = line->String.sliceToEnd(~start=i + 1)
+        let value = value->String.startsWith(" ") ? value->String.sliceToEnd(~start=1) : value
+        (line->String.slice(~start=0, ~end=i), value)
+      }
+      switch field {
+      | "data" => data->Array.push(value)
+      | "event" => event := value
+      | "id" => id := Some(value)
+      | _ => ()
+      }
+    }
+  )
+  if read->Result.isOk {
+    dispatch()
+  }
+  read
+}
+
+"#);
+    }
+
+    if runtime.link_pagination {
+        output.push_str(r#"/** One page of a `Link`-paginated list */
+type rec page<'a> = {
+  data: 'a,
+  /** Absolute URL of the `rel="next"` link, `None` on the last page */
+  nextUrl: option<string>,
+  /** Request the next page with the same config and headers; `None` on the last page */
+  fetchNext: unit => promise<option<result<page<'a>, apiError>>>,
+}
+
+type url = {href: string}
+@new external makeUrl: (string, string) => url = "URL"
+
+/** Target URLs of a `Link` header (RFC 8288) keyed by relation, e.g. `next`
+ *
+ * Relative targets are resolved against `baseUrl`, the URL of the request.
+ */
+let parseLinkHeader = (header: string, ~baseUrl: string): Dict.t<string> => {
+  let links = Dict.make()
+  // Every link starts with `<`, which can't appear unescaped in a URL
+  header
+  ->String.split("<")
+  ->Array.forEach(part =>
+    switch part->String.indexOfOpt(">") {
+    | Some(close) =>
+      let raw = part->String.slice(~start=0, ~end=close)
+      let target = try makeUrl(raw, baseUrl).href catch {
+      | Exn.Error(_) => raw
+      }
+      part
+      ->String.sliceToEnd(~start=close + 1)
+      ->String.split(";")
+      ->Array.forEach(param =>
+        switch param->String.split("=")->Array.map(String.trim) {
+        | [key, value] if key->String.toLowerCase == "rel" =>
+          value
+          ->String.replaceAll("\"", "")
+          ->String.replaceAll(",", "")
+          ->String.split(" ")
+          ->Array.forEach(rel =>
+            if rel != "" {
+              links->Dict.set(rel->String.toLowerCase, target)
+            }
+          )
+        | _ => ()
+        }
+      )
+    | None => ()
+    }
+  )
+  links
+}
+
+"#);
+    }
+
+    output.push_str(&generate_fieldsets(spec));
+
+    if config.response_style != ResponseStyle::Success {
+        output.push_str(r#"/** An HTTP response as seen by a per-operation decoder, error statuses included */
+type receivedResponse = {
+  status: int,
+  headers: Dict.t<string>,
+  body: option<Js.Json.t>,
+  /** The body as JSON text, or the status text when there is none */
+  text: string,
+}
+
+/** An error status response, after running the config's status handlers */
+let receiveError = (config: config, e: apiError): receivedResponse => {
+  notifyStatus(config, e)
+  let text = e.body->Option.mapOr(e.message, JSON.stringify)
+  {status: e.status, headers: Dict.make(), body: e.body, text}
+}
+
+"#);
+    }
+
+    if config.response_style == ResponseStyle::Variant {
+        output.push_str(r#"/** Decode any HTTP response into an operation's outcome
+ *
+ * Error statuses reach `decode` like successes, after the config's status
+ * handlers ran. Network and client-side failures, and bodies that don't match
+ * the schema of their status, are returned as `Error`.
+ */
+let decodeOutcome = (
+  config: config,
+  response: result<httpResponse, apiError>,
+  decode: receivedResponse => 'outcome,
+): result<'outcome, apiError> => {
+  let received = switch response {
+  | Ok(res) =>
+    checkServerVersion(config, res)
+    Ok({status: res.status, headers: res.headers, body: Some(res.body), text: res.body->JSON.stringify})
+  | Error(e) if e.status > 0 => Ok(receiveError(config, e))
+  | Error(e) => Error(e)
+  }
+  switch received {
+  | Ok(res) =>
+    try Ok(decode(res)) catch {
+    | Exn.Error(e) =>
+      Error({status: 0, message: Exn.message(e)->Option.getOr("Parse error"), body: res.body})
+    }
+  | Error(e) => Error(e)
+  }
+}
+
+"#);
+        for endpoint in &spec.endpoints {
+            output.push_str(&generate_outcome(endpoint));
+            output.push('\n');
+        }
+    }
+
+    if config.response_style == ResponseStyle::TypedErrors {
+        output.push_str(r#"/** Decode a response into an operation's success body or typed failure
+ *
+ * `ok` reads a 2xx body and `failure` an error status, after the config's
+ * status handlers ran. Failures without a response become `network(e)`, and
+ * bodies that don't match their schema `invalid(message, body)`.
+ */
+let decodeTyped = (
+  config: config,
+  response: result<httpResponse, apiError>,
+  ~ok: httpResponse => 'ok,
+  ~failure: receivedResponse => 'failure,
+  ~network: apiError => 'failure,
+  ~invalid: (string, Js.Json.t) => 'failure,
+): result<'ok, 'failure> => {
+  let message = e => Exn.message(e)->Option.getOr("Parse error")
+  switch response {
+  | Ok(res) =>
+    checkServerVersion(config, res)
+    try Ok(ok(res)) catch {
+    | Exn.Error(e) => Error(invalid(message(e), res.body))
+    }
+  | Error(e) if e.status > 0 =>
+    try Error(failure(receiveError(config, e))) catch {
+    | Exn.Error(err) => Error(invalid(message(err), e.body->Option.getOr(JSON.Encode.null)))
+    }
+  | Error(e) => Error(network(e))
+  }
+}
+
+"#);
+        for endpoint in &spec.endpoints {
+            output.push_str(&generate_failure(endpoint));
+            output.push('\n');
+        }
+    }
+
+    output.push_str(r#"/** API client functor - provide your own HttpTransport implementation */
+module Make = (Http: HttpTransport) => {
+  /** Wait `ms` milliseconds */
+  let sleep = (ms: int): promise<unit> =>
+    Promise.make((resolve, _) => setTimeout(() => resolve(), ms)->ignore)
+
+  /** Send a request through the transport, enforcing the request size limit,
+   * tracking it in the config's `RequestScope` and giving up after `timeoutMs`
+   * (the config's unless given)
+   *
+   * The config's request interceptors run first, its response interceptors on
+   * the outcome, each in order. `onRequest` and `onResponse` observe the
+   * intercepted request and what the transport returned.
+   *
+   * On timeout the request's signal is aborted, cancelling it in transports that
+   * honor `req.signal`.
+   */
+  let sendRequest = async (
+    config: config,
+    ~timeoutMs: option<int>=?,
+    req: httpRequest,
+  ): result<httpResponse, apiError> => {
+    let req = await req->intercept(config.requestInterceptors)
+    config.onRequest->Option.forEach(f => f({method: req.method, url: req.url}))
+    let startedAt = Date.now()
+    let size = switch (config.maxRequestBytes, req.body) {
+    | (Some(_), Some(body)) => body->JSON.stringify->byteLength
+    | _ => 0
+    }
+    let send = config.transport->Option.getOr(Http.request)
+    let send = async req =>
+      switch timeoutMs->Option.orElse(config.timeoutMs) {
+      | Some(ms) =>
+        // A signal of its own, aborted on timeout or with the caller's
+        let controller = makeAbortController()
+        switch req.signal {
+        | Some(signal) if signal->aborted => controller->abort
+        | Some(signal) => signal->onAbort(() => controller->abort)
+        | None => ()
+        }
+        let timer = ref(None)
+        let timedOut = Promise.make((resolve, _) =>
+          timer := Some(setTimeout(() => {
+            controller->abort
+            resolve(Error({
+              status: 0,
+              message: `No response within ${ms->Int.toString}ms`,
+              body: None,
+              kind: Timeout({timeoutMs: ms}),
+            }))
+          }, ms))
+        )
+        let result = await Promise.race([send({...req, signal: Some(controller->abortSignal)}), timedOut])
+        timer.contents->Option.forEach(clearTimeout)
+        result
+      | None => await send(req)
+      }
+    let result = switch config.maxRequestBytes {
+    | Some(limit) if size > limit => Error(payloadTooLarge(~what="Request body", ~limit, ~size))
+    | _ =>
+      switch config.scope {
+      | Some(scope) =>
+        await scope->RequestScope.track(~signal=?req.signal, signal =>
+          send({...req, signal: Some(signal)})
+        )
+      | None => await send(req)
+      }
+    }
+    config.onResponse->Option.forEach(f =>
+      f({
+        method: req.method,
+        url: req.url,
+        status: switch result {
+        | Ok(res) => res.status
+        | Error(e) => e.status
+        },
+        durationMs: Date.now() -. startedAt,
+      })
+    )
+    await result->intercept(config.responseInterceptors)
+  }
+
+"#);
+
+    if config.generate_retry {
+        output.push_str(r#"  /** Send a request, retrying failures the config's `retry` policy
+   * allows with exponential backoff
+   */
+  let sendRetrying = async (
+    config: config,
+    ~timeoutMs: option<int>=?,
+    req: httpRequest,
+  ): result<httpResponse, apiError> => {
+    let policy = config.retry
+    let retryable = (e: apiError) =>
+      policy.retryMethods->Array.includes(req.method) &&
+        switch (e.status, e.kind) {
+        | (_, Some(Timeout(_))) => true
+        | (0, None) => true
+        | (status, _) => policy.retryStatuses->Array.includes(status)
+        }
+    let rec loop = async (attempt: int, delayMs: int) =>
+      switch await sendRequest(config, ~timeoutMs?, req) {
+      | Error(e) if attempt < policy.maxAttempts && retryable(e) =>
+        await sleep(delayMs)
+        await loop(attempt + 1, Math.Int.min(delayMs * 2, policy.maxDelayMs))
+      | result => result
+      }
+    await loop(1, Math.Int.min(policy.initialDelayMs, policy.maxDelayMs))
+  }
+
+"#);
+    }
+
+    if runtime.request_policy {
+        output.push_str(r#"  /** Network failures, timeouts and 5xx responses are worth retrying */
+  let isTransient = (e: apiError): bool =>
+    switch (e.status, e.kind) {
+    | (_, Some(Timeout(_))) => true
+    | (0, None) => true
+    | (status, _) => status >= 500
+    }
+
+  /** Send a request, giving up after `timeoutMs` and retrying transient failures
+   * `retries` times with exponential backoff (100ms, 200ms, ...)
+   */
+  let sendWithPolicy = async (
+    config: config,
+    req: httpRequest,
+    ~timeoutMs: option<int>=?,
+    ~retries: int=0,
+    (),
+  ): result<httpResponse, apiError> => {
+    let attempt = () => sendRequest(config, ~timeoutMs?, req)
+    let rec loop = async (remaining: int, delayMs: int) =>
+      switch await attempt() {
+      | Error(e) if remaining > 0 && isTransient(e) =>
+        await sleep(delayMs)
+        await loop(remaining - 1, delayMs * 2)
+      | result => result
+      }
+    await loop(retries, 100)
+  }
+
+"#);
+    }
+
+    if runtime.link_pagination {
+        output.push_str(r#"  /** Send a list request and decode it as a page whose `fetchNext` follows `Link: rel="next"` */
+  let rec fetchPage = async (
+    send: httpRequest => promise<result<httpResponse, apiError>>,
+    config: config,
+    req: httpRequest,
+    decode: Js.Json.t => 'a,
+  ): result<page<'a>, apiError> =>
+    switch await send(req) {
+    | Ok(res) =>
+      checkServerVersion(config, res)
+      switch decode(res.body) {
+      | data =>
+        let nextUrl =
+          res.headers
+          ->headerValue("Link")
+          ->Option.flatMap(header => parseLinkHeader(header, ~baseUrl=req.url)->Dict.get("next"))
+        let fetchNext = async () =>
+          switch nextUrl {
+          | Some(url) => Some(await fetchPage(send, config, {...req, url}, decode))
+          | None => None
+          }
+        Ok({data, nextUrl, fetchNext})
+      | exception Exn.Error(e) =>
+        Error({status: 0, message: Exn.message(e)->Option.getOr("Parse error"), body: Some(res.body)})
+      }
+    | Error(e) =>
+      notifyStatus(config, e)
+      Error(e)
+    }
+
+"#);
+    }
+
+    // Generate endpoint functions inside the functor
+    for endpoint in &spec.endpoints {
+        output.push_str(&generate_endpoint(endpoint, &runtime, config));
+        output.push('\n');
+        if config.generate_exn {
+            output.push_str(&generate_exn_wrapper(endpoint, "", &result_type(endpoint, config)));
+            output.push('\n');
+        }
+        if endpoint.link_pagination {
+            output.push_str(&generate_page_endpoint(endpoint, &runtime, config));
+            output.push('\n');
+            if config.generate_exn {
+                let page_type = format!("page<{}>", success_type(endpoint));
+                output.push_str(&generate_exn_wrapper(endpoint, "Page", &page_type));
+                output.push('\n');
+            }
+        }
+        if let Some(pagination) = endpoint.pagination.as_ref().filter(|_| config.generate_pagination) {
+            output.push_str(&generate_all_endpoint(endpoint, pagination, &runtime, config));
+            output.push('\n');
+            if config.generate_exn {
+                let items_type = format!("array<{}>", pagination.item.to_rescript());
+                output.push_str(&generate_exn_wrapper(endpoint, "All", &items_type));
+                output.push('\n');
+            }
+        }
+        if endpoint.stream {
+            output.push_str(&generate_stream_endpoint(endpoint, &runtime, config));
+            output.push('\n');
+        }
+        if let Some(data) = &endpoint.events {
+            output.push_str(&generate_events_endpoint(endpoint, data, &runtime, config));
+            output.push('\n');
+        }
+        if let Some(item) = &endpoint.ndjson {
+            output.push_str(&generate_items_endpoint(endpoint, item, &runtime, config));
+            output.push('\n');
+        }
+    }
+
+    if config.client_surface == ClientSurface::Instance {
+        output.push_str(&generate_instance(spec, config));
+    }
+
+    output.push_str("}\n\n");
+
+    // Default client using FetchClient
+    output.push_str("/** Default client using fetch */\n");
+    output.push_str("module Client = Make(FetchClient)\n\n");
+
+    if config.client_surface == ClientSurface::Instance {
+        output.push_str("/** Bind the default client's operations to a config */\n");
+        output.push_str("let make = Client.make\n\n");
+    }
+
+    // Generate aliases map (operationId -> path-based name)
     output.push_str("/** Operation aliases for convenience */\n");
     output.push_str("module Aliases = {\n");
     for endpoint in &spec.endpoints {
@@ -187,175 +1463,1683 @@ module Make = (Http: HttpClient) => {
             output.push_str(&format!("  let {} = Client.{}\n", alias, endpoint.operation_id));
         }
     }
-    output.push_str("}\n");
+    output.push_str("}\n\n");
+
+    // Metric names for monitoring labels (operationId -> tag.operation)
+    output.push_str("/** Stable operation names for metrics and tracing labels */\n");
+    output.push_str("module OperationNames = {\n");
+    for endpoint in &spec.endpoints {
+        output.push_str(&format!(
+            "  let {} = \"{}\"\n",
+            endpoint.operation_id, endpoint.metric_name
+        ));
+    }
+    output.push_str("\n  /** All operation names, in spec order */\n");
+    output.push_str("  let all = [\n");
+    for endpoint in &spec.endpoints {
+        output.push_str(&format!("    {},\n", endpoint.operation_id));
+    }
+    output.push_str("  ]\n");
+    output.push_str("}\n\n");
+
+    region::append(&mut output);
+
+    Ok(output)
+}
+
+/// JSON expression for the `body` argument, per `config.body_serializer`
+pub(crate) fn body_json(ty: &RsType, config: &Config) -> String {
+    match (config.body_serializer, ty) {
+        (BodySerializer::Stringify, _) => "body->Obj.magic".to_string(),
+        (BodySerializer::Schema, RsType::Named(type_name)) => format!("serialize{}(body)", type_name),
+        (BodySerializer::Schema, RsType::Json) => "body".to_string(),
+        (BodySerializer::Schema, ty) => {
+            format!("body->S.reverseConvertToJsonOrThrow({})", ty.to_schema())
+        }
+    }
+}
+
+pub(crate) fn generate_endpoint(endpoint: &Endpoint, runtime: &Runtime, config: &Config) -> String {
+    let mut output = String::new();
+
+    // Documentation
+    if let Some(doc) = endpoint_doc(endpoint) {
+        output.push_str(&doc_comment(&doc, "  "));
+    }
+    output.push_str(&endpoint_deprecation(endpoint, "  "));
+
+    let fn_name = &endpoint.operation_id;
+
+    // Build parameter list
+    let mut params = vec!["config: config".to_string()];
+    params.extend(labeled_params(endpoint));
+
+    let success_response = endpoint.success_response();
+    let return_type = result_type(endpoint, config);
+
+    output.push_str(&format!(
+        "  let {} = async ({}, ()): result<{}, {}> => {{\n",
+        fn_name,
+        params.join(", "),
+        return_type,
+        error_type(endpoint, config)
+    ));
+
+    output.push_str(&request_setup(endpoint, runtime, config));
+    // Transports only know Blobs, so an `ArrayBuffer` is read from one
+    let send = match success_response.and_then(|r| r.ty.as_ref()) {
+        Some(RsType::RawArrayBuffer) => format!("readArrayBuffer({})", send_call(endpoint, config)),
+        _ => send_call(endpoint, config),
+    };
+    let response = format!("await {}", send);
+    if let Some(decode) = decode_response(endpoint, config, &response, "    ") {
+        output.push_str(&format!("\n    {}\n  }}\n", decode));
+        return output;
+    }
+    output.push_str(&format!("\n    switch await {} {{\n", send));
+
+    // Parse response
+    output.push_str("    | Ok(res) =>\n");
+    output.push_str("      checkServerVersion(config, res)\n");
+    match success_response.map(|r| &r.ty) {
+        Some(Some(ty @ (RsType::Named(_) | RsType::Negotiated { .. }))) => {
+            output.push_str(&format!(
+                "      try {{\n        Ok({})\n      }} catch {{\n      | Exn.Error(e) => Error({{status: 0, message: Exn.message(e)->Option.getOr(\"Parse error\"), body: Some(res.body)}})\n      }}\n",
+                decode_json(ty, "res.body")
+            ));
+        }
+        Some(None) | None => output.push_str("      Ok()\n"),
+        Some(Some(RsType::RawText)) => output.push_str(
+            "      Ok({contentType: res.headers->headerValue(\"Content-Type\"), body: res.body->JSON.Decode.string->Option.getOr(\"\")})\n",
+        ),
+        Some(Some(RsType::RawBlob | RsType::RawArrayBuffer)) => output.push_str(
+            "      Ok({contentType: res.headers->headerValue(\"Content-Type\"), body: res.body->Obj.magic})\n",
+        ),
+        _ => output.push_str("      Ok(res.body->Obj.magic)\n"),
+    }
+
+    output.push_str("    | Error(e) =>\n");
+    output.push_str("      notifyStatus(config, e)\n");
+    output.push_str("      Error(e)\n");
+    output.push_str("    }\n");
+    output.push_str("  }\n");
+
+    output
+}
+
+/// `{operationId}Page`, the endpoint's result wrapped in a `page` with a `fetchNext` continuation
+fn generate_page_endpoint(endpoint: &Endpoint, runtime: &Runtime, config: &Config) -> String {
+    let mut output = String::new();
+    let fn_name = &endpoint.operation_id;
+
+    output.push_str(&format!(
+        "  /** `{}` as one page of results, with `fetchNext` following the `Link` header's `rel=\"next\"` URL */\n",
+        fn_name
+    ));
+    let mut params = vec!["config: config".to_string()];
+    params.extend(labeled_params(endpoint));
+    output.push_str(&format!(
+        "  let {}Page = async ({}, ()): result<page<{}>, apiError> => {{\n",
+        fn_name,
+        params.join(", "),
+        success_type(endpoint)
+    ));
+
+    output.push_str(&request_setup(endpoint, runtime, config));
+
+    let success = endpoint.success_response();
+    let decode = match success.and_then(|r| r.ty.as_ref()) {
+        Some(RsType::Named(type_name)) => format!("parse{}", type_name),
+        Some(RsType::Json) | None => "json => json".to_string(),
+        Some(ty) => format!("json => S.parseJsonOrThrow(json, {})", ty.to_schema()),
+    };
+    output.push_str(&format!(
+        "\n    await fetchPage(req => {}, config, req, {})\n",
+        send_call(endpoint, config),
+        decode
+    ));
+    output.push_str("  }\n");
+
+    output
+}
+
+/// `{operationId}EachPage`, requesting page after page of a list and handing each
+/// page's items to `~onPage`, and `{operationId}All` collecting them
+///
+/// `fetchFrom` takes the page, offset or cursor argument, shadowing it so the
+/// request is built as in the endpoint itself. Paging stops after `~maxPages`,
+/// and for page numbers and offsets when a page repeats the previous one, so a
+/// server ignoring the parameter can't loop forever.
+fn generate_all_endpoint(
+    endpoint: &Endpoint,
+    pagination: &Pagination,
+    runtime: &Runtime,
+    config: &Config,
+) -> String {
+    let mut output = String::new();
+    let fn_name = &endpoint.operation_id;
+    let required = |name: &str| endpoint.parameters.iter().any(|p| p.name == name && p.required);
+    // The argument for a value, and the value passed (or `default` when omitted)
+    let wrap = |name: &str, value: String| {
+        if required(name) { value } else { format!("Some({})", value) }
+    };
+    let current = |name: &str, default: &str| {
+        if required(name) {
+            name.to_string()
+        } else {
+            format!("{}->Option.getOr({})", name, default)
+        }
+    };
+    // A page shorter than the requested size is the last one
+    let short = |size: &Option<String>| match size {
+        Some(size) if required(size) => format!(" || received->Array.length < {}", size),
+        Some(size) => format!(
+            " || {}->Option.mapOr(false, size => received->Array.length < size)",
+            size
+        ),
+        None => String::new(),
+    };
+    let more = "maxPages->Option.mapOr(true, max => pagesFetched.contents < max)";
+    let done = "maxPages->Option.mapOr(false, max => pagesFetched.contents >= max)";
+    let numbered = |size: &Option<String>, next: String| {
+        format!(
+            "if lastBody.contents == Some(res.body->JSON.stringify) {{\n  Ok()\n}} else {{\n  lastBody := Some(res.body->JSON.stringify)\n  pagesFetched := pagesFetched.contents + 1\n  onPage(received)\n  if received->Array.length == 0{} || {} {{\n    Ok()\n  }} else {{\n    await fetchFrom({})\n  }}\n}}",
+            short(size),
+            done,
+            next
+        )
+    };
+
+    let (doc, arg, start, next) = match &pagination.kind {
+        PaginationKind::Page { page, size } => (
+            format!(
+                "requesting pages from `~{}` (else 1) until one comes back short, empty or repeated",
+                page
+            ),
+            page,
+            wrap(page, current(page, "1")),
+            numbered(size, wrap(page, format!("{} + 1", current(page, "1")))),
+        ),
+        PaginationKind::Offset { offset, limit } => (
+            format!(
+                "advancing `~{}` past the items received until a page comes back short, empty or repeated",
+                offset
+            ),
+            offset,
+            offset.clone(),
+            numbered(
+                limit,
+                wrap(offset, format!("{} + received->Array.length", current(offset, "0"))),
+            ),
+        ),
+        PaginationKind::Cursor { cursor, next: (field, ty) } => {
+            let (next, optional) = flatten_optional(&format!("data.{}", field), ty);
+            let next = if optional { next } else { format!("Some({})", next) };
+            let changed = if required(cursor) {
+                format!("next != {}", cursor)
+            } else {
+                format!("Some(next) != {}", cursor)
+            };
+            (
+                format!("sending back `{}` as `~{}` until the response has none", field, cursor),
+                cursor,
+                cursor.clone(),
+                format!(
+                    "pagesFetched := pagesFetched.contents + 1\nonPage(received)\nswitch {} {{\n| Some(next) if next != \"\" && {} && {} => await fetchFrom({})\n| _ => Ok()\n}}",
+                    next,
+                    changed,
+                    more,
+                    wrap(cursor, "next".to_string())
+                ),
+            )
+        }
+    };
+    let received = match &pagination.items_field {
+        Some((field, ty)) => match flatten_optional(&format!("data.{}", field), ty) {
+            (items, true) => format!("{}->Option.getOr([])", items),
+            (items, false) => items,
+        },
+        None => "data".to_string(),
+    };
+    let items_type = pagination.item.to_rescript();
+    let success = endpoint.success_response().and_then(|r| r.ty.as_ref());
+
+    output.push_str(&format!(
+        "  /** Each page of `{}`, {}, or until `~maxPages`; `onPage` gets the items of each */\n",
+        fn_name, doc
+    ));
+    output.push_str(&endpoint_deprecation(endpoint, "  "));
+    let mut params = vec!["config: config".to_string()];
+    params.extend(labeled_params(endpoint));
+    params.push("~maxPages: option<int>=?".to_string());
+    params.push(format!("~onPage: array<{}> => unit", items_type));
+    output.push_str(&format!(
+        "  let {}EachPage = async ({}, ()): result<unit, apiError> => {{\n",
+        fn_name,
+        params.join(", ")
+    ));
+    // Warn once about deprecated arguments, not about the pages requested
+    output.push_str(&deprecation_warnings(endpoint));
+    output.push_str("    let pagesFetched = ref(0)\n");
+    if !matches!(pagination.kind, PaginationKind::Cursor { .. }) {
+        output.push_str("    let lastBody = ref(None)\n");
+    }
+    output.push_str(&format!(
+        "    let rec fetchFrom = async ({}): result<unit, apiError> => {{\n",
+        arg
+    ));
+    for line in request_build(endpoint, runtime, config).lines() {
+        match line {
+            "" => output.push('\n'),
+            line => output.push_str(&format!("  {}\n", line)),
+        }
+    }
+    output.push_str(&format!("\n      switch await {} {{\n", send_call(endpoint, config)));
+    output.push_str("      | Ok(res) =>\n");
+    output.push_str("        checkServerVersion(config, res)\n");
+    output.push_str(&format!(
+        "        switch {} {{\n",
+        success.map_or("res.body".to_string(), |ty| decode_json(ty, "res.body"))
+    ));
+    output.push_str("        | data =>\n");
+    output.push_str(&format!("          let received = {}\n", received));
+    for line in next.lines() {
+        output.push_str(&format!("          {}\n", line));
+    }
+    output.push_str("        | exception Exn.Error(e) =>\n");
+    output.push_str(
+        "          Error({status: 0, message: Exn.message(e)->Option.getOr(\"Parse error\"), body: Some(res.body)})\n",
+    );
+    output.push_str("        }\n");
+    output.push_str("      | Error(e) =>\n");
+    output.push_str("        notifyStatus(config, e)\n");
+    output.push_str("        Error(e)\n");
+    output.push_str("      }\n");
+    output.push_str("    }\n");
+    output.push_str(&format!("    await fetchFrom({})\n", start));
+    output.push_str("  }\n\n");
+
+    output.push_str(&format!(
+        "  /** Every item of `{}`, collected from `{}EachPage` */\n",
+        fn_name, fn_name
+    ));
+    output.push_str(&endpoint_deprecation(endpoint, "  "));
+    let (params, args) = all_params(endpoint);
+    output.push_str(&format!(
+        "  let {}All = async ({}, ()): result<array<{}>, apiError> => {{\n",
+        fn_name,
+        params.join(", "),
+        items_type
+    ));
+    output.push_str("    let collected = []\n");
+    output.push_str(&format!(
+        "    (await {}EachPage({}, ~onPage=received => collected->Array.pushMany(received), ()))->Result.map(() => collected)\n",
+        fn_name,
+        args.join(", ")
+    ));
+    output.push_str("  }\n");
+
+    output
+}
+
+/// Parameters of `{operationId}All` after the leading `config`, and the arguments
+/// passing them on, both starting with `config`
+fn all_params(endpoint: &Endpoint) -> (Vec<String>, Vec<String>) {
+    let mut params = vec!["config: config".to_string()];
+    params.extend(labeled_params(endpoint));
+    params.push("~maxPages: option<int>=?".to_string());
+    let mut args = vec!["config".to_string()];
+    args.extend(forwarded_args(endpoint));
+    args.push("~maxPages?".to_string());
+    (params, args)
+}
+
+/// `expr` of type `ty` with nested options merged into one, and whether it is an option
+fn flatten_optional(expr: &str, ty: &RsType) -> (String, bool) {
+    match ty {
+        RsType::Option(inner) | RsType::Nullable(inner) => match flatten_optional(expr, inner) {
+            (inner, true) => (format!("{}->Option.flatMap(v => v)", inner), true),
+            (inner, false) => (inner, true),
+        },
+        _ => (expr.to_string(), false),
+    }
+}
+
+/// A function reading an operation's body as it arrives, e.g. `{operationId}Events`
+struct Streaming {
+    suffix: &'static str,
+    doc: String,
+    /// Labeled callback parameter, e.g. `~onChunk: Uint8Array.t => unit`
+    callback: String,
+    /// `Accept` header sent with the request
+    accept: Option<&'static str>,
+    /// Statements reading `res.body`, ending with the `result<unit, apiError>`
+    read: String,
+}
+
+/// `{operationId}Stream`, handing the body to `~onChunk` as it arrives instead of buffering it
+fn generate_stream_endpoint(endpoint: &Endpoint, runtime: &Runtime, config: &Config) -> String {
+    let streaming = Streaming {
+        suffix: "Stream",
+        doc: format!(
+            "`{}` with the body read incrementally: `onChunk` gets each chunk of bytes as it arrives",
+            endpoint.operation_id
+        ),
+        callback: "~onChunk: Uint8Array.t => unit".to_string(),
+        accept: None,
+        read: "await forEachChunk(bodyStream(res.body), onChunk)\n".to_string(),
+    };
+    generate_streaming_endpoint(endpoint, &streaming, runtime, config)
+}
+
+/// `{operationId}Events`, subscribing to a `text/event-stream` response and decoding
+/// each event's `data` as it arrives
+fn generate_events_endpoint(
+    endpoint: &Endpoint,
+    data: &RsType,
+    runtime: &Runtime,
+    config: &Config,
+) -> String {
+    let read = match data {
+        RsType::String => "await forEachServerEvent(bodyStream(res.body), onEvent)\n".to_string(),
+        ty => decode_each(
+            "forEachServerEvent",
+            "e",
+            "",
+            &decode_json(ty, "JSON.parseExn(e.data)"),
+            "onEvent({event: e.event, id: e.id, data})",
+        ),
+    };
+    let streaming = Streaming {
+        suffix: "Events",
+        doc: format!(
+            "`{}` as Server-Sent Events: `onEvent` gets each event, its `data` decoded, until the stream ends",
+            endpoint.operation_id
+        ),
+        callback: format!("~onEvent: serverEvent<{}> => unit", data.to_rescript()),
+        accept: Some("text/event-stream"),
+        read,
+    };
+    generate_streaming_endpoint(endpoint, &streaming, runtime, config)
+}
+
+/// `{operationId}Items`, reading a newline-delimited JSON response and decoding each
+/// line as it arrives
+fn generate_items_endpoint(
+    endpoint: &Endpoint,
+    item: &RsType,
+    runtime: &Runtime,
+    config: &Config,
+) -> String {
+    let streaming = Streaming {
+        suffix: "Items",
+        doc: format!(
+            "`{}` as newline-delimited JSON: `onItem` gets each line, decoded, as it arrives",
+            endpoint.operation_id
+        ),
+        callback: format!("~onItem: {} => unit", item.to_rescript()),
+        accept: Some("application/x-ndjson"),
+        read: decode_each(
+            "forEachLine",
+            "line",
+            " && line->String.trim != \"\"",
+            &decode_json(item, "JSON.parseExn(line)"),
+            "onItem(data)",
+        ),
+    };
+    generate_streaming_endpoint(endpoint, &streaming, runtime, config)
+}
+
+/// Read a body with `reader`, decoding each `param` and passing it as `data` to `deliver`
+///
+/// The first decode failure becomes the `Error` and skips the rest of the body;
+/// exceptions raised by the callback in `deliver` are not caught.
+fn decode_each(reader: &str, param: &str, condition: &str, decode: &str, deliver: &str) -> String {
+    let mut output = String::from("let failure = ref(None)\n");
+    output.push_str(&format!("let read = await {}(bodyStream(res.body), {} =>\n", reader, param));
+    output.push_str(&format!("  if failure.contents->Option.isNone{} {{\n", condition));
+    output.push_str(&format!("    switch {} {{\n", decode));
+    output.push_str(&format!("    | data => {}\n", deliver));
+    output.push_str("    | exception Exn.Error(e) =>\n");
+    output.push_str(
+        "      failure := Some({status: 0, message: Exn.message(e)->Option.getOr(\"Parse error\"), body: None})\n",
+    );
+    output.push_str("    }\n");
+    output.push_str("  }\n");
+    output.push_str(")\n");
+    output.push_str("switch failure.contents {\n");
+    output.push_str("| Some(e) => Error(e)\n");
+    output.push_str("| None => read\n");
+    output.push_str("}\n");
+    output
+}
+
+/// A [`Streaming`] function: the request, then `read` once the response arrives
+fn generate_streaming_endpoint(
+    endpoint: &Endpoint,
+    streaming: &Streaming,
+    runtime: &Runtime,
+    config: &Config,
+) -> String {
+    let mut output = format!("  /** {} */\n", streaming.doc);
+    output.push_str(&endpoint_deprecation(endpoint, "  "));
+    let mut params = vec!["config: config".to_string()];
+    params.extend(labeled_params(endpoint));
+    params.push(streaming.callback.clone());
+    output.push_str(&format!(
+        "  let {}{} = async ({}, ()): result<unit, apiError> => {{\n",
+        endpoint.operation_id,
+        streaming.suffix,
+        params.join(", ")
+    ));
+
+    output.push_str(&request_setup(endpoint, runtime, config));
+    if let Some(accept) = streaming.accept {
+        output.push_str(&format!("    req.headers->Dict.set(\"Accept\", \"{}\")\n", accept));
+    }
+    output.push_str("    let req = {...req, responseType: #blob, stream: true}\n");
+    output.push_str(&format!("\n    switch await {} {{\n", send_call(endpoint, config)));
+    output.push_str("    | Ok(res) =>\n");
+    output.push_str("      checkServerVersion(config, res)\n");
+    for line in streaming.read.lines() {
+        output.push_str(&format!("      {}\n", line));
+    }
+    output.push_str("    | Error(e) =>\n");
+    output.push_str("      notifyStatus(config, e)\n");
+    output.push_str("      Error(e)\n");
+    output.push_str("    }\n");
+    output.push_str("  }\n");
+
+    output
+}
+
+/// `{operationId}{suffix}Exn`, returning the wrapped function's `Ok` value and raising
+/// `ApiError` with its `Error`
+fn generate_exn_wrapper(endpoint: &Endpoint, suffix: &str, return_type: &str) -> String {
+    let name = format!("{}{}", endpoint.operation_id, suffix);
+    let (params, mut args) = if suffix == "All" {
+        all_params(endpoint)
+    } else {
+        let mut params = vec!["config: config".to_string()];
+        params.extend(labeled_params(endpoint));
+        let mut args = vec!["config".to_string()];
+        args.extend(forwarded_args(endpoint));
+        (params, args)
+    };
+    args.push("()".to_string());
+
+    format!(
+        "  /** `{name}`, raising `ApiError` instead of returning `Error` */\n{deprecation}  let {name}Exn = async ({params}, ()): {return_type} =>\n    switch await {name}({args}) {{\n    | Ok(value) => value\n    | Error(e) => raise(ApiError(e))\n    }}\n",
+        name = name,
+        deprecation = endpoint_deprecation(endpoint, "  "),
+        params = params.join(", "),
+        return_type = return_type,
+        args = args.join(", "),
+    )
+}
+
+/// Statements of an endpoint function that build `req`, from deprecation warnings
+/// through path, query, headers and body
+fn request_setup(endpoint: &Endpoint, runtime: &Runtime, config: &Config) -> String {
+    deprecation_warnings(endpoint) + &request_build(endpoint, runtime, config)
+}
+
+/// Soft warnings for deprecated parameters that were supplied
+fn deprecation_warnings(endpoint: &Endpoint) -> String {
+    let mut output = String::new();
+    for p in endpoint.parameters.iter().filter(|p| p.deprecated) {
+        let warn = format!(
+            "warnDeprecatedParam(config, \"{}\", \"{}\")",
+            endpoint.operation_id, p.name
+        );
+        if p.required || p.location == ParameterLocation::Path {
+            output.push_str(&format!("    {}\n", warn));
+        } else {
+            output.push_str(&format!(
+                "    if {}->Option.isSome {{\n      {}\n    }}\n",
+                p.name, warn
+            ));
+        }
+    }
+    output
+}
+
+/// Statements building `req` from the arguments: path, query, headers and body
+fn request_build(endpoint: &Endpoint, runtime: &Runtime, config: &Config) -> String {
+    let mut output = String::new();
+
+    // Collect parameters by location
+    let path_params: Vec<_> = endpoint.parameters.iter()
+        .filter(|p| matches!(p.location, ParameterLocation::Path))
+        .collect();
+    let query_params: Vec<_> = endpoint.parameters.iter()
+        .filter(|p| matches!(p.location, ParameterLocation::Query))
+        .collect();
+    let header_params: Vec<_> = endpoint.parameters.iter()
+        .filter(|p| matches!(p.location, ParameterLocation::Header))
+        .collect();
+
+    let success_response = endpoint.success_response();
+
+    // Build path with interpolation
+    let path = build_path(&endpoint.path, &path_params);
+    output.push_str(&format!("    let path = {}\n", path));
+
+    // Build query dict
+    output.push_str("    let query = []\n");
+    for p in &query_params {
+        if p.required {
+            output.push_str(&format!("    {}\n", query_set(p, &p.name)));
+        } else {
+            output.push_str(&format!(
+                "    switch {} {{ | Some(v) => {} | None => () }}\n",
+                p.name,
+                query_set(p, "v")
+            ));
+        }
+    }
+
+    // Build headers dict and apply authentication
+    output.push_str("    let headers = Dict.fromArray(config.headers->Dict.toArray)\n");
+    // Fetch sets the multipart Content-Type itself, with the part boundary
+    if endpoint.request_body.as_ref().is_none_or(|b| b.form_type().is_none()) {
+        output.push_str("    headers->Dict.set(\"Content-Type\", \"application/json\")\n");
+    }
+    if let Some(apply) = auth_call(endpoint, runtime) {
+        output.push_str(&format!("    await {}\n", apply));
+    }
+
+    for p in &header_params {
+        if p.required {
+            let value = param_text(p, &p.name);
+            output.push_str(&format!(
+                "    headers->Dict.set(\"{}\", {})\n",
+                p.original_name, value
+            ));
+        } else {
+            let value = param_text(p, "v");
+            output.push_str(&format!(
+                "    switch {} {{ | Some(v) => headers->Dict.set(\"{}\", {}) | None => () }}\n",
+                p.name, p.original_name, value
+            ));
+        }
+    }
+
+    for p in endpoint.parameters.iter().filter(|p| p.location == ParameterLocation::Cookie) {
+        if p.required {
+            output.push_str(&format!(
+                "    headers->addCookie(\"{}\", {})\n",
+                p.original_name,
+                param_text(p, &p.name)
+            ));
+        } else {
+            output.push_str(&format!(
+                "    switch {} {{ | Some(v) => headers->addCookie(\"{}\", {}) | None => () }}\n",
+                p.name,
+                p.original_name,
+                param_text(p, "v")
+            ));
+        }
+    }
+    if accept_type(endpoint).is_some() {
+        output.push_str("    headers->Dict.set(\"Accept\", (accept :> string))\n");
+    }
+
+    // Build request body
+    let (body_expr, form) = match &endpoint.request_body {
+        Some(body) if body.is_multipart() => {
+            output.push_str("    let form = makeFormData()\n");
+            for field in &body.form {
+                output.push_str(&format!("    {}\n", form_append(field)));
+            }
+            ("None".to_string(), "      form,\n")
+        }
+        Some(body) => match &body.choice {
+            Some(choice) => {
+                output.push_str("    let (body, form) = switch body {\n");
+                output.push_str("    | Json(body) =>\n");
+                output.push_str("      headers->Dict.set(\"Content-Type\", \"application/json\")\n");
+                let json = body_json(&choice.json, config);
+                output.push_str(&format!("      (Some({}), None)\n", json));
+                output.push_str("    | Multipart(body) =>\n");
+                output.push_str("      let form = makeFormData()\n");
+                for field in &body.form {
+                    output.push_str(&format!("      {}\n", form_append(field)));
+                }
+                output.push_str("      (None, Some(form))\n");
+                output.push_str("    }\n");
+                ("body".to_string(), "      form: ?form,\n")
+            }
+            None => (format!("Some({})", body_json(&body.ty, config)), ""),
+        },
+        None => ("None".to_string(), ""),
+    };
+
+    let response_type = match success_response.map(|r| r.ty.as_ref()) {
+        Some(Some(RsType::RawText)) => "#text".to_string(),
+        Some(Some(RsType::RawBlob)) => "#blob".to_string(),
+        Some(Some(RsType::RawArrayBuffer)) => "#blob".to_string(),
+        // Read the way the requested media type is decoded
+        Some(Some(RsType::Negotiated { media, .. })) => {
+            let arms: Vec<String> = media
+                .iter()
+                .map(|(media_type, kind)| {
+                    let read = match kind {
+                        MediaKind::Json => "#json",
+                        MediaKind::Text => "#text",
+                        MediaKind::Blob => "#blob",
+                    };
+                    format!("| #{} => {}", string_literal(media_type), read)
+                })
+                .collect();
+            format!("switch accept {{ {} }}", arms.join(" "))
+        }
+        // A success without content (e.g. 204) is read as JSON and ignored
+        _ => "#json".to_string(),
+    };
+
+    // Make request (polymorphic variant for Fetch API)
+    let method = match endpoint.method {
+        HttpMethod::Get => "#GET",
+        HttpMethod::Post => "#POST",
+        HttpMethod::Put => "#PUT",
+        HttpMethod::Patch => "#PATCH",
+        HttpMethod::Delete => "#DELETE",
+        HttpMethod::Head => "#HEAD",
+        HttpMethod::Options => "#OPTIONS",
+    };
+
+    output.push_str(&format!(r#"
+    let req: httpRequest = {{
+      method: {},
+      url: buildUrl(config.baseUrl, path, query),
+      headers,
+      body: {},
+      maxResponseBytes: config.maxResponseBytes,
+      responseType: {},
+      signal,
+      credentials: config.credentials,
+{}    }}
+"#, method, body_expr, response_type, form));
+
+    output
+}
+
+/// Statement adding a form field's parts to `form`, skipped when an optional field is unset
+fn form_append(field: &FormField) -> String {
+    let append = |var: &str| match &field.kind {
+        FormFieldKind::File => format!("form->appendFormFile(\"{}\", {})", field.name, var),
+        FormFieldKind::Files => format!(
+            "{}->Array.forEach(file => form->appendFormFile(\"{}\", file))",
+            var, field.name
+        ),
+        // Arrays of scalars repeat the part, like a multi-select form control
+        FormFieldKind::Value(RsType::Array(item)) if form_text(item, "item").is_some() => format!(
+            "{}->Array.forEach(item => form->appendFormValue(\"{}\", {}))",
+            var,
+            field.name,
+            form_text(item, "item").unwrap_or_default()
+        ),
+        FormFieldKind::Value(ty) => {
+            let text = match ty {
+                RsType::Json => format!("{}->JSON.stringify", var),
+                _ => form_text(ty, var).unwrap_or_else(|| {
+                    let schema = ty.to_schema();
+                    format!("{}->S.reverseConvertToJsonOrThrow({})->JSON.stringify", var, schema)
+                }),
+            };
+            format!("form->appendFormValue(\"{}\", {})", field.name, text)
+        }
+    };
+    if field.required {
+        append(&format!("body.{}", field.field))
+    } else {
+        format!("switch body.{} {{ | Some(v) => {} | None => () }}", field.field, append("v"))
+    }
+}
+
+/// A scalar form value or query array item as text; `None` for values sent as JSON
+fn form_text(ty: &RsType, var: &str) -> Option<String> {
+    match ty {
+        RsType::String
+        | RsType::Int
+        | RsType::Float
+        | RsType::Bool
+        | RsType::DateTime
+        | RsType::Date
+        | RsType::BigInt
+        | RsType::Int64String
+        | RsType::StringEnum(_) => Some(wire_string(ty, var)),
+        _ => None,
+    }
+}
+
+/// Call adding the credentials an endpoint's `security` asks for, `None` when it sends none
+///
+/// Without any declared requirements, every credential the config holds is sent.
+fn auth_call(endpoint: &Endpoint, runtime: &Runtime) -> Option<String> {
+    if !runtime.auth {
+        return None;
+    }
+    let Some(security) = &endpoint.security else {
+        let mut schemes: Vec<String> = Vec::new();
+        for scheme in runtime.security_schemes.iter().filter_map(auth_scheme) {
+            if !schemes.contains(&scheme) {
+                schemes.push(scheme);
+            }
+        }
+        return Some(format!(
+            "applyConfiguredAuth(headers, query, config.auth, [{}])",
+            schemes.join(", ")
+        ));
+    };
+    let mut requirements: Vec<Vec<String>> = Vec::new();
+    for requirement in security {
+        // A requirement naming a scheme the client can't send is never satisfied
+        let schemes: Option<Vec<String>> = requirement
+            .iter()
+            .map(|name| auth_scheme(runtime.security_schemes.iter().find(|s| &s.name == name)?))
+            .collect();
+        let Some(mut schemes) = schemes else { continue };
+        schemes.sort_unstable();
+        schemes.dedup();
+        if !requirements.contains(&schemes) {
+            requirements.push(schemes);
+        }
+    }
+    if requirements.iter().all(|r| r.is_empty()) {
+        return None;
+    }
+    let requirements: Vec<String> = requirements
+        .iter()
+        .map(|r| format!("[{}]", r.join(", ")))
+        .collect();
+    Some(format!("applyAuth(headers, query, config.auth, [{}])", requirements.join(", ")))
+}
+
+/// `authScheme` value for a security scheme, `None` if the client can't send it
+fn auth_scheme(scheme: &SecurityScheme) -> Option<String> {
+    match &scheme.kind {
+        SecuritySchemeKind::Bearer
+        | SecuritySchemeKind::OAuth2 { .. }
+        | SecuritySchemeKind::OpenIdConnect => Some("Bearer".to_string()),
+        SecuritySchemeKind::ApiKey { name, location } => Some(format!(
+            "ApiKey({{scheme: {}, name: {}, location: #{}}})",
+            string_literal(&scheme.name),
+            string_literal(name),
+            match location {
+                ParameterLocation::Query => "query",
+                ParameterLocation::Cookie => "cookie",
+                _ => "header",
+            }
+        )),
+        SecuritySchemeKind::Unsupported => None,
+    }
+}
+
+/// Optional runtime helpers, included only when some generated endpoint needs them
+pub(crate) struct Runtime {
+    /// `authConfig`, auth arguments to `makeConfig` and `applyAuth`
+    auth: bool,
+    /// Declared security schemes, which endpoints' requirements are resolved against
+    security_schemes: Vec<SecurityScheme>,
+    /// The `Auth` module alias and `~tokenStore` on `makeConfig`, for OAuth2 schemes
+    oauth2: bool,
+    /// `rawBody` for wildcard media type responses
+    raw_body: bool,
+    /// `negotiated` and `mediaType`, for success bodies offered in several media types
+    negotiation: bool,
+    /// `formData` bindings and `httpRequest.form`, for `multipart/form-data` bodies
+    multipart: bool,
+    /// `Wire` formatting for non-string path, query and header values
+    wire: bool,
+    /// `QueryStyle`, for object and non-scalar array query parameters, and `deepObject`
+    query_styles: bool,
+    /// `addCookie`, for cookie parameters (also used by `auth`)
+    cookies: bool,
+    /// `warnDeprecatedParam`
+    deprecated_params: bool,
+    /// `sendWithPolicy`, for `x-timeout-ms` / `x-retries`
+    request_policy: bool,
+    /// `page`, `parseLinkHeader` and `fetchPage`, for `Link`-paginated lists
+    link_pagination: bool,
+    /// `blobArrayBuffer`, for binary responses read as `ArrayBuffer`
+    array_buffer: bool,
+    /// `readableStream`, `bodyStream` and `forEachChunk`, for `x-rescript-stream` and event stream operations
+    stream: bool,
+    /// `serverEvent` and `forEachServerEvent`, for `text/event-stream` responses
+    events: bool,
+    /// `forEachLine`, for newline-delimited JSON responses (also used by `events`)
+    ndjson: bool,
+    /// `apiError.problem` and `decodeProblem`, for `application/problem+json` errors
+    problem_details: bool,
+}
+
+impl Runtime {
+    pub(crate) fn for_spec(spec: &ApiSpec) -> Self {
+        let params = || spec.endpoints.iter().flat_map(|e| e.parameters.iter());
+        Self {
+            auth: spec.has_security,
+            security_schemes: spec.security_schemes.clone(),
+            oauth2: spec.has_oauth2(),
+            raw_body: spec
+                .endpoints
+                .iter()
+                .flat_map(|e| e.responses.iter())
+                .any(|r| r.ty.as_ref().is_some_and(RsType::is_raw_body)),
+            array_buffer: spec
+                .endpoints
+                .iter()
+                .flat_map(|e| e.responses.iter())
+                .any(|r| matches!(r.ty, Some(RsType::RawArrayBuffer))),
+            negotiation: spec
+                .endpoints
+                .iter()
+                .flat_map(|e| e.responses.iter())
+                .any(|r| matches!(r.ty, Some(RsType::Negotiated { .. }))),
+            multipart: spec.endpoints.iter().any(|e| {
+                e.request_body.as_ref().is_some_and(|b| b.form_type().is_some())
+            }),
+            wire: params()
+                .any(|p| match p.location {
+                    ParameterLocation::Path => path_segment(p).contains("Wire."),
+                    ParameterLocation::Query => query_set(p, "v").contains("Wire."),
+                    _ => param_text(p, "v").contains("Wire."),
+                })
+                || spec
+                    .endpoints
+                    .iter()
+                    .filter_map(|e| e.request_body.as_ref())
+                    .flat_map(|b| b.form.iter())
+                    .any(|f| form_append(f).contains("Wire.")),
+            query_styles: params()
+                .filter(|p| p.location == ParameterLocation::Query)
+                .any(|p| query_set(p, "v").contains("QueryStyle.")),
+            cookies: params().any(|p| p.location == ParameterLocation::Cookie),
+            deprecated_params: params().any(|p| p.deprecated),
+            request_policy: spec.endpoints.iter().any(has_request_policy),
+            link_pagination: spec.endpoints.iter().any(|e| e.link_pagination),
+            stream: spec
+                .endpoints
+                .iter()
+                .any(|e| e.stream || e.events.is_some() || e.ndjson.is_some()),
+            events: spec.endpoints.iter().any(|e| e.events.is_some()),
+            ndjson: spec.endpoints.iter().any(|e| e.ndjson.is_some()),
+            problem_details: spec.problem_details,
+        }
+    }
+}
 
-    Ok(output)
+/// Field of a tag's operations in the `make` record, cleaned up like record
+/// fields (`type` -> `type_`, `2fa` -> `_2fa`); `default` when untagged or
+/// nothing usable is left
+fn group_name(tag: Option<&str>) -> String {
+    match tag.map(|tag| sanitize_field_name(tag, FieldCase::Camel)) {
+        Some(name) if name.chars().any(|c| c != '_') => name,
+        _ => "default".to_string(),
+    }
 }
 
-fn generate_endpoint(endpoint: &Endpoint, _config: &Config) -> String {
+/// Generate `make(~config)` and the record types it returns
+///
+/// Operations are grouped by their first tag (`default` when untagged), so
+/// callers write `api.pet.getPetById(~petId, ())`.
+fn generate_instance(spec: &ApiSpec, config: &Config) -> String {
+    let mut groups: Vec<(String, Vec<&Endpoint>)> = Vec::new();
+    for endpoint in &spec.endpoints {
+        let group = group_name(endpoint.tags.first().map(String::as_str));
+        match groups.iter_mut().find(|(name, _)| *name == group) {
+            Some((_, endpoints)) => endpoints.push(endpoint),
+            None => groups.push((group, vec![endpoint])),
+        }
+    }
+
     let mut output = String::new();
 
-    // Documentation
-    if let Some(doc) = &endpoint.doc {
-        output.push_str(&format!("  /** {} */\n", doc));
+    for (group, endpoints) in &groups {
+        output.push_str(&format!("  /** Operations tagged `{}` */\n", group));
+        output.push_str(&format!("  type {}Api = {{\n", group));
+        for endpoint in endpoints {
+            let mut args: Vec<String> = Vec::new();
+            for p in endpoint.parameters.iter().filter(|p| p.location == ParameterLocation::Path) {
+                args.push(format!("~{}: {}", p.name, param_type(p)));
+            }
+            if let Some(body) = &endpoint.request_body {
+                args.push(format!("~body: {}", body.ty.to_rescript()));
+            }
+            for location in LABELED_LOCATIONS {
+                for p in endpoint.parameters.iter().filter(|p| p.location == location) {
+                    let optional = if p.required { "" } else { "=?" };
+                    args.push(format!("~{}: {}{}", p.name, param_type(p), optional));
+                }
+            }
+            if let Some((ty, _)) = accept_type(endpoint) {
+                args.push(format!("~accept: {}=?", ty));
+            }
+            for (name, _) in policy_params(endpoint) {
+                args.push(format!("~{}: int=?", name));
+            }
+            if endpoint.timeout_ms.is_none() {
+                args.push("~timeoutMs: int=?".to_string());
+            }
+            args.push("~signal: abortSignal=?".to_string());
+            args.push("unit".to_string());
+            output.push_str(&format!(
+                "    {}{}: ({}) => promise<result<{}, {}>>,\n",
+                endpoint_deprecation(endpoint, "").replace('\n', " "),
+                endpoint.operation_id,
+                args.join(", "),
+                result_type(endpoint, config),
+                error_type(endpoint, config)
+            ));
+        }
+        output.push_str("  }\n\n");
     }
 
-    let fn_name = &endpoint.operation_id;
+    output.push_str("  /** Every operation, grouped by tag */\n");
+    output.push_str("  type api = {\n");
+    for (group, _) in &groups {
+        output.push_str(&format!("    {}: {}Api,\n", group, group));
+    }
+    output.push_str("  }\n\n");
 
-    // Collect parameters by location
-    let path_params: Vec<_> = endpoint.parameters.iter()
-        .filter(|p| matches!(p.location, ParameterLocation::Path))
-        .collect();
-    let query_params: Vec<_> = endpoint.parameters.iter()
-        .filter(|p| matches!(p.location, ParameterLocation::Query))
-        .collect();
-    let header_params: Vec<_> = endpoint.parameters.iter()
-        .filter(|p| matches!(p.location, ParameterLocation::Header))
-        .collect();
+    output.push_str("  /** Bind every operation to a config */\n");
+    output.push_str("  let make = (~config: config): api => {\n");
+    for (group, endpoints) in &groups {
+        output.push_str(&format!("    {}: {{\n", group));
+        for endpoint in endpoints {
+            let mut params: Vec<String> = Vec::new();
+            let mut args = vec!["config".to_string()];
+            for p in endpoint.parameters.iter().filter(|p| p.location == ParameterLocation::Path) {
+                params.push(format!("~{}", p.name));
+                args.push(format!("~{}", p.name));
+            }
+            if endpoint.request_body.is_some() {
+                params.push("~body".to_string());
+                args.push("~body".to_string());
+            }
+            for location in LABELED_LOCATIONS {
+                for p in endpoint.parameters.iter().filter(|p| p.location == location) {
+                    if p.required {
+                        params.push(format!("~{}", p.name));
+                        args.push(format!("~{}", p.name));
+                    } else {
+                        params.push(format!("~{}=?", p.name));
+                        args.push(format!("~{}?", p.name));
+                    }
+                }
+            }
+            if accept_type(endpoint).is_some() {
+                params.push("~accept=?".to_string());
+                args.push("~accept?".to_string());
+            }
+            let mut call_params: Vec<&str> =
+                policy_params(endpoint).into_iter().map(|(name, _)| name).collect();
+            if endpoint.timeout_ms.is_none() {
+                call_params.push("timeoutMs");
+            }
+            call_params.push("signal");
+            for name in call_params {
+                params.push(format!("~{}=?", name));
+                args.push(format!("~{}?", name));
+            }
+            params.push("()".to_string());
+            args.push("()".to_string());
+            output.push_str(&format!(
+                "      {}: ({}) => {}({}),\n",
+                endpoint.operation_id,
+                params.join(", "),
+                endpoint.operation_id,
+                args.join(", ")
+            ));
+        }
+        output.push_str("    },\n");
+    }
+    output.push_str("  }\n");
 
-    // Build parameter list
-    let mut params = vec!["config: config".to_string()];
+    output
+}
+
+fn has_request_policy(endpoint: &Endpoint) -> bool {
+    endpoint.timeout_ms.is_some() || endpoint.retries.is_some()
+}
+
+/// Transport call of an endpoint, with its spec timeout and retries if any,
+/// else the config's retry policy when generated
+fn send_call(endpoint: &Endpoint, config: &Config) -> String {
+    if !has_request_policy(endpoint) {
+        let send = if config.generate_retry { "sendRetrying" } else { "sendRequest" };
+        return format!("{}(config, ~timeoutMs?, req)", send);
+    }
+    let mut args = vec!["config".to_string(), "req".to_string()];
+    args.extend(policy_params(endpoint).into_iter().map(|(name, _)| format!("~{}", name)));
+    args.push("()".to_string());
+    format!("sendWithPolicy({})", args.join(", "))
+}
+
+/// `(name, default)` of the timeout and retry arguments an endpoint takes
+fn policy_params(endpoint: &Endpoint) -> Vec<(&'static str, u32)> {
+    [("timeoutMs", endpoint.timeout_ms), ("retries", endpoint.retries)]
+        .into_iter()
+        .filter_map(|(name, default)| Some((name, default?)))
+        .collect()
+}
+
+/// Labeled arguments of an endpoint function, after the leading `config`
+///
+/// Path params and body first, then query, header and cookie params (optional ones as
+/// `~x: option<_>=?`), `~accept` for negotiated responses,
+/// then `~timeoutMs` / `~retries` defaulting to the operation's `x-timeout-ms` / `x-retries`
+/// (`~timeoutMs` is optional without one), and last an optional abort `~signal`.
+pub(crate) fn labeled_params(endpoint: &Endpoint) -> Vec<String> {
+    let mut params = Vec::new();
 
-    for p in &path_params {
-        params.push(format!("~{}: {}", p.name, p.ty.to_rescript()));
+    for p in endpoint.parameters.iter().filter(|p| matches!(p.location, ParameterLocation::Path)) {
+        params.push(format!("~{}: {}", p.name, param_type(p)));
     }
 
     if let Some(body) = &endpoint.request_body {
         params.push(format!("~body: {}", body.ty.to_rescript()));
     }
 
-    // Optional query parameters
-    for p in &query_params {
-        if p.required {
-            params.push(format!("~{}: {}", p.name, p.ty.to_rescript()));
-        } else {
-            params.push(format!("~{}=?", p.name));
+    for location in LABELED_LOCATIONS {
+        for p in endpoint.parameters.iter().filter(|p| p.location == location) {
+            if p.required {
+                params.push(format!("~{}: {}", p.name, param_type(p)));
+            } else {
+                params.push(format!("~{}: option<{}>=?", p.name, param_type(p)));
+            }
         }
     }
+    if let Some((ty, default)) = accept_type(endpoint) {
+        params.push(format!("~accept: {}=#{}", ty, string_literal(default)));
+    }
 
-    // Optional header parameters
-    for p in &header_params {
-        if p.required {
-            params.push(format!("~{}: {}", p.name, p.ty.to_rescript()));
-        } else {
-            params.push(format!("~{}=?", p.name));
+    for (name, default) in policy_params(endpoint) {
+        params.push(format!("~{}: int={}", name, default));
+    }
+    if endpoint.timeout_ms.is_none() {
+        params.push("~timeoutMs: option<int>=?".to_string());
+    }
+    params.push("~signal: option<abortSignal>=?".to_string());
+
+    params
+}
+
+/// Locations of the parameters following `~body`, in argument order
+const LABELED_LOCATIONS: [ParameterLocation; 3] =
+    [ParameterLocation::Query, ParameterLocation::Header, ParameterLocation::Cookie];
+
+/// Type of the `~accept` parameter of a negotiated response and its default media type
+fn accept_type(endpoint: &Endpoint) -> Option<(String, &str)> {
+    let Some(RsType::Negotiated { media, .. }) = endpoint.success_response()?.ty.as_ref() else {
+        return None;
+    };
+    let cases: Vec<String> =
+        media.iter().map(|(media_type, _)| format!("#{}", string_literal(media_type))).collect();
+    Some((format!("[{}]", cases.join(" | ")), media.first()?.0.as_str()))
+}
+
+/// Arguments passing on the parameters declared by [`labeled_params`], in the same order
+fn forwarded_args(endpoint: &Endpoint) -> Vec<String> {
+    let mut args = Vec::new();
+
+    for p in endpoint.parameters.iter().filter(|p| matches!(p.location, ParameterLocation::Path)) {
+        args.push(format!("~{}", p.name));
+    }
+
+    if endpoint.request_body.is_some() {
+        args.push("~body".to_string());
+    }
+
+    for location in LABELED_LOCATIONS {
+        for p in endpoint.parameters.iter().filter(|p| p.location == location) {
+            if p.required {
+                args.push(format!("~{}", p.name));
+            } else {
+                args.push(format!("~{}?", p.name));
+            }
         }
     }
+    if accept_type(endpoint).is_some() {
+        args.push("~accept".to_string());
+    }
+
+    for (name, _) in policy_params(endpoint) {
+        args.push(format!("~{}", name));
+    }
+    if endpoint.timeout_ms.is_none() {
+        args.push("~timeoutMs?".to_string());
+    }
+    args.push("~signal?".to_string());
 
-    // Determine return type
-    let success_response = endpoint.responses.iter()
-        .find(|r| r.status >= 200 && r.status < 300);
+    args
+}
 
-    let return_type = success_response
+/// ReScript type of an endpoint's successful result
+fn success_type(endpoint: &Endpoint) -> String {
+    endpoint
+        .success_response()
         .and_then(|r| r.ty.as_ref())
         .map(|t| t.to_rescript())
-        .unwrap_or_else(|| "unit".to_string());
+        .unwrap_or_else(|| "unit".to_string())
+}
 
-    output.push_str(&format!(
-        "  let {} = async ({}, ()): result<{}, apiError> => {{\n",
-        fn_name,
-        params.join(", "),
-        return_type
-    ));
+/// ReScript type of an endpoint's `Ok` result, per `config.response_style`
+pub(crate) fn result_type(endpoint: &Endpoint, config: &Config) -> String {
+    match config.response_style {
+        ResponseStyle::Success | ResponseStyle::TypedErrors => success_type(endpoint),
+        ResponseStyle::Variant => format!("{}Outcome", endpoint.operation_id),
+    }
+}
 
-    // Build path with interpolation
-    let path = build_path(&endpoint.path, &path_params);
-    output.push_str(&format!("    let path = {}\n", path));
+/// ReScript type of an endpoint's `Error` result, per `config.response_style`
+pub(crate) fn error_type(endpoint: &Endpoint, config: &Config) -> String {
+    match config.response_style {
+        ResponseStyle::Success | ResponseStyle::Variant => "apiError".to_string(),
+        ResponseStyle::TypedErrors => format!("{}Failure", endpoint.operation_id),
+    }
+}
 
-    // Build query dict
-    output.push_str("    let query = Dict.make()\n");
-    for p in &query_params {
-        if p.required {
-            output.push_str(&format!(
-                "    query->Dict.set(\"{}\", {}->String.make)\n",
-                p.name, p.name
-            ));
-        } else {
-            output.push_str(&format!(
-                "    switch {} {{ | Some(v) => query->Dict.set(\"{}\", v->String.make) | None => () }}\n",
-                p.name, p.name
-            ));
+/// Expression decoding `response`, a `result<httpResponse, apiError>`, into the endpoint's
+/// per-operation result types; `None` for `ResponseStyle::Success`
+///
+/// `indent` is that of the line the expression starts on.
+pub(crate) fn decode_response(
+    endpoint: &Endpoint,
+    config: &Config,
+    response: &str,
+    indent: &str,
+) -> Option<String> {
+    let name = endpoint.operation_id.to_pascal_case();
+    match config.response_style {
+        ResponseStyle::Success => None,
+        ResponseStyle::Variant => {
+            Some(format!("decodeOutcome(config, {}, decode{}Outcome)", response, name))
+        }
+        ResponseStyle::TypedErrors => {
+            let ok = match endpoint.success_response().and_then(|r| r.ty.as_ref()) {
+                Some(ty) => format!("res => {}", decode_json(ty, "res.body")),
+                None => "_ => ()".to_string(),
+            };
+            let failure = format!("{}Failure", endpoint.operation_id);
+            let args = [
+                "config".to_string(),
+                response.to_string(),
+                format!("~ok={}", ok),
+                format!("~failure=decode{}Failure", name),
+                format!("~network=(e): {} => NetworkError(e)", failure),
+                format!("~invalid=(message, body): {} => DecodeError(message, body)", failure),
+            ];
+            let args: String = args.iter().map(|a| format!("{}  {},\n", indent, a)).collect();
+            Some(format!("decodeTyped(\n{}{})", args, indent))
         }
     }
+}
 
-    // Build headers dict and apply authentication
-    output.push_str("    let headers = Dict.fromArray(config.headers->Dict.toArray)\n");
-    output.push_str("    headers->Dict.set(\"Content-Type\", \"application/json\")\n");
-    output.push_str("    applyAuth(headers, config.auth)\n");
+/// `{operationId}Outcome`, a case per documented status, and its decoder
+pub(crate) fn generate_outcome(endpoint: &Endpoint) -> String {
+    let doc = format!("Documented outcomes of `{}`, keyed by status code", endpoint.operation_id);
+    status_variant(endpoint, "Outcome", &doc, endpoint.responses.iter().collect(), "")
+}
 
-    for p in &header_params {
-        if p.required {
-            output.push_str(&format!(
-                "    headers->Dict.set(\"{}\", {})\n",
-                p.name, p.name
-            ));
-        } else {
-            output.push_str(&format!(
-                "    switch {} {{ | Some(v) => headers->Dict.set(\"{}\", v) | None => () }}\n",
-                p.name, p.name
-            ));
+/// `{operationId}Failure`, a case per documented error status plus failures without
+/// one, and the decoder of its status cases
+pub(crate) fn generate_failure(endpoint: &Endpoint) -> String {
+    let doc = format!("Ways `{}` can fail", endpoint.operation_id);
+    let errors = endpoint.responses.iter().filter(|r| !r.is_success()).collect();
+    let failures = concat!(
+        "  /** No response to decode: network failure, timeout, abort or size limit */\n",
+        "  | NetworkError(apiError)\n",
+        "  /** A body that doesn't match the schema of its status, with the parse error */\n",
+        "  | DecodeError(string, Js.Json.t)\n",
+    );
+    status_variant(endpoint, "Failure", &doc, errors, failures)
+}
+
+/// Variant `{operationId}{suffix}` with a case per response and `UnexpectedStatus`,
+/// then `extra` cases, and `decode{OperationId}{suffix}` for the status cases
+fn status_variant(
+    endpoint: &Endpoint,
+    suffix: &str,
+    doc: &str,
+    responses: Vec<&Response>,
+    extra: &str,
+) -> String {
+    let type_name = format!("{}{}", endpoint.operation_id, suffix);
+    let mut output = doc_comment(doc, "");
+    output.push_str(&format!("type {} =\n", type_name));
+
+    // Exact codes match first, then ranges, then `default`
+    let mut arms: Vec<(u8, String)> = Vec::new();
+    let mut exhaustive = false;
+    for response in responses {
+        if let Some(doc) = response.doc.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+            output.push_str(&doc_comment(doc, "  "));
+        }
+        let case = status_case(response.status);
+        // Ranges and `default` cover several statuses, so their cases carry the status
+        let (rank, pattern, guard) = match response.status {
+            ResponseStatus::Code(code) => (0, code.to_string(), String::new()),
+            ResponseStatus::Range(class) => (
+                1,
+                "status".to_string(),
+                format!(" if status >= {} && status < {}", class * 100, (class + 1) * 100),
+            ),
+            ResponseStatus::Default => (2, "status".to_string(), String::new()),
+        };
+        let (status_type, status_arg) = match response.status {
+            ResponseStatus::Code(_) => ("", ""),
+            _ => ("int, ", "status, "),
+        };
+        match &response.ty {
+            Some(ty) => {
+                output.push_str(&format!("  | {}({}{})\n", case, status_type, ty.to_rescript()));
+                arms.push((rank, format!(
+                    "  | ({}, Some(json)){} => {}({}{})\n",
+                    pattern,
+                    guard,
+                    case,
+                    status_arg,
+                    decode_json(ty, "json")
+                )));
+            }
+            None if status_arg.is_empty() => {
+                output.push_str(&format!("  | {}\n", case));
+                arms.push((rank, format!("  | ({}, _){} => {}\n", pattern, guard, case)));
+            }
+            None => {
+                output.push_str(&format!("  | {}(int)\n", case));
+                arms.push((rank, format!("  | ({}, _){} => {}(status)\n", pattern, guard, case)));
+                exhaustive |= response.status == ResponseStatus::Default;
+            }
         }
     }
+    output.push_str("  /** A status the spec doesn't document, with the response text */\n");
+    output.push_str("  | UnexpectedStatus(int, string)\n");
+    output.push_str(extra);
+    output.push('\n');
 
-    // Build request body
-    let body_expr = if let Some(body) = &endpoint.request_body {
-        match &body.ty {
-            RsType::Named(type_name) => {
-                format!("Some(serialize{}(body))", type_name)
+    output.push_str(&format!(
+        "let decode{}{} = (res: receivedResponse): {} =>\n",
+        endpoint.operation_id.to_pascal_case(),
+        suffix,
+        type_name
+    ));
+    output.push_str("  switch (res.status, res.body) {\n");
+    arms.sort_by_key(|(rank, _)| *rank);
+    for (_, arm) in arms {
+        output.push_str(&arm);
+    }
+    // A body-less `default` catches everything, so the fallback would be unused
+    if !exhaustive {
+        output.push_str("  | (status, _) => UnexpectedStatus(status, res.text)\n");
+    }
+    output.push_str("  }\n");
+
+    output
+}
+
+/// Expression decoding the JSON in `json` as a response body of type `ty`
+pub(crate) fn decode_json(ty: &RsType, json: &str) -> String {
+    match ty {
+        RsType::Named(type_name) => format!("parse{}({})", type_name, json),
+        RsType::Json => json.to_string(),
+        RsType::RawText => format!(
+            "{{contentType: res.headers->headerValue(\"Content-Type\"), body: {}->JSON.Decode.string->Option.getOr(\"\")}}",
+            json
+        ),
+        RsType::RawBlob | RsType::RawArrayBuffer => format!(
+            "{{contentType: res.headers->headerValue(\"Content-Type\"), body: {}->Obj.magic}}",
+            json
+        ),
+        // Bodies of the default's kind are the fallback, e.g. when `Content-Type` is missing
+        RsType::Negotiated { json: json_type, media } => {
+            let decode = |kind: MediaKind| match kind {
+                MediaKind::Json => format!("Json({})", decode_json(json_type, json)),
+                MediaKind::Text => format!("Text({})", decode_json(&RsType::RawText, json)),
+                MediaKind::Blob => format!("Blob({})", decode_json(&RsType::RawBlob, json)),
+            };
+            let default = media.first().map_or(MediaKind::Json, |(_, kind)| *kind);
+            let mut arms = Vec::new();
+            for kind in [MediaKind::Json, MediaKind::Text, MediaKind::Blob] {
+                let types: Vec<String> = media
+                    .iter()
+                    .filter(|(_, k)| *k == kind && kind != default)
+                    .map(|(media_type, _)| string_literal(media_type))
+                    .collect();
+                if !types.is_empty() {
+                    arms.push(format!("| Some({}) => {}", types.join(" | "), decode(kind)));
+                }
             }
-            _ => "Some(body->Obj.magic)".to_string()
+            arms.push(format!("| _ => {}", decode(default)));
+            format!("switch res.headers->mediaType {{ {} }}", arms.join(" "))
         }
-    } else {
-        "None".to_string()
-    };
+        ty => format!("S.parseJsonOrThrow({}, {})", json, ty.to_schema()),
+    }
+}
 
-    // Make request (polymorphic variant for Fetch API)
-    let method = match endpoint.method {
-        HttpMethod::Get => "#GET",
-        HttpMethod::Post => "#POST",
-        HttpMethod::Put => "#PUT",
-        HttpMethod::Patch => "#PATCH",
-        HttpMethod::Delete => "#DELETE",
-        HttpMethod::Head => "#HEAD",
-        HttpMethod::Options => "#OPTIONS",
+/// Outcome case of a declared status: its reason phrase (RFC 9110) and the code, e.g.
+/// `NotFound404`, the class of a range (`ClientError4XX`), or `Default`
+fn status_case(status: ResponseStatus) -> String {
+    let status = match status {
+        ResponseStatus::Code(code) => code,
+        ResponseStatus::Range(class) => {
+            let name = match class {
+                1 => "Informational",
+                2 => "Success",
+                3 => "Redirection",
+                4 => "ClientError",
+                5 => "ServerError",
+                _ => "Status",
+            };
+            return format!("{}{}XX", name, class);
+        }
+        ResponseStatus::Default => return "Default".to_string(),
     };
+    let reason = match status {
+        100 => "Continue",
+        101 => "SwitchingProtocols",
+        200 => "Ok",
+        201 => "Created",
+        202 => "Accepted",
+        203 => "NonAuthoritativeInformation",
+        204 => "NoContent",
+        205 => "ResetContent",
+        206 => "PartialContent",
+        207 => "MultiStatus",
+        300 => "MultipleChoices",
+        301 => "MovedPermanently",
+        302 => "Found",
+        303 => "SeeOther",
+        304 => "NotModified",
+        307 => "TemporaryRedirect",
+        308 => "PermanentRedirect",
+        400 => "BadRequest",
+        401 => "Unauthorized",
+        402 => "PaymentRequired",
+        403 => "Forbidden",
+        404 => "NotFound",
+        405 => "MethodNotAllowed",
+        406 => "NotAcceptable",
+        407 => "ProxyAuthenticationRequired",
+        408 => "RequestTimeout",
+        409 => "Conflict",
+        410 => "Gone",
+        411 => "LengthRequired",
+        412 => "PreconditionFailed",
+        413 => "ContentTooLarge",
+        414 => "UriTooLong",
+        415 => "UnsupportedMediaType",
+        416 => "RangeNotSatisfiable",
+        417 => "ExpectationFailed",
+        421 => "MisdirectedRequest",
+        422 => "UnprocessableContent",
+        423 => "Locked",
+        424 => "FailedDependency",
+        425 => "TooEarly",
+        426 => "UpgradeRequired",
+        428 => "PreconditionRequired",
+        429 => "TooManyRequests",
+        431 => "RequestHeaderFieldsTooLarge",
+        451 => "UnavailableForLegalReasons",
+        500 => "InternalServerError",
+        501 => "NotImplemented",
+        502 => "BadGateway",
+        503 => "ServiceUnavailable",
+        504 => "GatewayTimeout",
+        505 => "HttpVersionNotSupported",
+        507 => "InsufficientStorage",
+        508 => "LoopDetected",
+        511 => "NetworkAuthenticationRequired",
+        _ => "Status",
+    };
+    format!("{}{}", reason, status)
+}
 
-    output.push_str(&format!(r#"
-    let req: httpRequest = {{
-      method: {},
-      url: buildUrl(config.baseUrl, path, query),
-      headers,
-      body: {},
-    }}
+/// `@deprecated` line for a deprecated operation, or nothing
+fn endpoint_deprecation(endpoint: &Endpoint, indent: &str) -> String {
+    if !endpoint.deprecated {
+        return String::new();
+    }
+    let operation = format!("{} {}", endpoint.method.as_str(), endpoint.path);
+    format!("{}{}\n", indent, deprecated_attribute(&operation))
+}
+
+/// Build the doc comment for an endpoint, including error and deprecation sections
+///
+/// Documented error statuses are listed individually. When none are documented,
+/// the `default` response description stands in, unless `default` is the only
+/// success path (no explicit 2xx response).
+fn endpoint_doc(endpoint: &Endpoint) -> Option<String> {
+    let mut errors: Vec<String> = endpoint
+        .responses
+        .iter()
+        .filter(|r| r.is_error())
+        .filter_map(|r| {
+            let doc = r.doc.as_deref()?.trim();
+            (!doc.is_empty()).then(|| format!("- {}: {}", r.status, doc))
+        })
+        .collect();
 
-    switch await Http.request(req) {{
-"#, method, body_expr));
+    let deprecated: Vec<String> = endpoint
+        .parameters
+        .iter()
+        .filter(|p| p.deprecated)
+        .map(|p| match &p.doc {
+            Some(doc) => format!("@deprecated ~{}: {}", p.name, doc.trim()),
+            None => format!("@deprecated ~{}", p.name),
+        })
+        .collect();
 
-    // Parse response
-    if let Some(response) = success_response {
-        if let Some(ty) = &response.ty {
-            if let RsType::Named(type_name) = ty {
-                output.push_str(&format!(
-                    "    | Ok(json) => try {{\n      Ok(parse{}(json))\n    }} catch {{\n    | Exn.Error(e) => Error({{status: 0, message: Exn.message(e)->Option.getOr(\"Parse error\"), body: Some(json)}})\n    }}\n",
-                    type_name
-                ));
-            } else {
-                output.push_str("    | Ok(json) => Ok(json->Obj.magic)\n");
+    let default_doc = endpoint
+        .responses
+        .iter()
+        .find(|r| r.status == ResponseStatus::Default)
+        .and_then(|r| r.doc.as_deref())
+        .map(str::trim)
+        .filter(|doc| !doc.is_empty());
+    if errors.is_empty() && endpoint.success_response().is_some() {
+        if let Some(doc) = default_doc {
+            errors.push(format!("- default: {}", doc));
+        }
+    }
+
+    if errors.is_empty() && deprecated.is_empty() {
+        return endpoint.doc.clone();
+    }
+
+    let mut lines: Vec<String> = endpoint.doc.iter().cloned().collect();
+    if !errors.is_empty() {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push("Errors:".to_string());
+        lines.extend(errors);
+    }
+    if !deprecated.is_empty() {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(deprecated);
+    }
+    Some(lines.join("\n"))
+}
+
+/// Serialize a `content`-defined parameter value according to its media type
+///
+/// Returns `None` for schema-defined parameters, which use plain string conversion.
+fn content_param_expr(param: &Parameter, var: &str) -> Option<String> {
+    let content_type = param.content_type.as_deref()?;
+    if !content_type.contains("json") {
+        return Some(wire_string(&param.ty, var));
+    }
+
+    Some(match &param.ty {
+        RsType::Named(type_name) => format!("{}->serialize{}->JSON.stringify", var, type_name),
+        RsType::Json => format!("{}->JSON.stringify", var),
+        _ => format!("{}->JSON.stringifyAny->Option.getOr(\"\")", var),
+    })
+}
+
+/// ReScript type of a parameter as seen by callers
+pub(crate) fn param_type(param: &Parameter) -> String {
+    match &param.fieldset {
+        Some(schema) => format!("array<Fields.{}.field>", schema),
+        None => param.ty.to_rescript(),
+    }
+}
+
+/// Statement adding the query parameter value in `var` to `query`, per its `style`
+///
+/// Arrays of scalars are formatted item by item: repeated as `name=a&name=b`
+/// when exploded, else joined. Objects and other arrays go through `QueryStyle`
+/// as JSON; anything else is one `name=value` pair.
+fn query_set(param: &Parameter, var: &str) -> String {
+    let name = &param.original_name;
+    let plain = || format!("query->Array.push((\"{}\", {}))", name, param_wire(param, var));
+    if param.fieldset.is_some() || param.content_type.is_some() {
+        return plain();
+    }
+    if let Some(item) = array_item_text(&param.ty) {
+        let separator = match param.style {
+            ParameterStyle::Form if param.explode => {
+                return format!(
+                    "{}->Array.forEach(item => query->Array.push((\"{}\", {})))",
+                    var, name, item
+                );
             }
+            ParameterStyle::PipeDelimited => "|",
+            ParameterStyle::SpaceDelimited => " ",
+            _ => ",",
+        };
+        let items = if item == "item" {
+            var.to_string()
         } else {
-            output.push_str("    | Ok(_) => Ok()\n");
+            format!("{}->Array.map(item => {})", var, item)
+        };
+        return format!(
+            "query->Array.push((\"{}\", {}->Array.join(\"{}\")))",
+            name, items, separator
+        );
+    }
+    let json = param_json(&param.ty, var);
+    match param.style {
+        ParameterStyle::DeepObject => {
+            format!("QueryStyle.deepObject(query, \"{}\", {})", name, json)
         }
-    } else {
-        output.push_str("    | Ok(json) => Ok(json->Obj.magic)\n");
+        ParameterStyle::PipeDelimited => {
+            format!("QueryStyle.delimited(query, \"{}\", {}, \"|\")", name, json)
+        }
+        ParameterStyle::SpaceDelimited => {
+            format!("QueryStyle.delimited(query, \"{}\", {}, \" \")", name, json)
+        }
+        ParameterStyle::Form
+            if matches!(param.ty, RsType::Named(_) | RsType::Dict(_) | RsType::Array(_)) =>
+        {
+            format!(
+                "QueryStyle.form(query, \"{}\", {}, ~explode={})",
+                name, json, param.explode
+            )
+        }
+        _ => plain(),
     }
+}
 
-    output.push_str("    | Error(e) => Error(e)\n");
-    output.push_str("    }\n");
-    output.push_str("  }\n");
+/// Wire text of `item`, an item of an array of scalars
+fn array_item_text(ty: &RsType) -> Option<String> {
+    match ty {
+        RsType::Array(item) => form_text(item, "item"),
+        _ => None,
+    }
+}
+
+/// String expression of a header or cookie value, with array items joined by `,`
+fn param_text(param: &Parameter, var: &str) -> String {
+    match array_item_text(&param.ty) {
+        Some(item) if item == "item" => format!("{}->Array.join(\",\")", var),
+        Some(item) => format!("{}->Array.map(item => {})->Array.join(\",\")", var, item),
+        None => param_wire(param, var),
+    }
+}
 
+/// JSON of a parameter value, for the `QueryStyle` serializers
+fn param_json(ty: &RsType, var: &str) -> String {
+    match ty {
+        RsType::Named(type_name) => format!("{}->serialize{}", var, type_name),
+        RsType::Json => var.to_string(),
+        ty => format!("{}->S.reverseConvertToJsonOrThrow({})", var, ty.to_schema()),
+    }
+}
+
+/// String expression sent on the wire for a parameter value
+fn param_wire(param: &Parameter, var: &str) -> String {
+    if let Some(schema) = &param.fieldset {
+        return format!("Fields.{}.select({})", schema, var);
+    }
+    content_param_expr(param, var).unwrap_or_else(|| wire_string(&param.ty, var))
+}
+
+/// Generate `Fields`, typed field names for sparse fieldset parameters
+///
+/// One submodule per schema referenced by an `x-rescript-fieldset` parameter,
+/// so `~fields=[#id, #email]` can only name properties the schema has.
+fn generate_fieldsets(spec: &ApiSpec) -> String {
+    let mut schemas: Vec<&str> = spec
+        .endpoints
+        .iter()
+        .flat_map(|e| e.parameters.iter())
+        .filter_map(|p| p.fieldset.as_deref())
+        .collect();
+    schemas.sort_unstable();
+    schemas.dedup();
+    if schemas.is_empty() {
+        return String::new();
+    }
+
+    let mut output = String::from("/** Selectable fields for sparse fieldset parameters */\nmodule Fields = {\n");
+    for schema in schemas {
+        let fields = spec.types.iter().find_map(|t| match t {
+            TypeDef::Record { name, fields, .. } if name == schema => Some(fields),
+            _ => None,
+        });
+        let cases: Vec<String> = fields
+            .into_iter()
+            .flatten()
+            .map(|f| format!("#\"{}\"", f.original_name))
+            .collect();
+
+        output.push_str(&format!("  module {} = {{\n", schema));
+        output.push_str(&format!("    type field = [{}]\n\n", cases.join(" | ")));
+        output.push_str("    /** Comma-separated selection, e.g. `id,email` */\n");
+        output.push_str(
+            "    let select = (fields: array<field>): string =>\n      fields->Array.map(f => (f :> string))->Array.join(\",\")\n",
+        );
+        output.push_str("  }\n");
+    }
+    output.push_str("}\n\n");
     output
 }
 
+/// Render a path, query or header value as a string without locale-dependent formatting
+///
+/// Primitives go through the runtime's `Wire` helpers; anything else falls back
+/// to `String.make`, which uses JS `String()` and is locale-independent too.
+pub(crate) fn wire_string(ty: &RsType, var: &str) -> String {
+    match ty {
+        RsType::String => var.to_string(),
+        RsType::Int => format!("{}->Wire.int", var),
+        RsType::Float => format!("{}->Wire.float", var),
+        RsType::Bool => format!("{}->Wire.bool", var),
+        RsType::DateTime => format!("{}->Wire.dateTime", var),
+        RsType::Date => format!("{}->Wire.date", var),
+        RsType::BigInt => format!("{}->Wire.bigint", var),
+        RsType::Int64String => var.to_string(),
+        RsType::StringEnum(_) => format!("({} :> string)", var),
+        _ => format!("{}->String.make", var),
+    }
+}
+
 fn build_path(path: &str, path_params: &[&Parameter]) -> String {
     if path_params.is_empty() {
         return format!("\"{}\"", path);
@@ -365,27 +3149,61 @@ fn build_path(path: &str, path_params: &[&Parameter]) -> String {
     let mut template = path.to_string();
 
     for param in path_params {
-        // Convert param to string based on type
-        let param_expr = match &param.ty {
-            RsType::String => param.name.clone(),
-            RsType::Int => format!("{}->Int.toString", param.name),
-            RsType::Float => format!("{}->Float.toString", param.name),
-            RsType::Bool => format!("{}->Bool.toString", param.name),
-            _ => format!("{}->String.make", param.name),
-        };
+        let segment = path_segment(param);
 
         // Handle {param} style
-        let placeholder = format!("{{{}}}", param.name);
-        template = template.replace(&placeholder, &format!("${{{}}}", param_expr));
+        let placeholder = format!("{{{}}}", param.original_name);
+        template = template.replace(&placeholder, &segment);
 
         // Handle :param style
-        let placeholder_colon = format!(":{}", param.name);
-        template = template.replace(&placeholder_colon, &format!("${{{}}}", param_expr));
+        let placeholder_colon = format!(":{}", param.original_name);
+        template = template.replace(&placeholder_colon, &segment);
     }
 
     format!("`{}`", template)
 }
 
+/// Template text of a path parameter: its percent-encoded value, after the `.` or
+/// `;name=` of the `label` and `matrix` styles
+///
+/// Array items are encoded one by one, so the separators between them stay literal.
+fn path_segment(param: &Parameter) -> String {
+    let prefix = match param.style {
+        ParameterStyle::Label => ".".to_string(),
+        ParameterStyle::Matrix => format!(";{}=", param.original_name),
+        _ => String::new(),
+    };
+    let separator = match param.style {
+        ParameterStyle::Label if param.explode => ".",
+        ParameterStyle::Matrix if param.explode => prefix.as_str(),
+        _ => ",",
+    };
+    let item = match &param.ty {
+        RsType::Array(item) if param.fieldset.is_none() && param.content_type.is_none() => {
+            form_text(item, "item").map(|text| uri_component(item, &text))
+        }
+        _ => None,
+    };
+    let value = match item {
+        Some(item) => {
+            format!("{}->Array.map(item => {})->Array.join(\"{}\")", param.name, item, separator)
+        }
+        None if param.fieldset.is_some() || param.content_type.is_some() => {
+            format!("encodeURIComponent({})", param_wire(param, &param.name))
+        }
+        None => uri_component(&param.ty, &param_wire(param, &param.name)),
+    };
+    format!("{}${{{}}}", prefix, value)
+}
+
+/// `text` percent-encoded for a URL, unless a value of type `ty` never needs it
+fn uri_component(ty: &RsType, text: &str) -> String {
+    match ty {
+        RsType::Int | RsType::Float | RsType::Bool | RsType::BigInt => text.to_string(),
+        _ => format!("encodeURIComponent({})", text),
+    }
+}
+
 fn generate_path_alias(path: &str, method: &HttpMethod) -> String {
     let method_prefix = match method {
         HttpMethod::Get => "get",
@@ -411,3 +3229,111 @@ fn generate_path_alias(path: &str, method: &HttpMethod) -> String {
 
     format!("{}{}", method_prefix, path_name)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn rs_type() -> impl Strategy<Value = RsType> {
+        let leaf = prop_oneof![
+            Just(RsType::String),
+            Just(RsType::Int),
+            Just(RsType::Float),
+            Just(RsType::Bool),
+            Just(RsType::DateTime),
+            Just(RsType::Date),
+            Just(RsType::BigInt),
+            Just(RsType::Int64String),
+            Just(RsType::Json),
+            "[A-Z][a-zA-Z]{0,8}".prop_map(RsType::Named),
+            prop::collection::vec("[a-z]{1,6}", 1..4).prop_map(RsType::StringEnum),
+        ];
+        leaf.prop_recursive(3, 16, 4, |inner| {
+            prop_oneof![
+                inner.clone().prop_map(|t| RsType::Option(Box::new(t))),
+                inner.clone().prop_map(|t| RsType::Array(Box::new(t))),
+                inner.prop_map(|t| RsType::Dict(Box::new(t))),
+            ]
+        })
+    }
+
+    /// Spec with one query and one path parameter of the given schema type
+    fn spec_with_params(schema: &str, options: &crate::ir::LowerOptions) -> ApiSpec {
+        let yaml = format!(
+            r#"
+openapi: "3.0.0"
+info: {{ title: Test, version: "1.0.0" }}
+paths:
+  /items/{{id}}:
+    get:
+      operationId: getItem
+      parameters:
+        - {{ name: id, in: path, required: true, schema: {schema} }}
+        - {{ name: q, in: query, schema: {schema} }}
+        - {{ name: X-Q, in: header, required: true, schema: {schema} }}
+      responses:
+        "204": {{ description: done }}
+"#
+        );
+        let spec: openapiv3::OpenAPI = serde_yaml::from_str(&yaml).unwrap();
+        crate::ir::lower_with_options(&spec, options).unwrap()
+    }
+
+    fn calls_locale_api(code: &str) -> bool {
+        code.contains(".toLocale") || code.contains("->toLocale") || code.contains("Intl.")
+    }
+
+    proptest! {
+        #[test]
+        fn wire_string_is_locale_independent(ty in rs_type(), var in "[a-z][a-zA-Z0-9]{0,10}") {
+            let expr = wire_string(&ty, &var);
+            prop_assert!(!calls_locale_api(&expr));
+            prop_assert!(expr.contains(&var));
+            match ty {
+                RsType::Int => prop_assert_eq!(expr, format!("{}->Wire.int", var)),
+                RsType::Float => prop_assert_eq!(expr, format!("{}->Wire.float", var)),
+                RsType::Bool => prop_assert_eq!(expr, format!("{}->Wire.bool", var)),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_group_name() {
+        assert_eq!(group_name(Some("pet")), "pet");
+        assert_eq!(group_name(Some("User Accounts")), "userAccounts");
+        assert_eq!(group_name(Some("type")), "type_");
+        assert_eq!(group_name(Some("2fa")), "_2fa");
+        assert_eq!(group_name(Some("")), "default");
+        assert_eq!(group_name(Some("--")), "default");
+        assert_eq!(group_name(None), "default");
+    }
+
+    #[test]
+    fn generated_params_use_wire_helpers() {
+        let dates = crate::ir::LowerOptions {
+            formats: vec!["date-time=date".parse().unwrap(), "date=date".parse().unwrap()],
+            ..Default::default()
+        };
+        let cases = [
+            ("{ type: integer }", Default::default(), Some("Wire.int")),
+            ("{ type: integer, format: int64 }", Default::default(), Some("Wire.int")),
+            ("{ type: number }", Default::default(), Some("Wire.float")),
+            ("{ type: number, format: double }", Default::default(), Some("Wire.float")),
+            ("{ type: boolean }", Default::default(), Some("Wire.bool")),
+            ("{ type: string, format: date-time }", Default::default(), None),
+            ("{ type: string, format: date-time }", dates.clone(), Some("Wire.dateTime")),
+            ("{ type: string, format: date }", dates, Some("Wire.date")),
+            ("{ type: string, enum: [a, b] }", Default::default(), None),
+            ("{ type: array, items: { type: number } }", Default::default(), Some("Wire.float")),
+        ];
+        for (schema, options, helper) in cases {
+            let client = generate(&spec_with_params(schema, &options), &Config::default()).unwrap();
+            assert!(!calls_locale_api(&client), "{}", schema);
+            if let Some(helper) = helper {
+                assert!(client.contains(&format!("->{}", helper)), "{} should use {}", schema, helper);
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Explain how a single schema or operation is generated
+//!
+//! Prints the IR an element lowers to and the exact code each generated file
+//! would contain for it, to debug surprising output without regenerating
+//! everything.
+
+use crate::ir::ApiSpec;
+use super::client::Runtime;
+use super::{client, schema, spy, types, Config, ResponseStyle};
+use anyhow::{bail, Result};
+use heck::ToLowerCamelCase;
+
+/// Explain a component schema, matched by its spec or ReScript type name
+pub fn schema(spec: &ApiSpec, config: &Config, name: &str) -> Result<String> {
+    let Some(type_def) = spec
+        .types
+        .iter()
+        .find(|t| t.name() == name || t.name().to_lower_camel_case() == name)
+    else {
+        let available: Vec<&str> = spec.types.iter().map(|t| t.name()).collect();
+        bail!("Unknown schema '{}'; available: {}", name, available.join(", "));
+    };
+
+    let mut output = section("IR", &format!("{:#?}\n", type_def));
+    output.push_str(&section(
+        &format!("{}Types.res", config.module_prefix),
+        &types::generate_definition(spec, type_def),
+    ));
+    if config.generate_schema {
+        output.push_str(&section(
+            &format!("{}Schema.res", config.module_prefix),
+            &schema::generate_schema(type_def, config),
+        ));
+    }
+    Ok(output)
+}
+
+/// Explain an operation, matched by its (possibly derived) operationId
+pub fn operation(spec: &ApiSpec, config: &Config, operation_id: &str) -> Result<String> {
+    let Some(endpoint) = spec.endpoints.iter().find(|e| e.operation_id == operation_id) else {
+        let available: Vec<&str> = spec.endpoints.iter().map(|e| e.operation_id.as_str()).collect();
+        bail!("Unknown operation '{}'; available: {}", operation_id, available.join(", "));
+    };
+
+    let mut output = section("IR", &format!("{:#?}\n", endpoint));
+    if config.generate_client {
+        let mut code = match config.response_style {
+            ResponseStyle::Success => String::new(),
+            ResponseStyle::Variant => client::generate_outcome(endpoint) + "\n",
+            ResponseStyle::TypedErrors => client::generate_failure(endpoint) + "\n",
+        };
+        code.push_str(&client::generate_endpoint(endpoint, &Runtime::for_spec(spec), config));
+        output.push_str(&section(&format!("{}Client.res", config.module_prefix), &code));
+    }
+    if config.generate_spy {
+        output.push_str(&section(
+            &format!("{}ClientSpy.res", config.module_prefix),
+            &spy::generate_spy_endpoint(endpoint, config),
+        ));
+    }
+    Ok(output)
+}
+
+fn section(title: &str, body: &str) -> String {
+    format!("== {} ==\n\n{}\n", title, body)
+}
@@ -0,0 +1,200 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! API metadata module generation
+//!
+//! `ApiMeta.res` records which spec the client was generated from (title,
+//! version and a content hash) and provides a typed check against the version
+//! a server reports, which the client runs on every successful response.
+//! The spec's `servers` become a `Servers` module of typed base URLs.
+
+use crate::ir::{ApiSpec, Server, RESERVED_KEYWORDS};
+use super::{doc_comment, region, string_literal, Config};
+use anyhow::Result;
+use heck::{ToLowerCamelCase, ToPascalCase};
+
+/// Response header read for the server's API version unless overridden in `makeConfig`
+pub const DEFAULT_VERSION_HEADER: &str = "X-API-Version";
+
+pub fn generate(spec: &ApiSpec, config: &Config) -> Result<String> {
+    let mut output = String::new();
+
+    // Header
+    output.push_str("// SPDX-License-Identifier: AGPL-3.0-or-later\n");
+    output.push_str("// Generated by rescript-openapi - DO NOT EDIT\n");
+    output.push_str(&format!("// Source: {} v{}\n\n", spec.title, spec.version));
+
+    output.push_str(&config.opens(true, &[]));
+
+    output.push_str("/** Title of the spec this client was generated from */\n");
+    output.push_str(&format!("let title = {}\n\n", string_literal(&spec.title)));
+    output.push_str("/** API version this client was generated against */\n");
+    output.push_str(&format!("let version = {}\n\n", string_literal(&spec.version)));
+    output.push_str("/** SHA-256 of the spec document, to detect stale generated code */\n");
+    output.push_str(&format!("let specHash = \"{}\"\n\n", spec.spec_hash));
+    output.push_str("/** Response header carrying the server's API version */\n");
+    output.push_str(&format!("let versionHeader = \"{}\"\n\n", DEFAULT_VERSION_HEADER));
+
+    output.push_str(r#"/** A server reporting a different API version than the client was built for */
+type versionMismatch = {
+  expected: string,
+  actual: string,
+  /** Major versions differ, so request or response shapes may be incompatible */
+  breaking: bool,
+}
+
+let majorOf = (v: string): string => {
+  let v = v->String.startsWith("v") ? v->String.sliceToEnd(~start=1) : v
+  v->String.split(".")->Array.get(0)->Option.getOr(v)
+}
+
+/** Compare a server-reported version with the generated one */
+let checkVersion = (serverVersion: string): result<unit, versionMismatch> => {
+  let actual = serverVersion->String.trim
+  if actual == version {
+    Ok()
+  } else {
+    Error({expected: version, actual, breaking: majorOf(actual) != majorOf(version)})
+  }
+}
+
+"#);
+
+    if !spec.servers.is_empty() {
+        output.push_str(&generate_servers(&spec.servers));
+    }
+
+    region::append(&mut output);
+
+    Ok(output)
+}
+
+/// `Servers` module: a function per server substituting its URL variables, and
+/// a polymorphic variant to pick one by name
+fn generate_servers(servers: &[Server]) -> String {
+    let mut tags: Vec<String> = Vec::new();
+    for (index, server) in servers.iter().enumerate() {
+        let mut tag = server_tag(server, index);
+        if tags.contains(&tag) {
+            tag = (2..)
+                .map(|i| format!("{}{}", tag, i))
+                .find(|t| !tags.contains(t))
+                .expect("some suffix is free");
+        }
+        tags.push(tag);
+    }
+
+    let mut output = String::new();
+    output.push_str("/** Servers from the spec's `servers`, e.g. for `makeConfig(~baseUrl)` */\n");
+    output.push_str("module Servers = {\n");
+    output.push_str("  /** A declared server, named after its description */\n");
+    let variants: Vec<String> = tags.iter().map(|t| format!("#{}", t)).collect();
+    output.push_str(&format!("  type t = [{}]\n", variants.join(" | ")));
+
+    for (server, tag) in servers.iter().zip(&tags) {
+        let mut doc = match &server.doc {
+            Some(description) => vec![description.clone(), String::new()],
+            None => Vec::new(),
+        };
+        doc.push(format!("`{}`", server.url));
+        for variable in &server.variables {
+            if let Some(description) = &variable.doc {
+                doc.push(format!("- `~{}`: {}", variable_name(&variable.name), description));
+            }
+        }
+        output.push('\n');
+        output.push_str(&doc_comment(&doc.join("\n"), "  "));
+
+        let mut params: Vec<String> = server
+            .variables
+            .iter()
+            .map(|variable| {
+                let name = variable_name(&variable.name);
+                if variable.values.is_empty() {
+                    format!("~{}: string={}", name, string_literal(&variable.default))
+                } else {
+                    let values: Vec<String> =
+                        variable.values.iter().map(|v| format!("#{}", string_literal(v))).collect();
+                    format!("~{}: [{}]=#{}", name, values.join(" | "), string_literal(&variable.default))
+                }
+            })
+            .collect();
+        params.push("()".to_string());
+        let params = match params.len() {
+            1 => "()".to_string(),
+            _ => format!("({})", params.join(", ")),
+        };
+        output.push_str(&format!(
+            "  let {} = {}: string => {}\n",
+            function_name(tag),
+            params,
+            url_template(server)
+        ));
+    }
+
+    output.push_str("\n  /** Base URL of a server, with the default value of each variable */\n");
+    output.push_str("  let url = (server: t): string =>\n    switch server {\n");
+    for tag in &tags {
+        output.push_str(&format!("    | #{} => {}()\n", tag, function_name(tag)));
+    }
+    output.push_str("    }\n\n");
+    output.push_str("  /** Every declared server, in spec order */\n");
+    output.push_str(&format!("  let all: array<t> = [{}]\n", variants.join(", ")));
+    output.push_str("}\n\n");
+    output
+}
+
+/// Variant name of a server: its description without a trailing "server",
+/// e.g. `Production server` -> `Production`, else `Server1`, `Server2`, ...
+fn server_tag(server: &Server, index: usize) -> String {
+    let fallback = || format!("Server{}", index + 1);
+    let Some(description) = &server.doc else {
+        return fallback();
+    };
+    let name = description.to_pascal_case();
+    let name = match name.strip_suffix("Server") {
+        Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+        _ => name,
+    };
+    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name
+    } else {
+        fallback()
+    }
+}
+
+/// Function returning a server's URL, e.g. `#Production` -> `production`
+fn function_name(tag: &str) -> String {
+    let name = tag.to_lower_camel_case();
+    // `url` and `all` are the module's own helpers
+    if RESERVED_KEYWORDS.contains(&name.as_str()) || name == "url" || name == "all" {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+/// Labeled argument for a server variable
+fn variable_name(name: &str) -> String {
+    let name = name.to_lower_camel_case();
+    if RESERVED_KEYWORDS.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+/// ReScript template string of a server URL, its `{variables}` interpolated
+fn url_template(server: &Server) -> String {
+    let mut template = server.url.replace('\\', "\\\\").replace('`', "\\`").replace('$', "\\$");
+    for variable in &server.variables {
+        let name = variable_name(&variable.name);
+        let value = if variable.values.is_empty() {
+            name
+        } else {
+            format!("({} :> string)", name)
+        };
+        template = template.replace(&format!("{{{}}}", variable.name), &format!("${{{}}}", value));
+    }
+    format!("`{}`", template)
+}
@@ -7,21 +7,235 @@
 //! - Type definitions (records, variants, aliases)
 //! - rescript-schema validators
 //! - HTTP client functions using fetch
+//! - API metadata (version, spec hash) for compatibility checks
+//! - OAuth2 scopes, flow endpoints and token refresh, for specs that use OAuth2
 
+pub mod auth;
 pub mod client;
+pub mod explain;
+pub mod meta;
+pub mod region;
 pub mod schema;
+pub mod spy;
 pub mod types;
 
 use crate::ir::ApiSpec;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 
+/// What client functions return
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResponseStyle {
+    /// `result<success, apiError>`: the 2xx body, with error statuses as `apiError`
+    #[default]
+    Success,
+    /// `result<{operationId}Outcome, apiError>`: a case per documented status code
+    Variant,
+    /// `result<success, {operationId}Failure>`: a case per documented error status,
+    /// plus network and decode failures
+    TypedErrors,
+}
+
+/// How client operations are exposed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClientSurface {
+    /// Standalone functions taking the config as first argument
+    #[default]
+    Functions,
+    /// Also `make(~config)`, returning operations grouped by tag and bound to the config
+    Instance,
+}
+
+/// How request bodies are turned into JSON
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BodySerializer {
+    /// Through the generated schema, applying variant encodings and coercions
+    #[default]
+    Schema,
+    /// Pass the runtime value to `JSON.stringify` unchanged
+    Stringify,
+}
+
+/// How generated files reference their dependencies and each other
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Imports {
+    /// Standard library opened in files that use it; `None` skips the open
+    pub core_module: Option<String>,
+    /// Module aliased as `S` in the schema file
+    pub schema_module: String,
+    /// Qualifier for references between generated modules, e.g. `MyApp`
+    pub namespace: Option<String>,
+    /// Extra modules opened at the top of every generated file
+    pub extra_opens: Vec<String>,
+}
+
+impl Default for Imports {
+    fn default() -> Self {
+        Self {
+            core_module: Some("RescriptCore".to_string()),
+            schema_module: "RescriptSchema.S".to_string(),
+            namespace: None,
+            extra_opens: Vec::new(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
 pub struct Config {
     pub output_dir: PathBuf,
     pub module_prefix: String,
+    pub imports: Imports,
     pub generate_schema: bool,
+    /// Add a name -> schema registry to Schema.res
+    pub generate_schema_registry: bool,
+    /// Start constraint error messages with the field description, for human-readable validation errors
+    pub describe_errors: bool,
     pub generate_client: bool,
+    /// Operation surface of the generated client
+    pub client_surface: ClientSurface,
+    /// Return type of client functions
+    pub response_style: ResponseStyle,
+    /// How the client serializes request bodies
+    pub body_serializer: BodySerializer,
+    /// Add `*Exn` wrappers raising `ApiError` instead of returning `Error`
+    pub generate_exn: bool,
+    /// Retry transient failures with exponential backoff, per a policy in the client config
+    pub generate_retry: bool,
+    /// Generate `ClientSpy.res`, a recording test double of the client
+    pub generate_spy: bool,
+    /// Add `{operationId}EachPage` and `{operationId}All` for paged list operations
+    pub generate_pagination: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            output_dir: PathBuf::from("src/api"),
+            module_prefix: "Api".to_string(),
+            imports: Imports::default(),
+            generate_schema: true,
+            generate_schema_registry: false,
+            describe_errors: false,
+            generate_client: true,
+            client_surface: ClientSurface::Functions,
+            response_style: ResponseStyle::Success,
+            body_serializer: BodySerializer::Schema,
+            generate_exn: false,
+            generate_retry: false,
+            generate_spy: false,
+            generate_pagination: true,
+        }
+    }
+}
+
+impl Config {
+    /// Reference to a sibling generated module, e.g. `Types` -> `MyApp.ApiTypes`
+    pub fn module_ref(&self, name: &str) -> String {
+        match &self.imports.namespace {
+            Some(namespace) => format!("{}.{}{}", namespace, self.module_prefix, name),
+            None => format!("{}{}", self.module_prefix, name),
+        }
+    }
+
+    /// Open statements for a generated file, followed by a blank line
+    ///
+    /// `siblings` are generated modules (e.g. `Types`) the file opens.
+    pub fn opens(&self, with_core: bool, siblings: &[&str]) -> String {
+        let mut modules: Vec<String> = Vec::new();
+        if with_core {
+            modules.extend(self.imports.core_module.clone());
+        }
+        modules.extend(self.imports.extra_opens.iter().cloned());
+        modules.extend(siblings.iter().map(|name| self.module_ref(name)));
+
+        if modules.is_empty() {
+            return String::new();
+        }
+        let mut output: String = modules.iter().map(|m| format!("open {}\n", m)).collect();
+        output.push('\n');
+        output
+    }
+}
+
+/// `@deprecated` attribute for a spec item, e.g. a schema name or `GET /users`
+pub fn deprecated_attribute(item: &str) -> String {
+    format!("@deprecated(\"`{}` is deprecated in the API\")", item)
+}
+
+/// File-level attribute silencing deprecation warnings, if the spec deprecates anything
+///
+/// Generated code necessarily refers to deprecated types, fields and
+/// operations; only uses in application code should warn.
+pub fn allow_deprecated(spec: &ApiSpec) -> &'static str {
+    if spec.has_deprecations() {
+        "@@warning(\"-3\")\n\n"
+    } else {
+        ""
+    }
+}
+
+/// Quote a spec string as a ReScript string literal
+///
+/// Escapes quotes, backslashes and control characters; other characters,
+/// including non-ASCII text, are kept as they are.
+pub fn string_literal(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c if c.is_control() => literal.push_str(&format!("\\u{{{:04x}}}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Render a doc comment at the given indentation
+///
+/// Single-line docs stay as `/** ... */`; multi-line docs (e.g. with `@see`
+/// links) use a block comment with one ` * ` prefixed line each. A `*/` in
+/// the text is broken up so it can't end the comment early.
+pub fn doc_comment(doc: &str, indent: &str) -> String {
+    let doc = doc.replace("*/", "* /");
+    if !doc.contains('\n') {
+        return format!("{}/** {} */\n", indent, doc);
+    }
+
+    let mut output = format!("{}/**\n", indent);
+    for line in doc.lines() {
+        if line.trim().is_empty() {
+            output.push_str(&format!("{} *\n", indent));
+        } else {
+            output.push_str(&format!("{} * {}\n", indent, line.trim_end()));
+        }
+    }
+    output.push_str(&format!("{} */\n", indent));
+    output
+}
+
+/// Write a generated file, keeping the customization region of any existing file
+pub fn write_file(path: &Path, content: &str) -> Result<()> {
+    let content = match fs::read_to_string(path) {
+        Ok(existing) => region::merge(content, &existing)
+            .with_context(|| format!("Refusing to overwrite {}", path.display()))?,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => content.to_string(),
+        Err(error) => {
+            return Err(error).with_context(|| format!("Failed to read existing {}", path.display()))
+        }
+    };
+    fs::write(path, content)?;
+    Ok(())
 }
 
 /// Generate ReScript code from IR
@@ -31,21 +245,81 @@ pub fn generate(spec: &ApiSpec, config: &Config) -> Result<()> {
     // Generate Types.res - all type definitions
     let types_code = types::generate(spec, config)?;
     let types_path = config.output_dir.join(format!("{}Types.res", config.module_prefix));
-    fs::write(&types_path, types_code)?;
+    write_file(&types_path, &types_code)?;
+
+    // Generate Meta.res - spec version and hash
+    let meta_code = meta::generate(spec, config)?;
+    let meta_path = config.output_dir.join(format!("{}Meta.res", config.module_prefix));
+    write_file(&meta_path, &meta_code)?;
+
+    // Generate Auth.res - OAuth2 scopes, endpoints and token refresh
+    if spec.has_oauth2() {
+        let auth_code = auth::generate(spec, config)?;
+        let auth_path = config.output_dir.join(format!("{}Auth.res", config.module_prefix));
+        write_file(&auth_path, &auth_code)?;
+    }
 
     // Generate Schema.res - rescript-schema validators
     if config.generate_schema {
         let schema_code = schema::generate(spec, config)?;
         let schema_path = config.output_dir.join(format!("{}Schema.res", config.module_prefix));
-        fs::write(&schema_path, schema_code)?;
+        write_file(&schema_path, &schema_code)?;
     }
 
     // Generate Client.res - HTTP client functions
     if config.generate_client {
         let client_code = client::generate(spec, config)?;
         let client_path = config.output_dir.join(format!("{}Client.res", config.module_prefix));
-        fs::write(&client_path, client_code)?;
+        write_file(&client_path, &client_code)?;
+    }
+
+    // Generate ClientSpy.res - recording test double
+    if config.generate_spy {
+        let spy_code = spy::generate(spec, config)?;
+        let spy_path = config.output_dir.join(format!("{}ClientSpy.res", config.module_prefix));
+        write_file(&spy_path, &spy_code)?;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_literal() {
+        assert_eq!(string_literal("plain"), r#""plain""#);
+        assert_eq!(string_literal(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(string_literal(r"C:\temp"), r#""C:\\temp""#);
+        assert_eq!(string_literal("a\nb\r\tc"), r#""a\nb\r\tc""#);
+        assert_eq!(string_literal("nul\0bell\u{7}"), r#""nul\u{0000}bell\u{0007}""#);
+        assert_eq!(string_literal("caf\u{e9} \u{1f600}"), "\"caf\u{e9} \u{1f600}\"");
+        // Template delimiters have no meaning inside a string literal
+        assert_eq!(string_literal("${x}`"), r#""${x}`""#);
+    }
+
+    #[test]
+    fn test_doc_comment() {
+        assert_eq!(doc_comment("A pet", "  "), "  /** A pet */\n");
+        assert_eq!(doc_comment("Ends */ early", ""), "/** Ends * / early */\n");
+        assert_eq!(doc_comment("One\n\nTwo */", ""), "/**\n * One\n *\n * Two * /\n */\n");
+    }
+
+    #[test]
+    fn test_write_file_keeps_unreadable_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ApiTypes.res");
+        let mut generated = String::from("type a = int\n");
+        region::append(&mut generated);
+
+        write_file(&path, &generated).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), generated);
+
+        // Not UTF-8, so the region can't be read back: leave the file alone
+        let existing = [region::REGION_BEGIN.as_bytes(), b"\n\xff\n"].concat();
+        fs::write(&path, &existing).unwrap();
+        assert!(write_file(&path, &generated).is_err());
+        assert_eq!(fs::read(&path).unwrap(), existing);
+    }
+}
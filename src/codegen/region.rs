@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Protected customization region at the end of generated files
+//!
+//! Every generated file ends with an empty marked block. Anything written
+//! between the markers is carried over when the file is regenerated, so small
+//! hand-written helpers can live next to the generated code.
+
+use anyhow::{bail, Result};
+
+/// Marker opening the preserved region
+pub const REGION_BEGIN: &str = "// @rescript-openapi custom-begin";
+
+/// Marker closing the preserved region
+pub const REGION_END: &str = "// @rescript-openapi custom-end";
+
+/// Append an empty customization region to generated output
+pub fn append(output: &mut String) {
+    output.push_str(REGION_BEGIN);
+    output.push_str("\n// Code between these markers is preserved across regeneration\n");
+    output.push_str(REGION_END);
+    output.push('\n');
+}
+
+/// Extract the body of the customization region from a file, if present
+pub fn extract(content: &str) -> Option<&str> {
+    let start = content.find(REGION_BEGIN)? + REGION_BEGIN.len();
+    let end = start + content[start..].find(REGION_END)?;
+    Some(&content[start..end])
+}
+
+/// Carry the customization region of an existing file into freshly generated output
+///
+/// Returns the generated output unchanged if either side has no region, and
+/// fails if the existing file opens a region it never closes, rather than
+/// dropping the custom code in it.
+pub fn merge(generated: &str, existing: &str) -> Result<String> {
+    let preserved = match extract(existing) {
+        Some(preserved) => preserved,
+        None if existing.contains(REGION_BEGIN) => bail!(
+            "The customization region is missing its closing `{}` marker",
+            REGION_END
+        ),
+        None => return Ok(generated.to_string()),
+    };
+    let Some(fresh) = extract(generated) else {
+        return Ok(generated.to_string());
+    };
+
+    let start = generated.find(REGION_BEGIN).unwrap_or(0) + REGION_BEGIN.len();
+    let end = start + fresh.len();
+    Ok(format!("{}{}{}", &generated[..start], preserved, &generated[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_keeps_custom_code() {
+        let mut generated = String::from("type a = int\n\n");
+        append(&mut generated);
+
+        let existing = format!(
+            "type a = string\n\n{}\nlet helper = () => 42\n{}\n",
+            REGION_BEGIN, REGION_END
+        );
+
+        let merged = merge(&generated, &existing).unwrap();
+        assert!(merged.starts_with("type a = int\n"));
+        assert!(merged.contains("let helper = () => 42"));
+        assert!(!merged.contains("preserved across regeneration"));
+    }
+
+    #[test]
+    fn test_merge_without_existing_region() {
+        let mut generated = String::from("type a = int\n");
+        append(&mut generated);
+        assert_eq!(merge(&generated, "type a = int\n").unwrap(), generated);
+    }
+
+    #[test]
+    fn test_merge_rejects_unclosed_region() {
+        let mut generated = String::from("type a = int\n");
+        append(&mut generated);
+        let existing = format!("type a = int\n\n{}\nlet helper = () => 42\n", REGION_BEGIN);
+        assert!(merge(&generated, &existing).is_err());
+    }
+}
@@ -3,10 +3,10 @@
 
 //! rescript-schema validator generation with topological sorting
 
-use crate::ir::{ApiSpec, TypeDef, Field, RsType};
-use super::Config;
+use crate::ir::{is_scalar_union, ApiSpec, Constraints, TypeDef, Field, RsType, VariantCase, Webhook};
+use super::{allow_deprecated, deprecated_attribute, doc_comment, region, string_literal, Config};
 use anyhow::Result;
-use heck::ToLowerCamelCase;
+use heck::{ToLowerCamelCase, ToPascalCase};
 use std::collections::{HashMap, HashSet, VecDeque};
 
 pub fn generate(spec: &ApiSpec, config: &Config) -> Result<String> {
@@ -18,23 +18,72 @@ pub fn generate(spec: &ApiSpec, config: &Config) -> Result<String> {
     output.push_str(&format!("// Source: {} v{}\n\n", spec.title, spec.version));
 
     // Import types
-    output.push_str(&format!("open {}Types\n\n", config.module_prefix));
+    output.push_str(&config.opens(false, &["Types"]));
+    output.push_str(allow_deprecated(spec));
 
     // Module alias for rescript-schema
-    output.push_str("module S = RescriptSchema.S\n\n");
+    output.push_str(&format!("module S = {}\n\n", config.imports.schema_module));
 
-    // Topologically sort types by dependencies
-    let sorted_types = topological_sort(&spec.types);
+    // Generate schemas in dependency order, recursive types via `S.recursive`
+    let types: Vec<&TypeDef> = spec.types.iter().collect();
+    output.push_str(&generate_schemas(&types, config, true));
 
-    // Generate schema for each type in dependency order
-    for type_def in sorted_types {
-        output.push_str(&generate_schema(type_def));
+    for webhook in &spec.webhooks {
+        output.push_str(&generate_webhook_parser(webhook));
+    }
+
+    if config.generate_schema_registry {
+        output.push_str(&generate_registry(spec));
         output.push('\n');
     }
 
+    region::append(&mut output);
+
     Ok(output)
 }
 
+/// Parser for the payload of a webhook the API sends, e.g. `parseNewPetWebhook`
+fn generate_webhook_parser(webhook: &Webhook) -> String {
+    let name = format!("parse{}Webhook", webhook.name.to_pascal_case());
+    let mut output = String::new();
+    if let Some(doc) = &webhook.doc {
+        output.push_str(&doc_comment(doc, ""));
+    }
+    if webhook.deprecated {
+        output.push_str(&deprecated_attribute(&name));
+        output.push('\n');
+    }
+    output.push_str(&format!(
+        "let {} = (json: Js.Json.t): {} => {{\n",
+        name,
+        webhook.payload.to_rescript()
+    ));
+    output.push_str(&format!("  S.parseJsonOrThrow(json, {})\n", webhook.payload.to_schema()));
+    output.push_str("}\n\n");
+    output
+}
+
+/// Runtime registry mapping API type names to their (existentially wrapped) schemas
+fn generate_registry(spec: &ApiSpec) -> String {
+    let mut output = String::new();
+
+    output.push_str("/** A schema of any type, for lookup by name at runtime */\n");
+    output.push_str("type anySchema = AnySchema(S.t<'a>): anySchema\n\n");
+
+    output.push_str("/** Every generated schema keyed by its API type name */\n");
+    output.push_str("let schemas: Dict.t<anySchema> = Dict.fromArray([\n");
+    for type_def in &spec.types {
+        output.push_str(&format!(
+            "  (\"{}\", AnySchema({}Schema)),\n",
+            type_def.name(),
+            type_def.name().to_lower_camel_case()
+        ));
+    }
+    output.push_str("])\n");
+
+    output
+}
+
 /// Extract type dependencies from a TypeDef
 fn get_dependencies(type_def: &TypeDef) -> HashSet<String> {
     let mut deps = HashSet::new();
@@ -66,7 +115,10 @@ fn collect_type_deps(ty: &RsType, deps: &mut HashSet<String>) {
         RsType::Named(name) => {
             deps.insert(name.to_lower_camel_case());
         }
-        RsType::Option(inner) | RsType::Array(inner) | RsType::Dict(inner) => {
+        RsType::Option(inner)
+        | RsType::Nullable(inner)
+        | RsType::Array(inner)
+        | RsType::Dict(inner) => {
             collect_type_deps(inner, deps);
         }
         RsType::Tuple(types) => {
@@ -78,166 +130,450 @@ fn collect_type_deps(ty: &RsType, deps: &mut HashSet<String>) {
     }
 }
 
-/// Topologically sort types so dependencies come before dependents
-pub fn topological_sort(types: &[TypeDef]) -> Vec<&TypeDef> {
-    // Build name -> TypeDef map
-    let type_map: HashMap<String, &TypeDef> = types
+/// Types grouped so dependencies come before dependents
+///
+/// Each group is a single type or a set of mutually recursive types (a cycle
+/// in the dependency graph, see [`is_recursive`]). Groups are ordered with
+/// Kahn's algorithm, ties broken by name for a deterministic order; types in
+/// a group keep their input order.
+pub fn type_groups<'a>(types: &[&'a TypeDef]) -> Vec<Vec<&'a TypeDef>> {
+    let names: Vec<String> = types.iter().map(|t| t.name().to_lower_camel_case()).collect();
+    let index: HashMap<&str, usize> =
+        names.iter().enumerate().map(|(i, name)| (name.as_str(), i)).collect();
+
+    // Only keep deps that are actually in our type set
+    let deps: Vec<Vec<usize>> = types
         .iter()
-        .map(|t| {
-            let name = match t {
-                TypeDef::Record { name, .. } => name.to_lower_camel_case(),
-                TypeDef::Variant { name, .. } => name.to_lower_camel_case(),
-                TypeDef::Alias { name, .. } => name.to_lower_camel_case(),
-            };
-            (name, t)
+        .map(|type_def| {
+            let mut deps: Vec<usize> = get_dependencies(type_def)
+                .iter()
+                .filter_map(|dep| index.get(dep.as_str()).copied())
+                .collect();
+            deps.sort();
+            deps
         })
         .collect();
 
-    // Build dependency graph (adjacency list: type -> types it depends on)
-    let mut deps_map: HashMap<String, HashSet<String>> = HashMap::new();
-    let mut all_names: Vec<String> = Vec::new();
+    let components = strongly_connected(&deps);
+    let mut component_of = vec![0; types.len()];
+    for (c, members) in components.iter().enumerate() {
+        for &member in members {
+            component_of[member] = c;
+        }
+    }
+    // Groups are known by their first name in sort order
+    let key = |c: usize| components[c].iter().map(|&i| &names[i]).min().cloned().unwrap_or_default();
+
+    // Kahn's algorithm over groups: in-degree counts the groups each one depends on
+    let mut group_deps: Vec<HashSet<usize>> = vec![HashSet::new(); components.len()];
+    for (i, type_deps) in deps.iter().enumerate() {
+        for &dep in type_deps {
+            if component_of[dep] != component_of[i] {
+                group_deps[component_of[i]].insert(component_of[dep]);
+            }
+        }
+    }
+    let mut in_degree: Vec<usize> = group_deps.iter().map(HashSet::len).collect();
+
+    // Start with groups that have no dependencies (sorted for deterministic order)
+    let mut ready: Vec<usize> = (0..components.len()).filter(|&c| in_degree[c] == 0).collect();
+    ready.sort_by_key(|&c| key(c));
+    let mut queue: VecDeque<usize> = ready.into_iter().collect();
+
+    let mut sorted: Vec<Vec<&TypeDef>> = Vec::new();
+    while let Some(c) = queue.pop_front() {
+        sorted.push(components[c].iter().map(|&i| types[i]).collect());
+
+        // Collect newly ready groups and sort them for deterministic order
+        let mut newly_ready: Vec<usize> = Vec::new();
+        for (other, other_deps) in group_deps.iter().enumerate() {
+            if other_deps.contains(&c) {
+                in_degree[other] -= 1;
+                if in_degree[other] == 0 {
+                    newly_ready.push(other);
+                }
+            }
+        }
+        newly_ready.sort_by_key(|&c| key(c));
+        queue.extend(newly_ready);
+    }
 
-    for type_def in types {
-        let name = match type_def {
-            TypeDef::Record { name, .. } => name.to_lower_camel_case(),
-            TypeDef::Variant { name, .. } => name.to_lower_camel_case(),
-            TypeDef::Alias { name, .. } => name.to_lower_camel_case(),
-        };
-        all_names.push(name.clone());
+    sorted
+}
 
-        let deps = get_dependencies(type_def);
-        // Only keep deps that are actually in our type set
-        let filtered_deps: HashSet<String> = deps
-            .into_iter()
-            .filter(|d| type_map.contains_key(d))
-            .collect();
-        deps_map.insert(name, filtered_deps);
+/// Topologically sort types so dependencies come before dependents
+///
+/// Mutually recursive types end up next to each other, in no dependency
+/// order; use [`type_groups`] to tell them apart.
+#[deprecated(note = "use `type_groups`, which keeps mutually recursive types together")]
+pub fn topological_sort(types: &[TypeDef]) -> Vec<&TypeDef> {
+    let types: Vec<&TypeDef> = types.iter().collect();
+    type_groups(&types).into_iter().flatten().collect()
+}
+
+/// Whether a group from [`type_groups`] has to be declared recursively
+pub fn is_recursive(group: &[&TypeDef]) -> bool {
+    match group {
+        [type_def] => get_dependencies(type_def).contains(&type_def.name().to_lower_camel_case()),
+        _ => true,
     }
+}
 
-    // Kahn's algorithm for topological sort
-    // Calculate in-degree (how many types depend on this type)
-    let mut in_degree: HashMap<String, usize> = HashMap::new();
-    for name in &all_names {
-        in_degree.insert(name.clone(), 0);
+/// Strongly connected components of a graph given as adjacency lists
+/// (Tarjan's algorithm); members of each component are in index order
+fn strongly_connected(deps: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct Tarjan<'a> {
+        deps: &'a [Vec<usize>],
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        next: usize,
+        components: Vec<Vec<usize>>,
     }
 
-    // Build reverse graph: for each type, which types depend on it
-    for (name, deps) in &deps_map {
-        // Each dependency means 'name' has an incoming edge
-        // (dep must come before name in the sorted order)
-        *in_degree.get_mut(name).unwrap() += deps.len();
+    impl Tarjan<'_> {
+        fn visit(&mut self, v: usize) {
+            self.index[v] = Some(self.next);
+            self.low[v] = self.next;
+            self.next += 1;
+            self.stack.push(v);
+            self.on_stack[v] = true;
+
+            for &w in &self.deps[v] {
+                match self.index[w] {
+                    None => {
+                        self.visit(w);
+                        self.low[v] = self.low[v].min(self.low[w]);
+                    }
+                    Some(w_index) if self.on_stack[w] => self.low[v] = self.low[v].min(w_index),
+                    Some(_) => {}
+                }
+            }
+
+            if Some(self.low[v]) == self.index[v] {
+                let mut component = Vec::new();
+                while let Some(w) = self.stack.pop() {
+                    self.on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                component.sort();
+                self.components.push(component);
+            }
+        }
     }
 
-    // Start with types that have no dependencies (sorted for deterministic order)
-    let mut zero_degree: Vec<String> = in_degree
-        .iter()
-        .filter(|(_, &degree)| degree == 0)
-        .map(|(name, _)| name.clone())
-        .collect();
-    zero_degree.sort();
-    let mut queue: VecDeque<String> = zero_degree.into_iter().collect();
+    let mut tarjan = Tarjan {
+        deps,
+        index: vec![None; deps.len()],
+        low: vec![0; deps.len()],
+        on_stack: vec![false; deps.len()],
+        stack: Vec::new(),
+        next: 0,
+        components: Vec::new(),
+    };
+    for v in 0..deps.len() {
+        if tarjan.index[v].is_none() {
+            tarjan.visit(v);
+        }
+    }
+    tarjan.components
+}
 
-    let mut sorted: Vec<&TypeDef> = Vec::new();
+/// Schemas for types in dependency order, with helpers when `top_level`
+///
+/// A recursive group is built around one of its types (the first record or
+/// variant), whose schema is wrapped in `S.recursive`: the others are defined
+/// inside it, referring to it through the `S.recursive` parameter, and again
+/// at top level once it exists. Cycles left among the others are broken the
+/// same way.
+fn generate_schemas(types: &[&TypeDef], config: &Config, top_level: bool) -> String {
+    let mut output = String::new();
 
-    while let Some(name) = queue.pop_front() {
-        if let Some(&type_def) = type_map.get(&name) {
-            sorted.push(type_def);
+    for group in type_groups(types) {
+        if !is_recursive(&group) {
+            if top_level {
+                output.push_str(&generate_schema(group[0], config));
+                output.push('\n');
+            } else {
+                output.push_str(&schema_doc(group[0]));
+                output.push_str(&format!(
+                    "{} = {}\n",
+                    schema_binding(group[0]),
+                    schema_expression(group[0], config)
+                ));
+            }
+            continue;
         }
 
-        // For each type that depends on this one, decrease its in-degree
-        // Collect newly ready types and sort them for deterministic order
-        let mut newly_ready: Vec<String> = Vec::new();
-        for (other_name, other_deps) in &deps_map {
-            if other_deps.contains(&name) {
-                let degree = in_degree.get_mut(other_name).unwrap();
-                *degree -= 1;
-                if *degree == 0 {
-                    newly_ready.push(other_name.clone());
-                }
+        let root = group
+            .iter()
+            .position(|t| !matches!(t, TypeDef::Alias { .. }))
+            .unwrap_or(0);
+        let others: Vec<&TypeDef> = group
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != root)
+            .map(|(_, t)| *t)
+            .collect();
+        let root = group[root];
+        let root_name = root.name().to_lower_camel_case();
+        let group_fn = format!("{}GroupSchemas", root_name);
+        let names: Vec<String> =
+            others.iter().map(|t| format!("{}Schema", t.name().to_lower_camel_case())).collect();
+        let pattern = |names: &[String]| match names {
+            [name] => name.clone(),
+            names => format!("({})", names.join(", ")),
+        };
+
+        // The other members are defined once, in a function of the root schema
+        // that is applied both inside `S.recursive` and at the top level
+        if !others.is_empty() {
+            output.push_str(&format!(
+                "/** Schemas recursive with `{}`, built on its schema */\n",
+                root_name
+            ));
+            output.push_str(&format!("let {} = ({}Schema: S.t<{}>) => {{\n", group_fn, root_name, root_name));
+            let body = format!("{}{}", generate_schemas(&others, config, false), pattern(&names));
+            for line in body.lines() {
+                output.push_str(&format!("  {}\n", line));
+            }
+            output.push_str("}\n");
+            if top_level {
+                output.push('\n');
             }
         }
-        newly_ready.sort();
-        for ready_name in newly_ready {
-            queue.push_back(ready_name);
+        if top_level {
+            output.push_str(&schema_doc(root));
+        }
+
+        // Members the root does not refer to are not bound in its schema
+        let root_deps = get_dependencies(root);
+        let used: Vec<String> = others
+            .iter()
+            .zip(&names)
+            .map(|(t, name)| {
+                if root_deps.contains(&t.name().to_lower_camel_case()) {
+                    name.clone()
+                } else {
+                    "_".to_string()
+                }
+            })
+            .collect();
+        output.push_str(&format!("{} = S.recursive({}Schema => {{\n", schema_binding(root), root_name));
+        let mut body = String::new();
+        if !others.is_empty() {
+            body.push_str(&format!("let {} = {}({}Schema)\n", pattern(&used), group_fn, root_name));
+        }
+        body.push_str(&schema_expression(root, config));
+        for line in body.lines() {
+            output.push_str(&format!("  {}\n", line));
+        }
+        output.push_str("})\n");
+        if top_level {
+            output.push_str(&schema_helpers(root));
+            output.push('\n');
         }
-    }
 
-    // If we didn't get all types, there's a cycle - just append remaining
-    if sorted.len() < types.len() {
-        for type_def in types {
-            let name = match type_def {
-                TypeDef::Record { name, .. } => name.to_lower_camel_case(),
-                TypeDef::Variant { name, .. } => name.to_lower_camel_case(),
-                TypeDef::Alias { name, .. } => name.to_lower_camel_case(),
-            };
-            if !sorted.iter().any(|t| {
-                let n = match t {
-                    TypeDef::Record { name, .. } => name.to_lower_camel_case(),
-                    TypeDef::Variant { name, .. } => name.to_lower_camel_case(),
-                    TypeDef::Alias { name, .. } => name.to_lower_camel_case(),
-                };
-                n == name
-            }) {
-                sorted.push(type_def);
+        if others.is_empty() {
+            continue;
+        }
+        output.push_str(&format!("let {} = {}({}Schema)\n", pattern(&names), group_fn, root_name));
+        if top_level {
+            for other in &others {
+                output.push_str(&schema_helpers(other));
             }
+            output.push('\n');
         }
     }
 
-    sorted
+    output
 }
 
-fn generate_schema(type_def: &TypeDef) -> String {
-    let mut output = String::new();
+pub(crate) fn generate_schema(type_def: &TypeDef, config: &Config) -> String {
+    let mut output = schema_doc(type_def);
+    output.push_str(&format!(
+        "{} = {}\n",
+        schema_binding(type_def),
+        schema_expression(type_def, config)
+    ));
+    output.push_str(&schema_helpers(type_def));
+    output
+}
+
+/// Doc comment for a type's schema, e.g. "Schema for `pet`: A pet in the store"
+fn schema_doc(type_def: &TypeDef) -> String {
+    let doc = match type_def {
+        TypeDef::Record { doc, .. } | TypeDef::Variant { doc, .. } | TypeDef::Alias { doc, .. } => {
+            doc
+        }
+    };
+    match doc {
+        Some(doc) => {
+            let type_name = type_def.name().to_lower_camel_case();
+            doc_comment(&format!("Schema for `{}`: {}", type_name, doc), "")
+        }
+        None => String::new(),
+    }
+}
 
+/// `let userSchema: S.t<user>`, unannotated for aliases
+fn schema_binding(type_def: &TypeDef) -> String {
+    let type_name = type_def.name().to_lower_camel_case();
     match type_def {
-        TypeDef::Record { name, doc, fields } => {
-            let schema_name = format!("{}Schema", name.to_lower_camel_case());
+        TypeDef::Alias { .. } => format!("let {}Schema", type_name),
+        _ => format!("let {}Schema: S.t<{}>", type_name, type_name),
+    }
+}
 
-            if let Some(doc) = doc {
-                output.push_str(&format!("/** Schema for {} */\n", doc));
-            }
+/// `parse{Name}` and `serialize{Name}` for records and variants with payloads
+fn schema_helpers(type_def: &TypeDef) -> String {
+    let helpers = match type_def {
+        TypeDef::Record { .. } => true,
+        TypeDef::Variant { cases, .. } => cases.iter().any(|c| c.payload.is_some()),
+        TypeDef::Alias { .. } => false,
+    };
+    if !helpers {
+        return String::new();
+    }
 
-            let type_name = name.to_lower_camel_case();
-            output.push_str(&format!("let {}: S.t<{}> = S.object(s => ({{\n", schema_name, type_name));
+    let name = type_def.name();
+    let type_name = name.to_lower_camel_case();
+    let schema_name = format!("{}Schema", type_name);
+    let mut output = String::new();
 
-            for field in fields {
-                output.push_str(&generate_field_schema(field));
-            }
+    // Add parse helper using parseJsonOrThrow
+    output.push('\n');
+    output.push_str(&format!(
+        "let parse{} = (json: Js.Json.t): {} => {{\n",
+        name,
+        type_name
+    ));
+    output.push_str(&format!("  S.parseJsonOrThrow(json, {})\n", schema_name));
+    output.push_str("}\n");
+
+    // Add serialize helper using reverseConvertToJsonOrThrow
+    output.push('\n');
+    output.push_str(&format!(
+        "let serialize{} = (value: {}): Js.Json.t => {{\n",
+        name,
+        type_name
+    ));
+    output.push_str(&format!("  S.reverseConvertToJsonOrThrow(value, {})\n", schema_name));
+    output.push_str("}\n");
+
+    if let TypeDef::Variant { cases, .. } = type_def {
+        output.push_str(&string_coercion(&type_name, cases));
+    }
+
+    output
+}
 
-            output.push_str(&format!("}}: {}))\n", type_name));
+/// For a string-or-number union, a schema reading either form as a string and
+/// writing strings as the string case, for ids that are only passed around
+fn string_coercion(type_name: &str, cases: &[VariantCase]) -> String {
+    if !is_scalar_union(cases) {
+        return String::new();
+    }
+    let Some(string_case) = cases.iter().find(|c| matches!(c.payload, Some(RsType::String))) else {
+        return String::new();
+    };
+    let arms: Vec<String> = cases
+        .iter()
+        .map(|case| match &case.payload {
+            Some(RsType::Int) => format!("| {}(v) => v->Int.toString", case.name),
+            Some(RsType::Float) => format!("| {}(v) => v->Float.toString", case.name),
+            Some(RsType::Bool) => format!("| {}(v) => v ? \"true\" : \"false\"", case.name),
+            _ => format!("| {}(v) => v", case.name),
+        })
+        .collect();
+    // Annotated, as the cases' names recur in other unions
+    format!(
+        "\n/** `{name}` read as a string whichever form it was sent in */\nlet {name}StringSchema: S.t<string> = {name}Schema->S.transform(_ => {{\n  parser: (value: {name}) => switch value {{ {arms} }},\n  serializer: (value): {name} => {case}(value),\n}})\n",
+        name = type_name,
+        arms = arms.join(" "),
+        case = string_case.name
+    )
+}
 
-            // Add parse helper using parseJsonOrThrow
-            output.push('\n');
-            output.push_str(&format!(
-                "let parse{} = (json: Js.Json.t): {} => {{\n",
-                name,
-                name.to_lower_camel_case()
-            ));
-            output.push_str(&format!("  S.parseJsonOrThrow(json, {})\n", schema_name));
-            output.push_str("}\n");
+/// The schema value for a type, without a trailing newline
+fn schema_expression(type_def: &TypeDef, config: &Config) -> String {
+    let mut output = String::new();
 
-            // Add serialize helper using reverseConvertToJsonOrThrow
-            output.push('\n');
-            output.push_str(&format!(
-                "let serialize{} = (value: {}): Js.Json.t => {{\n",
-                name,
-                name.to_lower_camel_case()
-            ));
-            output.push_str(&format!("  S.reverseConvertToJsonOrThrow(value, {})\n", schema_name));
-            output.push_str("}\n");
+    match type_def {
+        TypeDef::Record { name, fields, .. } if fields.iter().any(|f| f.additional) => {
+            output.push_str(&record_with_extra_schema(name, fields, config));
         }
 
-        TypeDef::Variant { name, doc, cases } => {
-            let schema_name = format!("{}Schema", name.to_lower_camel_case());
+        TypeDef::Record { name, fields, .. } => {
             let type_name = name.to_lower_camel_case();
+            output.push_str("S.object(s => ({\n");
 
-            if let Some(doc) = doc {
-                output.push_str(&format!("/** Schema for {} */\n", doc));
+            for field in fields {
+                output.push_str(&generate_field_schema(name, field, config));
             }
 
-            // String enum variant - use S.union with literals
-            if cases.iter().all(|c| c.payload.is_none()) {
-                output.push_str(&format!("let {}: S.t<{}> = S.union([\n", schema_name, type_name));
+            // Named schemas make parse errors point at the API type
+            output.push_str(&format!("}}: {}))->S.setName(\"{}\")", type_name, name));
+        }
+
+        TypeDef::Variant { name, cases, discriminator, .. } => {
+            let type_name = name.to_lower_camel_case();
+
+            if let Some(property) = discriminator {
+                // Discriminated union - pick the case from the tag instead of trying each
+                let tags: Vec<&str> = cases.iter().flat_map(|c| &c.tags).map(String::as_str).collect();
+                output.push_str("S.json->S.transform(s => {\n");
+                output.push_str("  parser: json =>\n");
+                output.push_str(&format!(
+                    "    switch json->JSON.Decode.object->Option.flatMap(o => o->Dict.get({}))->Option.flatMap(JSON.Decode.string) {{\n",
+                    string_literal(property)
+                ));
+                for case in cases {
+                    if let (false, Some(ty)) = (case.tags.is_empty(), &case.payload) {
+                        let tags: Vec<String> = case.tags.iter().map(|t| string_literal(t)).collect();
+                        output.push_str(&format!(
+                            "    | Some({}) => ({}(S.parseJsonOrThrow(json, {})): {})\n",
+                            tags.join(" | "),
+                            case.name,
+                            ty.to_schema(),
+                            type_name
+                        ));
+                    }
+                }
+                output.push_str(&format!(
+                    "    | _ => s.fail(\"Expected {} to be one of: {}\")\n    }},\n",
+                    property,
+                    tags.join(", ")
+                ));
+                // The tag is written even when the member schema doesn't declare it
+                output.push_str(&format!("  serializer: (v: {}) =>\n    switch v {{\n", type_name));
+                for case in cases {
+                    if let (Some(tag), Some(ty)) = (case.tags.first(), &case.payload) {
+                        output.push_str(&format!(
+                            "    | {}(x) =>\n      let o = S.reverseConvertToJsonOrThrow(x, {})->JSON.Decode.object->Option.getOr(Dict.make())\n      o->Dict.set({}, JSON.Encode.string({}))\n      JSON.Encode.object(o)\n",
+                            case.name,
+                            ty.to_schema(),
+                            string_literal(property),
+                            string_literal(tag)
+                        ));
+                    }
+                }
+                output.push_str("    },\n");
+                output.push_str(&format!("}})->S.setName(\"{}\")", name));
+            } else if cases.iter().any(|c| c.literal.is_some()) {
+                // Numeric or mixed enum - literals of the cases, which are their values at runtime
+                output.push_str("S.union([\n");
+                for case in cases {
+                    output.push_str(&format!("  S.literal({}),\n", case.name));
+                }
+                output.push_str(&format!("])->S.setName(\"{}\")", name));
+            } else if cases.iter().all(|c| c.payload.is_none()) {
+                // String enum variant - use S.union with literals
+                output.push_str("S.union([\n");
 
                 for case in cases {
                     output.push_str(&format!(
@@ -246,19 +582,22 @@ fn generate_schema(type_def: &TypeDef) -> String {
                     ));
                 }
 
-                output.push_str("])\n");
+                output.push_str(&format!("])->S.setName(\"{}\")", name));
             } else {
                 // oneOf/anyOf variant - wrap each referenced type's schema
-                output.push_str(&format!("let {}: S.t<{}> = S.union([\n", schema_name, type_name));
+                output.push_str("S.union([\n");
 
                 for case in cases {
                     match &case.payload {
                         Some(ty) => {
-                            // Wrap the inner schema to transform to variant constructor
+                            // Wrap the inner schema to transform to variant constructor;
+                            // annotated because case names (e.g. `S`, `User`) can recur across variants
                             output.push_str(&format!(
-                                "  {}->S.transform(s => {{\n    parser: v => {}(v),\n    serializer: v => switch v {{ | {}(x) => x | _ => S.fail(\"Expected {}\") }}\n  }}),\n",
+                                "  {}->S.transform(s => {{\n    parser: v => ({}(v): {}),\n    serializer: (v: {}) => switch v {{ | {}(x) => x | _ => s.fail(\"Expected {}\") }}\n  }}),\n",
                                 ty.to_schema(),
                                 case.name,
+                                type_name,
+                                type_name,
                                 case.name,
                                 case.name
                             ));
@@ -275,46 +614,169 @@ fn generate_schema(type_def: &TypeDef) -> String {
                     }
                 }
 
-                output.push_str("])\n");
+                output.push_str(&format!("])->S.setName(\"{}\")", name));
             }
+        }
 
-            // Add parse/serialize helpers for variants with payloads
-            if cases.iter().any(|c| c.payload.is_some()) {
-                output.push('\n');
+        TypeDef::Alias { name, target, constraints, .. } => {
+            let schema = refined(target.to_schema(), target, constraints, None);
+            output.push_str(&format!("{}->S.setName(\"{}\")", schema, name));
+        }
+    }
+
+    output
+}
+
+/// Schema for a field's value, decoding from its wire type when coerced
+///
+/// Option wrappers stay outermost so absent and null values are still accepted.
+fn field_type_schema(field: &Field, config: &Config) -> String {
+    let value = |ty: &RsType| {
+        let schema = match &field.coerce_from {
+            Some(wire) => format!("{}->S.coerce({})", wire.to_schema(), ty.to_schema()),
+            None => ty.to_schema(),
+        };
+        refined(schema, ty, &field.constraints, field_description(field, config))
+    };
+    match &field.ty {
+        RsType::Option(inner) => format!("S.option({})", value(inner)),
+        RsType::Nullable(inner) => format!("S.nullable({})", value(inner)),
+        ty => value(ty),
+    }
+}
+
+/// A schema with refinements enforcing the constraints that apply to its type
+///
+/// With a `description`, each refinement fails with it in front of the rule,
+/// e.g. `Primary email address: Must be at least 8 characters long`.
+fn refined(schema: String, ty: &RsType, constraints: &Constraints, description: Option<&str>) -> String {
+    let text = |rule: String| match description {
+        Some(description) => format!("{}: {}", description, rule),
+        None => rule,
+    };
+    // `~message` for built-in refinements, which have their own default
+    let message = |rule: String| match description {
+        Some(_) => format!(", ~message={}", string_literal(&text(rule))),
+        None => String::new(),
+    };
+    let fail = |rule: String| format!("s.fail({})", string_literal(&text(rule)));
+    let mut output = schema;
+    match ty {
+        RsType::String => {
+            if let Some(min) = constraints.min_length {
+                let rule = format!("Must be at least {} characters long", min);
+                output.push_str(&format!("->S.String.min({}{})", min, message(rule)));
+            }
+            if let Some(max) = constraints.max_length {
+                let rule = format!("Must be at most {} characters long", max);
+                output.push_str(&format!("->S.String.max({}{})", max, message(rule)));
+            }
+            if let Some(pattern) = &constraints.pattern {
+                // A JSON string literal is a valid ReScript one
+                let literal = serde_json::Value::String(pattern.clone()).to_string();
+                let rule = format!("Must match {}", pattern);
                 output.push_str(&format!(
-                    "let parse{} = (json: Js.Json.t): {} => {{\n",
-                    name,
-                    type_name
+                    "->S.String.pattern(RegExp.fromString({}){})",
+                    literal,
+                    message(rule)
                 ));
-                output.push_str(&format!("  S.parseJsonOrThrow(json, {})\n", schema_name));
-                output.push_str("}\n");
-
-                output.push('\n');
+            }
+        }
+        RsType::Int => {
+            // Exclusive bounds on integers are the next integer in
+            if let Some(min) = constraints.minimum {
+                let min = if constraints.exclusive_minimum { min + 1.0 } else { min };
+                let rule = format!("Must be at least {}", min);
+                output.push_str(&format!("->S.Int.min({}{})", min, message(rule)));
+            }
+            if let Some(max) = constraints.maximum {
+                let max = if constraints.exclusive_maximum { max - 1.0 } else { max };
+                let rule = format!("Must be at most {}", max);
+                output.push_str(&format!("->S.Int.max({}{})", max, message(rule)));
+            }
+            if let Some(step) = constraints.multiple_of.filter(|m| *m > 0.0) {
                 output.push_str(&format!(
-                    "let serialize{} = (value: {}): Js.Json.t => {{\n",
-                    name,
-                    type_name
+                    "->S.refine(s => v => if mod(v, {}) != 0 {{{}}})",
+                    step,
+                    fail(format!("Must be a multiple of {}", step))
                 ));
-                output.push_str(&format!("  S.reverseConvertToJsonOrThrow(value, {})\n", schema_name));
-                output.push_str("}\n");
             }
         }
-
-        TypeDef::Alias { name, doc, target } => {
-            let schema_name = format!("{}Schema", name.to_lower_camel_case());
-
-            if let Some(doc) = doc {
-                output.push_str(&format!("/** Schema for {} */\n", doc));
+        RsType::Float => {
+            if let Some(min) = constraints.minimum {
+                if constraints.exclusive_minimum {
+                    output.push_str(&format!(
+                        "->S.refine(s => v => if v <= {:?} {{{}}})",
+                        min,
+                        fail(format!("Must be greater than {}", min))
+                    ));
+                } else {
+                    let rule = format!("Must be at least {}", min);
+                    output.push_str(&format!("->S.Float.min({:?}{})", min, message(rule)));
+                }
+            }
+            if let Some(max) = constraints.maximum {
+                if constraints.exclusive_maximum {
+                    output.push_str(&format!(
+                        "->S.refine(s => v => if v >= {:?} {{{}}})",
+                        max,
+                        fail(format!("Must be less than {}", max))
+                    ));
+                } else {
+                    let rule = format!("Must be at most {}", max);
+                    output.push_str(&format!("->S.Float.max({:?}{})", max, message(rule)));
+                }
+            }
+            // Compared with a tolerance, as e.g. 19.99 isn't an exact multiple of 0.01 in binary
+            if let Some(step) = constraints.multiple_of.filter(|m| *m > 0.0) {
+                output.push_str(&format!(
+                    "->S.refine(s => v => {{let q = v /. {:?}; if Math.abs(q -. Math.round(q)) > 1e-9 {{{}}}}})",
+                    step,
+                    fail(format!("Must be a multiple of {}", step))
+                ));
             }
-
-            output.push_str(&format!("let {} = {}\n", schema_name, target.to_schema()));
         }
+        RsType::Array(_) => {
+            if let Some(min) = constraints.min_items {
+                let rule = format!("Must have at least {} items", min);
+                output.push_str(&format!("->S.Array.min({}{})", min, message(rule)));
+            }
+            if let Some(max) = constraints.max_items {
+                let rule = format!("Must have at most {} items", max);
+                output.push_str(&format!("->S.Array.max({}{})", max, message(rule)));
+            }
+            // Structural equality, so records and nested arrays compare by value
+            if constraints.unique_items {
+                output.push_str(&format!(
+                    "->S.refine(s => v => if v->Array.someWithIndex((x, i) => v->Array.findIndex(y => y == x) != i) {{{}}})",
+                    fail("Items must be unique".to_string())
+                ));
+            }
+        }
+        _ => {}
     }
-
     output
 }
 
-fn generate_field_schema(field: &Field) -> String {
+/// First line of a field's description, for validation messages with `describe_errors`
+fn field_description<'a>(field: &'a Field, config: &Config) -> Option<&'a str> {
+    field
+        .doc
+        .as_deref()
+        .filter(|_| config.describe_errors)
+        .and_then(|doc| doc.lines().map(str::trim).find(|line| !line.is_empty()))
+        .map(|line| line.trim_end_matches('.'))
+}
+
+/// Generate a named field schema so decode errors identify the field
+fn generate_field_schema(type_name: &str, field: &Field, config: &Config) -> String {
+    format!("  {}: {},\n", field.name, field_definition(type_name, field, config))
+}
+
+/// `s.field(...)` (or `s.fieldOr` when optional) reading a field by its wire name
+///
+/// Fields with a `default` read an option and fill in the default when absent.
+fn field_definition(type_name: &str, field: &Field, config: &Config) -> String {
     let method = if field.optional { "fieldOr" } else { "field" };
     let default = if field.optional {
         ", None"
@@ -322,25 +784,76 @@ fn generate_field_schema(field: &Field) -> String {
         ""
     };
 
-    let schema = field.ty.to_schema();
+    let value_schema = match &field.default {
+        Some(default) => {
+            format!("S.option({})->S.Option.getOr({})", field_type_schema(field, config), default)
+        }
+        None => field_type_schema(field, config),
+    };
+    let schema = format!(
+        "{}->S.setName({})",
+        value_schema,
+        string_literal(&format!("{}.{}", type_name, field.original_name))
+    );
+
+    format!("s.{}({}, {}{})", method, string_literal(&field.original_name), schema, default)
+}
 
-    if field.name != field.original_name {
-        format!(
-            "  {}: s.{}(\"{}\", {}{}),\n",
-            field.name,
-            method,
-            field.original_name,
-            schema,
-            default
-        )
-    } else {
-        format!(
-            "  {}: s.{}(\"{}\", {}{}),\n",
-            field.name,
-            method,
+/// Schema for a record whose `additional` field collects undeclared properties
+///
+/// Declared fields are read by an object schema, which drops unknown keys, so
+/// the rest are picked from the raw JSON and validated as a dict.
+fn record_with_extra_schema(name: &str, fields: &[Field], config: &Config) -> String {
+    let type_name = name.to_lower_camel_case();
+    let (extra, declared): (Vec<&Field>, Vec<&Field>) = fields.iter().partition(|f| f.additional);
+    let Some(extra) = extra.first() else {
+        return String::new();
+    };
+
+    let mut output = String::from("{\n  let declared = S.object(s => {\n");
+    for field in &declared {
+        output.push_str(&format!(
+            "    \"{}\": {},\n",
             field.name,
-            schema,
-            default
-        )
+            field_definition(name, field, config)
+        ));
+    }
+    output.push_str("  })\n");
+    output.push_str(&format!("  let extraSchema = {}\n", extra.ty.to_schema()));
+
+    let keys: Vec<String> = declared.iter().map(|f| string_literal(&f.original_name)).collect();
+    output.push_str("  S.json->S.transform(_ => {\n    parser: json => {\n");
+    output.push_str("      let fields = S.parseJsonOrThrow(json, declared)\n");
+    output.push_str("      let extra =\n        json\n        ->JSON.Decode.object\n        ->Option.getOr(Dict.make())\n        ->Dict.toArray\n");
+    output.push_str(&format!(
+        "        ->Array.filter(((key, _)) => !([{}]->Array.includes(key)))\n        ->Dict.fromArray\n",
+        keys.join(", ")
+    ));
+    output.push_str("      {\n");
+    for field in &declared {
+        output.push_str(&format!("        {}: fields[\"{}\"],\n", field.name, field.name));
     }
+    output.push_str(&format!(
+        "        {}: S.parseJsonOrThrow(JSON.Encode.object(extra), extraSchema),\n      }}\n    }},\n",
+        extra.name
+    ));
+
+    let values: Vec<String> = declared
+        .iter()
+        .map(|f| format!("\"{}\": value.{}", f.name, f.name))
+        .collect();
+    output.push_str(&format!("    serializer: (value: {}) => {{\n", type_name));
+    output.push_str(&format!(
+        "      let json = S.reverseConvertToJsonOrThrow({{{}}}, declared)\n",
+        values.join(", ")
+    ));
+    output.push_str(&format!(
+        "      switch (json->JSON.Decode.object, S.reverseConvertToJsonOrThrow(value.{}, extraSchema)->JSON.Decode.object) {{\n",
+        extra.name
+    ));
+    // Declared fields win over extra entries with the same key
+    output.push_str("      | (Some(fields), Some(extra)) =>\n        JSON.Encode.object(Dict.fromArray(Array.concat(extra->Dict.toArray, fields->Dict.toArray)))\n");
+    output.push_str("      | _ => json\n      }\n    },\n");
+    output.push_str(&format!("  }})->S.setName(\"{}\")\n}}", name));
+    output
 }
@@ -0,0 +1,275 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Recording test double for the generated client
+//!
+//! `ClientSpy.res` mirrors every client function signature, records each call
+//! (operationId, parameters, body) and answers from stubbed responses, so
+//! component tests can assert on API usage without a mock HTTP layer.
+//!
+//! Unstubbed operations answer with the success response's `example` when the
+//! spec has one (stubs and examples are parsed through the response schema), and `setLatency` / `setErrorRate` add delays and failures
+//! with the operation's declared error statuses, so the spy doubles as a
+//! development mock for loading and error states.
+
+use crate::ir::{ApiSpec, Endpoint, ParameterLocation, RsType};
+use super::client::{body_json, decode_json, decode_response, error_type, labeled_params, result_type};
+use super::{allow_deprecated, region, string_literal, Config, ResponseStyle};
+use anyhow::Result;
+
+pub fn generate(spec: &ApiSpec, config: &Config) -> Result<String> {
+    let mut output = String::new();
+
+    // Header
+    output.push_str("// SPDX-License-Identifier: AGPL-3.0-or-later\n");
+    output.push_str("// Generated by rescript-openapi - DO NOT EDIT\n");
+    output.push_str(&format!("// Source: {} v{}\n\n", spec.title, spec.version));
+
+    output.push_str(&config.opens(true, &["Types", "Schema", "Client"]));
+    output.push_str(allow_deprecated(spec));
+
+    output.push_str(r#"/** A recorded client call */
+type call = {
+  operationId: string,
+  params: Dict.t<JSON.t>,
+  body: option<JSON.t>,
+}
+
+/** Every call made through the spy, oldest first */
+let calls: array<call> = []
+
+/** Stubbed responses keyed by operationId */
+let responses: Dict.t<result<JSON.t, apiError>> = Dict.make()
+
+/** Delay before each response in milliseconds, picked uniformly from (min, max) */
+let latency = ref((0, 0))
+
+/** Chance (0.0 to 1.0) that a call fails with one of its declared error statuses */
+let errorRate = ref(0.0)
+
+/** Clear recorded calls, stubs, latency and error injection between tests */
+let reset = (): unit => {
+  calls->Array.splice(~start=0, ~remove=calls->Array.length, ~insert=[])
+  responses->Dict.keysToArray->Array.forEach(k => responses->Dict.delete(k))
+  latency := (0, 0)
+  errorRate := 0.0
+}
+
+/** Delay every response by `minMs` to `maxMs` milliseconds */
+let setLatency = (~minMs: int, ~maxMs: int=minMs, ()): unit => {
+  latency := (minMs, maxMs)
+}
+
+/** Fail this fraction of calls with a random declared error status */
+let setErrorRate = (rate: float): unit => {
+  errorRate := rate
+}
+
+/** Stub the result returned for an operation */
+let stub = (operationId: string, response: result<JSON.t, apiError>): unit => {
+  responses->Dict.set(operationId, response)
+}
+
+/** Calls made to one operation, oldest first */
+let callsTo = (operationId: string): array<call> => {
+  calls->Array.filter(c => c.operationId == operationId)
+}
+
+/** Whether an operation was called at least once */
+let wasCalled = (operationId: string): bool => callsTo(operationId)->Array.length > 0
+
+/** Fail unless an operation was called (exactly `times` times, if given) */
+let assertCalled = (~times: option<int>=?, operationId: string): unit => {
+  let count = callsTo(operationId)->Array.length
+  switch times {
+  | Some(expected) if count != expected =>
+    Exn.raiseError(`Expected ${operationId} to be called ${expected->Int.toString} times, got ${count->Int.toString}`)
+  | None if count == 0 => Exn.raiseError(`Expected ${operationId} to be called`)
+  | _ => ()
+  }
+}
+
+/** Fail if an operation was called */
+let assertNotCalled = (operationId: string): unit => {
+  if wasCalled(operationId) {
+    Exn.raiseError(`Expected ${operationId} not to be called`)
+  }
+}
+
+/** Fail unless the latest call to an operation had this parameter value */
+let assertCalledWith = (operationId: string, name: string, value: JSON.t): unit => {
+  switch callsTo(operationId)->Array.at(-1) {
+  | None => Exn.raiseError(`Expected ${operationId} to be called`)
+  | Some(c) if c.params->Dict.get(name) != Some(value) =>
+    Exn.raiseError(`Expected ${operationId} to be called with ${name}=${value->JSON.stringify}`)
+  | Some(_) => ()
+  }
+}
+
+let record = (operationId: string, params: Dict.t<JSON.t>, body: option<JSON.t>): unit => {
+  calls->Array.push({operationId, params, body})
+}
+
+let delay = async (): unit => {
+  let (minMs, maxMs) = latency.contents
+  if maxMs > 0 {
+    let ms = Math.Int.random(minMs, maxMs + 1)
+    await Promise.make((resolve, _) => setTimeout(() => resolve(), ms)->ignore)
+  }
+}
+
+/** Error statuses an operation declares, with their example bodies */
+type errorResponse = {status: int, example: option<JSON.t>}
+
+let respond = async (
+  operationId: string,
+  ~example: option<JSON.t>=?,
+  ~errors: array<errorResponse>=[],
+  (),
+): result<JSON.t, apiError> => {
+  await delay()
+  let injected = errorRate.contents > 0.0 && Math.random() < errorRate.contents
+  switch responses->Dict.get(operationId) {
+  | _ if injected =>
+    let {status, example} = switch errors->Array.get(Math.Int.random(0, errors->Array.length)) {
+    | Some(e) => e
+    | None => {status: 500, example: None}
+    }
+    Error({status, message: `Injected ${status->Int.toString} for ${operationId}`, body: example})
+  | Some(response) => response
+  | None =>
+    switch example {
+    | Some(json) => Ok(json)
+    | None => Error({status: 0, message: `No stubbed response for ${operationId}`, body: None})
+    }
+  }
+}
+
+"#);
+
+    for endpoint in &spec.endpoints {
+        output.push_str(&generate_spy_endpoint(endpoint, config));
+        output.push('\n');
+    }
+
+    region::append(&mut output);
+
+    Ok(output)
+}
+
+pub(crate) fn generate_spy_endpoint(endpoint: &Endpoint, config: &Config) -> String {
+    let mut output = String::new();
+
+    let decoded = config.response_style != ResponseStyle::Success;
+    let config_param = if decoded { "config: config" } else { "_config: config" };
+    let mut params = vec![config_param.to_string()];
+    params.extend(labeled_params(endpoint));
+
+    output.push_str(&format!(
+        "let {} = async ({}, ()): result<{}, {}> => {{\n",
+        endpoint.operation_id,
+        params.join(", "),
+        result_type(endpoint, config),
+        error_type(endpoint, config)
+    ));
+
+    output.push_str("  let params = Dict.make()\n");
+    for p in &endpoint.parameters {
+        if p.required || matches!(p.location, ParameterLocation::Path) {
+            output.push_str(&format!(
+                "  params->Dict.set(\"{}\", {}->Obj.magic)\n",
+                p.name, p.name
+            ));
+        } else {
+            output.push_str(&format!(
+                "  switch {} {{ | Some(v) => params->Dict.set(\"{}\", v->Obj.magic) | None => () }}\n",
+                p.name, p.name
+            ));
+        }
+    }
+
+    let body = match &endpoint.request_body {
+        // Files aren't JSON; record the form's fields as given
+        Some(body) if body.is_multipart() => "Some(body->Obj.magic)".to_string(),
+        Some(body) => match &body.choice {
+            Some(choice) => format!(
+                "switch body {{ | Json(body) => Some({}) | Multipart(body) => Some(body->Obj.magic) }}",
+                body_json(&choice.json, config)
+            ),
+            None => format!("Some({})", body_json(&body.ty, config)),
+        },
+        None => "None".to_string(),
+    };
+
+    output.push_str(&format!(
+        "  record(\"{}\", params, {})\n",
+        endpoint.operation_id, body
+    ));
+    // Stubs, examples and injected errors are decoded as real responses would be
+    let status = endpoint.success_response().and_then(|r| r.status.first_code());
+    output.push_str(&format!(
+        "  let response: result<JSON.t, apiError> = await respond({})\n",
+        respond_args(endpoint).join(", ")
+    ));
+    output.push_str(&format!(
+        "  let response = response->Result.map((body): httpResponse => {{status: {}, headers: Dict.make(), body}})\n",
+        status.unwrap_or(200)
+    ));
+    match decode_response(endpoint, config, "response", "  ") {
+        Some(decode) => output.push_str(&format!("  {}\n", decode)),
+        None => output.push_str(&decode_success(endpoint)),
+    }
+    output.push_str("}\n");
+
+    output
+}
+
+/// Success style: the success body parsed through its schema, so examples and
+/// stubs get the same `Date.t` fields and coercions as real responses
+fn decode_success(endpoint: &Endpoint) -> String {
+    match endpoint.success_response().and_then(|r| r.ty.as_ref()) {
+        None => "  response->Result.map(_ => ())\n".to_string(),
+        Some(ty @ (RsType::Json | RsType::RawText | RsType::RawBlob | RsType::RawArrayBuffer)) => {
+            format!("  response->Result.map(res => {})\n", decode_json(ty, "res.body"))
+        }
+        Some(ty) => format!(
+            "  switch response {{\n  | Ok(res) =>\n    try {{\n      Ok({})\n    }} catch {{\n    | Exn.Error(e) => Error({{status: 0, message: Exn.message(e)->Option.getOr(\"Parse error\"), body: Some(res.body)}})\n    }}\n  | Error(e) => Error(e)\n  }}\n",
+            decode_json(ty, "res.body")
+        ),
+    }
+}
+
+/// Arguments to `respond`: the operationId, then examples and error statuses from the spec
+fn respond_args(endpoint: &Endpoint) -> Vec<String> {
+    let mut args = vec![string_literal(&endpoint.operation_id)];
+
+    let success = endpoint.success_response();
+    if let Some(example) = success.and_then(|r| r.example.as_ref()) {
+        args.push(format!("~example={}", json_literal(example)));
+    }
+
+    let errors: Vec<String> = endpoint
+        .responses
+        .iter()
+        .filter(|r| r.is_error())
+        .map(|r| {
+            let example = match &r.example {
+                Some(example) => format!("Some({})", json_literal(example)),
+                None => "None".to_string(),
+            };
+            // A range is represented by its first status, e.g. 400 for `4XX`
+            let status = r.status.first_code().unwrap_or(500);
+            format!("{{status: {}, example: {}}}", status, example)
+        })
+        .collect();
+    if !errors.is_empty() {
+        args.push(format!("~errors=[{}]", errors.join(", ")));
+    }
+
+    args.push("()".to_string());
+    args
+}
+
+fn json_literal(value: &serde_json::Value) -> String {
+    format!("JSON.parseExn({})", string_literal(&value.to_string()))
+}
@@ -3,54 +3,177 @@
 
 //! ReScript type generation
 
-use crate::ir::{ApiSpec, TypeDef};
-use super::Config;
-use super::schema::topological_sort;
+use crate::ir::{is_scalar_union, ApiSpec, Field, FormFieldKind, RequestBody, RsType, TypeDef};
+use super::{allow_deprecated, deprecated_attribute, doc_comment, region, string_literal, Config};
+use super::schema::{is_recursive, type_groups};
 use anyhow::Result;
 use heck::ToLowerCamelCase;
 
-pub fn generate(spec: &ApiSpec, _config: &Config) -> Result<String> {
+pub fn generate(spec: &ApiSpec, config: &Config) -> Result<String> {
     let mut output = String::new();
 
     // Header
     output.push_str("// SPDX-License-Identifier: AGPL-3.0-or-later\n");
     output.push_str("// Generated by rescript-openapi - DO NOT EDIT\n");
     output.push_str(&format!("// Source: {} v{}\n\n", spec.title, spec.version));
+    output.push_str(&config.opens(false, &[]));
+    output.push_str(allow_deprecated(spec));
 
-    // Topologically sort types so dependencies come before dependents
-    let sorted_types = topological_sort(&spec.types);
-
-    // Generate each type in dependency order
-    for type_def in sorted_types {
-        output.push_str(&generate_type(type_def));
-        output.push('\n');
+    // Generate types in dependency order, mutually recursive ones as `type rec ... and ...`
+    let types: Vec<&TypeDef> = spec.types.iter().collect();
+    for group in type_groups(&types) {
+        if is_recursive(&group) {
+            output.push_str(&generate_recursive_group(spec, &group));
+        } else {
+            output.push_str(&generate_definition(spec, group[0]));
+        }
     }
+    output.push_str(&generate_forms(spec));
+
+    region::append(&mut output);
 
     Ok(output)
 }
 
-fn generate_type(type_def: &TypeDef) -> String {
+/// Generate a type, plus a `make` constructor for records sent as request bodies
+pub(crate) fn generate_definition(spec: &ApiSpec, type_def: &TypeDef) -> String {
+    let mut output = generate_type(type_def, "type");
+    output.push('\n');
+    output.push_str(&generate_constructor(spec, type_def));
+    output
+}
+
+/// Generate mutually recursive types as one `type rec ... and ...` declaration,
+/// followed by their constructors
+fn generate_recursive_group(spec: &ApiSpec, group: &[&TypeDef]) -> String {
     let mut output = String::new();
+    for (i, type_def) in group.iter().enumerate() {
+        let keyword = if i == 0 { "type rec" } else { "and" };
+        output.push_str(&generate_type(type_def, keyword));
+    }
+    output.push('\n');
+    for type_def in group {
+        output.push_str(&generate_constructor(spec, type_def));
+    }
+    output
+}
 
-    match type_def {
-        TypeDef::Record { name, doc, fields } => {
-            if let Some(doc) = doc {
-                output.push_str(&format!("/** {} */\n", doc));
+/// `make{Name}` for a record sent as a request body, if it is one
+fn generate_constructor(spec: &ApiSpec, type_def: &TypeDef) -> String {
+    let TypeDef::Record { name, fields, .. } = type_def else {
+        return String::new();
+    };
+    let is_request_body = spec.endpoints.iter().any(|e| {
+        let json = e.request_body.as_ref().and_then(|body| body.json_type());
+        matches!(json, Some(RsType::Named(n)) if n == name)
+    });
+    if !is_request_body {
+        return String::new();
+    }
+    let mut output = generate_make(name, fields);
+    output.push('\n');
+    output
+}
+
+/// Doc line added to fields the server fills in
+const SERVER_GENERATED_DOC: &str = "Set by the server; left unset by `make` unless required.";
+
+/// Doc line added to fields only clients send
+const WRITE_ONLY_DOC: &str = "Only sent to the server; absent from responses.";
+
+/// Generate `make{Name}`, building a request payload from labeled arguments
+///
+/// Optional fields become optional arguments and optional server-generated
+/// fields are left out, so callers only name what they actually send. The
+/// record still needs a value for a required server-generated field, so it
+/// stays an argument.
+fn generate_make(name: &str, fields: &[Field]) -> String {
+    let type_name = name.to_lower_camel_case();
+    let unset = |f: &Field| f.server_generated && matches!(f.ty, RsType::Option(_) | RsType::Nullable(_));
+    let args: Vec<String> = fields
+        .iter()
+        .filter(|f| !unset(f))
+        .map(|f| match &f.ty {
+            RsType::Option(inner) | RsType::Nullable(inner) => {
+                format!("~{}: {}=?", f.name, inner.to_rescript())
             }
+            ty if f.additional => format!("~{}: {}=Dict.make()", f.name, ty.to_rescript()),
+            ty if f.default.is_some() => format!(
+                "~{}: {}={}",
+                f.name,
+                ty.to_rescript(),
+                f.default.as_deref().unwrap_or_default()
+            ),
+            ty => format!("~{}: {}", f.name, ty.to_rescript()),
+        })
+        .collect();
+    let values: Vec<String> = fields
+        .iter()
+        .map(|f| {
+            if unset(f) {
+                format!("{}: None", f.name)
+            } else {
+                f.name.clone()
+            }
+        })
+        .collect();
+
+    let mut output = format!("/** Build a `{}` from labeled arguments, e.g. for a request body */\n", type_name);
+    output.push_str(&format!("let make{} = (\n", name));
+    for arg in &args {
+        output.push_str(&format!("  {},\n", arg));
+    }
+    output.push_str(&format!("  (),\n): {} => {{\n", type_name));
+    for value in &values {
+        output.push_str(&format!("  {},\n", value));
+    }
+    output.push_str("}\n");
+    output
+}
+
+/// Declare a type, starting with `keyword` (`type`, or `type rec`/`and` in a recursive group)
+fn generate_type(type_def: &TypeDef, keyword: &str) -> String {
+    let mut output = String::new();
 
+    let (TypeDef::Record { doc, .. } | TypeDef::Variant { doc, .. } | TypeDef::Alias { doc, .. }) =
+        type_def;
+    if let Some(doc) = doc {
+        output.push_str(&doc_comment(doc, ""));
+    }
+    if type_def.deprecated() {
+        output.push_str(&format!("{}\n", deprecated_attribute(type_def.name())));
+    }
+
+    match type_def {
+        TypeDef::Record { name, fields, .. } => {
             let type_name = name.to_lower_camel_case();
-            output.push_str(&format!("type {} = {{\n", type_name));
+            output.push_str(&format!("{} {} = {{\n", keyword, type_name));
 
             for field in fields {
-                if let Some(doc) = &field.doc {
-                    output.push_str(&format!("  /** {} */\n", doc));
+                let note = if field.server_generated {
+                    Some(SERVER_GENERATED_DOC)
+                } else if field.write_only {
+                    Some(WRITE_ONLY_DOC)
+                } else {
+                    None
+                };
+                let doc = match (&field.doc, note) {
+                    (Some(doc), Some(note)) => Some(format!("{}\n\n{}", doc, note)),
+                    (None, Some(note)) => Some(note.to_string()),
+                    (doc, None) => doc.clone(),
+                };
+                if let Some(doc) = &doc {
+                    output.push_str(&doc_comment(doc, "  "));
                 }
 
+                output.push_str("  ");
+                if field.deprecated {
+                    let property = format!("{}.{}", name, field.original_name);
+                    output.push_str(&format!("{} ", deprecated_attribute(&property)));
+                }
                 // Use @as for JSON field mapping if different
                 if field.name != field.original_name {
-                    output.push_str(&format!("  @as(\"{}\") ", field.original_name));
-                } else {
-                    output.push_str("  ");
+                    output.push_str(&format!("@as({}) ", string_literal(&field.original_name)));
                 }
 
                 output.push_str(&format!("{}: {},\n", field.name, field.ty.to_rescript()));
@@ -59,10 +182,7 @@ fn generate_type(type_def: &TypeDef) -> String {
             output.push_str("}\n");
         }
 
-        TypeDef::Variant { name, doc, cases } => {
-            if let Some(doc) = doc {
-                output.push_str(&format!("/** {} */\n", doc));
-            }
+        TypeDef::Variant { name, cases, .. } => {
 
             let type_name = name.to_lower_camel_case();
             let has_payloads = cases.iter().any(|c| c.payload.is_some());
@@ -70,7 +190,11 @@ fn generate_type(type_def: &TypeDef) -> String {
             if has_payloads {
                 // oneOf/anyOf with payloads - generate regular variant type
                 // type pet = Cat(cat) | Dog(dog)
-                output.push_str(&format!("type {} =\n", type_name));
+                if is_scalar_union(cases) {
+                    // Same runtime value as the JSON scalar, e.g. for path parameters
+                    output.push_str("@unboxed\n");
+                }
+                output.push_str(&format!("{} {} =\n", keyword, type_name));
 
                 for case in cases {
                     match &case.payload {
@@ -82,6 +206,13 @@ fn generate_type(type_def: &TypeDef) -> String {
                         }
                     }
                 }
+            } else if cases.iter().any(|c| c.literal.is_some()) {
+                // Numeric or mixed enum - each case is represented by its value at runtime
+                output.push_str(&format!("{} {} =\n", keyword, type_name));
+                for case in cases {
+                    let literal = case.literal.as_deref().unwrap_or_default();
+                    output.push_str(&format!("  | @as({}) {}\n", literal, case.name));
+                }
             } else {
                 // String enum - generate as polymorphic variant for better JSON interop
                 output.push_str(&format!("type {} = [\n", type_name));
@@ -124,15 +255,68 @@ fn generate_type(type_def: &TypeDef) -> String {
             }
         }
 
-        TypeDef::Alias { name, doc, target } => {
-            if let Some(doc) = doc {
-                output.push_str(&format!("/** {} */\n", doc));
-            }
+        TypeDef::Alias { name, target, .. } => {
 
             let type_name = name.to_lower_camel_case();
-            output.push_str(&format!("type {} = {}\n", type_name, target.to_rescript()));
+            output.push_str(&format!("{} {} = {}\n", keyword, type_name, target.to_rescript()));
         }
     }
 
     output
 }
+
+/// Generate the record types of `multipart/form-data` bodies, one per operation, and
+/// the variant of bodies that may also be sent as JSON
+fn generate_forms(spec: &ApiSpec) -> String {
+    let mut output = String::new();
+    for endpoint in &spec.endpoints {
+        let Some(body) = &endpoint.request_body else {
+            continue;
+        };
+        let Some(form_type) = body.form_type() else {
+            continue;
+        };
+        output.push_str(&format!(
+            "/** Parts of the `{}` multipart/form-data body */\n",
+            endpoint.operation_id
+        ));
+        if body.form.is_empty() {
+            output.push_str(&format!("type {} = unit\n\n", form_type.to_rescript()));
+        } else {
+            output.push_str(&format!("type {} = {{\n", form_type.to_rescript()));
+            output.push_str(&form_fields(body));
+            output.push_str("}\n\n");
+        }
+        if let Some(choice) = &body.choice {
+            output.push_str(&format!(
+                "/** Body of `{}`, in the encoding to send it with */\n",
+                endpoint.operation_id
+            ));
+            output.push_str(&format!(
+                "type {} = Json({}) | Multipart({})\n\n",
+                body.ty.to_rescript(),
+                choice.json.to_rescript(),
+                form_type.to_rescript()
+            ));
+        }
+    }
+    output
+}
+
+/// Fields of a form record, one per part
+fn form_fields(body: &RequestBody) -> String {
+    let mut output = String::new();
+    for field in &body.form {
+        if let Some(doc) = &field.doc {
+            output.push_str(&doc_comment(doc, "  "));
+        }
+        let ty = match &field.kind {
+            FormFieldKind::File => "Fetch.Blob.t".to_string(),
+            FormFieldKind::Files => "array<Fetch.Blob.t>".to_string(),
+            FormFieldKind::Value(ty) => ty.to_rescript(),
+        };
+        let optional = if field.required { "" } else { "?" };
+        output.push_str(&format!("  {}{}: {},\n", field.field, optional, ty));
+    }
+    output
+}
@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Compatibility report between previously generated code and a new spec
+//!
+//! Both sides are reduced to their top-level and nested declarations
+//! (`type`, `let`, `module`), keyed by qualified name (e.g. `Make.getUser`),
+//! so app owners can see which symbols a spec upgrade removes or changes.
+//! Comments, blank lines and customization regions are ignored.
+
+use crate::codegen::region;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// How a generated symbol differs between the old and new output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    /// Gone from the new output; callers will fail to compile
+    Removed,
+    /// Definition differs; callers may need updating
+    Changed,
+    /// Only in the new output
+    Added,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolChange {
+    pub file: String,
+    /// Qualified name, e.g. `Make.getUser`
+    pub symbol: String,
+    pub change: Change,
+}
+
+/// Spec hash recorded by earlier output, from `manifest.json` or the Meta module
+pub fn previous_spec_hash(dir: &Path, module_prefix: &str) -> Option<String> {
+    let manifest = std::fs::read_to_string(dir.join(crate::manifest::MANIFEST_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str::<crate::manifest::Manifest>(&json).ok());
+    if let Some(manifest) = manifest {
+        return Some(manifest.spec_hash);
+    }
+
+    let meta = std::fs::read_to_string(dir.join(format!("{}Meta.res", module_prefix))).ok()?;
+    meta.lines().find_map(|line| {
+        let hash = line.strip_prefix("let specHash = \"")?.strip_suffix('"')?;
+        Some(hash.to_string())
+    })
+}
+
+/// Generated `.res` files in a directory, as `(filename, content)` pairs
+///
+/// Only files carrying the generator's header are read, so hand-written
+/// modules next to the generated ones are never reported.
+pub fn read_generated(dir: &Path) -> std::io::Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "res") {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)?;
+        if content.contains("Generated by rescript-openapi") {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            files.push((name, content));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Compare old and new `(filename, content)` outputs symbol by symbol
+///
+/// Results are ordered by change kind, then file, then symbol.
+pub fn compare(old: &[(String, String)], new: &[(String, String)]) -> Vec<SymbolChange> {
+    let collect = |files: &[(String, String)]| -> BTreeMap<(String, String), String> {
+        files
+            .iter()
+            .flat_map(|(file, content)| {
+                symbols(content)
+                    .into_iter()
+                    .map(move |(symbol, body)| ((file.clone(), symbol), body))
+            })
+            .collect()
+    };
+    let old = collect(old);
+    let new = collect(new);
+
+    let mut changes: Vec<SymbolChange> = Vec::new();
+    for ((file, symbol), body) in &old {
+        let change = match new.get(&(file.clone(), symbol.clone())) {
+            None => Change::Removed,
+            Some(new_body) if new_body != body => Change::Changed,
+            Some(_) => continue,
+        };
+        changes.push(SymbolChange { file: file.clone(), symbol: symbol.clone(), change });
+    }
+    for (file, symbol) in new.keys() {
+        if !old.contains_key(&(file.clone(), symbol.clone())) {
+            changes.push(SymbolChange {
+                file: file.clone(),
+                symbol: symbol.clone(),
+                change: Change::Added,
+            });
+        }
+    }
+    changes.sort_by(|a, b| (a.change, &a.file, &a.symbol).cmp(&(b.change, &b.file, &b.symbol)));
+    changes
+}
+
+/// Declarations in a generated file, mapped to their normalized definitions
+///
+/// Modules map to an empty definition so they are reported as added or
+/// removed, while changes inside them are reported on their members.
+fn symbols(content: &str) -> BTreeMap<String, String> {
+    let content = strip_region(content);
+    let mut symbols: BTreeMap<String, String> = BTreeMap::new();
+    // Enclosing modules as (indent, name)
+    let mut scopes: Vec<(usize, String)> = Vec::new();
+    // Symbol whose definition is being collected, with its indent
+    let mut current: Option<(usize, String)> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || is_comment(trimmed) {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        // Declarations nested in a definition (e.g. local lets) are part of its body
+        let nested = current.as_ref().is_some_and(|(def_indent, _)| indent > *def_indent);
+
+        if let Some((keyword, name)) = declaration(trimmed).filter(|_| !nested) {
+            while scopes.last().is_some_and(|(scope_indent, _)| *scope_indent >= indent) {
+                scopes.pop();
+            }
+            let qualified: Vec<&str> = scopes
+                .iter()
+                .map(|(_, n)| n.as_str())
+                .chain(std::iter::once(name))
+                .collect();
+            let qualified = qualified.join(".");
+
+            if keyword == "module" && trimmed.ends_with('{') {
+                symbols.insert(qualified.clone(), String::new());
+                scopes.push((indent, name.to_string()));
+                current = None;
+            } else {
+                symbols.insert(qualified.clone(), trimmed.to_string());
+                current = Some((indent, qualified));
+            }
+            continue;
+        }
+
+        match &current {
+            // Closing brace of an enclosing module ends the definition
+            Some((def_indent, _)) if indent < *def_indent => current = None,
+            Some((_, name)) => {
+                let body = symbols.entry(name.clone()).or_default();
+                body.push('\n');
+                body.push_str(trimmed);
+            }
+            None => {}
+        }
+    }
+
+    symbols
+}
+
+/// `(keyword, name)` for a `type`, `let` or `module` declaration line
+fn declaration(line: &str) -> Option<(&'static str, &str)> {
+    let line = line.strip_prefix("@genType ").unwrap_or(line);
+    for keyword in ["type", "let", "module"] {
+        let Some(rest) = line.strip_prefix(keyword).and_then(|r| r.strip_prefix(' ')) else {
+            continue;
+        };
+        let rest = rest
+            .strip_prefix("rec ")
+            .or_else(|| rest.strip_prefix("type "))
+            .unwrap_or(rest);
+        let end = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\''))
+            .unwrap_or(rest.len());
+        if end > 0 {
+            return Some((keyword, &rest[..end]));
+        }
+    }
+    None
+}
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with("//") || line.starts_with("/*") || line.starts_with('*')
+}
+
+/// Drop the customization region, which holds user code
+fn strip_region(content: &str) -> String {
+    match region::extract(content) {
+        Some(custom) => content.replacen(custom, "", 1),
+        None => content.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, content: &str) -> Vec<(String, String)> {
+        vec![(name.to_string(), content.to_string())]
+    }
+
+    #[test]
+    fn test_symbols_are_qualified_and_ignore_comments() {
+        let symbols = symbols(
+            "/** A user */\ntype user = {\n  id: string,\n}\n\nmodule Make = (Http: HttpClient) => {\n  /** Get */\n  let getUser = async () => {\n    let path = \"/users\"\n    Ok()\n  }\n}\n",
+        );
+        assert_eq!(
+            symbols.keys().collect::<Vec<_>>(),
+            vec!["Make", "Make.getUser", "user"]
+        );
+        assert_eq!(symbols["user"], "type user = {\nid: string,\n}");
+    }
+
+    #[test]
+    fn test_compare_reports_removed_changed_added() {
+        let old = file("ApiTypes.res", "type a = int\ntype b = string\ntype c = bool\n");
+        let new = file("ApiTypes.res", "/** Docs don't count */\ntype a = int\ntype b = float\ntype d = unit\n");
+
+        let changes: Vec<_> = compare(&old, &new)
+            .into_iter()
+            .map(|c| (c.symbol, c.change))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("c".to_string(), Change::Removed),
+                ("b".to_string(), Change::Changed),
+                ("d".to_string(), Change::Added),
+            ]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Operation filtering by tag and path
+//!
+//! Prunes an OpenAPI document down to the selected operations and the
+//! components reachable from them, so the same subset can drive code
+//! generation and be handed to other tools.
+
+use crate::upgrade::WEBHOOKS_EXTENSION;
+use openapiv3::{OpenAPI, Operation, PathItem, ReferenceOr};
+use std::collections::{BTreeSet, VecDeque};
+
+const COMPONENT_REF_PREFIX: &str = "#/components/";
+
+/// Which operations to keep; empty lists match everything
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct Filter {
+    /// Keep operations carrying any of these tags
+    pub tags: Vec<String>,
+    /// Keep operations whose path starts with any of these prefixes
+    pub paths: Vec<String>,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.paths.is_empty()
+    }
+
+    fn matches(&self, path: &str, op: &Operation) -> bool {
+        let tag_ok = self.tags.is_empty() || op.tags.iter().any(|t| self.tags.contains(t));
+        let path_ok = self.paths.is_empty() || self.paths.iter().any(|p| path.starts_with(p));
+        tag_ok && path_ok
+    }
+}
+
+/// Return a copy of the spec with only matching operations and reachable components
+pub fn apply(spec: &OpenAPI, filter: &Filter) -> OpenAPI {
+    let mut filtered = spec.clone();
+    if filter.is_empty() {
+        return filtered;
+    }
+
+    filtered.paths.paths.retain(|path, item| match item {
+        ReferenceOr::Item(item) => {
+            retain_operations(path, item, filter);
+            item.iter().next().is_some()
+        }
+        // Referenced path items can't be inspected for tags; keep them on path match only
+        ReferenceOr::Reference { .. } => {
+            filter.tags.is_empty() && filter.matches(path, &Operation::default())
+        }
+    });
+
+    // Only keep tags still referenced by some operation
+    let used_tags: BTreeSet<&String> = filtered
+        .paths
+        .iter()
+        .filter_map(|(_, item)| item.as_item())
+        .flat_map(|item| item.iter().flat_map(|(_, op)| op.tags.iter()))
+        .collect();
+    let tags: Vec<_> = filtered
+        .tags
+        .iter()
+        .filter(|t| used_tags.contains(&t.name))
+        .cloned()
+        .collect();
+    filtered.tags = tags;
+
+    prune_components(&mut filtered);
+    filtered
+}
+
+fn retain_operations(path: &str, item: &mut PathItem, filter: &Filter) {
+    for slot in [
+        &mut item.get,
+        &mut item.put,
+        &mut item.post,
+        &mut item.delete,
+        &mut item.options,
+        &mut item.head,
+        &mut item.patch,
+        &mut item.trace,
+    ] {
+        if slot.as_ref().is_some_and(|op| !filter.matches(path, op)) {
+            *slot = None;
+        }
+    }
+}
+
+/// Drop components not reachable from the kept paths and webhooks
+///
+/// Refs are followed transitively through every section, so a shared response
+/// only a dropped operation used goes, along with the schemas it points to.
+/// Security schemes are named by requirements rather than `$ref`s and stay.
+fn prune_components(spec: &mut OpenAPI) {
+    let Some(components) = &spec.components else {
+        return;
+    };
+    let sections = serde_json::to_value(components).unwrap_or_default();
+
+    let mut queue: VecDeque<(String, String)> = VecDeque::new();
+    collect_component_refs(&serde_json::to_value(&spec.paths).unwrap_or_default(), &mut queue);
+    if let Some(webhooks) = spec.extensions.get(WEBHOOKS_EXTENSION) {
+        collect_component_refs(webhooks, &mut queue);
+    }
+
+    let mut reachable: BTreeSet<(String, String)> = BTreeSet::new();
+    while let Some(target) = queue.pop_front() {
+        if !reachable.insert(target.clone()) {
+            continue;
+        }
+        if let Some(value) = sections.get(&target.0).and_then(|section| section.get(&target.1)) {
+            collect_component_refs(value, &mut queue);
+        }
+    }
+
+    let reached = |section: &str, name: &str| {
+        reachable.contains(&(section.to_string(), name.to_string()))
+    };
+    if let Some(components) = &mut spec.components {
+        components.schemas.retain(|name, _| reached("schemas", name));
+        components.responses.retain(|name, _| reached("responses", name));
+        components.parameters.retain(|name, _| reached("parameters", name));
+        components.examples.retain(|name, _| reached("examples", name));
+        components.request_bodies.retain(|name, _| reached("requestBodies", name));
+        components.headers.retain(|name, _| reached("headers", name));
+        components.links.retain(|name, _| reached("links", name));
+        components.callbacks.retain(|name, _| reached("callbacks", name));
+    }
+}
+
+/// `(section, name)` of every `#/components/{section}/{name}` ref in `value`
+fn collect_component_refs(value: &serde_json::Value, out: &mut VecDeque<(String, String)>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("$ref", serde_json::Value::String(reference)) => {
+                        let mut segments = reference
+                            .strip_prefix(COMPONENT_REF_PREFIX)
+                            .into_iter()
+                            .flat_map(|pointer| pointer.split('/'))
+                            .map(|segment| segment.replace("~1", "/").replace("~0", "~"));
+                        if let (Some(section), Some(name)) = (segments.next(), segments.next()) {
+                            out.push_back((section, name));
+                        }
+                    }
+                    _ => collect_component_refs(value, out),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_component_refs(item, out);
+            }
+        }
+        _ => {}
+    }
+}
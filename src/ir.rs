@@ -6,13 +6,19 @@
 //! Transforms OpenAPI structures into a codegen-friendly IR that maps
 //! directly to ReScript constructs.
 
+use crate::parser::{Diagnostic, ErrorStrategy, Severity};
+use crate::upgrade::{PREFIX_ITEMS_EXTENSION, WEBHOOKS_EXTENSION};
 use anyhow::{Context, Result};
-use heck::{ToLowerCamelCase, ToPascalCase};
-use openapiv3::{OpenAPI, ReferenceOr, Schema, SchemaKind, Type};
-use std::collections::BTreeMap;
+use heck::{ToLowerCamelCase, ToPascalCase, ToSnakeCase};
+use openapiv3::{
+    AdditionalProperties, IntegerFormat, ObjectType, OpenAPI, ReferenceOr, Schema, SchemaKind,
+    StringFormat, StringType, Type, VariantOrUnknownOrEmpty,
+};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 /// ReScript reserved keywords that cannot be used as field names
-const RESERVED_KEYWORDS: &[&str] = &[
+pub(crate) const RESERVED_KEYWORDS: &[&str] = &[
     "type", "let", "module", "open", "include", "external", "if", "else",
     "switch", "when", "rec", "and", "as", "exception", "try", "catch",
     "while", "for", "in", "to", "downto", "assert", "lazy", "private",
@@ -20,9 +26,12 @@ const RESERVED_KEYWORDS: &[&str] = &[
     "land", "lor", "lxor", "lsl", "lsr", "asr", "await", "async",
 ];
 
-/// Sanitize a field name to avoid ReScript reserved keywords
-fn sanitize_field_name(name: &str) -> String {
-    let lower_name = name.to_lower_camel_case();
+/// Sanitize a field name into a ReScript identifier that isn't a reserved keyword
+pub(crate) fn sanitize_field_name(name: &str, field_case: FieldCase) -> String {
+    let lower_name = match field_case {
+        FieldCase::Camel | FieldCase::SnakeWire => identifier(&name.to_lower_camel_case()),
+        FieldCase::Preserve => identifier(name),
+    };
     if RESERVED_KEYWORDS.contains(&lower_name.as_str()) {
         format!("{}_", lower_name)
     } else {
@@ -30,14 +39,839 @@ fn sanitize_field_name(name: &str) -> String {
     }
 }
 
+/// A name as a lowercase identifier: characters other than letters, digits
+/// and `_` become `_`, a leading capital is lowered and a leading digit
+/// prefixed with `_` (`X-Request-Id` -> `x_Request_Id`, `2fa` -> `_2fa`)
+fn identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    match identifier.chars().next() {
+        None => "_".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("_{}", identifier),
+        Some(first) => {
+            identifier.replace_range(..1, &first.to_ascii_lowercase().to_string());
+            identifier
+        }
+    }
+}
+
+/// Arguments and locals of generated client functions, which parameters can't be named
+const CLIENT_NAMES: &[&str] = &[
+    "config", "body", "accept", "timeoutMs", "retries", "signal", "onChunk", "onEvent",
+    "onItem", "onPage", "maxPages", "path", "query", "headers", "form", "req", "res",
+    "fetchFrom", "pagesFetched", "lastBody", "collected",
+];
+
+/// Argument name of a parameter, with a `_` suffix if it is a keyword or
+/// clashes with a client argument (`~signal_` for a `signal` query parameter)
+fn parameter_name(name: &str) -> String {
+    let name = name.to_lower_camel_case();
+    if RESERVED_KEYWORDS.contains(&name.as_str()) || CLIENT_NAMES.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+/// Append `@see <url>` lines for externalDocs links to a doc string
+fn with_external_docs(
+    doc: Option<String>,
+    external_docs: &[&openapiv3::ExternalDocumentation],
+) -> Option<String> {
+    if external_docs.is_empty() {
+        return doc;
+    }
+
+    let mut lines: Vec<String> = doc.into_iter().collect();
+    for docs in external_docs {
+        match &docs.description {
+            Some(description) => lines.push(format!("@see {} {}", docs.url, description)),
+            None => lines.push(format!("@see {}", docs.url)),
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+/// Vendor extensions used by Swagger 2.0 tooling to mark nullable schemas
+const NULLABLE_EXTENSIONS: &[&str] = &["x-nullable", "x-isnullable"];
+
+/// Function name for an operation without an operationId
+///
+/// Verb from the method, nouns from the path, `By<Param>` for path parameters:
+/// `GET /users/{id}/posts` becomes `getUsersByIdPosts`.
+fn derive_operation_id(method: &str, path: &str) -> String {
+    let verb = match method {
+        "post" => "create",
+        "put" => "update",
+        other => other,
+    };
+
+    let mut name = verb.to_string();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(param) => name.push_str(&format!("By{}", param.to_pascal_case())),
+            None => name.push_str(&segment.to_pascal_case()),
+        }
+    }
+    name.to_lower_camel_case()
+}
+
+/// Fingerprint a spec by hashing its normalized JSON form
+///
+/// Formatting, comments and YAML-vs-JSON differences don't change the hash.
+pub(crate) fn spec_hash(spec: &OpenAPI) -> String {
+    let normalized = serde_json::to_vec(spec).unwrap_or_default();
+    crate::bundle::sha256_hex(&normalized)
+}
+
+/// Whether any security scheme or requirement is declared, globally or per operation
+fn has_security(spec: &OpenAPI) -> bool {
+    let schemes = spec
+        .components
+        .as_ref()
+        .is_some_and(|c| !c.security_schemes.is_empty());
+    let required = spec.security.as_ref().is_some_and(|s| !s.is_empty())
+        || spec
+            .operations()
+            .any(|(_, _, op)| op.security.as_ref().is_some_and(|s| !s.is_empty()));
+    schemes || required
+}
+
+/// Security schemes from `components.securitySchemes`, in declaration order
+fn security_schemes(spec: &OpenAPI) -> Vec<SecurityScheme> {
+    let Some(components) = &spec.components else {
+        return Vec::new();
+    };
+    components
+        .security_schemes
+        .iter()
+        .filter_map(|(name, scheme)| match scheme {
+            ReferenceOr::Item(scheme) => Some((name, scheme)),
+            ReferenceOr::Reference { .. } => None,
+        })
+        .map(|(name, scheme)| {
+            let kind = match scheme {
+                openapiv3::SecurityScheme::HTTP { scheme, .. }
+                    if scheme.eq_ignore_ascii_case("bearer") =>
+                {
+                    SecuritySchemeKind::Bearer
+                }
+                openapiv3::SecurityScheme::APIKey { location, name, .. } => {
+                    SecuritySchemeKind::ApiKey {
+                        name: name.clone(),
+                        location: match location {
+                            openapiv3::APIKeyLocation::Header => ParameterLocation::Header,
+                            openapiv3::APIKeyLocation::Query => ParameterLocation::Query,
+                            openapiv3::APIKeyLocation::Cookie => ParameterLocation::Cookie,
+                        },
+                    }
+                }
+                openapiv3::SecurityScheme::OAuth2 { flows, .. } => oauth2_scheme(flows),
+                openapiv3::SecurityScheme::OpenIDConnect { .. } => {
+                    SecuritySchemeKind::OpenIdConnect
+                }
+                openapiv3::SecurityScheme::HTTP { .. } => SecuritySchemeKind::Unsupported,
+            };
+            SecurityScheme { name: name.clone(), kind }
+        })
+        .collect()
+}
+
+/// A server whose URL template and variables are kept as declared
+fn lower_server(server: &openapiv3::Server) -> Server {
+    let variables = server
+        .variables
+        .iter()
+        .flatten()
+        .map(|(name, variable)| {
+            let mut values = variable.enumeration.clone();
+            // A default outside its `enum` is still a value the server accepts
+            if !values.is_empty() && !values.contains(&variable.default) {
+                values.insert(0, variable.default.clone());
+            }
+            ServerVariable {
+                name: name.clone(),
+                values,
+                default: variable.default.clone(),
+                doc: variable.description.clone(),
+            }
+        })
+        .collect();
+    Server {
+        url: server.url.clone(),
+        doc: server.description.clone(),
+        variables,
+    }
+}
+
+/// Endpoints and scopes of an `oauth2` scheme's flows; scopes are merged across flows
+fn oauth2_scheme(flows: &openapiv3::OAuth2Flows) -> SecuritySchemeKind {
+    let mut oauth_flows = Vec::new();
+    let mut scopes: Vec<(String, String)> = Vec::new();
+    let mut add_scopes = |flow_scopes: Vec<(&String, &String)>| {
+        for (scope, description) in flow_scopes {
+            if !scopes.iter().any(|(s, _)| s == scope) {
+                scopes.push((scope.clone(), description.clone()));
+            }
+        }
+    };
+    if let Some(flow) = &flows.authorization_code {
+        add_scopes(flow.scopes.iter().collect());
+        oauth_flows.push(OAuthFlow {
+            flow: "authorizationCode",
+            authorization_url: Some(flow.authorization_url.clone()),
+            token_url: Some(flow.token_url.clone()),
+            refresh_url: flow.refresh_url.clone(),
+        });
+    }
+    if let Some(flow) = &flows.client_credentials {
+        add_scopes(flow.scopes.iter().collect());
+        oauth_flows.push(OAuthFlow {
+            flow: "clientCredentials",
+            authorization_url: None,
+            token_url: Some(flow.token_url.clone()),
+            refresh_url: flow.refresh_url.clone(),
+        });
+    }
+    if let Some(flow) = &flows.password {
+        add_scopes(flow.scopes.iter().collect());
+        oauth_flows.push(OAuthFlow {
+            flow: "password",
+            authorization_url: None,
+            token_url: Some(flow.token_url.clone()),
+            refresh_url: flow.refresh_url.clone(),
+        });
+    }
+    if let Some(flow) = &flows.implicit {
+        add_scopes(flow.scopes.iter().collect());
+        oauth_flows.push(OAuthFlow {
+            flow: "implicit",
+            authorization_url: Some(flow.authorization_url.clone()),
+            token_url: None,
+            refresh_url: flow.refresh_url.clone(),
+        });
+    }
+    SecuritySchemeKind::OAuth2 { flows: oauth_flows, scopes }
+}
+
+/// Whether a property is filled in by the server rather than sent by clients
+fn is_server_generated(schema: &Schema) -> bool {
+    schema.schema_data.read_only
+        || schema.schema_data.extensions.get(SERVER_GENERATED_EXTENSION)
+            == Some(&serde_json::Value::Bool(true))
+}
+
+/// Whether a property is only sent by clients and never returned (`writeOnly`)
+fn is_write_only(schema: &Schema) -> bool {
+    schema.schema_data.write_only
+}
+
+/// Give request bodies `{Name}Input` types without read-only fields
+///
+/// Records with read-only or write-only fields get an input copy without the
+/// read-only ones, and so does every type referring to one, with its
+/// references pointing at the input copies. Request bodies are retyped to use them, while the original
+/// records, left for responses, lose their write-only fields. Returns the
+/// number of types added.
+fn split_read_write(types: &mut Vec<TypeDef>, endpoints: &mut [Endpoint]) -> usize {
+    // Types needing an input copy: records with one-directional fields, then their dependents
+    let mut split: BTreeSet<String> = types
+        .iter()
+        .filter(|t| {
+            matches!(t, TypeDef::Record { fields, .. }
+                if fields.iter().any(|f| f.server_generated || f.write_only))
+        })
+        .map(|t| t.name().to_string())
+        .collect();
+    loop {
+        let dependents: Vec<String> = types
+            .iter()
+            .filter(|t| !split.contains(t.name()))
+            .filter(|t| type_def_refs(t).iter().any(|ty| mentions(ty, &split)))
+            .map(|t| t.name().to_string())
+            .collect();
+        if dependents.is_empty() {
+            break;
+        }
+        split.extend(dependents);
+    }
+
+    let mut taken: HashSet<String> = types.iter().map(|t| t.name().to_string()).collect();
+    let renames: BTreeMap<String, String> = split
+        .iter()
+        .map(|name| {
+            let base = format!("{}Input", name);
+            let input = (1..)
+                .map(|n| if n == 1 { base.clone() } else { format!("{}{}", base, n) })
+                .find(|candidate| !taken.contains(candidate))
+                .unwrap_or_default();
+            taken.insert(input.clone());
+            (name.clone(), input)
+        })
+        .collect();
+
+    let inputs: Vec<TypeDef> = types
+        .iter()
+        .filter_map(|type_def| {
+            let input_name = renames.get(type_def.name())?;
+            let mut input = type_def.clone();
+            match &mut input {
+                TypeDef::Record { name, doc, fields, .. } => {
+                    *name = input_name.clone();
+                    fields.retain(|f| !f.server_generated);
+                    for field in fields.iter_mut() {
+                        rename_named(&mut field.ty, &renames);
+                    }
+                    *doc = Some(input_doc(type_def.name(), doc.as_deref()));
+                }
+                TypeDef::Variant { name, doc, cases, .. } => {
+                    *name = input_name.clone();
+                    for payload in cases.iter_mut().filter_map(|c| c.payload.as_mut()) {
+                        rename_named(payload, &renames);
+                    }
+                    *doc = Some(input_doc(type_def.name(), doc.as_deref()));
+                }
+                TypeDef::Alias { name, doc, target, .. } => {
+                    *name = input_name.clone();
+                    rename_named(target, &renames);
+                    *doc = Some(input_doc(type_def.name(), doc.as_deref()));
+                }
+            }
+            Some(input)
+        })
+        .collect();
+
+    for type_def in types.iter_mut() {
+        if let TypeDef::Record { fields, .. } = type_def {
+            fields.retain(|f| !f.write_only);
+        }
+    }
+    for endpoint in endpoints.iter_mut() {
+        if let Some(body) = &mut endpoint.request_body {
+            rename_named(&mut body.ty, &renames);
+            if let Some(choice) = &mut body.choice {
+                rename_named(&mut choice.json, &renames);
+            }
+            for field in &mut body.form {
+                if let FormFieldKind::Value(ty) = &mut field.kind {
+                    rename_named(ty, &renames);
+                }
+            }
+        }
+    }
+    let added = inputs.len();
+    types.extend(inputs);
+    added
+}
+
+/// Doc comment of an input copy made by [`split_read_write`]
+fn input_doc(name: &str, doc: Option<&str>) -> String {
+    let note = format!("`{}` as sent in request bodies, without read-only fields", name);
+    match doc {
+        Some(doc) => format!("{}\n\n{}", doc, note),
+        None => note,
+    }
+}
+
+/// Types a type definition refers to
+fn type_def_refs(type_def: &TypeDef) -> Vec<&RsType> {
+    match type_def {
+        TypeDef::Record { fields, .. } => fields.iter().map(|f| &f.ty).collect(),
+        TypeDef::Variant { cases, .. } => cases.iter().filter_map(|c| c.payload.as_ref()).collect(),
+        TypeDef::Alias { target, .. } => vec![target],
+    }
+}
+
+/// Whether a type names one of `names`, possibly nested in containers
+fn mentions(ty: &RsType, names: &BTreeSet<String>) -> bool {
+    match ty {
+        RsType::Named(name) => names.contains(name),
+        RsType::Option(inner)
+        | RsType::Nullable(inner)
+        | RsType::Array(inner)
+        | RsType::Dict(inner) => mentions(inner, names),
+        RsType::Negotiated { json, .. } => mentions(json, names),
+        RsType::Tuple(types) => types.iter().any(|t| mentions(t, names)),
+        _ => false,
+    }
+}
+
+/// Point named references at their replacements, possibly nested in containers
+fn rename_named(ty: &mut RsType, renames: &BTreeMap<String, String>) {
+    match ty {
+        RsType::Named(name) => {
+            if let Some(renamed) = renames.get(name) {
+                *name = renamed.clone();
+            }
+        }
+        RsType::Option(inner)
+        | RsType::Nullable(inner)
+        | RsType::Array(inner)
+        | RsType::Dict(inner) => rename_named(inner, renames),
+        RsType::Negotiated { json, .. } => rename_named(json, renames),
+        RsType::Tuple(types) => {
+            for t in types {
+                rename_named(t, renames);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Promote inline string enums shared by two or more record fields to named aliases
+///
+/// Each alias is named after the field that uses it most (`order` -> `Order`,
+/// suffixed with `Enum` or a number if taken), so `[#"asc" | #"desc"]` repeated
+/// on every list endpoint becomes one `sortOrder` type with its own schema.
+fn promote_enums(types: &mut Vec<TypeDef>) {
+    // Enum values -> fields using them, in order of first appearance
+    let mut uses: Vec<(Vec<String>, Vec<FieldSite>)> = Vec::new();
+    for type_def in types.iter() {
+        if let TypeDef::Record { name, fields, .. } = type_def {
+            for field in fields {
+                if let Some(values) = inline_enum(&field.ty) {
+                    let site = (name.clone(), field.original_name.clone());
+                    match uses.iter_mut().find(|(v, _)| v == values) {
+                        Some((_, sites)) => sites.push(site),
+                        None => uses.push((values.clone(), vec![site])),
+                    }
+                }
+            }
+        }
+    }
+
+    let mut taken: HashSet<String> = types.iter().map(|t| t.name().to_string()).collect();
+    for (values, sites) in uses.into_iter().filter(|(_, sites)| sites.len() >= 2) {
+        let alias = enum_alias_name(&sites, &taken);
+        taken.insert(alias.clone());
+
+        for type_def in types.iter_mut() {
+            if let TypeDef::Record { fields, .. } = type_def {
+                for field in fields.iter_mut() {
+                    if inline_enum(&field.ty) == Some(&values) {
+                        replace_inline_enum(&mut field.ty, &alias);
+                    }
+                }
+            }
+        }
+
+        let used_by: Vec<String> = sites.iter().map(|(t, f)| format!("{}.{}", t, f)).collect();
+        types.push(TypeDef::Alias {
+            name: alias,
+            doc: Some(format!("Shared by {}", used_by.join(", "))),
+            target: RsType::StringEnum(values),
+            constraints: Constraints::default(),
+            deprecated: false,
+        });
+    }
+}
+
+/// `(type name, JSON property name)` of a record field
+type FieldSite = (String, String);
+
+/// Values of an inline string enum, possibly wrapped in option/array types
+fn inline_enum(ty: &RsType) -> Option<&Vec<String>> {
+    match ty {
+        RsType::StringEnum(values) => Some(values),
+        RsType::Option(inner) | RsType::Nullable(inner) | RsType::Array(inner) => inline_enum(inner),
+        _ => None,
+    }
+}
+
+fn replace_inline_enum(ty: &mut RsType, alias: &str) {
+    match ty {
+        RsType::StringEnum(_) => *ty = RsType::Named(alias.to_string()),
+        RsType::Option(inner) | RsType::Nullable(inner) | RsType::Array(inner) => {
+            replace_inline_enum(inner, alias)
+        }
+        _ => {}
+    }
+}
+
+/// PascalCase of the most common field name among the uses, made unique
+fn enum_alias_name(sites: &[FieldSite], taken: &HashSet<String>) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for (_, field) in sites {
+        match counts.iter_mut().find(|(name, _)| name == field) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((field, 1)),
+        }
+    }
+    // First most common name wins ties, keeping the choice stable
+    let best = counts
+        .iter()
+        .fold(counts[0], |best, &candidate| if candidate.1 > best.1 { candidate } else { best });
+    let base = best.0.to_pascal_case();
+
+    std::iter::once(base.clone())
+        .chain(std::iter::once(format!("{}Enum", base)))
+        .chain((2..).map(|n| format!("{}Enum{}", base, n)))
+        .find(|name| !taken.contains(name))
+        .unwrap_or(base)
+}
+
+/// How JSON property names map to ReScript record field names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FieldCase {
+    /// camelCase field names with `@as` for the original JSON name
+    #[default]
+    Camel,
+    /// Keep JSON names where they are valid identifiers; keywords and other
+    /// names are made valid (`x-request-id` -> `x_request_id`) with `@as`
+    Preserve,
+    /// camelCase field names sent as snake_case (`userId` -> `user_id`),
+    /// for backends that snake_case every property the spec names otherwise
+    SnakeWire,
+}
+
+impl FieldCase {
+    /// JSON name a spec property is sent and received as
+    fn wire_name(self, prop_name: &str) -> String {
+        match self {
+            FieldCase::Camel | FieldCase::Preserve => prop_name.to_string(),
+            FieldCase::SnakeWire => prop_name.to_snake_case(),
+        }
+    }
+}
+
+/// Whether a media type is read as bytes rather than text: files, images, audio, video
+fn is_binary_media_type(media_type: &str) -> bool {
+    let essence = media_type.split(';').next().unwrap_or_default().trim();
+    matches!(essence, "application/octet-stream" | "application/pdf" | "application/zip")
+        || ["image/", "audio/", "video/", "font/"].iter().any(|p| essence.starts_with(p))
+}
+
+/// Whether a string schema holds raw bytes (`format: binary`), i.e. a file
+fn is_binary(string: &StringType) -> bool {
+    matches!(string.format, VariantOrUnknownOrEmpty::Item(StringFormat::Binary))
+}
+
+/// ReScript type used for `integer` schemas with `format: int64`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Int64Mode {
+    /// 32-bit `int` (values above 2^31-1 overflow)
+    #[default]
+    Int,
+    /// `float` (exact up to 2^53)
+    Float,
+    /// `bigint`, decoded from numbers or numeric strings and sent as a string
+    #[value(name = "bigint")]
+    #[serde(rename = "bigint")]
+    BigInt,
+    /// `string`, decoded from numbers or strings and sent as a string
+    String,
+}
+
+/// ReScript type a `string` schema with a `format` lowers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FormatType {
+    /// Plain `string`
+    String,
+    /// `Date.t`, decoded from and encoded as ISO 8601
+    Date,
+}
+
+/// String formats with a configurable type, and the type each gets by default
+pub const FORMAT_DEFAULTS: &[(&str, FormatType)] = &[
+    ("date-time", FormatType::String),
+    ("date", FormatType::String),
+    ("uuid", FormatType::String),
+    ("uri", FormatType::String),
+];
+
+/// A string format mapped to another type than its default
+///
+/// Parsed from `format=type`, e.g. `date-time=date`; `date` is only
+/// available for `date-time` and `date`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FormatMapping {
+    pub format: String,
+    pub ty: FormatType,
+}
+
+impl std::str::FromStr for FormatMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (format, ty) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected format=type, got '{}'", s))?;
+        if !FORMAT_DEFAULTS.iter().any(|(known, _)| *known == format) {
+            let known: Vec<&str> = FORMAT_DEFAULTS.iter().map(|(known, _)| *known).collect();
+            return Err(format!(
+                "unknown format '{}' (expected one of {})",
+                format,
+                known.join(", ")
+            ));
+        }
+        let ty = match ty {
+            "string" => FormatType::String,
+            "date" if matches!(format, "date-time" | "date") => FormatType::Date,
+            "date" => return Err(format!("format '{}' can only map to string", format)),
+            _ => return Err(format!("unknown type '{}' (expected string or date)", ty)),
+        };
+        Ok(FormatMapping {
+            format: format.to_string(),
+            ty,
+        })
+    }
+}
+
+/// Extension marking a query parameter as a sparse fieldset (`fields=a,b`) over a schema
+const FIELDSET_EXTENSION: &str = "x-rescript-fieldset";
+
+/// Extension marking a property as filled in by the server, like `readOnly`
+const SERVER_GENERATED_EXTENSION: &str = "x-server-generated";
+
+/// Record types for request bodies and responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RequestTypes {
+    /// One record for both, with read-only and write-only fields as the spec declares them
+    #[default]
+    Shared,
+    /// `{Name}Input` records without read-only fields for request bodies;
+    /// response records drop write-only fields
+    Split,
+}
+
+/// Extension naming the JSON type a property is actually sent as
+const COERCE_EXTENSION: &str = "x-rescript-coerce";
+
+/// Extensions naming the cases of a numeric enum, in the order of its values
+const ENUM_NAMES_EXTENSIONS: &[&str] = &["x-enum-varnames", "x-enumNames"];
+
+/// Operation extension giving its default timeout in milliseconds
+const TIMEOUT_EXTENSION: &str = "x-timeout-ms";
+
+/// Operation extension giving how often transient failures are retried by default
+const RETRIES_EXTENSION: &str = "x-retries";
+
+/// Operation extension asking for a `{operationId}Stream` variant reading the body incrementally
+const STREAM_EXTENSION: &str = "x-rescript-stream";
+
+/// Media type of Server-Sent Events responses
+const EVENT_STREAM: &str = "text/event-stream";
+
+/// Media types of newline-delimited JSON responses, one value per line
+const NDJSON_MEDIA_TYPES: &[&str] =
+    &["application/x-ndjson", "application/ndjson", "application/jsonl"];
+
+/// RFC 7807 media type of error bodies describing a problem
+const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Type of `application/problem+json` bodies without a schema, and of `apiError.problem`
+pub const PROBLEM_DETAILS: &str = "ProblemDetails";
+
+/// A field whose wire representation differs from its schema type,
+/// e.g. numbers the server sends as strings
+///
+/// Parsed from `Type.field=wire`, where `wire` is `string`, `integer`,
+/// `number` or `boolean`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Coercion {
+    /// Component schema name as written in the spec
+    pub type_name: String,
+    /// Property name as written in the spec
+    pub field: String,
+    /// JSON type found on the wire
+    pub wire: String,
+}
+
+impl std::str::FromStr for Coercion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (target, wire) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected Type.field=wire, got '{}'", s))?;
+        let (type_name, field) = target
+            .split_once('.')
+            .ok_or_else(|| format!("expected Type.field before '=', got '{}'", target))?;
+        if wire_type(wire).is_none() {
+            return Err(format!(
+                "unknown wire type '{}' (expected string, integer, number or boolean)",
+                wire
+            ));
+        }
+        Ok(Coercion {
+            type_name: type_name.to_string(),
+            field: field.to_string(),
+            wire: wire.to_string(),
+        })
+    }
+}
+
+/// Map a JSON type name to the ReScript type it decodes as
+fn wire_type(name: &str) -> Option<RsType> {
+    match name {
+        "string" => Some(RsType::String),
+        "integer" => Some(RsType::Int),
+        "number" => Some(RsType::Float),
+        "boolean" => Some(RsType::Bool),
+        _ => None,
+    }
+}
+
+/// How responses with only wildcard media types (`*/*`, `application/*`) are exposed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WildcardResponses {
+    /// Body as a string, with its content type
+    #[default]
+    Text,
+    /// Body as a Blob, with its content type
+    Blob,
+    /// Treat the response as bodiless
+    Ignore,
+}
+
+/// How binary responses (`application/octet-stream`, images, `format: binary`) are read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BinaryResponses {
+    /// Body as a Blob, with its content type
+    #[default]
+    Blob,
+    /// Body as an ArrayBuffer, with its content type
+    ArrayBuffer,
+}
+
+/// What records with both declared properties and `additionalProperties` keep of the rest
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExtraProperties {
+    /// Collect them into an `extra` dict field
+    #[default]
+    Field,
+    /// Drop them when decoding
+    Ignore,
+}
+
+/// Options controlling how the OpenAPI spec is lowered to IR
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct LowerOptions {
+    pub field_case: FieldCase,
+    pub int64: Int64Mode,
+    pub error_strategy: ErrorStrategy,
+    /// Treat Swagger-era `x-nullable` / `x-isnullable` like `nullable: true`
+    pub compat_nullable: bool,
+    /// Per-field wire type overrides, in addition to `x-rescript-coerce`
+    pub coercions: Vec<Coercion>,
+    /// String format types overriding [`FORMAT_DEFAULTS`]; the last mapping of a format wins
+    pub formats: Vec<FormatMapping>,
+    pub wildcard_responses: WildcardResponses,
+    pub binary_responses: BinaryResponses,
+    /// Replace identical inline string enums on several fields with one shared alias
+    pub promote_enums: bool,
+    /// Whether request bodies get their own records without read-only fields
+    pub request_types: RequestTypes,
+    pub extra_properties: ExtraProperties,
+    /// Negotiate success bodies offered in several media types even when one is `application/json`
+    pub negotiate_responses: bool,
+}
+
+/// Every problem found while lowering with [`ErrorStrategy::Collect`]
+#[derive(Debug, thiserror::Error)]
+pub struct LowerError {
+    /// Errors and warnings, in the order they were found
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl std::fmt::Display for LowerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to lower spec: {}", crate::parser::summarize(&self.diagnostics))?;
+        for diagnostic in &self.diagnostics {
+            write!(f, "\n  {}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
 /// Root IR node representing the entire API
 #[derive(Debug)]
 pub struct ApiSpec {
     pub title: String,
     pub version: String,
     pub description: Option<String>,
+    /// SHA-256 of the normalized spec document, hex-encoded
+    pub spec_hash: String,
+    /// The spec declares security schemes or requirements, so clients need auth support
+    pub has_security: bool,
+    /// `components.securitySchemes`, which endpoints' `security` refers to by name
+    pub security_schemes: Vec<SecurityScheme>,
+    /// Top-level `servers`, in spec order
+    pub servers: Vec<Server>,
+    /// Some response is `application/problem+json`, so `ProblemDetails` is among the types
+    pub problem_details: bool,
     pub types: Vec<TypeDef>,
     pub endpoints: Vec<Endpoint>,
+    /// Requests the API sends to consumers (OpenAPI 3.1 `webhooks`), by name
+    pub webhooks: Vec<Webhook>,
+    /// Warnings raised while lowering (e.g. lossy type mappings)
+    pub diagnostics: Vec<Diagnostic>,
+    /// How much of the spec was represented faithfully
+    pub report: LoweringReport,
+}
+
+impl ApiSpec {
+    /// Whether some security scheme is `oauth2`, so an auth module with its flows is generated
+    pub fn has_oauth2(&self) -> bool {
+        self.security_schemes
+            .iter()
+            .any(|s| matches!(s.kind, SecuritySchemeKind::OAuth2 { .. }))
+    }
+
+    /// Whether any type, field or operation is deprecated, so generated code
+    /// that uses them has to silence the compiler's deprecation warnings
+    pub fn has_deprecations(&self) -> bool {
+        self.endpoints.iter().any(|e| e.deprecated)
+            || self.types.iter().any(|t| match t {
+                TypeDef::Record { fields, deprecated, .. } => {
+                    *deprecated || fields.iter().any(|f| f.deprecated)
+                }
+                _ => t.deprecated(),
+            })
+    }
+}
+
+/// Counts describing a lowering run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoweringReport {
+    pub records: usize,
+    pub variants: usize,
+    pub aliases: usize,
+    pub operations: usize,
+    /// Schemas the IR can't express, lowered to untyped `JSON.t`
+    /// (inline objects, `allOf`, `not`, arrays without `items`, ...)
+    pub degraded_schemas: usize,
+    /// Names made up by the generator: derived operationIds, `CaseN`
+    /// variant cases and promoted enum types
+    pub synthesized_names: usize,
+    /// Path items given as a `$ref`, whose operations are not generated
+    pub skipped_operations: usize,
+}
+
+impl std::fmt::Display for LoweringReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Lowered {} type(s) ({} record(s), {} variant(s), {} alias(es)) and {} operation(s)",
+            self.records + self.variants + self.aliases,
+            self.records,
+            self.variants,
+            self.aliases,
+            self.operations
+        )?;
+        writeln!(f, "  {} schema(s) degraded to JSON.t", self.degraded_schemas)?;
+        writeln!(f, "  {} synthesized name(s)", self.synthesized_names)?;
+        write!(f, "  {} operation(s) skipped", self.skipped_operations)
+    }
 }
 
 /// A ReScript type definition
@@ -48,18 +882,26 @@ pub enum TypeDef {
         name: String,
         doc: Option<String>,
         fields: Vec<Field>,
+        /// Marked `deprecated: true`; generated with `@deprecated`
+        deprecated: bool,
     },
     /// Variant type: type status = | Active | Inactive
     Variant {
         name: String,
         doc: Option<String>,
         cases: Vec<VariantCase>,
+        /// Wire property naming the case (`discriminator.propertyName`); every case has a `tag`
+        discriminator: Option<String>,
+        deprecated: bool,
     },
     /// Alias: type userId = string
     Alias {
         name: String,
         doc: Option<String>,
         target: RsType,
+        /// Validation keywords of the aliased schema
+        constraints: Constraints,
+        deprecated: bool,
     },
 }
 
@@ -71,6 +913,90 @@ impl TypeDef {
             TypeDef::Alias { name, .. } => name,
         }
     }
+
+    pub fn deprecated(&self) -> bool {
+        match self {
+            TypeDef::Record { deprecated, .. }
+            | TypeDef::Variant { deprecated, .. }
+            | TypeDef::Alias { deprecated, .. } => *deprecated,
+        }
+    }
+
+    fn set_deprecated(&mut self, value: bool) {
+        match self {
+            TypeDef::Record { deprecated, .. }
+            | TypeDef::Variant { deprecated, .. }
+            | TypeDef::Alias { deprecated, .. } => *deprecated = value,
+        }
+    }
+}
+
+/// Validation keywords enforced by a generated schema on top of its type
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Constraints {
+    /// `minLength` of a string
+    pub min_length: Option<usize>,
+    /// `maxLength` of a string
+    pub max_length: Option<usize>,
+    /// `pattern` of a string, an ECMA-262 regular expression
+    pub pattern: Option<String>,
+    /// `minimum` of a number
+    pub minimum: Option<f64>,
+    /// `maximum` of a number
+    pub maximum: Option<f64>,
+    /// `minimum` itself is rejected (`exclusiveMinimum`)
+    pub exclusive_minimum: bool,
+    /// `maximum` itself is rejected (`exclusiveMaximum`)
+    pub exclusive_maximum: bool,
+    /// `multipleOf` of a number
+    pub multiple_of: Option<f64>,
+    /// `minItems` of an array
+    pub min_items: Option<usize>,
+    /// `maxItems` of an array
+    pub max_items: Option<usize>,
+    /// Items must be pairwise distinct (`uniqueItems`)
+    pub unique_items: bool,
+}
+
+impl Constraints {
+    /// Validation keywords of a schema that apply to its type
+    pub fn of(schema: &Schema) -> Self {
+        match &schema.schema_kind {
+            SchemaKind::Type(Type::String(string)) => Constraints {
+                min_length: string.min_length,
+                max_length: string.max_length,
+                pattern: string.pattern.clone(),
+                ..Default::default()
+            },
+            SchemaKind::Type(Type::Integer(integer)) => Constraints {
+                minimum: integer.minimum.map(|v| v as f64),
+                maximum: integer.maximum.map(|v| v as f64),
+                exclusive_minimum: integer.exclusive_minimum,
+                exclusive_maximum: integer.exclusive_maximum,
+                multiple_of: integer.multiple_of.map(|v| v as f64),
+                ..Default::default()
+            },
+            SchemaKind::Type(Type::Number(number)) => Constraints {
+                minimum: number.minimum,
+                maximum: number.maximum,
+                exclusive_minimum: number.exclusive_minimum,
+                exclusive_maximum: number.exclusive_maximum,
+                multiple_of: number.multiple_of,
+                ..Default::default()
+            },
+            SchemaKind::Type(Type::Array(array)) => Constraints {
+                min_items: array.min_items,
+                max_items: array.max_items,
+                unique_items: array.unique_items,
+                ..Default::default()
+            },
+            _ => Constraints::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Constraints::default()
+    }
 }
 
 /// A field in a record type
@@ -81,6 +1007,22 @@ pub struct Field {
     pub ty: RsType,
     pub optional: bool,
     pub doc: Option<String>,
+    /// JSON type the value arrives as, coerced to `ty` when decoding
+    pub coerce_from: Option<RsType>,
+    /// Set by the server (`readOnly` or `x-server-generated`), never sent by clients
+    pub server_generated: bool,
+    /// Only sent by clients (`writeOnly`), never returned by the server
+    pub write_only: bool,
+    /// Validation keywords of the property
+    pub constraints: Constraints,
+    /// Marked `deprecated: true`; generated with `@deprecated`
+    pub deprecated: bool,
+    /// ReScript value filled in when the property is absent (`default`), e.g.
+    /// `10` or `#"asc"`; `ty` is then not wrapped in `option`
+    pub default: Option<String>,
+    /// Holds the properties not declared in the schema (`additionalProperties`),
+    /// with `ty` a `Dict`
+    pub additional: bool,
 }
 
 /// A case in a variant type
@@ -88,6 +1030,31 @@ pub struct Field {
 pub struct VariantCase {
     pub name: String,
     pub payload: Option<RsType>,
+    /// Discriminator values selecting this case, e.g. `email`; the first is
+    /// the one written when serializing
+    pub tags: Vec<String>,
+    /// JSON value a constant case stands for at runtime, as written in
+    /// `@as(...)`: `1`, `"a"`, `true` or `null`
+    pub literal: Option<String>,
+}
+
+/// Whether a variant's cases wrap distinct JSON scalars (`S(string) | I(int)`),
+/// so it can be `@unboxed` and share the raw value's runtime representation
+pub fn is_scalar_union(cases: &[VariantCase]) -> bool {
+    let mut kinds: Vec<&str> = Vec::new();
+    for case in cases {
+        let kind = match &case.payload {
+            Some(RsType::String) => "string",
+            Some(RsType::Int | RsType::Float) => "number",
+            Some(RsType::Bool) => "boolean",
+            _ => return false,
+        };
+        if kinds.contains(&kind) {
+            return false;
+        }
+        kinds.push(kind);
+    }
+    !kinds.is_empty()
 }
 
 /// ReScript type representation
@@ -98,7 +1065,17 @@ pub enum RsType {
     Float,
     Bool,
     Unit,
+    /// `Date.t` sent as an ISO 8601 timestamp (`format: date-time`)
+    DateTime,
+    /// `Date.t` sent as an ISO 8601 calendar date (`format: date`)
+    Date,
+    /// `bigint` for `format: int64` (`--int64 bigint`)
+    BigInt,
+    /// `string` for `format: int64` (`--int64 string`)
+    Int64String,
     Option(Box<RsType>),
+    /// Value that may be `null` (and absent); decodes both to `None`
+    Nullable(Box<RsType>),
     Array(Box<RsType>),
     Dict(Box<RsType>),
     Json,
@@ -106,6 +1083,63 @@ pub enum RsType {
     Tuple(Vec<RsType>),
     /// Inline string enum (polymorphic variant)
     StringEnum(Vec<String>),
+    /// Unparsed response text for wildcard media types
+    RawText,
+    /// Unparsed response blob for wildcard or binary media types
+    RawBlob,
+    /// Unparsed response bytes for binary media types (`--binary-responses array-buffer`)
+    RawArrayBuffer,
+    /// Success body offered in several media types: one is requested with `~accept` and
+    /// the body read per its `Content-Type`
+    Negotiated {
+        /// Type of JSON bodies (`unit` when no media type is JSON)
+        json: Box<RsType>,
+        /// Each offered media type and how its body is read, the default first
+        media: Vec<(String, MediaKind)>,
+    },
+}
+
+/// How the body of one media type of a negotiated response is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    /// Decoded with the JSON schema (`application/json`, `+json`)
+    Json,
+    /// Kept as text (`text/*`, XML, CSV, YAML)
+    Text,
+    /// Kept as a Blob (anything else, e.g. images and PDFs)
+    Blob,
+}
+
+impl MediaKind {
+    fn of(media_type: &str) -> Self {
+        let essence = media_essence(media_type);
+        if essence == "application/json" || essence.ends_with("+json") {
+            MediaKind::Json
+        } else if essence.starts_with("text/")
+            || essence.ends_with("+xml")
+            || matches!(
+                essence.as_str(),
+                "application/xml" | "application/csv" | "application/yaml" | "application/x-yaml"
+            )
+        {
+            MediaKind::Text
+        } else {
+            MediaKind::Blob
+        }
+    }
+}
+
+/// Media type without its parameters, lowercased: `Text/CSV; charset=utf-8` -> `text/csv`
+fn media_essence(media_type: &str) -> String {
+    media_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
+
+/// Content entry for a media type, however the spec capitalizes or parameterizes it
+fn media_content<'a>(
+    content: &'a openapiv3::Content,
+    media_type: &str,
+) -> Option<&'a openapiv3::MediaType> {
+    content.iter().find(|(m, _)| media_essence(m) == media_type).map(|(_, media)| media)
 }
 
 impl RsType {
@@ -116,7 +1150,12 @@ impl RsType {
             RsType::Float => "float".to_string(),
             RsType::Bool => "bool".to_string(),
             RsType::Unit => "unit".to_string(),
-            RsType::Option(inner) => format!("option<{}>", inner.to_rescript()),
+            RsType::DateTime | RsType::Date => "Date.t".to_string(),
+            RsType::BigInt => "bigint".to_string(),
+            RsType::Int64String => "string".to_string(),
+            RsType::Option(inner) | RsType::Nullable(inner) => {
+                format!("option<{}>", inner.to_rescript())
+            }
             RsType::Array(inner) => format!("array<{}>", inner.to_rescript()),
             RsType::Dict(inner) => format!("Dict.t<{}>", inner.to_rescript()),
             RsType::Json => "JSON.t".to_string(),
@@ -133,9 +1172,25 @@ impl RsType {
                     .collect();
                 format!("[{}]", cases.join(" | "))
             }
+            RsType::RawText => "rawBody<string>".to_string(),
+            RsType::RawBlob => "rawBody<Fetch.Blob.t>".to_string(),
+            RsType::RawArrayBuffer => "rawBody<ArrayBuffer.t>".to_string(),
+            RsType::Negotiated { json, .. } => format!("negotiated<{}>", json.to_rescript()),
         }
     }
 
+    /// Whether this is a response body read without a schema: raw text, bytes or a
+    /// negotiated body, which the client decodes itself
+    pub fn is_raw_body(&self) -> bool {
+        matches!(
+            self,
+            RsType::RawText | RsType::RawBlob | RsType::RawArrayBuffer | RsType::Negotiated { .. }
+        )
+    }
+
+    /// rescript-schema expression for this type
+    ///
+    /// Panics for raw bodies (see [`RsType::is_raw_body`]), which have no schema.
     pub fn to_schema(&self) -> String {
         match self {
             RsType::String => "S.string".to_string(),
@@ -143,14 +1198,31 @@ impl RsType {
             RsType::Float => "S.float".to_string(),
             RsType::Bool => "S.bool".to_string(),
             RsType::Unit => "S.unit".to_string(),
+            RsType::DateTime => date_schema("date-time", "d->Date.toISOString"),
+            RsType::Date => {
+                date_schema("date", "d->Date.toISOString->String.slice(~start=0, ~end=10)")
+            }
+            RsType::BigInt => int64_schema(
+                "v => try BigInt.fromStringExn(v) catch {| _ => s.fail(\"Invalid int64\")}",
+                "v->BigInt.toString",
+            ),
+            RsType::Int64String => int64_schema("v => v", "v"),
             RsType::Option(inner) => format!("S.option({})", inner.to_schema()),
+            RsType::Nullable(inner) => format!("S.nullable({})", inner.to_schema()),
             RsType::Array(inner) => format!("S.array({})", inner.to_schema()),
             RsType::Dict(inner) => format!("S.dict({})", inner.to_schema()),
             RsType::Json => "S.json".to_string(),
             RsType::Named(name) => format!("{}Schema", name.to_lower_camel_case()),  // schemaName convention
             RsType::Tuple(types) => {
-                let schemas: Vec<_> = types.iter().map(|t| t.to_schema()).collect();
-                format!("S.tuple(s => ({}))", schemas.join(", "))
+                let items: Vec<_> = types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, t)| format!("s.item({}, {})", i, t.to_schema()))
+                    .collect();
+                format!("S.tuple(s => ({}))", items.join(", "))
+            }
+            RsType::StringEnum(values) if values.len() == 1 => {
+                format!("S.literal(#\"{}\")", values[0])
             }
             RsType::StringEnum(values) => {
                 // Generate S.union with S.literal for each value
@@ -160,24 +1232,371 @@ impl RsType {
                     .collect();
                 format!("S.union([{}])", literals.join(", "))
             }
+            RsType::RawText
+            | RsType::RawBlob
+            | RsType::RawArrayBuffer
+            | RsType::Negotiated { .. } => unreachable!("raw bodies have no schema"),
         }
     }
 }
 
-/// HTTP endpoint definition
-#[derive(Debug)]
-pub struct Endpoint {
-    pub operation_id: String,
-    pub method: HttpMethod,
-    pub path: String,
-    pub doc: Option<String>,
-    pub parameters: Vec<Parameter>,
-    pub request_body: Option<RequestBody>,
-    pub responses: Vec<Response>,
-}
+/// Cases of an enum whose values aren't all non-null strings, one per distinct value
+///
+/// Covers `integer`, `number` and `boolean` enums, enums allowing `null` and
+/// untyped enums mixing JSON types. Each case stands for its value at runtime
+/// (`@as(1)`, `@as("a")`, `@as(null)`). Cases are named by `x-enum-varnames`
+/// (or `x-enumNames`) when it lists a distinct name for every value, otherwise
+/// after the value: `Active`, `V1`, `VMinus1`, `V0_5`, `True`, `Null`.
+fn literal_enum_cases(schema: &Schema) -> Option<Vec<VariantCase>> {
+    use serde_json::Value;
 
-#[derive(Debug, Clone, Copy)]
-pub enum HttpMethod {
+    let mut values: Vec<Value> = match &schema.schema_kind {
+        SchemaKind::Type(Type::String(string)) if string.enumeration.contains(&None) => {
+            let value = |v: &Option<String>| v.clone().map_or(Value::Null, Value::String);
+            string.enumeration.iter().map(value).collect()
+        }
+        SchemaKind::Type(Type::Integer(int)) => {
+            int.enumeration.iter().map(|v| v.map_or(Value::Null, Value::from)).collect()
+        }
+        SchemaKind::Type(Type::Number(num)) => {
+            num.enumeration.iter().map(|v| v.map_or(Value::Null, Value::from)).collect()
+        }
+        SchemaKind::Type(Type::Boolean(boolean)) => {
+            boolean.enumeration.iter().map(|v| v.map_or(Value::Null, Value::Bool)).collect()
+        }
+        SchemaKind::Any(any) => any.enumeration.clone(),
+        _ => return None,
+    };
+    // Only scalars can be literals
+    values.retain(|v| !v.is_array() && !v.is_object());
+    let mut literals: Vec<String> = Vec::new();
+    values.retain(|v| {
+        let literal = enum_literal(v);
+        let new = !literals.contains(&literal);
+        if new {
+            literals.push(literal);
+        }
+        new
+    });
+    if values.is_empty() {
+        return None;
+    }
+
+    let names: Option<Vec<String>> = ENUM_NAMES_EXTENSIONS
+        .iter()
+        .find_map(|key| schema.schema_data.extensions.get(*key)?.as_array())
+        .and_then(|names| names.iter().map(|n| n.as_str().map(|n| n.to_pascal_case())).collect())
+        .filter(|names: &Vec<String>| {
+            names.len() == values.len()
+                && names.iter().all(|n| n.starts_with(|c: char| c.is_ascii_uppercase()))
+                && names.iter().collect::<HashSet<_>>().len() == names.len()
+        });
+    let names = names.unwrap_or_else(|| {
+        let mut taken = HashSet::new();
+        values
+            .iter()
+            .zip(&literals)
+            .map(|(value, literal)| {
+                let base = match value {
+                    Value::String(s) if s.is_empty() => "Empty".to_string(),
+                    Value::String(s) => {
+                        let name = s.to_pascal_case();
+                        if name.starts_with(|c: char| c.is_ascii_uppercase()) {
+                            name
+                        } else {
+                            format!("V{}", name)
+                        }
+                    }
+                    Value::Null => "Null".to_string(),
+                    Value::Bool(b) => b.to_string().to_pascal_case(),
+                    _ => format!("V{}", literal.replace('-', "Minus").replace('.', "_")),
+                };
+                let name = if taken.contains(&base) {
+                    (2..)
+                        .map(|n| format!("{}{}", base, n))
+                        .find(|n| !taken.contains(n))
+                        .unwrap_or_default()
+                } else {
+                    base
+                };
+                taken.insert(name.clone());
+                name
+            })
+            .collect()
+    });
+
+    Some(
+        names
+            .into_iter()
+            .zip(literals)
+            .map(|(name, literal)| VariantCase {
+                name,
+                payload: None,
+                tags: Vec::new(),
+                literal: Some(literal),
+            })
+            .collect(),
+    )
+}
+
+/// A scalar enum value as written in `@as(...)`, e.g. `1`, `0.5`, `"a"` or `null`
+fn enum_literal(value: &serde_json::Value) -> String {
+    match value.as_f64() {
+        // `1.0` and `1` are the same JSON number
+        Some(n) if !value.is_i64() && !value.is_u64() && n.fract() == 0.0 && n.abs() < 1e15 => {
+            format!("{}", n as i64)
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// Schema decoding an ISO 8601 string into `Date.t`, rejecting unparseable ones
+fn date_schema(format: &str, serialize: &str) -> String {
+    format!(
+        "S.string->S.transform(s => {{parser: v => {{let d = Date.fromString(v); d->Date.getTime->Float.isNaN ? s.fail(\"Invalid {}\") : d}}, serializer: d => {}}})",
+        format, serialize
+    )
+}
+
+/// Schema for an int64 sent as a JSON number or string, always encoded as a string
+///
+/// `parse` maps the decimal text to the ReScript value and `serialize` maps it back.
+fn int64_schema(parse: &str, serialize: &str) -> String {
+    format!(
+        "S.json->S.transform(s => {{parser: json => switch json {{| JSON.String(v) => v | JSON.Number(v) => v->Float.toString | _ => s.fail(\"Expected int64\")}}->({}), serializer: v => JSON.String({})}})",
+        parse, serialize
+    )
+}
+
+/// HTTP endpoint definition
+#[derive(Debug)]
+pub struct Endpoint {
+    pub operation_id: String,
+    pub method: HttpMethod,
+    pub path: String,
+    pub tags: Vec<String>,
+    /// Stable, low-cardinality name for metrics labels (`tag.operation_id`)
+    pub metric_name: String,
+    pub doc: Option<String>,
+    pub parameters: Vec<Parameter>,
+    pub request_body: Option<RequestBody>,
+    pub responses: Vec<Response>,
+    /// Default timeout from `x-timeout-ms`
+    pub timeout_ms: Option<u32>,
+    /// Default retry count from `x-retries`
+    pub retries: Option<u32>,
+    /// GET whose success response declares a `Link` header, paginated via `rel="next"`
+    pub link_pagination: bool,
+    /// How to walk the pages of a list taking page, offset or cursor query parameters;
+    /// also generated as a function collecting every item
+    pub pagination: Option<Pagination>,
+    /// Marked `x-rescript-stream: true`; also generated as a chunk-reading variant
+    pub stream: bool,
+    /// Type of each event's `data` when the success response is `text/event-stream`
+    /// (`string` without a schema); also generated as an event subscription
+    pub events: Option<RsType>,
+    /// Type of each line when the success response is newline-delimited JSON;
+    /// also generated as a function decoding lines as they arrive
+    pub ndjson: Option<RsType>,
+    /// Marked `deprecated: true`; generated with `@deprecated`
+    pub deprecated: bool,
+    /// Alternative security requirements, each naming the schemes it needs together;
+    /// the operation's own `security`, else the global one. Empty for public operations,
+    /// `None` when neither declares any.
+    pub security: Option<Vec<Vec<String>>>,
+}
+
+/// Pages of a list operation, recognized by its query parameters and response
+#[derive(Debug, Clone)]
+pub struct Pagination {
+    pub kind: PaginationKind,
+    /// Field of the response record holding a page's items, with its type (possibly
+    /// optional); `None` when the response is the array itself
+    pub items_field: Option<(String, RsType)>,
+    /// Type of one item
+    pub item: RsType,
+}
+
+/// Query parameter that selects a page, by the ReScript name of its argument
+#[derive(Debug, Clone)]
+pub enum PaginationKind {
+    /// Page number counted up by one, with the page size parameter if there is one
+    Page { page: String, size: Option<String> },
+    /// Offset advanced by the number of items received, with the limit parameter
+    /// if there is one
+    Offset { offset: String, limit: Option<String> },
+    /// Opaque cursor taken from a field of the response, with that field's type
+    Cursor { cursor: String, next: (String, RsType) },
+}
+
+// Names of pagination parameters and response fields, compared by `pagination_key`
+const PAGE_PARAMS: &[&str] = &["page", "pagenumber", "pageno"];
+const PAGE_SIZE_PARAMS: &[&str] = &["limit", "pagesize", "perpage", "size", "pagelimit"];
+const OFFSET_PARAMS: &[&str] = &["offset", "skip"];
+const CURSOR_PARAMS: &[&str] =
+    &["cursor", "after", "pagetoken", "nexttoken", "startingafter", "continuationtoken"];
+const ITEMS_FIELDS: &[&str] = &["items", "data", "results", "records", "entries", "values"];
+const NEXT_CURSOR_FIELDS: &[&str] =
+    &["nextcursor", "nextpagetoken", "nexttoken", "endcursor", "cursor", "next", "after"];
+
+/// Name in lowercase without separators, so `page_size`, `pageSize` and `Page-Size` match
+fn pagination_key(name: &str) -> String {
+    name.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_ascii_lowercase()
+}
+
+/// Recognize the paginated GET lists among `endpoints`
+///
+/// A list is an array response, or a record with an `items`-like array field (or
+/// a single array field). It pages by cursor when it takes a cursor parameter
+/// and the record has a next-cursor field, else by offset or page number.
+/// `Link`-paginated lists already have their own `{operationId}Page`.
+fn detect_pagination(types: &[TypeDef], endpoints: &mut [Endpoint]) {
+    for endpoint in endpoints.iter_mut() {
+        if matches!(endpoint.method, HttpMethod::Get) && !endpoint.link_pagination {
+            endpoint.pagination = pagination(types, endpoint);
+        }
+    }
+}
+
+fn pagination(types: &[TypeDef], endpoint: &Endpoint) -> Option<Pagination> {
+    let param = |names: &[&str], string: bool| {
+        endpoint
+            .parameters
+            .iter()
+            .filter(|p| p.location == ParameterLocation::Query)
+            .filter(|p| matches!((&p.ty, string), (RsType::Int, false) | (RsType::String, true)))
+            .find(|p| names.contains(&pagination_key(&p.original_name).as_str()))
+            .map(|p| p.name.clone())
+    };
+    let field = |fields: &[Field], names: &[&str], accept: fn(&RsType) -> bool| {
+        names.iter().find_map(|name| {
+            fields
+                .iter()
+                .filter(|f| accept(unwrap_optional(&f.ty)))
+                .find(|f| pagination_key(&f.original_name) == *name)
+                .map(|f| (f.name.clone(), f.ty.clone()))
+        })
+    };
+
+    let (items_field, item, fields) = match endpoint.success_response()?.ty.as_ref()? {
+        RsType::Array(item) => (None, (**item).clone(), &[][..]),
+        RsType::Named(name) => {
+            let fields = types.iter().find_map(|t| match t {
+                TypeDef::Record { name: record, fields, .. } if record == name => Some(fields),
+                _ => None,
+            })?;
+            let is_array = |ty: &RsType| matches!(ty, RsType::Array(_));
+            let arrays: Vec<&Field> =
+                fields.iter().filter(|f| is_array(unwrap_optional(&f.ty))).collect();
+            let (name, ty) = match field(fields, ITEMS_FIELDS, is_array) {
+                Some(items) => items,
+                None if arrays.len() == 1 => (arrays[0].name.clone(), arrays[0].ty.clone()),
+                None => return None,
+            };
+            let RsType::Array(item) = unwrap_optional(&ty) else {
+                return None;
+            };
+            let item = (**item).clone();
+            (Some((name, ty)), item, &fields[..])
+        }
+        _ => return None,
+    };
+
+    let cursor = param(CURSOR_PARAMS, true)
+        .zip(field(fields, NEXT_CURSOR_FIELDS, |ty| matches!(ty, RsType::String)));
+    let kind = if let Some((cursor, next)) = cursor {
+        PaginationKind::Cursor { cursor, next }
+    } else if let Some(offset) = param(OFFSET_PARAMS, false) {
+        PaginationKind::Offset { offset, limit: param(PAGE_SIZE_PARAMS, false) }
+    } else {
+        let page = param(PAGE_PARAMS, false)?;
+        PaginationKind::Page { page, size: param(PAGE_SIZE_PARAMS, false) }
+    };
+    Some(Pagination { kind, items_field, item })
+}
+
+/// Type inside any `option` (or nullable) wrappers
+fn unwrap_optional(ty: &RsType) -> &RsType {
+    match ty {
+        RsType::Option(inner) | RsType::Nullable(inner) => unwrap_optional(inner),
+        ty => ty,
+    }
+}
+
+impl Endpoint {
+    /// The first documented 2xx response, whose body the client decodes
+    pub fn success_response(&self) -> Option<&Response> {
+        self.responses.iter().find(|r| r.is_success())
+    }
+}
+
+/// A named entry of `components.securitySchemes`
+#[derive(Debug, Clone)]
+pub struct SecurityScheme {
+    pub name: String,
+    pub kind: SecuritySchemeKind,
+}
+
+/// How a security scheme's credentials are sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecuritySchemeKind {
+    /// `http` with scheme `bearer`: an `Authorization: Bearer` header
+    Bearer,
+    /// `apiKey`: a key sent as the header, query parameter or cookie `name`
+    ApiKey { name: String, location: ParameterLocation },
+    /// `oauth2`, whose access tokens are sent as bearer tokens
+    OAuth2 {
+        flows: Vec<OAuthFlow>,
+        /// Scope names and descriptions, across all flows
+        scopes: Vec<(String, String)>,
+    },
+    /// `openIdConnect`, whose access tokens are sent as bearer tokens
+    OpenIdConnect,
+    /// Schemes the client can't send credentials for, e.g. `http` basic
+    Unsupported,
+}
+
+/// An entry of the spec's `servers`
+#[derive(Debug, Clone)]
+pub struct Server {
+    /// URL template, e.g. `https://{region}.example.com/v1`
+    pub url: String,
+    pub doc: Option<String>,
+    pub variables: Vec<ServerVariable>,
+}
+
+/// A `{name}` placeholder in a server URL
+#[derive(Debug, Clone)]
+pub struct ServerVariable {
+    pub name: String,
+    /// Allowed values from `enum`; empty when any string is allowed
+    pub values: Vec<String>,
+    pub default: String,
+    pub doc: Option<String>,
+}
+
+/// Endpoints of one OAuth2 flow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OAuthFlow {
+    /// Key in `flows`, e.g. `authorizationCode`
+    pub flow: &'static str,
+    pub authorization_url: Option<String>,
+    pub token_url: Option<String>,
+    pub refresh_url: Option<String>,
+}
+
+/// A webhook whose JSON payload consumers decode
+#[derive(Debug)]
+pub struct Webhook {
+    /// Key in `webhooks`, e.g. `newPet`
+    pub name: String,
+    pub doc: Option<String>,
+    pub payload: RsType,
+    pub deprecated: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum HttpMethod {
     Get,
     Post,
     Put,
@@ -204,13 +1623,44 @@ impl HttpMethod {
 #[derive(Debug)]
 pub struct Parameter {
     pub name: String,
+    /// Name as declared, used on the wire
+    pub original_name: String,
     pub location: ParameterLocation,
     pub ty: RsType,
     pub required: bool,
     pub doc: Option<String>,
+    /// Media type for parameters defined via `content` instead of `schema`
+    pub content_type: Option<String>,
+    pub deprecated: bool,
+    /// Schema whose property names this sparse fieldset parameter selects
+    pub fieldset: Option<String>,
+    /// How array and object values are serialized (OpenAPI `style`)
+    pub style: ParameterStyle,
+    /// Whether array items and object properties become separate values (OpenAPI
+    /// `explode`, which defaults to true for `form` only)
+    pub explode: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// OpenAPI `style` of a parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterStyle {
+    /// `a,b` for path and header values
+    Simple,
+    /// `.a,b` in a path, `.a.b` exploded
+    Label,
+    /// `;name=a,b` in a path, `;name=a;name=b` exploded
+    Matrix,
+    /// `name=a,b`, or `name=a&name=b` exploded; the query and cookie default
+    Form,
+    /// `name=a%20b`
+    SpaceDelimited,
+    /// `name=a|b`
+    PipeDelimited,
+    /// `name[key]=value` for objects
+    DeepObject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ParameterLocation {
     Path,
     Query,
@@ -223,172 +1673,1140 @@ pub struct RequestBody {
     pub ty: RsType,
     pub required: bool,
     pub content_type: String,
+    /// Parts of a `multipart/form-data` body, whose `ty` names the record of
+    /// them the client declares; empty for JSON bodies
+    pub form: Vec<FormField>,
+    /// Set when the body accepts both JSON and `multipart/form-data`; `ty` then
+    /// names the variant callers pick an encoding with
+    pub choice: Option<BodyChoice>,
+}
+
+/// The encodings of a body accepting both JSON and `multipart/form-data`
+#[derive(Debug)]
+pub struct BodyChoice {
+    /// Type of the JSON body (`Json(...)`)
+    pub json: RsType,
+    /// Record of the form parts (`Multipart(...)`), declared by the client
+    pub form: RsType,
+}
+
+impl RequestBody {
+    /// Whether the body is only accepted as `multipart/form-data`
+    pub fn is_multipart(&self) -> bool {
+        self.content_type == "multipart/form-data"
+    }
+
+    /// Type of the body when sent as JSON, if it can be
+    pub fn json_type(&self) -> Option<&RsType> {
+        match &self.choice {
+            Some(choice) => Some(&choice.json),
+            None if self.is_multipart() => None,
+            None => Some(&self.ty),
+        }
+    }
+
+    /// Record type of the form parts, if the body can be sent as `multipart/form-data`
+    pub fn form_type(&self) -> Option<&RsType> {
+        match &self.choice {
+            Some(choice) => Some(&choice.form),
+            None if self.is_multipart() => Some(&self.ty),
+            None => None,
+        }
+    }
+}
+
+/// A part of a `multipart/form-data` request body
+#[derive(Debug)]
+pub struct FormField {
+    /// Part name on the wire
+    pub name: String,
+    /// Field name in the generated record
+    pub field: String,
+    pub kind: FormFieldKind,
+    pub required: bool,
+    pub doc: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum FormFieldKind {
+    /// A value sent as text (objects and arrays of them as JSON)
+    Value(RsType),
+    /// A file (`type: string, format: binary`)
+    File,
+    /// Several files under the same name (`type: array` of binary strings)
+    Files,
 }
 
 #[derive(Debug)]
 pub struct Response {
-    pub status: u16,
+    pub status: ResponseStatus,
     pub ty: Option<RsType>,
     pub doc: Option<String>,
+    /// JSON `example` (or first of `examples`) of the response body
+    pub example: Option<serde_json::Value>,
+}
+
+impl Response {
+    /// Whether the status is 2xx (or the `2XX` range)
+    pub fn is_success(&self) -> bool {
+        matches!(self.status, ResponseStatus::Code(200..=299) | ResponseStatus::Range(2))
+    }
+
+    /// Whether the status is 4xx or 5xx (or one of their ranges)
+    pub fn is_error(&self) -> bool {
+        matches!(self.status, ResponseStatus::Code(400..) | ResponseStatus::Range(4..))
+    }
+}
+
+/// Statuses a response is declared for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseStatus {
+    Code(u16),
+    /// A class of statuses by its first digit, e.g. `4` for `4XX`
+    Range(u16),
+    /// The `default` response, for every status not declared otherwise
+    Default,
+}
+
+impl ResponseStatus {
+    /// Lowest status code covered, `None` for `default`
+    pub fn first_code(&self) -> Option<u16> {
+        match self {
+            ResponseStatus::Code(code) => Some(*code),
+            ResponseStatus::Range(class) => Some(class * 100),
+            ResponseStatus::Default => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ResponseStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResponseStatus::Code(code) => write!(f, "{}", code),
+            ResponseStatus::Range(class) => write!(f, "{}XX", class),
+            ResponseStatus::Default => write!(f, "default"),
+        }
+    }
 }
 
 /// Lower OpenAPI spec to IR
 pub fn lower(spec: &OpenAPI) -> Result<ApiSpec> {
-    let mut lowerer = Lowerer::new(spec);
+    lower_with_options(spec, &LowerOptions::default())
+}
+
+/// Lower OpenAPI spec to IR with explicit lowering options
+pub fn lower_with_options(spec: &OpenAPI, options: &LowerOptions) -> Result<ApiSpec> {
+    let mut lowerer = Lowerer::new(spec, options);
     lowerer.lower()
 }
 
+/// Problems to report when validating a spec
+///
+/// `FailFast` reports validation findings up to and including the first
+/// error; `Collect` reports them all plus every lowering problem.
+pub fn diagnostics(spec: &OpenAPI, error_strategy: ErrorStrategy) -> Result<Vec<Diagnostic>> {
+    let mut diagnostics = crate::parser::validate(spec);
+    if error_strategy == ErrorStrategy::FailFast {
+        if let Some(first_error) = diagnostics.iter().position(|d| d.severity == Severity::Error) {
+            diagnostics.truncate(first_error + 1);
+        }
+        return Ok(diagnostics);
+    }
+
+    let lower_options = LowerOptions {
+        error_strategy: ErrorStrategy::Collect,
+        ..Default::default()
+    };
+    match lower_with_options(spec, &lower_options) {
+        Ok(api_spec) => diagnostics.extend(api_spec.diagnostics),
+        Err(error) => match error.downcast::<LowerError>() {
+            Ok(lower_error) => diagnostics.extend(lower_error.diagnostics),
+            Err(error) => return Err(error),
+        },
+    }
+    Ok(diagnostics)
+}
+
 struct Lowerer<'a> {
     spec: &'a OpenAPI,
+    options: &'a LowerOptions,
     types: BTreeMap<String, TypeDef>,
+    diagnostics: RefCell<Vec<Diagnostic>>,
+    /// Function names taken so far, seeded with every explicit operationId
+    operation_ids: RefCell<HashSet<String>>,
+    report: RefCell<LoweringReport>,
+    /// Shared types created for inline schemas, e.g. `StringOrInt`
+    synthesized: RefCell<BTreeMap<String, TypeDef>>,
+    /// Name given to each shared union, by the name it would have without collisions
+    shared_unions: RefCell<HashMap<String, String>>,
+    /// A response uses `application/problem+json`
+    problem_details: Cell<bool>,
+    /// Indices of the `--coerce` options that matched a property
+    matched_coercions: RefCell<HashSet<usize>>,
 }
 
 impl<'a> Lowerer<'a> {
-    fn new(spec: &'a OpenAPI) -> Self {
+    fn new(spec: &'a OpenAPI, options: &'a LowerOptions) -> Self {
+        let explicit_ids = spec
+            .operations()
+            .filter_map(|(_, _, op)| op.operation_id.as_ref())
+            .map(|id| id.to_lower_camel_case())
+            .collect();
         Self {
             spec,
+            options,
             types: BTreeMap::new(),
+            diagnostics: RefCell::new(Vec::new()),
+            operation_ids: RefCell::new(explicit_ids),
+            report: RefCell::new(LoweringReport::default()),
+            synthesized: RefCell::new(BTreeMap::new()),
+            shared_unions: RefCell::new(HashMap::new()),
+            problem_details: Cell::new(false),
+            matched_coercions: RefCell::new(HashSet::new()),
+        }
+    }
+
+    fn warn(&self, message: String, path: String) {
+        self.diagnostics.borrow_mut().push(Diagnostic {
+            severity: Severity::Warning,
+            message,
+            path: Some(path),
+        });
+    }
+
+    /// Apply the error strategy to a failed lowering step
+    fn fail(&self, error: anyhow::Error, path: String) -> Result<()> {
+        match self.options.error_strategy {
+            ErrorStrategy::FailFast => Err(error),
+            ErrorStrategy::Collect => {
+                self.diagnostics.borrow_mut().push(Diagnostic {
+                    severity: Severity::Error,
+                    message: format!("{:#}", error),
+                    path: Some(path),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Warn when a numeric schema cannot be represented exactly in ReScript
+    fn check_numeric_precision(&self, schema: &Schema, path: &str) {
+        if let SchemaKind::Type(Type::Integer(int)) = &schema.schema_kind {
+            let is_int64 = matches!(int.format, VariantOrUnknownOrEmpty::Item(IntegerFormat::Int64));
+            if is_int64 && self.options.int64 == Int64Mode::Int {
+                self.warn(
+                    "int64 integer mapped to 32-bit int; values above 2^31-1 will overflow (use --int64 float, bigint or string)"
+                        .to_string(),
+                    path.to_string(),
+                );
+            }
         }
     }
 
     fn lower(&mut self) -> Result<ApiSpec> {
+        // Schemas and operations are lowered in name and path order, so the
+        // suffixes given to colliding synthesized types and derived operation
+        // ids don't depend on the order the spec declares them in
+
         // First pass: collect all schema types
         if let Some(components) = &self.spec.components {
-            for (name, schema) in &components.schemas {
+            let mut schemas: Vec<_> = components.schemas.iter().collect();
+            schemas.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, schema) in schemas {
                 if let ReferenceOr::Item(schema) = schema {
-                    let type_def = self
+                    match self
                         .lower_schema(name, schema)
-                        .with_context(|| format!("Failed to lower schema '{}'", name))?;
-                    self.types.insert(name.clone(), type_def);
+                        .with_context(|| format!("Failed to lower schema '{}'", name))
+                    {
+                        Ok(mut type_def) => {
+                            type_def.set_deprecated(schema.schema_data.deprecated);
+                            self.types.insert(name.clone(), type_def);
+                        }
+                        Err(error) => self.fail(error, format!("components.schemas.{}", name))?,
+                    }
                 }
             }
         }
 
-        // Second pass: collect endpoints
-        let mut endpoints = Vec::new();
+        // Second pass: collect endpoints, kept in declaration order
+        let mut operations = Vec::new();
         for (path, item) in self.spec.paths.iter() {
             if let ReferenceOr::Item(path_item) = item {
                 for (method, op) in path_item.iter() {
-                    let endpoint = self.lower_operation(path, method, op)?;
-                    endpoints.push(endpoint);
+                    operations.push((operations.len(), path, method, op));
                 }
+            } else {
+                self.report.borrow_mut().skipped_operations += 1;
+            }
+        }
+        operations.sort_by(|a, b| (a.1, a.2).cmp(&(b.1, b.2)));
+        let mut lowered = Vec::new();
+        for (index, path, method, op) in operations {
+            match self
+                .lower_operation(path, method, op)
+                .with_context(|| format!("Failed to lower operation {} {}", method, path))
+            {
+                Ok(endpoint) => lowered.push((index, endpoint)),
+                Err(error) => self.fail(error, format!("paths.{}.{}", path, method))?,
+            }
+        }
+        lowered.sort_by_key(|(index, _)| *index);
+        let mut endpoints: Vec<Endpoint> = lowered.into_iter().map(|(_, endpoint)| endpoint).collect();
+
+        // Third pass: webhook payloads, whose inline types are synthesized too
+        let webhooks = self.lower_webhooks()?;
+
+        for (name, type_def) in self.synthesized.take() {
+            self.types.entry(name).or_insert(type_def);
+        }
+
+        // A typo in `--coerce` would otherwise silently do nothing
+        for (index, coercion) in self.options.coercions.iter().enumerate() {
+            if !self.matched_coercions.borrow().contains(&index) {
+                self.warn(
+                    format!(
+                        "--coerce {}.{}={} matches no property; ignoring",
+                        coercion.type_name, coercion.field, coercion.wire
+                    ),
+                    format!("components.schemas.{}.properties.{}", coercion.type_name, coercion.field),
+                );
             }
         }
 
+        let diagnostics = self.diagnostics.take();
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return Err(LowerError { diagnostics }.into());
+        }
+
+        let mut types: Vec<TypeDef> = self.types.values().cloned().collect();
+        let mut report = self.report.take();
+        detect_pagination(&types, &mut endpoints);
+        if self.options.request_types == RequestTypes::Split {
+            report.synthesized_names += split_read_write(&mut types, &mut endpoints);
+        }
+        if self.options.promote_enums {
+            let before = types.len();
+            promote_enums(&mut types);
+            report.synthesized_names += types.len() - before;
+        }
+        for type_def in &types {
+            match type_def {
+                TypeDef::Record { .. } => report.records += 1,
+                TypeDef::Variant { .. } => report.variants += 1,
+                TypeDef::Alias { .. } => report.aliases += 1,
+            }
+        }
+        report.operations = endpoints.len();
+
         Ok(ApiSpec {
             title: self.spec.info.title.clone(),
             version: self.spec.info.version.clone(),
             description: self.spec.info.description.clone(),
-            types: self.types.values().cloned().collect(),
+            spec_hash: spec_hash(self.spec),
+            has_security: has_security(self.spec),
+            security_schemes: security_schemes(self.spec),
+            servers: self.spec.servers.iter().map(lower_server).collect(),
+            problem_details: self.problem_details.get(),
+            types,
             endpoints,
+            webhooks,
+            diagnostics,
+            report,
         })
     }
 
-    fn lower_schema(&self, name: &str, schema: &Schema) -> Result<TypeDef> {
-        let doc = schema.schema_data.description.clone();
-        let rs_name = name.to_pascal_case();
+    /// Sanitized field name for a property, suffixed if an earlier property took it
+    ///
+    /// Properties like `type` and `type_` (or `userId` and `user_id`) sanitize
+    /// to the same name; the first in spec order keeps it and later ones are
+    /// numbered, so the choice only depends on the spec. `context` locates the
+    /// property in warnings.
+    fn unique_field_name(
+        &self,
+        prop_name: &str,
+        case: FieldCase,
+        taken: &BTreeMap<String, &str>,
+        context: String,
+    ) -> String {
+        let field_name = sanitize_field_name(prop_name, case);
+        let Some(other) = taken.get(&field_name) else {
+            return field_name;
+        };
 
-        match &schema.schema_kind {
-            SchemaKind::Type(Type::Object(obj)) => {
-                let mut fields = Vec::new();
-
-                for (prop_name, prop_schema) in &obj.properties {
-                    let required = obj.required.contains(prop_name);
-                    let ty = self.boxed_schema_to_type(prop_schema)?;
-                    let field_ty = if required {
-                        ty
-                    } else {
-                        RsType::Option(Box::new(ty))
-                    };
-
-                    let field_doc = if let ReferenceOr::Item(s) = prop_schema {
-                        s.schema_data.description.clone()
-                    } else {
-                        None
-                    };
-
-                    fields.push(Field {
-                        name: sanitize_field_name(prop_name),
-                        original_name: prop_name.clone(),
-                        ty: field_ty,
-                        optional: !required,
-                        doc: field_doc,
-                    });
-                }
+        let renamed = (2..)
+            .map(|n| format!("{}{}", field_name, n))
+            .find(|candidate| !taken.contains_key(candidate))
+            .unwrap_or_default();
+        self.warn(
+            format!(
+                "Properties '{}' and '{}' both map to field '{}'; '{}' is renamed to '{}'",
+                other, prop_name, field_name, prop_name, renamed
+            ),
+            context,
+        );
+        self.report.borrow_mut().synthesized_names += 1;
+        renamed
+    }
 
-                Ok(TypeDef::Record {
-                    name: rs_name,
-                    doc,
-                    fields,
-                })
+    /// JSON name of a property, unless another property is already sent under it
+    ///
+    /// With `snake-wire`, `userId` and `user_id` would both be sent as
+    /// `user_id`; the property already named that way keeps it, and the other
+    /// is sent under its spec name, with a warning.
+    fn unique_wire_name<'p>(
+        &self,
+        schema_name: &str,
+        prop_name: &'p str,
+        taken: &mut BTreeMap<String, &'p str>,
+    ) -> String {
+        let wire_name = self.options.field_case.wire_name(prop_name);
+        match taken.get(&wire_name) {
+            Some(other) if *other != prop_name => {
+                self.warn(
+                    format!(
+                        "Properties '{}' and '{}' are both sent as '{}'; '{}' is sent as '{}'",
+                        other, prop_name, wire_name, prop_name, prop_name
+                    ),
+                    format!("components.schemas.{}.properties.{}", schema_name, prop_name),
+                );
+                prop_name.to_string()
+            }
+            _ => {
+                taken.insert(wire_name.clone(), prop_name);
+                wire_name
             }
+        }
+    }
 
-            SchemaKind::Type(Type::String(string_type)) => {
-                if !string_type.enumeration.is_empty() {
-                    // String enum -> variant type
-                    let cases = string_type
-                        .enumeration
-                        .iter()
-                        .filter_map(|v| v.as_ref())
-                        .map(|v| VariantCase {
-                            name: v.to_pascal_case(),
-                            payload: None,
-                        })
-                        .collect();
+    /// ReScript literal for a property's `default`, if it has one of its type
+    ///
+    /// Scalars, enum cases and empty arrays are supported; other defaults are
+    /// left to the server and the field stays optional, with a warning if the
+    /// value doesn't fit the type.
+    fn field_default(&self, ty: &RsType, prop: &Schema, path: &str) -> Option<String> {
+        use serde_json::Value;
 
-                    Ok(TypeDef::Variant {
-                        name: rs_name,
-                        doc,
-                        cases,
-                    })
-                } else {
-                    Ok(TypeDef::Alias {
-                        name: rs_name,
-                        doc,
-                        target: RsType::String,
-                    })
+        let value = prop.schema_data.default.as_ref()?;
+        let literal = match (ty, value) {
+            (RsType::String | RsType::Int64String, Value::String(_)) => Some(value.to_string()),
+            (RsType::Int, Value::Number(n)) => n.as_i64().map(|n| n.to_string()),
+            (RsType::Float, Value::Number(n)) => n.as_f64().map(|n| format!("{:?}", n)),
+            (RsType::BigInt, Value::Number(n)) => n.as_i64().map(|n| format!("{}n", n)),
+            (RsType::Bool, Value::Bool(b)) => Some(b.to_string()),
+            (RsType::StringEnum(values), Value::String(v)) if values.contains(v) => {
+                Some(format!("#\"{}\"", v))
+            }
+            (RsType::Array(_), Value::Array(items)) if items.is_empty() => Some("[]".to_string()),
+            (RsType::Named(name), _) => {
+                let synthesized = self.synthesized.borrow();
+                match self.types.get(name).or_else(|| synthesized.get(name)) {
+                    Some(TypeDef::Variant { cases, .. }) => {
+                        let literal = enum_literal(value);
+                        cases.iter().find_map(|case| match (&case.literal, value) {
+                            (Some(case_literal), _) if *case_literal == literal => {
+                                Some(case.name.clone())
+                            }
+                            (None, Value::String(v))
+                                if case.payload.is_none() && case.name == v.to_pascal_case() =>
+                            {
+                                Some(format!("#{}", case.name))
+                            }
+                            _ => None,
+                        })
+                    }
+                    // Named records and the like keep their default on the server
+                    _ => return None,
                 }
             }
+            (RsType::DateTime | RsType::Date | RsType::Json | RsType::Dict(_), _) => return None,
+            _ => None,
+        };
+        if literal.is_none() {
+            self.warn(
+                format!("default {} doesn't match the property type; left optional", value),
+                path.to_string(),
+            );
+        }
+        literal
+    }
 
-            SchemaKind::OneOf { one_of } => {
-                let cases = self.lower_variant_cases(one_of);
-                Ok(TypeDef::Variant {
-                    name: rs_name,
-                    doc,
-                    cases,
-                })
-            }
+    /// Record fields for the properties of an object schema
+    fn lower_fields(&self, name: &str, obj: &ObjectType) -> Result<Vec<Field>> {
+        let mut fields = Vec::new();
+        // Sanitized field name -> property it was taken by
+        let mut field_names: BTreeMap<String, &str> = BTreeMap::new();
+        // Wire name -> property sent under it
+        let mut wire_names: BTreeMap<String, &str> = obj
+            .properties
+            .keys()
+            .filter(|p| self.options.field_case.wire_name(p) == **p)
+            .map(|p| (p.clone(), p.as_str()))
+            .collect();
 
-            SchemaKind::AnyOf { any_of } => {
-                let cases = self.lower_variant_cases(any_of);
-                Ok(TypeDef::Variant {
-                    name: rs_name,
-                    doc,
-                    cases,
-                })
+        for (prop_name, prop_schema) in &obj.properties {
+            if let ReferenceOr::Item(prop) = prop_schema {
+                self.check_numeric_precision(
+                    prop,
+                    &format!("components.schemas.{}.properties.{}", name, prop_name),
+                );
             }
+            let server_generated = prop_schema.as_item().is_some_and(|s| is_server_generated(s));
+            let write_only = prop_schema.as_item().is_some_and(|s| is_write_only(s));
+            let required = obj.required.contains(prop_name);
+            let nullable = prop_schema.as_item().is_some_and(|s| self.is_nullable(s));
+            let ty = match prop_schema {
+                ReferenceOr::Item(prop) => self.inline_schema_to_type(
+                    &format!("{}{}", name.to_pascal_case(), prop_name.to_pascal_case()),
+                    prop,
+                )?,
+                ReferenceOr::Reference { .. } => self.boxed_schema_to_type(prop_schema)?,
+            };
+            let default = match prop_schema {
+                ReferenceOr::Item(prop) if !required && !nullable && !server_generated => {
+                    let path = format!("components.schemas.{}.properties.{}", name, prop_name);
+                    self.field_default(&ty, prop, &path)
+                }
+                _ => None,
+            };
+            let field_ty = if nullable {
+                RsType::Nullable(Box::new(ty))
+            } else if required || default.is_some() {
+                ty
+            } else {
+                RsType::Option(Box::new(ty))
+            };
 
-            _ => {
-                // Default to alias
-                let target = self.schema_kind_to_type(&schema.schema_kind)?;
-                Ok(TypeDef::Alias {
-                    name: rs_name,
-                    doc,
-                    target,
-                })
-            }
+            let field_doc = if let ReferenceOr::Item(s) = prop_schema {
+                s.schema_data.description.clone()
+            } else {
+                None
+            };
+
+            let field_name = self.unique_field_name(
+                prop_name,
+                self.options.field_case,
+                &field_names,
+                format!("components.schemas.{}.properties.{}", name, prop_name),
+            );
+            field_names.insert(field_name.clone(), prop_name);
+
+            fields.push(Field {
+                name: field_name,
+                original_name: self.unique_wire_name(name, prop_name, &mut wire_names),
+                ty: field_ty,
+                optional: !required && default.is_none(),
+                doc: field_doc,
+                coerce_from: self.field_coercion(name, prop_name, prop_schema),
+                server_generated,
+                write_only,
+                constraints: prop_schema.as_item().map(|s| Constraints::of(s)).unwrap_or_default(),
+                deprecated: prop_schema.as_item().is_some_and(|s| s.schema_data.deprecated),
+                default,
+                additional: false,
+            });
         }
-    }
 
-    fn schema_to_type(&self, schema: &ReferenceOr<Schema>) -> Result<RsType> {
-        match schema {
-            ReferenceOr::Reference { reference } => {
-                let name = reference
-                    .strip_prefix("#/components/schemas/")
-                    .unwrap_or(reference);
-                Ok(RsType::Named(name.to_pascal_case()))
+        if self.options.extra_properties == ExtraProperties::Field {
+            if let Some(value) = self.additional_properties_type(name, obj)? {
+                let field_name = (1..)
+                    .map(|n| if n == 1 { "extra".to_string() } else { format!("extra{}", n) })
+                    .find(|candidate| !field_names.contains_key(candidate))
+                    .unwrap_or_default();
+                fields.push(Field {
+                    original_name: field_name.clone(),
+                    name: field_name,
+                    ty: RsType::Dict(Box::new(value)),
+                    optional: false,
+                    doc: Some("Properties not declared in the schema".to_string()),
+                    coerce_from: None,
+                    server_generated: false,
+                    write_only: false,
+                    constraints: Constraints::default(),
+                    deprecated: false,
+                    default: None,
+                    additional: true,
+                });
             }
-            ReferenceOr::Item(schema) => self.schema_kind_to_type(&schema.schema_kind),
+        }
+
+        Ok(fields)
+    }
+
+    /// Value type of an object's undeclared properties, if it allows them
+    ///
+    /// An inline object value is lifted into a record named `{hint}Value`.
+    fn additional_properties_type(&self, hint: &str, obj: &ObjectType) -> Result<Option<RsType>> {
+        match &obj.additional_properties {
+            Some(AdditionalProperties::Any(true)) => Ok(Some(RsType::Json)),
+            Some(AdditionalProperties::Schema(schema)) => {
+                let ty = match schema.as_ref() {
+                    ReferenceOr::Item(value) => {
+                        self.inline_schema_to_type(&format!("{}Value", hint), value)?
+                    }
+                    reference => self.schema_to_type(reference)?,
+                };
+                Ok(Some(ty))
+            }
+            Some(AdditionalProperties::Any(false)) | None => Ok(None),
+        }
+    }
+
+    /// Properties and `required` lists of `allOf` members combined into one object
+    ///
+    /// `$ref` members are resolved, following their own `allOf`. Later members
+    /// override earlier properties of the same name, and a property is required
+    /// if any member requires it. `None` if some member isn't an object schema.
+    fn merge_all_of(
+        &self,
+        members: &[ReferenceOr<Schema>],
+        visiting: &mut Vec<String>,
+    ) -> Option<ObjectType> {
+        let mut merged = ObjectType::default();
+        for member in members {
+            let object = match member {
+                ReferenceOr::Reference { reference } => {
+                    let name = reference.strip_prefix("#/components/schemas/")?;
+                    // A parent that (indirectly) includes itself can't be flattened
+                    if visiting.iter().any(|v| v == name) {
+                        return None;
+                    }
+                    let schema = self.spec.components.as_ref()?.schemas.get(name)?.as_item()?;
+                    visiting.push(name.to_string());
+                    let object = self.object_members(schema, visiting);
+                    visiting.pop();
+                    object?
+                }
+                ReferenceOr::Item(schema) => self.object_members(schema, visiting)?,
+            };
+            merged.properties.extend(object.properties);
+            for prop_name in object.required {
+                if !merged.required.contains(&prop_name) {
+                    merged.required.push(prop_name);
+                }
+            }
+        }
+        Some(merged)
+    }
+
+    /// Parts of a `multipart/form-data` body, one per property of its object schema
+    ///
+    /// Binary strings (and arrays of them), also behind a `$ref`, are files;
+    /// other properties keep their type and are sent as text, inline objects
+    /// lifted into records named after the form (`hint`, e.g. `UploadForm`).
+    fn form_fields(
+        &self,
+        hint: &str,
+        schema: Option<&ReferenceOr<Schema>>,
+        context: &str,
+    ) -> Result<Vec<FormField>> {
+        let schema = match schema {
+            Some(ReferenceOr::Item(schema)) => Some(schema),
+            Some(ReferenceOr::Reference { reference }) => self.component_schema(reference),
+            None => None,
+        };
+        let Some(object) = schema.and_then(|s| self.object_members(s, &mut Vec::new())) else {
+            self.warn(
+                "Multipart body is not an object schema; sending no parts".to_string(),
+                context.to_string(),
+            );
+            return Ok(Vec::new());
+        };
+
+        let is_file = |schema: Option<&Schema>| {
+            matches!(
+                schema.map(|s| &s.schema_kind),
+                Some(SchemaKind::Type(Type::String(string))) if is_binary(string)
+            )
+        };
+        let mut fields = Vec::new();
+        // Field name -> property it was taken by
+        let mut field_names: BTreeMap<String, &str> = BTreeMap::new();
+        for (name, property) in &object.properties {
+            let item = match property {
+                ReferenceOr::Item(schema) => Some(schema.as_ref()),
+                ReferenceOr::Reference { reference } => self.component_schema(reference),
+            };
+            let files = match item.map(|s| &s.schema_kind) {
+                Some(SchemaKind::Type(Type::Array(array))) => {
+                    is_file(array.items.as_ref().and_then(|items| match items {
+                        ReferenceOr::Item(items) => Some(items.as_ref()),
+                        ReferenceOr::Reference { reference } => self.component_schema(reference),
+                    }))
+                }
+                _ => false,
+            };
+            let kind = if is_file(item) {
+                FormFieldKind::File
+            } else if files {
+                FormFieldKind::Files
+            } else {
+                let ty = match property {
+                    ReferenceOr::Item(schema) => self
+                        .inline_schema_to_type(&format!("{}{}", hint, name.to_pascal_case()), schema),
+                    ReferenceOr::Reference { .. } => self.boxed_schema_to_type(property),
+                };
+                match ty {
+                    Ok(ty) => FormFieldKind::Value(ty),
+                    Err(error) => {
+                        self.fail(error, format!("{}.properties.{}", context, name))?;
+                        FormFieldKind::Value(RsType::Json)
+                    }
+                }
+            };
+            let field = self.unique_field_name(
+                name,
+                FieldCase::Camel,
+                &field_names,
+                format!("{}.properties.{}", context, name),
+            );
+            field_names.insert(field.clone(), name);
+            fields.push(FormField {
+                name: name.clone(),
+                field,
+                kind,
+                required: object.required.contains(name),
+                doc: property.as_item().and_then(|s| s.schema_data.description.clone()),
+            });
+        }
+        Ok(fields)
+    }
+
+    /// The component schema a `#/components/schemas/...` reference points at
+    fn component_schema(&self, reference: &str) -> Option<&Schema> {
+        let name = reference.strip_prefix("#/components/schemas/")?;
+        self.spec.components.as_ref()?.schemas.get(name)?.as_item()
+    }
+
+    /// A schema as an object for `allOf` merging, if it is one
+    ///
+    /// Members without `type` (e.g. `{required: [id]}`) count as objects as long
+    /// as they only carry object keywords.
+    fn object_members(&self, schema: &Schema, visiting: &mut Vec<String>) -> Option<ObjectType> {
+        match &schema.schema_kind {
+            SchemaKind::Type(Type::Object(obj)) => Some(obj.clone()),
+            SchemaKind::AllOf { all_of } => self.merge_all_of(all_of, visiting),
+            SchemaKind::Any(any)
+                if any.typ.as_deref().is_none_or(|t| t == "object")
+                    && any.items.is_none()
+                    && any.enumeration.is_empty()
+                    && any.one_of.is_empty()
+                    && any.any_of.is_empty() =>
+            {
+                let mut object = match self.merge_all_of(&any.all_of, visiting) {
+                    Some(object) => object,
+                    None if any.all_of.is_empty() => ObjectType::default(),
+                    None => return None,
+                };
+                object.properties.extend(any.properties.clone());
+                for prop_name in &any.required {
+                    if !object.required.contains(prop_name) {
+                        object.required.push(prop_name.clone());
+                    }
+                }
+                Some(object)
+            }
+            _ => None,
+        }
+    }
+
+    fn lower_schema(&self, name: &str, schema: &Schema) -> Result<TypeDef> {
+        let doc = with_external_docs(
+            schema.schema_data.description.clone(),
+            &schema.schema_data.external_docs.iter().collect::<Vec<_>>(),
+        );
+        let rs_name = name.to_pascal_case();
+        self.check_numeric_precision(schema, &format!("components.schemas.{}", name));
+
+        // Inherited models: `allOf` of object schemas and `$ref`'d parents
+        if let SchemaKind::AllOf { all_of } = &schema.schema_kind {
+            if let Some(merged) = self.merge_all_of(all_of, &mut Vec::new()) {
+                return Ok(TypeDef::Record {
+                    name: rs_name,
+                    doc,
+                    fields: self.lower_fields(name, &merged)?,
+                    deprecated: false,
+                });
+            }
+            self.warn(
+                "allOf members are not all object schemas; generated as JSON.t".to_string(),
+                format!("components.schemas.{}", name),
+            );
+        }
+
+        if let Some(cases) = literal_enum_cases(schema) {
+            return Ok(TypeDef::Variant {
+                name: rs_name,
+                doc,
+                cases,
+                discriminator: None,
+                deprecated: false,
+            });
+        }
+
+        match &schema.schema_kind {
+            // Map-like object: `type scores = Dict.t<int>`
+            SchemaKind::Type(Type::Object(obj)) if obj.properties.is_empty() => {
+                match self.additional_properties_type(&rs_name, obj)? {
+                    Some(value) => Ok(TypeDef::Alias {
+                        name: rs_name,
+                        doc,
+                        target: RsType::Dict(Box::new(value)),
+                        constraints: Constraints::default(),
+                        deprecated: false,
+                    }),
+                    None => Ok(TypeDef::Record {
+                        name: rs_name,
+                        doc,
+                        fields: Vec::new(),
+                        deprecated: false,
+                    }),
+                }
+            }
+
+            SchemaKind::Type(Type::Object(obj)) => Ok(TypeDef::Record {
+                name: rs_name,
+                doc,
+                fields: self.lower_fields(name, obj)?,
+                deprecated: false,
+            }),
+
+            SchemaKind::Type(Type::String(string_type)) => {
+                if !string_type.enumeration.is_empty() {
+                    // String enum -> variant type
+                    let cases = string_type
+                        .enumeration
+                        .iter()
+                        .filter_map(|v| v.as_ref())
+                        .map(|v| VariantCase {
+                            name: v.to_pascal_case(),
+                            payload: None,
+                            tags: Vec::new(),
+                            literal: None,
+                        })
+                        .collect();
+
+                    Ok(TypeDef::Variant {
+                        name: rs_name,
+                        doc,
+                        cases,
+                        discriminator: None,
+                        deprecated: false,
+                    })
+                } else {
+                    Ok(TypeDef::Alias {
+                        name: rs_name,
+                        doc,
+                        target: self.string_type(string_type),
+                        constraints: Constraints::of(schema),
+                        deprecated: false,
+                    })
+                }
+            }
+
+            SchemaKind::OneOf { one_of } => {
+                if let Some((cases, property)) = self.discriminated_cases(name, schema, one_of) {
+                    return Ok(TypeDef::Variant {
+                        name: rs_name,
+                        doc,
+                        cases,
+                        discriminator: Some(property),
+                        deprecated: false,
+                    });
+                }
+                let cases = self
+                    .scalar_union_cases(one_of)
+                    .unwrap_or_else(|| self.lower_variant_cases(one_of));
+                Ok(TypeDef::Variant {
+                    name: rs_name,
+                    doc,
+                    cases,
+                    discriminator: None,
+                    deprecated: false,
+                })
+            }
+
+            SchemaKind::AnyOf { any_of } => {
+                if let Some((cases, property)) = self.discriminated_cases(name, schema, any_of) {
+                    return Ok(TypeDef::Variant {
+                        name: rs_name,
+                        doc,
+                        cases,
+                        discriminator: Some(property),
+                        deprecated: false,
+                    });
+                }
+                let cases = self
+                    .scalar_union_cases(any_of)
+                    .unwrap_or_else(|| self.lower_variant_cases(any_of));
+                Ok(TypeDef::Variant {
+                    name: rs_name,
+                    doc,
+                    cases,
+                    discriminator: None,
+                    deprecated: false,
+                })
+            }
+
+            _ => {
+                // Default to alias
+                let target = self.item_schema_to_type(schema)?;
+                Ok(TypeDef::Alias {
+                    name: rs_name,
+                    doc,
+                    target,
+                    constraints: Constraints::of(schema),
+                    deprecated: false,
+                })
+            }
+        }
+    }
+
+    /// Wire type of a property, from `--coerce` options or `x-rescript-coerce`
+    fn field_coercion(
+        &self,
+        type_name: &str,
+        prop_name: &str,
+        prop_schema: &ReferenceOr<Box<Schema>>,
+    ) -> Option<RsType> {
+        let configured = self
+            .options
+            .coercions
+            .iter()
+            .position(|c| c.type_name == type_name && c.field == prop_name)
+            .map(|index| {
+                self.matched_coercions.borrow_mut().insert(index);
+                self.options.coercions[index].wire.clone()
+            });
+        let wire = configured.or_else(|| {
+            let value = prop_schema.as_item()?.schema_data.extensions.get(COERCE_EXTENSION)?;
+            Some(value.as_str().unwrap_or_default().to_string())
+        })?;
+
+        let ty = wire_type(&wire);
+        if ty.is_none() {
+            self.warn(
+                format!("Unknown {} type '{}', ignoring", COERCE_EXTENSION, wire),
+                format!("components.schemas.{}.properties.{}", type_name, prop_name),
+            );
+        }
+        ty
+    }
+
+    /// Schema selected by a sparse fieldset parameter, if it names an object schema with properties
+    fn param_fieldset(&self, param: &openapiv3::ParameterData, path: &str) -> Option<String> {
+        let value = param.extensions.get(FIELDSET_EXTENSION)?;
+        let Some(name) = value.as_str() else {
+            self.warn(format!("{} must be a schema name", FIELDSET_EXTENSION), path.to_string());
+            return None;
+        };
+
+        let is_object = self
+            .spec
+            .components
+            .as_ref()
+            .and_then(|c| c.schemas.get(name))
+            .and_then(|s| s.as_item())
+            .is_some_and(|s| match &s.schema_kind {
+                SchemaKind::Type(Type::Object(obj)) => !obj.properties.is_empty(),
+                _ => false,
+            });
+        if !is_object {
+            self.warn(
+                format!("{} '{}' is not an object schema with properties, ignoring", FIELDSET_EXTENSION, name),
+                path.to_string(),
+            );
+            return None;
+        }
+        Some(name.to_pascal_case())
+    }
+
+    /// Raw body type for responses whose content is only wildcard media types
+    fn lower_response(
+        &self,
+        operation_id: &str,
+        status: ResponseStatus,
+        response: &openapiv3::Response,
+    ) -> Response {
+        // Plain JSON is preferred when a response offers both
+        let problem_media = media_content(&response.content, PROBLEM_JSON);
+        let (json, problem) = match media_content(&response.content, "application/json") {
+            Some(media) => (Some(media), false),
+            None => (problem_media, problem_media.is_some()),
+        };
+        if problem {
+            self.problem_details();
+        }
+        // `GetUserResponse` for success, `GetUser404Response` / `GetUser4XXResponse` for errors
+        let hint = match status {
+            ResponseStatus::Code(200..=299) | ResponseStatus::Range(2) => {
+                format!("{}Response", operation_id.to_pascal_case())
+            }
+            ResponseStatus::Default => format!("{}DefaultResponse", operation_id.to_pascal_case()),
+            status => format!("{}{}Response", operation_id.to_pascal_case(), status),
+        };
+        let json_type = || match json.map(|media| &media.schema) {
+            Some(Some(schema)) => self.media_schema_to_type(&hint, schema).ok(),
+            _ => None,
+        };
+        // Success bodies offered in several concrete media types are negotiated with
+        // `Accept`, unless one is plain JSON (then the only one read) and
+        // `--negotiate-responses` is off
+        let mut media: Vec<(String, MediaKind)> = Vec::new();
+        for media_type in response.content.keys().map(|m| media_essence(m)) {
+            let concrete = !media_type.ends_with("/*")
+                && media_type != PROBLEM_JSON
+                && media_type != EVENT_STREAM
+                && !NDJSON_MEDIA_TYPES.contains(&media_type.as_str());
+            if concrete && !media.iter().any(|(m, _)| *m == media_type) {
+                let kind = MediaKind::of(&media_type);
+                media.push((media_type, kind));
+            }
+        }
+        let success = matches!(status, ResponseStatus::Code(200..=299) | ResponseStatus::Range(2));
+        let has_json = media.iter().any(|(m, _)| m == "application/json");
+        let negotiate =
+            success && media.len() > 1 && (!has_json || self.options.negotiate_responses);
+        let ty = match json.map(|media| &media.schema) {
+            _ if negotiate => {
+                // `application/json` is the default, else the first declared
+                if let Some(index) = media.iter().position(|(m, _)| m == "application/json") {
+                    let default = media.remove(index);
+                    media.insert(0, default);
+                }
+                let json = if media.iter().any(|(_, kind)| *kind == MediaKind::Json) {
+                    json_type().unwrap_or(RsType::Json)
+                } else {
+                    RsType::Unit
+                };
+                Some(RsType::Negotiated { json: Box::new(json), media })
+            }
+            Some(Some(_)) => json_type(),
+            // RFC 7807 defines the members of a problem without a schema
+            Some(None) if problem => Some(RsType::Named(PROBLEM_DETAILS.to_string())),
+            Some(None) => None,
+            None => self
+                .wildcard_response_type(&response.content)
+                .or_else(|| self.binary_response_type(&response.content)),
+        };
+        let example = json.and_then(|media| {
+            media.example.clone().or_else(|| {
+                media.examples.values().find_map(|example| match example {
+                    ReferenceOr::Item(example) => example.value.clone(),
+                    ReferenceOr::Reference { .. } => None,
+                })
+            })
+        });
+
+        Response {
+            status,
+            ty,
+            doc: Some(response.description.clone()),
+            example,
+        }
+    }
+
+    /// Create the shared RFC 7807 `ProblemDetails` record, unless a component schema
+    /// of that name stands in for it
+    fn problem_details(&self) {
+        if self.problem_details.replace(true) || self.is_schema_name(PROBLEM_DETAILS) {
+            return;
+        }
+        let schema: Schema = serde_yaml::from_str(
+            r#"
+type: object
+description: Machine-readable details of an error (RFC 7807)
+properties:
+  type: { type: string, description: URI reference identifying the problem type }
+  title: { type: string, description: Short summary of the problem type }
+  status: { type: integer, description: HTTP status code of this occurrence }
+  detail: { type: string, description: Explanation specific to this occurrence }
+  instance: { type: string, description: URI reference identifying this occurrence }
+additionalProperties: true
+"#,
+        )
+        .expect("ProblemDetails schema is valid");
+        if let Ok(type_def) = self.lower_schema(PROBLEM_DETAILS, &schema) {
+            self.report.borrow_mut().synthesized_names += 1;
+            self.synthesized.borrow_mut().insert(PROBLEM_DETAILS.to_string(), type_def);
+        }
+    }
+
+    fn wildcard_response_type(
+        &self,
+        content: &openapiv3::Content,
+    ) -> Option<RsType> {
+        if !content.keys().any(|media_type| media_type.ends_with("/*")) {
+            return None;
+        }
+        match self.options.wildcard_responses {
+            WildcardResponses::Text => Some(RsType::RawText),
+            WildcardResponses::Blob => Some(RsType::RawBlob),
+            WildcardResponses::Ignore => None,
+        }
+    }
+
+    /// Raw body type for responses with a binary media type or a `format: binary` schema
+    fn binary_response_type(&self, content: &openapiv3::Content) -> Option<RsType> {
+        let binary = content.iter().any(|(media_type, media)| {
+            // Wildcards are `--wildcard-responses`' business
+            !media_type.ends_with("/*")
+                && (is_binary_media_type(media_type)
+                    || matches!(
+                        media.schema.as_ref().and_then(|s| s.as_item()).map(|s| &s.schema_kind),
+                        Some(SchemaKind::Type(Type::String(string))) if is_binary(string)
+                    ))
+        });
+        if !binary {
+            return None;
+        }
+        match self.options.binary_responses {
+            BinaryResponses::Blob => Some(RsType::RawBlob),
+            BinaryResponses::ArrayBuffer => Some(RsType::RawArrayBuffer),
+        }
+    }
+
+    /// Reserve a derived operation name, numbering it on collision (`getUsers2`)
+    fn unique_operation_id(&self, base: String) -> String {
+        self.report.borrow_mut().synthesized_names += 1;
+        let mut used = self.operation_ids.borrow_mut();
+        let mut candidate = base.clone();
+        let mut n = 2;
+        while used.contains(&candidate) {
+            candidate = format!("{}{}", base, n);
+            n += 1;
+        }
+        used.insert(candidate.clone());
+        candidate
+    }
+
+    /// Whether a schema admits `null`, including vendor markers in compat mode
+    fn is_nullable(&self, schema: &Schema) -> bool {
+        if schema.schema_data.nullable {
+            return true;
+        }
+        self.options.compat_nullable
+            && NULLABLE_EXTENSIONS.iter().any(|key| {
+                schema.schema_data.extensions.get(*key) == Some(&serde_json::Value::Bool(true))
+            })
+    }
+
+    fn schema_to_type(&self, schema: &ReferenceOr<Schema>) -> Result<RsType> {
+        match schema {
+            ReferenceOr::Reference { reference } => {
+                let name = reference
+                    .strip_prefix("#/components/schemas/")
+                    .unwrap_or(reference);
+                Ok(RsType::Named(name.to_pascal_case()))
+            }
+            ReferenceOr::Item(schema) => self.item_schema_to_type(schema),
+        }
+    }
+
+    /// Type of a request or response body, lifting an inline object (see `inline_schema_to_type`)
+    fn media_schema_to_type(&self, hint: &str, schema: &ReferenceOr<Schema>) -> Result<RsType> {
+        match schema {
+            ReferenceOr::Item(schema) => self.inline_schema_to_type(hint, schema),
+            ReferenceOr::Reference { .. } => self.schema_to_type(schema),
         }
     }
 
@@ -400,10 +2818,132 @@ impl<'a> Lowerer<'a> {
                     .unwrap_or(reference);
                 Ok(RsType::Named(name.to_pascal_case()))
             }
-            ReferenceOr::Item(schema) => self.schema_kind_to_type(&schema.schema_kind),
+            ReferenceOr::Item(schema) => self.item_schema_to_type(schema),
         }
     }
 
+    /// Cases of a `oneOf`/`anyOf` with a `discriminator`, named after their tags
+    ///
+    /// Each `$ref` member gets one case, named after the first mapping entry
+    /// pointing at it and selected by all of them, or tagged with its schema
+    /// name when the mapping doesn't mention it. Returns
+    /// `None` (plain cases) when a member is inline, since its tag is unknown.
+    fn discriminated_cases(
+        &self,
+        name: &str,
+        schema: &Schema,
+        members: &[ReferenceOr<Schema>],
+    ) -> Option<(Vec<VariantCase>, String)> {
+        let (property, mapping) = match &schema.schema_data.discriminator {
+            Some(discriminator) => {
+                let mapping = discriminator
+                    .mapping
+                    .iter()
+                    .map(|(tag, target)| (tag.clone(), target.clone()))
+                    .collect();
+                (discriminator.property_name.clone(), mapping)
+            }
+            None => self.const_discriminator(members)?,
+        };
+        let path = format!("components.schemas.{}", name);
+
+        let mut cases: Vec<VariantCase> = Vec::new();
+        for member in members {
+            let ReferenceOr::Reference { reference } = member else {
+                self.warn(
+                    "Inline members can't be selected by the discriminator; \
+                     cases are matched by shape instead"
+                        .to_string(),
+                    path,
+                );
+                return None;
+            };
+            let ref_name = reference
+                .strip_prefix("#/components/schemas/")
+                .unwrap_or(reference);
+            let type_name = ref_name.to_pascal_case();
+
+            // Mapping values are either full references or bare schema names
+            let mut tags: Vec<String> = mapping
+                .iter()
+                .filter(|(_, target)| target == reference || target.as_str() == ref_name)
+                .map(|(tag, _)| tag.clone())
+                .collect();
+            if tags.is_empty() {
+                tags.push(ref_name.to_string());
+            }
+            // A schema listed twice is still one case
+            if cases.iter().any(|c| matches!(&c.payload, Some(RsType::Named(n)) if *n == type_name)) {
+                continue;
+            }
+
+            let case_name = tags[0].to_pascal_case();
+            if let Some(existing) = cases.iter().find(|c| c.name == case_name) {
+                self.warn(
+                    format!(
+                        "Discriminator values '{}' and '{}' both map to case '{}'; \
+                         cases are matched by shape instead",
+                        existing.tags[0], tags[0], case_name
+                    ),
+                    path,
+                );
+                return None;
+            }
+            cases.push(VariantCase {
+                name: case_name,
+                payload: Some(RsType::Named(type_name)),
+                tags,
+                literal: None,
+            });
+        }
+
+        Some((cases, property))
+    }
+
+    /// A tag property for a union without a `discriminator`, with its mapping
+    ///
+    /// The first required property that every member (all `$ref`s to objects)
+    /// pins to a different single string, with `const` or a one-value `enum`.
+    fn const_discriminator(
+        &self,
+        members: &[ReferenceOr<Schema>],
+    ) -> Option<(String, Vec<(String, String)>)> {
+        let components = self.spec.components.as_ref()?;
+        let objects = members
+            .iter()
+            .map(|member| {
+                let ReferenceOr::Reference { reference } = member else {
+                    return None;
+                };
+                let name = reference.strip_prefix("#/components/schemas/")?;
+                let schema = components.schemas.get(name)?.as_item()?;
+                Some((reference, self.object_members(schema, &mut Vec::new())?))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let tag = |object: &ObjectType, property: &str| -> Option<String> {
+            if !object.required.iter().any(|r| r == property) {
+                return None;
+            }
+            match &object.properties.get(property)?.as_item()?.schema_kind {
+                SchemaKind::Type(Type::String(string)) => match string.enumeration.as_slice() {
+                    [Some(value)] => Some(value.clone()),
+                    _ => None,
+                },
+                _ => None,
+            }
+        };
+        let (_, first) = objects.first()?;
+        first.properties.keys().find_map(|property| {
+            let mapping: Vec<(String, String)> = objects
+                .iter()
+                .map(|(reference, object)| Some((tag(object, property)?, reference.to_string())))
+                .collect::<Option<_>>()?;
+            let distinct: HashSet<&str> = mapping.iter().map(|(tag, _)| tag.as_str()).collect();
+            (distinct.len() == mapping.len()).then(|| (property.clone(), mapping))
+        })
+    }
+
     /// Lower oneOf/anyOf schemas into variant cases
     ///
     /// Extracts meaningful names from $ref references (e.g., Cat from #/components/schemas/Cat)
@@ -432,12 +2972,13 @@ impl<'a> Lowerer<'a> {
                         .as_ref()
                         .map(|t| t.to_pascal_case())
                         .unwrap_or_else(|| {
+                            self.report.borrow_mut().synthesized_names += 1;
                             let name = format!("Case{}", fallback_index);
                             fallback_index += 1;
                             name
                         });
 
-                    let ty = self.schema_kind_to_type(&inline_schema.schema_kind).ok();
+                    let ty = self.item_schema_to_type(inline_schema).ok();
                     (name, ty)
                 }
             };
@@ -445,12 +2986,30 @@ impl<'a> Lowerer<'a> {
             cases.push(VariantCase {
                 name: case_name,
                 payload,
+                tags: Vec::new(),
+                literal: None,
             });
         }
 
         cases
     }
 
+    /// Type of an inline schema, including keywords outside its kind
+    ///
+    /// An OpenAPI 3.1 `prefixItems` array is a tuple of its item types.
+    fn item_schema_to_type(&self, schema: &Schema) -> Result<RsType> {
+        if let Some(items) = schema.schema_data.extensions.get(PREFIX_ITEMS_EXTENSION) {
+            let items: Vec<ReferenceOr<Schema>> = serde_json::from_value(items.clone())
+                .context("prefixItems must be a list of schemas")?;
+            let types = items
+                .iter()
+                .map(|item| self.schema_to_type(item))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(RsType::Tuple(types));
+        }
+        self.schema_kind_to_type(&schema.schema_kind)
+    }
+
     fn schema_kind_to_type(&self, kind: &SchemaKind) -> Result<RsType> {
         match kind {
             SchemaKind::Type(Type::String(string_type)) => {
@@ -463,10 +3022,20 @@ impl<'a> Lowerer<'a> {
                         .collect();
                     Ok(RsType::StringEnum(values))
                 } else {
-                    Ok(RsType::String)
+                    Ok(self.string_type(string_type))
                 }
             }
-            SchemaKind::Type(Type::Integer(_)) => Ok(RsType::Int),
+            SchemaKind::Type(Type::Integer(int)) => match int.format {
+                VariantOrUnknownOrEmpty::Item(IntegerFormat::Int64) => Ok(match self.options.int64 {
+                    Int64Mode::Int => RsType::Int,
+                    Int64Mode::Float => RsType::Float,
+                    Int64Mode::BigInt => RsType::BigInt,
+                    Int64Mode::String => RsType::Int64String,
+                }),
+                _ => Ok(RsType::Int),
+            },
+            // `multipleOf: 1` is the JSON Schema idiom for a whole number
+            SchemaKind::Type(Type::Number(num)) if num.multiple_of == Some(1.0) => Ok(RsType::Int),
             SchemaKind::Type(Type::Number(_)) => Ok(RsType::Float),
             SchemaKind::Type(Type::Boolean(_)) => Ok(RsType::Bool),
             SchemaKind::Type(Type::Array(arr)) => {
@@ -474,26 +3043,247 @@ impl<'a> Lowerer<'a> {
                     .items
                     .as_ref()
                     .map(|i| self.boxed_schema_to_type(i))
-                    .transpose()?
-                    .unwrap_or(RsType::Json);
+                    .transpose()?;
+                let item_type = item_type.unwrap_or_else(|| self.degraded());
                 Ok(RsType::Array(Box::new(item_type)))
             }
-            SchemaKind::Type(Type::Object(_)) => Ok(RsType::Json),
-            SchemaKind::Any(_) => Ok(RsType::Json),
-            _ => Ok(RsType::Json),
+            // `allOf: [$ref]` is the usual way to document or annotate a reference
+            SchemaKind::AllOf { all_of } if all_of.len() == 1 => self.schema_to_type(&all_of[0]),
+            SchemaKind::Type(Type::Object(obj)) if obj.properties.is_empty() => {
+                let value = match &obj.additional_properties {
+                    Some(AdditionalProperties::Any(true)) => RsType::Json,
+                    Some(AdditionalProperties::Schema(schema)) => self.schema_to_type(schema)?,
+                    _ => return Ok(self.degraded()),
+                };
+                Ok(RsType::Dict(Box::new(value)))
+            }
+            SchemaKind::OneOf { one_of: members } | SchemaKind::AnyOf { any_of: members } => {
+                if let Some(cases) = self.scalar_union_cases(members) {
+                    return Ok(self.shared_scalar_union(cases));
+                }
+                match self.ref_union_cases(members) {
+                    Some(cases) => Ok(self.shared_ref_union(cases)),
+                    None => Ok(self.degraded()),
+                }
+            }
+            // Inline objects, untyped schemas and compositions
+            _ => Ok(self.degraded()),
+        }
+    }
+
+    /// Type of a non-enum `string` schema, following its `format` (see [`FORMAT_DEFAULTS`])
+    fn string_type(&self, string_type: &StringType) -> RsType {
+        let format = match &string_type.format {
+            VariantOrUnknownOrEmpty::Item(StringFormat::DateTime) => "date-time",
+            VariantOrUnknownOrEmpty::Item(StringFormat::Date) => "date",
+            VariantOrUnknownOrEmpty::Unknown(format) => format.as_str(),
+            _ => return RsType::String,
+        };
+        let ty = self
+            .options
+            .formats
+            .iter()
+            .rev()
+            .find(|mapping| mapping.format == format)
+            .map(|mapping| mapping.ty)
+            .or_else(|| FORMAT_DEFAULTS.iter().find(|(f, _)| *f == format).map(|(_, ty)| *ty));
+        match (ty, format) {
+            (Some(FormatType::Date), "date") => RsType::Date,
+            (Some(FormatType::Date), _) => RsType::DateTime,
+            _ => RsType::String,
         }
     }
 
+    /// Cases for a `oneOf`/`anyOf` of one inline string and one inline number
+    /// schema (string-or-integer ids): `S(string)` and `I(int)` or `F(float)`
+    fn scalar_union_cases(&self, members: &[ReferenceOr<Schema>]) -> Option<Vec<VariantCase>> {
+        let [ReferenceOr::Item(first), ReferenceOr::Item(second)] = members else {
+            return None;
+        };
+        let mut cases = Vec::new();
+        for member in [first, second] {
+            let payload = match &member.schema_kind {
+                SchemaKind::Type(Type::String(string)) if string.enumeration.is_empty() => {
+                    RsType::String
+                }
+                kind @ SchemaKind::Type(Type::Integer(_) | Type::Number(_)) => {
+                    self.schema_kind_to_type(kind).ok()?
+                }
+                _ => return None,
+            };
+            let name = match payload {
+                RsType::String => "S",
+                RsType::Int => "I",
+                _ => "F",
+            };
+            cases.push(VariantCase {
+                name: name.to_string(),
+                payload: Some(payload),
+                tags: Vec::new(),
+                literal: None,
+            });
+        }
+        // Strings first, so the union reads `S(string) | I(int)` either way
+        cases.sort_by_key(|case| case.name != "S");
+        is_scalar_union(&cases).then_some(cases)
+    }
+
+    /// Cases for an inline `oneOf`/`anyOf` whose members all `$ref` distinct schemas
+    fn ref_union_cases(&self, members: &[ReferenceOr<Schema>]) -> Option<Vec<VariantCase>> {
+        if members.len() < 2 || members.iter().any(|m| m.as_item().is_some()) {
+            return None;
+        }
+        let cases = self.lower_variant_cases(members);
+        let distinct = cases
+            .iter()
+            .enumerate()
+            .all(|(i, case)| cases[..i].iter().all(|c| c.name != case.name));
+        distinct.then_some(cases)
+    }
+
+    /// Reference a shared type for an inline scalar union, creating it on first use
+    fn shared_scalar_union(&self, cases: Vec<VariantCase>) -> RsType {
+        let base = format!("StringOr{}", if cases[1].name == "I" { "Int" } else { "Float" });
+        self.shared_union(base, "A value sent as either a JSON string or a JSON number", cases)
+    }
+
+    /// Reference a shared untagged union of schemas (`catOrDog`), creating it on first use
+    fn shared_ref_union(&self, cases: Vec<VariantCase>) -> RsType {
+        let names: Vec<&str> = cases.iter().map(|c| c.name.as_str()).collect();
+        let doc = format!("One of {}, decoded as the first that matches", names.join(", "));
+        self.shared_union(names.join("Or"), &doc, cases)
+    }
+
+    /// Reference a synthesized variant named `base` (or a unique `{base}Union` if
+    /// a type already has that name), creating it on first use
+    fn shared_union(&self, base: String, doc: &str, cases: Vec<VariantCase>) -> RsType {
+        if let Some(name) = self.shared_unions.borrow().get(&base) {
+            return RsType::Named(name.clone());
+        }
+        let taken = self.is_schema_name(&base) || self.synthesized.borrow().contains_key(&base);
+        let name = if taken { self.synthesized_name(&format!("{}Union", base)) } else { base.clone() };
+
+        self.report.borrow_mut().synthesized_names += 1;
+        self.shared_unions.borrow_mut().insert(base, name.clone());
+        self.synthesized.borrow_mut().insert(
+            name.clone(),
+            TypeDef::Variant {
+                name: name.clone(),
+                doc: Some(doc.to_string()),
+                cases,
+                discriminator: None,
+                deprecated: false,
+            },
+        );
+        RsType::Named(name)
+    }
+
+    /// Whether a component schema generates a type named `name`
+    fn is_schema_name(&self, name: &str) -> bool {
+        self.spec
+            .components
+            .as_ref()
+            .is_some_and(|c| c.schemas.keys().any(|k| k.to_pascal_case() == name))
+    }
+
+    /// Type of a property, request body or response schema, lifting an inline
+    /// object into a record named after where it appears (`hint`, e.g.
+    /// `UserAddress` for `User.address`) instead of degrading it to `JSON.t`
+    fn inline_schema_to_type(&self, hint: &str, schema: &Schema) -> Result<RsType> {
+        if let Some(cases) = literal_enum_cases(schema) {
+            let name = self.synthesized_name(hint);
+            self.report.borrow_mut().synthesized_names += 1;
+            self.synthesized.borrow_mut().insert(
+                name.clone(),
+                TypeDef::Variant {
+                    name: name.clone(),
+                    doc: schema.schema_data.description.clone(),
+                    cases,
+                    discriminator: None,
+                    deprecated: false,
+                },
+            );
+            return Ok(RsType::Named(name));
+        }
+        match &schema.schema_kind {
+            // Annotated reference, see `schema_kind_to_type`
+            SchemaKind::AllOf { all_of } if all_of.len() == 1 => {}
+            // Tuple, see `item_schema_to_type`
+            _ if schema.schema_data.extensions.contains_key(PREFIX_ITEMS_EXTENSION) => {}
+            SchemaKind::Type(Type::Array(array)) => {
+                if let Some(ReferenceOr::Item(items)) = &array.items {
+                    let item = self.inline_schema_to_type(&format!("{}Item", hint), items)?;
+                    return Ok(RsType::Array(Box::new(item)));
+                }
+            }
+            SchemaKind::Type(Type::Object(obj)) if obj.properties.is_empty() => {
+                if let Some(value) = self.additional_properties_type(hint, obj)? {
+                    return Ok(RsType::Dict(Box::new(value)));
+                }
+            }
+            _ => {
+                let object = self
+                    .object_members(schema, &mut Vec::new())
+                    .filter(|object| !object.properties.is_empty());
+                if let Some(object) = object {
+                    return self.lift_object(hint, schema, &object);
+                }
+            }
+        }
+        self.item_schema_to_type(schema)
+    }
+
+    /// Name for a synthesized type, with a numeric suffix if a schema or
+    /// another synthesized type already uses it
+    fn synthesized_name(&self, hint: &str) -> String {
+        let base = hint.to_pascal_case();
+        let taken =
+            |name: &str| self.is_schema_name(name) || self.synthesized.borrow().contains_key(name);
+        if taken(&base) {
+            (2..)
+                .map(|n| format!("{}{}", base, n))
+                .find(|candidate| !taken(candidate))
+                .unwrap_or_default()
+        } else {
+            base
+        }
+    }
+
+    /// Synthesize a record for an inline object schema
+    fn lift_object(&self, hint: &str, schema: &Schema, object: &ObjectType) -> Result<RsType> {
+        let name = self.synthesized_name(hint);
+
+        // Nested objects are lifted while lowering the fields, named after this one
+        let fields = self.lower_fields(&name, object)?;
+        self.report.borrow_mut().synthesized_names += 1;
+        self.synthesized.borrow_mut().insert(
+            name.clone(),
+            TypeDef::Record {
+                name: name.clone(),
+                doc: schema.schema_data.description.clone(),
+                fields,
+                deprecated: false,
+            },
+        );
+        Ok(RsType::Named(name))
+    }
+
+    /// `JSON.t` for a schema the IR can't express, counted in the report
+    fn degraded(&self) -> RsType {
+        self.report.borrow_mut().degraded_schemas += 1;
+        RsType::Json
+    }
+
     fn lower_operation(
         &self,
         path: &str,
         method: &str,
         op: &openapiv3::Operation,
     ) -> Result<Endpoint> {
-        let operation_id = op
-            .operation_id
-            .clone()
-            .unwrap_or_else(|| format!("{}_{}", method, path.replace('/', "_")));
+        let operation_id = match &op.operation_id {
+            Some(id) => id.clone(),
+            None => self.unique_operation_id(derive_operation_id(method, path)),
+        };
 
         let http_method = match method.to_uppercase().as_str() {
             "GET" => HttpMethod::Get,
@@ -509,98 +3299,1573 @@ impl<'a> Lowerer<'a> {
         let mut parameters = Vec::new();
         for param in &op.parameters {
             if let ReferenceOr::Item(param) = param {
-                let location = match &param.parameter_data_ref() {
-                    openapiv3::ParameterData {
-                        name: _,
-                        description: _,
-                        required: _,
-                        deprecated: _,
-                        format:
-                            openapiv3::ParameterSchemaOrContent::Schema(ReferenceOr::Item(_schema)),
-                        example: _,
-                        examples: _,
-                        explode: _,
-                        extensions: _,
-                    } => match param {
-                        openapiv3::Parameter::Path { .. } => ParameterLocation::Path,
-                        openapiv3::Parameter::Query { .. } => ParameterLocation::Query,
-                        openapiv3::Parameter::Header { .. } => ParameterLocation::Header,
-                        openapiv3::Parameter::Cookie { .. } => ParameterLocation::Cookie,
-                    },
-                    _ => continue,
+                let (location, style) = match param {
+                    openapiv3::Parameter::Path { style, .. } => {
+                        let style = match style {
+                            openapiv3::PathStyle::Simple => ParameterStyle::Simple,
+                            openapiv3::PathStyle::Label => ParameterStyle::Label,
+                            openapiv3::PathStyle::Matrix => ParameterStyle::Matrix,
+                        };
+                        (ParameterLocation::Path, style)
+                    }
+                    openapiv3::Parameter::Query { style, .. } => {
+                        let style = match style {
+                            openapiv3::QueryStyle::Form => ParameterStyle::Form,
+                            openapiv3::QueryStyle::SpaceDelimited => ParameterStyle::SpaceDelimited,
+                            openapiv3::QueryStyle::PipeDelimited => ParameterStyle::PipeDelimited,
+                            openapiv3::QueryStyle::DeepObject => ParameterStyle::DeepObject,
+                        };
+                        (ParameterLocation::Query, style)
+                    }
+                    openapiv3::Parameter::Header { .. } => {
+                        (ParameterLocation::Header, ParameterStyle::Simple)
+                    }
+                    openapiv3::Parameter::Cookie { .. } => {
+                        (ParameterLocation::Cookie, ParameterStyle::Form)
+                    }
                 };
 
                 let param_data = param.parameter_data_ref();
-                let ty = if let openapiv3::ParameterSchemaOrContent::Schema(schema) =
-                    &param_data.format
+                // OpenAPI says to ignore these; the client sets them from the media types and auth
+                let reserved = ["Accept", "Content-Type", "Authorization"];
+                if location == ParameterLocation::Header
+                    && reserved.iter().any(|name| param_data.name.eq_ignore_ascii_case(name))
                 {
-                    self.schema_to_type(schema)?
-                } else {
-                    RsType::String
+                    continue;
+                }
+                let (ty, content_type) = match &param_data.format {
+                    openapiv3::ParameterSchemaOrContent::Schema(schema) => {
+                        if let ReferenceOr::Item(schema) = schema {
+                            self.check_numeric_precision(
+                                schema,
+                                &format!("paths.{}.{}.parameters.{}", path, method, param_data.name),
+                            );
+                        }
+                        (self.schema_to_type(schema)?, None)
+                    }
+                    // `content` parameters carry exactly one media type describing
+                    // how the value is serialized (typically application/json)
+                    openapiv3::ParameterSchemaOrContent::Content(content) => {
+                        let Some((media_type, media)) = content.first() else {
+                            continue;
+                        };
+                        let ty = media
+                            .schema
+                            .as_ref()
+                            .map(|s| self.schema_to_type(s))
+                            .transpose()?
+                            .unwrap_or(RsType::Json);
+                        (ty, Some(media_type.clone()))
+                    }
                 };
 
                 parameters.push(Parameter {
-                    name: param_data.name.to_lower_camel_case(),
+                    name: parameter_name(&param_data.name),
+                    original_name: param_data.name.clone(),
                     location,
                     ty,
                     required: param_data.required,
                     doc: param_data.description.clone(),
+                    content_type,
+                    deprecated: param_data.deprecated.unwrap_or(false),
+                    fieldset: self.param_fieldset(
+                        param_data,
+                        &format!("paths.{}.{}.parameters.{}", path, method, param_data.name),
+                    ),
+                    style,
+                    explode: param_data.explode.unwrap_or(style == ParameterStyle::Form),
                 });
             }
         }
 
-        let request_body = if let Some(body) = &op.request_body {
-            if let ReferenceOr::Item(body) = body {
-                body.content.get("application/json").map(|media| {
-                    let ty = media
+        let request_body = match &op.request_body {
+            Some(ReferenceOr::Item(body)) => {
+                let json = body.content.get("application/json").map(|media| {
+                    let hint = format!("{}Request", operation_id.to_pascal_case());
+                    media
                         .schema
                         .as_ref()
-                        .and_then(|s| self.schema_to_type(s).ok())
-                        .unwrap_or(RsType::Json);
-                    RequestBody {
+                        .and_then(|s| self.media_schema_to_type(&hint, s).ok())
+                        .unwrap_or(RsType::Json)
+                });
+                let form = match body.content.get("multipart/form-data") {
+                    Some(media) => {
+                        let context = format!("paths.{}.{}.requestBody", path, method);
+                        let hint = format!("{}Form", operation_id.to_pascal_case());
+                        Some(self.form_fields(&hint, media.schema.as_ref(), &context)?)
+                    }
+                    None => None,
+                };
+                let form_type = RsType::Named(format!("{}Form", operation_id.to_pascal_case()));
+                match (json, form) {
+                    (Some(json), Some(form)) => Some(RequestBody {
+                        ty: RsType::Named(format!("{}Body", operation_id.to_pascal_case())),
+                        required: body.required,
+                        content_type: "application/json".to_string(),
+                        form,
+                        choice: Some(BodyChoice { json, form: form_type }),
+                    }),
+                    (Some(ty), None) => Some(RequestBody {
                         ty,
                         required: body.required,
                         content_type: "application/json".to_string(),
-                    }
-                })
-            } else {
-                None
+                        form: Vec::new(),
+                        choice: None,
+                    }),
+                    (None, Some(form)) => Some(RequestBody {
+                        ty: form_type,
+                        required: body.required,
+                        content_type: "multipart/form-data".to_string(),
+                        form,
+                        choice: None,
+                    }),
+                    (None, None) => None,
+                }
             }
-        } else {
-            None
+            _ => None,
         };
 
+        let declared = op.responses.responses.iter().map(|(status, response)| {
+            let status = match status {
+                openapiv3::StatusCode::Code(code) => ResponseStatus::Code(*code),
+                openapiv3::StatusCode::Range(class) => ResponseStatus::Range(*class),
+            };
+            (status, response)
+        });
+        let default = op.responses.default.iter().map(|r| (ResponseStatus::Default, r));
         let mut responses = Vec::new();
-        for (status, response) in &op.responses.responses {
+        for (status, response) in declared.chain(default) {
             if let ReferenceOr::Item(response) = response {
-                let status_code = match status {
-                    openapiv3::StatusCode::Code(code) => *code,
-                    openapiv3::StatusCode::Range(_) => continue,
-                };
+                responses.push(self.lower_response(&operation_id, status, response));
+            }
+        }
 
-                let ty = response.content.get("application/json").and_then(|media| {
-                    media
-                        .schema
-                        .as_ref()
-                        .and_then(|s| self.schema_to_type(s).ok())
-                });
+        let success_content = op
+            .responses
+            .responses
+            .iter()
+            .find_map(|(status, response)| match (status, response) {
+                (openapiv3::StatusCode::Code(200..=299), ReferenceOr::Item(response)) => {
+                    Some(&response.content)
+                }
+                _ => None,
+            });
+        let events = success_content
+            .and_then(|content| content.get(EVENT_STREAM))
+            .map(|media| match &media.schema {
+                Some(schema) => {
+                    let hint = format!("{}Event", operation_id.to_pascal_case());
+                    self.media_schema_to_type(&hint, schema).unwrap_or(RsType::Json)
+                }
+                None => RsType::String,
+            });
+        // Each line is one item, whether the schema describes the item or the whole list
+        let ndjson = success_content
+            .and_then(|content| NDJSON_MEDIA_TYPES.iter().find_map(|m| content.get(*m)))
+            .map(|media| {
+                let hint = format!("{}Item", operation_id.to_pascal_case());
+                match media.schema.as_ref().map(|s| self.media_schema_to_type(&hint, s)) {
+                    Some(Ok(RsType::Array(item))) => *item,
+                    Some(Ok(item)) => item,
+                    Some(Err(_)) | None => RsType::Json,
+                }
+            });
 
-                responses.push(Response {
-                    status: status_code,
-                    ty,
-                    doc: Some(response.description.clone()),
-                });
+        // A JSON list whose success response declares `Link` pages via `rel="next"`
+        let link_pagination = matches!(http_method, HttpMethod::Get)
+            && op
+                .responses
+                .responses
+                .iter()
+                .find_map(|(status, response)| match (status, response) {
+                    (openapiv3::StatusCode::Code(200..=299), ReferenceOr::Item(response)) => {
+                        Some(response)
+                    }
+                    _ => None,
+                })
+                .is_some_and(|response| response.headers.keys().any(|h| h.eq_ignore_ascii_case("link")))
+            && responses
+                .iter()
+                .find(|r| r.is_success())
+                .and_then(|r| r.ty.as_ref())
+                .is_some_and(|ty| !ty.is_raw_body());
+
+        // Operation externalDocs first, then those of the tags it belongs to
+        let mut external_docs: Vec<_> = op.external_docs.iter().collect();
+        for tag in &op.tags {
+            if let Some(docs) = self
+                .spec
+                .tags
+                .iter()
+                .find(|t| &t.name == tag)
+                .and_then(|t| t.external_docs.as_ref())
+            {
+                external_docs.push(docs);
             }
         }
 
+        // First tag groups the metric; untagged operations share "default"
+        let metric_name = format!(
+            "{}.{}",
+            op.tags
+                .first()
+                .map(|t| t.to_snake_case())
+                .unwrap_or_else(|| "default".to_string()),
+            operation_id.to_snake_case()
+        );
+
+        let location = format!("paths.{}.{}", path, method);
         Ok(Endpoint {
             operation_id: operation_id.to_lower_camel_case(),
             method: http_method,
             path: path.to_string(),
-            doc: op.description.clone().or(op.summary.clone()),
+            tags: op.tags.clone(),
+            metric_name,
+            doc: with_external_docs(
+                op.description.clone().or(op.summary.clone()),
+                &external_docs,
+            ),
             parameters,
             request_body,
             responses,
+            timeout_ms: self.operation_setting(op, TIMEOUT_EXTENSION, &location),
+            retries: self.operation_setting(op, RETRIES_EXTENSION, &location),
+            link_pagination,
+            pagination: None,
+            stream: self.operation_flag(op, STREAM_EXTENSION, &location),
+            events,
+            ndjson,
+            deprecated: op.deprecated,
+            security: op.security.as_ref().or(self.spec.security.as_ref()).map(|requirements| {
+                requirements.iter().map(|r| r.keys().cloned().collect()).collect()
+            }),
         })
     }
+
+    /// Webhooks with a JSON request body, from the `webhooks` the parser moved
+    /// to an extension
+    fn lower_webhooks(&self) -> Result<Vec<Webhook>> {
+        let Some(value) = self.spec.extensions.get(WEBHOOKS_EXTENSION) else {
+            return Ok(Vec::new());
+        };
+        let items: BTreeMap<String, ReferenceOr<openapiv3::PathItem>> =
+            match serde_json::from_value(value.clone()) {
+                Ok(items) => items,
+                Err(error) => {
+                    let error = anyhow::Error::new(error).context("Invalid webhooks");
+                    self.fail(error, "webhooks".to_string())?;
+                    return Ok(Vec::new());
+                }
+            };
+
+        let mut webhooks = Vec::new();
+        for (name, item) in &items {
+            let ReferenceOr::Item(item) = item else {
+                self.warn(
+                    "Referenced webhooks are not supported; skipping".to_string(),
+                    format!("webhooks.{}", name),
+                );
+                continue;
+            };
+            let payload = item.iter().find_map(|(_, op)| {
+                let ReferenceOr::Item(body) = op.request_body.as_ref()? else {
+                    return None;
+                };
+                let schema = body.content.get("application/json")?.schema.as_ref()?;
+                let hint = format!("{}Payload", name.to_pascal_case());
+                Some((op, self.media_schema_to_type(&hint, schema)))
+            });
+            match payload {
+                Some((op, Ok(payload))) => webhooks.push(Webhook {
+                    name: name.clone(),
+                    doc: op.description.clone().or(op.summary.clone()),
+                    payload,
+                    deprecated: op.deprecated,
+                }),
+                Some((_, Err(error))) => self.fail(error, format!("webhooks.{}", name))?,
+                None => self.warn(
+                    "Webhook has no JSON request body; skipping".to_string(),
+                    format!("webhooks.{}", name),
+                ),
+            }
+        }
+        Ok(webhooks)
+    }
+
+    /// Non-negative integer operation extension, warning about other values
+    fn operation_setting(&self, op: &openapiv3::Operation, key: &str, path: &str) -> Option<u32> {
+        let value = op.extensions.get(key)?;
+        let setting = value.as_u64().and_then(|n| u32::try_from(n).ok());
+        if setting.is_none() {
+            self.warn(
+                format!("{} must be a non-negative integer, got {}; ignoring", key, value),
+                path.to_string(),
+            );
+        }
+        setting
+    }
+
+    /// Boolean operation extension such as `x-rescript-stream`, `false` when absent
+    fn operation_flag(&self, op: &openapiv3::Operation, key: &str, path: &str) -> bool {
+        let Some(value) = op.extensions.get(key) else {
+            return false;
+        };
+        value.as_bool().unwrap_or_else(|| {
+            self.warn(
+                format!("{} must be a boolean, got {}; ignoring", key, value),
+                path.to_string(),
+            );
+            false
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lower_yaml(yaml: &str, options: &LowerOptions) -> ApiSpec {
+        let spec: OpenAPI = serde_yaml::from_str(yaml).unwrap();
+        lower_with_options(&spec, options).unwrap()
+    }
+
+    const SNAKE_CASE_SPEC: &str = r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Account:
+      type: object
+      properties:
+        account_id: { type: string }
+        type: { type: string }
+        x-request-id: { type: string }
+        2fa: { type: boolean }
+        Name: { type: string }
+"#;
+
+    fn field_names(spec: &ApiSpec) -> Vec<(String, String)> {
+        match &spec.types[0] {
+            TypeDef::Record { fields, .. } => fields
+                .iter()
+                .map(|f| (f.name.clone(), f.original_name.clone()))
+                .collect(),
+            other => panic!("expected record, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_field_case_camel() {
+        let spec = lower_yaml(SNAKE_CASE_SPEC, &LowerOptions::default());
+        assert_eq!(
+            field_names(&spec),
+            vec![
+                ("accountId".to_string(), "account_id".to_string()),
+                ("type_".to_string(), "type".to_string()),
+                ("xRequestId".to_string(), "x-request-id".to_string()),
+                ("_2fa".to_string(), "2fa".to_string()),
+                ("name".to_string(), "Name".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_field_case_preserve() {
+        let options = LowerOptions {
+            field_case: FieldCase::Preserve,
+            ..Default::default()
+        };
+        let spec = lower_yaml(SNAKE_CASE_SPEC, &options);
+        assert_eq!(
+            field_names(&spec),
+            vec![
+                ("account_id".to_string(), "account_id".to_string()),
+                ("type_".to_string(), "type".to_string()),
+                // Kept as close to the wire name as a ReScript field allows
+                ("x_request_id".to_string(), "x-request-id".to_string()),
+                ("_2fa".to_string(), "2fa".to_string()),
+                ("name".to_string(), "Name".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_field_case_snake_wire() {
+        let options = LowerOptions {
+            field_case: FieldCase::SnakeWire,
+            ..Default::default()
+        };
+        let spec = lower_yaml(
+            r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Account:
+      type: object
+      properties:
+        accountId: { type: string }
+        created_at: { type: string }
+        type: { type: string }
+        ownerId: { type: string }
+        owner_id: { type: string }
+"#,
+            &options,
+        );
+        assert_eq!(
+            field_names(&spec),
+            vec![
+                ("accountId".to_string(), "account_id".to_string()),
+                ("createdAt".to_string(), "created_at".to_string()),
+                ("type_".to_string(), "type".to_string()),
+                // `owner_id` is already sent as `owner_id`, so `ownerId` keeps its name
+                ("ownerId".to_string(), "ownerId".to_string()),
+                ("ownerId2".to_string(), "owner_id".to_string()),
+            ]
+        );
+        let messages: Vec<&str> = spec.diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert!(messages.contains(
+            &"Properties 'owner_id' and 'ownerId' are both sent as 'owner_id'; 'ownerId' is sent as 'ownerId'"
+        ));
+    }
+
+    const NUMERIC_SPEC: &str = r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Counter:
+      type: object
+      properties:
+        big: { type: integer, format: int64 }
+        small: { type: integer, format: int32 }
+        whole: { type: number, multipleOf: 1 }
+        ratio: { type: number }
+"#;
+
+    fn field_types(spec: &ApiSpec) -> Vec<String> {
+        match &spec.types[0] {
+            TypeDef::Record { fields, .. } => fields.iter().map(|f| f.ty.to_rescript()).collect(),
+            other => panic!("expected record, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_numeric_lowering() {
+        let spec = lower_yaml(NUMERIC_SPEC, &LowerOptions::default());
+        assert_eq!(
+            field_types(&spec),
+            vec!["option<int>", "option<int>", "option<int>", "option<float>"]
+        );
+        assert_eq!(spec.diagnostics.len(), 1);
+        assert_eq!(
+            spec.diagnostics[0].path.as_deref(),
+            Some("components.schemas.Counter.properties.big")
+        );
+
+        let options = LowerOptions {
+            int64: Int64Mode::Float,
+            ..Default::default()
+        };
+        let spec = lower_yaml(NUMERIC_SPEC, &options);
+        assert_eq!(field_types(&spec)[0], "option<float>");
+        assert!(spec.diagnostics.is_empty());
+
+        for (int64, rescript, encoded) in [
+            (Int64Mode::BigInt, "option<bigint>", "JSON.String(v->BigInt.toString)"),
+            (Int64Mode::String, "option<string>", "JSON.String(v)"),
+        ] {
+            let spec = lower_yaml(NUMERIC_SPEC, &LowerOptions { int64, ..Default::default() });
+            assert_eq!(field_types(&spec)[..2], [rescript.to_string(), "option<int>".to_string()]);
+            assert!(spec.diagnostics.is_empty());
+            let TypeDef::Record { fields, .. } = &spec.types[0] else { unreachable!() };
+            let schema = fields[0].ty.to_schema();
+            assert!(schema.contains("JSON.Number(v) => v->Float.toString"), "{}", schema);
+            assert!(schema.contains(encoded), "{}", schema);
+        }
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let spec: OpenAPI = serde_yaml::from_str(
+            r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /counters:
+    get:
+      responses: { "204": { description: Empty } }
+components:
+  schemas:
+    Counter:
+      type: object
+      properties:
+        big: { type: integer, format: int64 }
+"#,
+        )
+        .unwrap();
+        let paths = |strategy| {
+            diagnostics(&spec, strategy)
+                .unwrap()
+                .into_iter()
+                .map(|d| d.path.unwrap_or_default())
+                .collect::<Vec<_>>()
+        };
+        // Lowering problems are only found when collecting
+        assert_eq!(paths(ErrorStrategy::FailFast), ["paths./counters.get"]);
+        assert_eq!(
+            paths(ErrorStrategy::Collect),
+            ["paths./counters.get", "components.schemas.Counter.properties.big"]
+        );
+    }
+
+    #[test]
+    fn test_scalar_unions() {
+        let spec = lower_yaml(
+            r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Order:
+      type: object
+      properties:
+        legacyId: { anyOf: [{ type: string }, { type: integer }] }
+        altId: { oneOf: [{ type: integer }, { type: string }] }
+        price: { anyOf: [{ type: string }, { type: number }] }
+        mixed: { anyOf: [{ type: string }, { type: boolean }, { type: integer }] }
+    Id:
+      oneOf: [{ type: string }, { type: integer, format: int32 }]
+    StringOrInt: { type: object }
+    StringOrIntUnion: { type: object }
+"#,
+            &LowerOptions::default(),
+        );
+        let find = |name: &str| spec.types.iter().find(|t| t.name() == name);
+        let cases = |name: &str| match find(name) {
+            Some(TypeDef::Variant { cases, .. }) => cases
+                .iter()
+                .map(|c| format!("{}({})", c.name, c.payload.as_ref().unwrap().to_rescript()))
+                .collect::<Vec<_>>(),
+            other => panic!("expected variant {}, got {:?}", name, other),
+        };
+
+        // Inline unions share one type, named around the schemas that took the plain name
+        let Some(TypeDef::Record { fields, .. }) = find("Order") else { panic!("no Order") };
+        let types: Vec<String> = fields.iter().map(|f| f.ty.to_rescript()).collect();
+        assert_eq!(
+            types,
+            vec![
+                "option<stringOrIntUnion2>",
+                "option<stringOrIntUnion2>",
+                "option<stringOrFloat>",
+                "option<JSON.t>",
+            ]
+        );
+        assert_eq!(cases("StringOrIntUnion2"), vec!["S(string)", "I(int)"]);
+        assert_eq!(cases("StringOrFloat"), vec!["S(string)", "F(float)"]);
+        // Component unions keep their own name
+        assert_eq!(cases("Id"), vec!["S(string)", "I(int)"]);
+    }
+
+    const NULLABLE_SPEC: &str = r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Item:
+      type: object
+      required: [a, b]
+      properties:
+        a: { type: string, nullable: true }
+        b: { type: string, x-nullable: true }
+"#;
+
+    #[test]
+    fn test_compat_nullable() {
+        let field_schemas = |spec: &ApiSpec| match &spec.types[0] {
+            TypeDef::Record { fields, .. } => {
+                fields.iter().map(|f| f.ty.to_schema()).collect::<Vec<_>>()
+            }
+            other => panic!("expected record, got {:?}", other),
+        };
+
+        let spec = lower_yaml(NULLABLE_SPEC, &LowerOptions::default());
+        assert_eq!(field_schemas(&spec), vec!["S.nullable(S.string)", "S.string"]);
+
+        let options = LowerOptions {
+            compat_nullable: true,
+            ..Default::default()
+        };
+        let spec = lower_yaml(NULLABLE_SPEC, &options);
+        assert_eq!(
+            field_schemas(&spec),
+            vec!["S.nullable(S.string)", "S.nullable(S.string)"]
+        );
+    }
+
+    #[test]
+    fn test_derived_operation_ids() {
+        let spec = lower_yaml(
+            r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /users:
+    get: { responses: { "200": { description: ok } } }
+    post: { responses: { "201": { description: ok } } }
+  /users/{user_id}/posts:
+    get: { responses: { "200": { description: ok } } }
+  /users/{user_id}/posts/:
+    get: { responses: { "200": { description: ok } } }
+  /health:
+    get: { operationId: getUsers, responses: { "200": { description: ok } } }
+"#,
+            &LowerOptions::default(),
+        );
+        let ids: Vec<_> = spec.endpoints.iter().map(|e| e.operation_id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["getUsers2", "createUsers", "getUsersByUserIdPosts", "getUsersByUserIdPosts2", "getUsers"]
+        );
+    }
+
+    #[test]
+    fn test_wildcard_responses() {
+        let yaml = r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /files/{id}:
+    get:
+      operationId: download
+      responses:
+        "200":
+          description: ok
+          content:
+            "*/*": { schema: { type: string, format: binary } }
+"#;
+        let response_type = |mode| {
+            let options = LowerOptions {
+                wildcard_responses: mode,
+                ..Default::default()
+            };
+            let spec = lower_yaml(yaml, &options);
+            spec.endpoints[0].responses[0].ty.as_ref().map(|t| t.to_rescript())
+        };
+
+        assert_eq!(response_type(WildcardResponses::Text).as_deref(), Some("rawBody<string>"));
+        assert_eq!(
+            response_type(WildcardResponses::Blob).as_deref(),
+            Some("rawBody<Fetch.Blob.t>")
+        );
+        assert_eq!(response_type(WildcardResponses::Ignore), None);
+    }
+
+    #[test]
+    fn test_binary_responses() {
+        let yaml = r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /avatars/{id}:
+    get:
+      operationId: getAvatar
+      responses:
+        "200":
+          description: ok
+          content:
+            image/png: {}
+  /exports/{id}:
+    get:
+      operationId: getExport
+      responses:
+        "200":
+          description: ok
+          content:
+            application/vnd.example.export: { schema: { type: string, format: binary } }
+  /notes/{id}:
+    get:
+      operationId: getNote
+      responses:
+        "200":
+          description: ok
+          content:
+            text/plain: { schema: { type: string } }
+"#;
+        let response_types = |mode| {
+            let options = LowerOptions {
+                binary_responses: mode,
+                ..Default::default()
+            };
+            let spec = lower_yaml(yaml, &options);
+            spec.endpoints
+                .iter()
+                .map(|e| e.responses[0].ty.as_ref().map(|t| t.to_rescript()))
+                .collect::<Vec<_>>()
+        };
+
+        let blob = Some("rawBody<Fetch.Blob.t>".to_string());
+        assert_eq!(response_types(BinaryResponses::Blob), vec![blob.clone(), blob, None]);
+        let buffer = Some("rawBody<ArrayBuffer.t>".to_string());
+        assert_eq!(
+            response_types(BinaryResponses::ArrayBuffer),
+            vec![buffer.clone(), buffer, None]
+        );
+    }
+
+    const COERCE_SPEC: &str = r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Order:
+      type: object
+      required: [total]
+      properties:
+        total: { type: number, x-rescript-coerce: string }
+        count: { type: integer }
+"#;
+
+    #[test]
+    fn test_field_coercion() {
+        let wire_types = |spec: &ApiSpec| match &spec.types[0] {
+            TypeDef::Record { fields, .. } => fields
+                .iter()
+                .map(|f| f.coerce_from.as_ref().map(|t| t.to_rescript()))
+                .collect::<Vec<_>>(),
+            other => panic!("expected record, got {:?}", other),
+        };
+
+        let spec = lower_yaml(COERCE_SPEC, &LowerOptions::default());
+        assert_eq!(wire_types(&spec), vec![Some("string".to_string()), None]);
+
+        let options = LowerOptions {
+            coercions: vec!["Order.count=string".parse().unwrap()],
+            ..Default::default()
+        };
+        let spec = lower_yaml(COERCE_SPEC, &options);
+        assert_eq!(
+            wire_types(&spec),
+            vec![Some("string".to_string()), Some("string".to_string())]
+        );
+
+        assert!(spec.diagnostics.is_empty());
+
+        let options = LowerOptions {
+            coercions: vec!["Ordr.count=string".parse().unwrap()],
+            ..Default::default()
+        };
+        let spec = lower_yaml(COERCE_SPEC, &options);
+        assert_eq!(wire_types(&spec), vec![Some("string".to_string()), None]);
+        assert_eq!(spec.diagnostics.len(), 1);
+        assert_eq!(spec.diagnostics[0].severity, Severity::Warning);
+        assert!(spec.diagnostics[0].message.contains("--coerce Ordr.count=string matches no property"));
+
+        assert!("Order.count".parse::<Coercion>().is_err());
+        assert!("Order.count=date".parse::<Coercion>().is_err());
+    }
+
+    const ENUM_SPEC: &str = r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Order:
+      type: object
+      properties:
+        order: { type: string, enum: [asc, desc] }
+        state: { type: string, enum: [open, closed] }
+    Search:
+      type: object
+      properties:
+        order: { type: array, items: { type: string, enum: [asc, desc] } }
+        direction: { type: string, enum: [asc, desc] }
+"#;
+
+    #[test]
+    fn test_promote_enums() {
+        let field_types = |spec: &ApiSpec, name: &str| match spec.types.iter().find(|t| t.name() == name) {
+            Some(TypeDef::Record { fields, .. }) => {
+                fields.iter().map(|f| f.ty.to_rescript()).collect::<Vec<_>>()
+            }
+            other => panic!("expected record, got {:?}", other),
+        };
+
+        let spec = lower_yaml(ENUM_SPEC, &LowerOptions::default());
+        assert_eq!(spec.types.len(), 2);
+
+        let options = LowerOptions {
+            promote_enums: true,
+            ..Default::default()
+        };
+        let spec = lower_yaml(ENUM_SPEC, &options);
+        // `Order` is taken by the component, and `state` is only used once
+        assert_eq!(
+            field_types(&spec, "Order"),
+            vec!["option<orderEnum>", "option<[#\"open\" | #\"closed\"]>"]
+        );
+        assert_eq!(
+            field_types(&spec, "Search"),
+            vec!["option<array<orderEnum>>", "option<orderEnum>"]
+        );
+        match spec.types.iter().find(|t| t.name() == "OrderEnum") {
+            Some(TypeDef::Alias { doc, target, .. }) => {
+                assert_eq!(doc.as_deref(), Some("Shared by Order.order, Search.order, Search.direction"));
+                assert_eq!(target.to_rescript(), "[#\"asc\" | #\"desc\"]");
+            }
+            other => panic!("expected alias, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_lowering_report() {
+        let spec = lower_yaml(
+            r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /users:
+    get:
+      responses:
+        "200":
+          description: ok
+          content:
+            application/json:
+              schema: { type: object }
+  /shared:
+    $ref: "other.yaml#/paths/shared"
+components:
+  schemas:
+    User:
+      type: object
+      properties:
+        id: { type: string }
+    Id: { type: string }
+    Pet:
+      oneOf:
+        - $ref: "#/components/schemas/User"
+        - type: integer
+"##,
+            &LowerOptions::default(),
+        );
+        assert_eq!(
+            spec.report,
+            LoweringReport {
+                records: 1,
+                variants: 1,
+                aliases: 1,
+                operations: 1,
+                degraded_schemas: 1,
+                synthesized_names: 2,
+                skipped_operations: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_field_name_collisions() {
+        let spec = lower_yaml(
+            r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Item:
+      type: object
+      properties:
+        type: { type: string }
+        type_: { type: string }
+        userId: { type: string }
+        user_id: { type: string }
+"#,
+            &LowerOptions::default(),
+        );
+        let fields: Vec<(&str, &str)> = match &spec.types[0] {
+            TypeDef::Record { fields, .. } => fields
+                .iter()
+                .map(|f| (f.name.as_str(), f.original_name.as_str()))
+                .collect(),
+            other => panic!("expected record, got {:?}", other),
+        };
+        assert_eq!(
+            fields,
+            vec![("type_", "type"), ("type_2", "type_"), ("userId", "userId"), ("userId2", "user_id")]
+        );
+        assert_eq!(spec.diagnostics.len(), 2);
+        assert_eq!(
+            spec.diagnostics[0].message,
+            "Properties 'type' and 'type_' both map to field 'type_'; 'type_' is renamed to 'type_2'"
+        );
+        assert_eq!(spec.diagnostics[1].path.as_deref(), Some("components.schemas.Item.properties.user_id"));
+        assert_eq!(spec.report.synthesized_names, 2);
+    }
+
+    #[test]
+    fn test_parameter_name_collisions() {
+        let spec = lower_yaml(
+            r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /events:
+    get:
+      operationId: listEvents
+      parameters:
+        - { name: signal, in: query, schema: { type: string } }
+        - { name: timeout_ms, in: query, schema: { type: integer } }
+        - { name: type, in: query, schema: { type: string } }
+        - { name: Accept-Language, in: header, schema: { type: string } }
+      responses:
+        "200": { description: OK }
+"#,
+            &LowerOptions::default(),
+        );
+        let names: Vec<(&str, &str)> = spec.endpoints[0]
+            .parameters
+            .iter()
+            .map(|p| (p.name.as_str(), p.original_name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("signal_", "signal"),
+                ("timeoutMs_", "timeout_ms"),
+                ("type_", "type"),
+                ("acceptLanguage", "Accept-Language"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_all_of_merges_objects() {
+        let spec = lower_yaml(
+            r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Pet:
+      type: object
+      required: [id]
+      properties:
+        id: { type: string }
+        name: { type: string }
+    Dog:
+      allOf:
+        - $ref: "#/components/schemas/Pet"
+        - type: object
+          required: [bark]
+          properties:
+            bark: { type: boolean }
+        - required: [name]
+    Puppy:
+      description: A young dog
+      allOf:
+        - $ref: "#/components/schemas/Dog"
+        - properties:
+            owner:
+              allOf:
+                - $ref: "#/components/schemas/Pet"
+    Mixed:
+      allOf:
+        - $ref: "#/components/schemas/Pet"
+        - type: string
+"##,
+            &LowerOptions::default(),
+        );
+        let fields = |name: &str| match spec.types.iter().find(|t| t.name() == name) {
+            Some(TypeDef::Record { fields, .. }) => fields
+                .iter()
+                .map(|f| format!("{}: {}", f.name, f.ty.to_rescript()))
+                .collect::<Vec<_>>(),
+            other => panic!("expected record, got {:?}", other),
+        };
+        assert_eq!(fields("Dog"), vec!["id: string", "name: string", "bark: bool"]);
+        assert_eq!(
+            fields("Puppy"),
+            vec!["id: string", "name: string", "bark: bool", "owner: option<pet>"]
+        );
+        assert!(matches!(
+            spec.types.iter().find(|t| t.name() == "Mixed"),
+            Some(TypeDef::Alias { target: RsType::Json, .. })
+        ));
+        assert_eq!(
+            spec.diagnostics[0].message,
+            "allOf members are not all object schemas; generated as JSON.t"
+        );
+    }
+
+    #[test]
+    fn test_discriminator_names_cases() {
+        let spec = lower_yaml(
+            r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Cat: { type: object, properties: { kind: { type: string } } }
+    Dog: { type: object, properties: { kind: { type: string } } }
+    Pet:
+      oneOf:
+        - $ref: "#/components/schemas/Cat"
+        - $ref: "#/components/schemas/Dog"
+      discriminator:
+        propertyName: kind
+        mapping:
+          house-cat: Cat
+          kitty: "#/components/schemas/Cat"
+    Shape:
+      oneOf:
+        - $ref: "#/components/schemas/Cat"
+        - type: object
+      discriminator:
+        propertyName: kind
+"##,
+            &LowerOptions::default(),
+        );
+        let variant = |name: &str| match spec.types.iter().find(|t| t.name() == name) {
+            Some(TypeDef::Variant { cases, discriminator, .. }) => (
+                cases
+                    .iter()
+                    .map(|c| format!("{}={}", c.name, if c.tags.is_empty() { "-".to_string() } else { c.tags.join("|") }))
+                    .collect::<Vec<_>>(),
+                discriminator.clone(),
+            ),
+            other => panic!("expected variant, got {:?}", other),
+        };
+        assert_eq!(
+            variant("Pet"),
+            (
+                vec!["HouseCat=house-cat|kitty".to_string(), "Dog=Dog".to_string()],
+                Some("kind".to_string())
+            )
+        );
+        assert_eq!(variant("Shape"), (vec!["Cat=-".to_string(), "Case1=-".to_string()], None));
+        assert_eq!(spec.diagnostics[0].path.as_deref(), Some("components.schemas.Shape"));
+    }
+
+    #[test]
+    fn test_inline_ref_union_is_shared() {
+        let spec = lower_yaml(
+            r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Cat: { type: object, properties: { name: { type: string } } }
+    Dog: { type: object, properties: { name: { type: string } } }
+    Home:
+      type: object
+      properties:
+        pet:
+          anyOf: [{ $ref: "#/components/schemas/Cat" }, { $ref: "#/components/schemas/Dog" }]
+        pets:
+          type: array
+          items:
+            oneOf: [{ $ref: "#/components/schemas/Cat" }, { $ref: "#/components/schemas/Dog" }]
+        other:
+          anyOf: [{ $ref: "#/components/schemas/Cat" }, { type: object }]
+"##,
+            &LowerOptions::default(),
+        );
+        let Some(TypeDef::Record { fields, .. }) = spec.types.iter().find(|t| t.name() == "Home")
+        else {
+            panic!("expected Home record");
+        };
+        let types: Vec<String> = fields.iter().map(|f| f.ty.to_rescript()).collect();
+        assert_eq!(
+            types,
+            vec!["option<catOrDog>", "option<array<catOrDog>>", "option<JSON.t>"]
+        );
+        assert!(matches!(
+            spec.types.iter().find(|t| t.name() == "CatOrDog"),
+            Some(TypeDef::Variant { cases, .. }) if cases.len() == 2
+        ));
+    }
+
+    #[test]
+    fn test_inline_objects_are_lifted() {
+        let spec = lower_yaml(
+            r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /users:
+    post:
+      operationId: createUser
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: object
+              properties:
+                name: { type: string }
+      responses:
+        "201":
+          description: Created
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    id: { type: string }
+components:
+  schemas:
+    UserAddress: { type: string }
+    User:
+      type: object
+      properties:
+        address:
+          type: object
+          description: Where the user lives
+          properties:
+            geo:
+              type: object
+              properties:
+                lat: { type: number }
+        tags: { type: object, additionalProperties: { type: string } }
+"##,
+            &LowerOptions::default(),
+        );
+        let field_types = |name: &str| match spec.types.iter().find(|t| t.name() == name) {
+            Some(TypeDef::Record { fields, .. }) => fields
+                .iter()
+                .map(|f| format!("{}: {}", f.name, f.ty.to_rescript()))
+                .collect::<Vec<_>>(),
+            other => panic!("expected record {}, got {:?}", name, other),
+        };
+        // `UserAddress` is taken by a schema
+        assert_eq!(
+            field_types("User"),
+            vec!["address: option<userAddress2>", "tags: option<Dict.t<string>>"]
+        );
+        assert_eq!(field_types("UserAddress2"), vec!["geo: option<userAddress2Geo>"]);
+        assert_eq!(field_types("UserAddress2Geo"), vec!["lat: option<float>"]);
+        assert_eq!(field_types("CreateUserResponseItem"), vec!["id: option<string>"]);
+
+        let endpoint = &spec.endpoints[0];
+        assert_eq!(
+            endpoint.request_body.as_ref().map(|b| b.ty.to_rescript()),
+            Some("createUserRequest".to_string())
+        );
+        assert_eq!(
+            endpoint.responses[0].ty.as_ref().map(|t| t.to_rescript()),
+            Some("array<createUserResponseItem>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_additional_properties() {
+        let yaml = r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Labels: { type: object, additionalProperties: { type: string } }
+    Closed: { type: object, additionalProperties: false }
+    Settings:
+      type: object
+      properties:
+        extra: { type: string }
+        limits:
+          type: object
+          additionalProperties:
+            type: object
+            properties:
+              max: { type: integer }
+      additionalProperties: true
+"##;
+        let fields = |spec: &ApiSpec, name: &str| match spec.types.iter().find(|t| t.name() == name) {
+            Some(TypeDef::Record { fields, .. }) => fields
+                .iter()
+                .map(|f| format!("{}: {}", f.name, f.ty.to_rescript()))
+                .collect::<Vec<_>>(),
+            other => panic!("expected record {}, got {:?}", name, other),
+        };
+
+        let spec = lower_yaml(yaml, &LowerOptions::default());
+        assert!(matches!(
+            spec.types.iter().find(|t| t.name() == "Labels"),
+            Some(TypeDef::Alias { target: RsType::Dict(_), .. })
+        ));
+        assert!(fields(&spec, "Closed").is_empty());
+        // A declared `extra` property keeps its name
+        assert_eq!(
+            fields(&spec, "Settings"),
+            vec![
+                "extra: option<string>",
+                "limits: option<Dict.t<settingsLimitsValue>>",
+                "extra2: Dict.t<JSON.t>",
+            ]
+        );
+
+        let options = LowerOptions {
+            extra_properties: ExtraProperties::Ignore,
+            ..Default::default()
+        };
+        let spec = lower_yaml(yaml, &options);
+        assert_eq!(
+            fields(&spec, "Settings"),
+            vec!["extra: option<string>", "limits: option<Dict.t<settingsLimitsValue>>"]
+        );
+    }
+
+    #[test]
+    fn test_format_mappings() {
+        let yaml = r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Timestamp: { type: string, format: date-time }
+    Event:
+      type: object
+      required: [id, day, at]
+      properties:
+        id: { type: string, format: uuid }
+        day: { type: string, format: date }
+        at: { $ref: "#/components/schemas/Timestamp" }
+"##;
+        let types = |options: &LowerOptions| {
+            let spec = lower_yaml(yaml, options);
+            spec.types
+                .iter()
+                .flat_map(|t| match t {
+                    TypeDef::Record { fields, .. } => fields
+                        .iter()
+                        .map(|f| format!("{}: {}", f.name, f.ty.to_rescript()))
+                        .collect(),
+                    TypeDef::Alias { name, target, .. } => {
+                        vec![format!("{} = {}", name, target.to_rescript())]
+                    }
+                    _ => Vec::new(),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            types(&LowerOptions::default()),
+            vec!["id: string", "day: string", "at: timestamp", "Timestamp = string"]
+        );
+
+        let options = LowerOptions {
+            formats: vec!["date-time=date".parse().unwrap(), "date=date".parse().unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(
+            types(&options),
+            vec!["id: string", "day: Date.t", "at: timestamp", "Timestamp = Date.t"]
+        );
+        assert!(RsType::Date.to_schema().contains("String.slice(~start=0, ~end=10)"));
+
+        assert!("uuid=date".parse::<FormatMapping>().is_err());
+        assert!("email=string".parse::<FormatMapping>().is_err());
+        assert!("date-time".parse::<FormatMapping>().is_err());
+    }
+
+    #[test]
+    fn test_numeric_enums() {
+        let yaml = r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Level: { type: integer, enum: [1, 2, 2, -1] }
+    Named: { type: integer, enum: [0, 1], x-enumNames: [off, on] }
+    Loose: { type: integer, enum: [0, 1], x-enum-varnames: [same, same] }
+    Reading:
+      type: object
+      properties:
+        scale: { type: number, enum: [0.5, 1] }
+"##;
+        let spec = lower_yaml(yaml, &LowerOptions::default());
+        let cases = |name: &str| match spec.types.iter().find(|t| t.name() == name) {
+            Some(TypeDef::Variant { cases, .. }) => cases
+                .iter()
+                .map(|c| format!("{}={}", c.name, c.literal.as_deref().unwrap_or_default()))
+                .collect::<Vec<_>>(),
+            other => panic!("expected variant {}, got {:?}", name, other),
+        };
+
+        assert_eq!(cases("Level"), vec!["V1=1", "V2=2", "VMinus1=-1"]);
+        assert_eq!(cases("Named"), vec!["Off=0", "On=1"]);
+        // Names that would clash fall back to the values
+        assert_eq!(cases("Loose"), vec!["V0=0", "V1=1"]);
+        assert_eq!(cases("ReadingScale"), vec!["V0_5=0.5", "V1=1"]);
+        match spec.types.iter().find(|t| t.name() == "Reading") {
+            Some(TypeDef::Record { fields, .. }) => {
+                assert_eq!(fields[0].ty.to_rescript(), "option<readingScale>")
+            }
+            other => panic!("expected record, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_mixed_enums() {
+        let yaml = r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Mixed: { enum: [auto, 1, 1.0, true, null, "1"] }
+    Status: { type: string, nullable: true, enum: [active, null] }
+    Plain: { type: string, enum: [active] }
+    Filter:
+      type: object
+      properties:
+        mode: { enum: [all, 0] }
+"##;
+        let spec = lower_yaml(yaml, &LowerOptions::default());
+        let cases = |name: &str| match spec.types.iter().find(|t| t.name() == name) {
+            Some(TypeDef::Variant { cases, .. }) => cases
+                .iter()
+                .map(|c| format!("{}={}", c.name, c.literal.as_deref().unwrap_or("-")))
+                .collect::<Vec<_>>(),
+            other => panic!("expected variant {}, got {:?}", name, other),
+        };
+
+        assert_eq!(
+            cases("Mixed"),
+            vec!["Auto=\"auto\"", "V1=1", "True=true", "Null=null", "V12=\"1\""]
+        );
+        assert_eq!(cases("Status"), vec!["Active=\"active\"", "Null=null"]);
+        // Plain string enums keep their polymorphic variant
+        assert_eq!(cases("Plain"), vec!["Active=-"]);
+        assert_eq!(cases("FilterMode"), vec!["All=\"all\"", "V0=0"]);
+    }
+
+    #[test]
+    fn test_split_request_types() {
+        let yaml = r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /orders:
+    post:
+      operationId: createOrder
+      requestBody:
+        content:
+          application/json:
+            schema: { $ref: "#/components/schemas/Order" }
+      responses:
+        "201":
+          description: Created
+          content:
+            application/json:
+              schema: { $ref: "#/components/schemas/Order" }
+components:
+  schemas:
+    Line:
+      type: object
+      required: [id, sku]
+      properties:
+        id: { type: string, readOnly: true }
+        sku: { type: string }
+    Order:
+      type: object
+      required: [lines, token]
+      properties:
+        lines: { type: array, items: { $ref: "#/components/schemas/Line" } }
+        token: { type: string, writeOnly: true }
+    Note: { type: object, properties: { text: { type: string } } }
+"##;
+        let fields = |spec: &ApiSpec, name: &str| match spec.types.iter().find(|t| t.name() == name) {
+            Some(TypeDef::Record { fields, .. }) => fields
+                .iter()
+                .map(|f| format!("{}: {}", f.name, f.ty.to_rescript()))
+                .collect::<Vec<_>>(),
+            other => panic!("expected record {}, got {:?}", name, other),
+        };
+
+        // Shared records keep one-directional fields as required as the spec says
+        let spec = lower_yaml(yaml, &LowerOptions::default());
+        assert_eq!(fields(&spec, "Line"), vec!["id: string", "sku: string"]);
+        assert_eq!(fields(&spec, "Order"), vec!["lines: array<line>", "token: string"]);
+
+        let options = LowerOptions { request_types: RequestTypes::Split, ..Default::default() };
+        let spec = lower_yaml(yaml, &options);
+        assert_eq!(fields(&spec, "Line"), vec!["id: string", "sku: string"]);
+        assert_eq!(fields(&spec, "LineInput"), vec!["sku: string"]);
+        assert_eq!(fields(&spec, "Order"), vec!["lines: array<line>"]);
+        assert_eq!(fields(&spec, "OrderInput"), vec!["lines: array<lineInput>", "token: string"]);
+        assert!(spec.types.iter().all(|t| t.name() != "NoteInput"));
+
+        let endpoint = &spec.endpoints[0];
+        let body = endpoint.request_body.as_ref().map(|b| b.ty.to_rescript());
+        assert_eq!(body.as_deref(), Some("orderInput"));
+        let response = endpoint.responses[0].ty.as_ref().map(|t| t.to_rescript());
+        assert_eq!(response.as_deref(), Some("order"));
+    }
+
+    #[test]
+    fn test_field_defaults() {
+        let yaml = r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Order: { type: string, enum: [asc, desc] }
+    Query:
+      type: object
+      required: [term]
+      properties:
+        term: { type: string, default: "*" }
+        limit: { type: integer, default: 20 }
+        ratio: { type: number, default: 1 }
+        order: { allOf: [{ $ref: "#/components/schemas/Order" }], default: desc }
+        level: { type: integer, enum: [1, 2], default: 2 }
+        tags: { type: array, items: { type: string }, default: [] }
+        since: { type: string, format: date-time, default: "2024-01-01T00:00:00Z" }
+        broken: { type: integer, default: "ten" }
+"##;
+        let spec = lower_yaml(yaml, &LowerOptions::default());
+        let Some(TypeDef::Record { fields, .. }) = spec.types.iter().find(|t| t.name() == "Query")
+        else {
+            panic!("expected record Query");
+        };
+        let fields: Vec<String> = fields
+            .iter()
+            .map(|f| match &f.default {
+                Some(default) => format!("{}: {} = {}", f.name, f.ty.to_rescript(), default),
+                None => format!("{}: {}", f.name, f.ty.to_rescript()),
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                // Required properties don't need their default
+                "term: string",
+                "limit: int = 20",
+                "ratio: float = 1.0",
+                "order: order = #Desc",
+                "level: queryLevel = V2",
+                "tags: array<string> = []",
+                "since: string = \"2024-01-01T00:00:00Z\"",
+                "broken: option<int>",
+            ]
+        );
+        assert_eq!(spec.diagnostics.len(), 1);
+        assert_eq!(
+            spec.diagnostics[0].path.as_deref(),
+            Some("components.schemas.Query.properties.broken")
+        );
+
+        // Dates are left to the server
+        let options = LowerOptions {
+            formats: vec!["date-time=date".parse().unwrap()],
+            ..Default::default()
+        };
+        let spec = lower_yaml(yaml, &options);
+        let Some(TypeDef::Record { fields, .. }) = spec.types.iter().find(|t| t.name() == "Query")
+        else {
+            panic!("expected record Query");
+        };
+        assert_eq!(fields[6].ty.to_rescript(), "option<Date.t>");
+        assert!(fields[6].default.is_none());
+    }
+
+    #[test]
+    fn test_prefix_items_tuples() {
+        let yaml = r##"
+openapi: 3.1.0
+info: { title: T, version: "1" }
+paths: {}
+components:
+  schemas:
+    Point:
+      type: array
+      prefixItems: [{ type: number }, { type: number }]
+      items: false
+    Shape:
+      type: object
+      required: [origin]
+      properties:
+        origin: { $ref: "#/components/schemas/Point" }
+        range: { type: array, prefixItems: [{ type: integer }, { $ref: "#/components/schemas/Point" }] }
+        prefixItems: { type: string, example: { prefixItems: [] } }
+"##;
+        // The parser keeps 3.1 keywords that `openapiv3` would drop
+        let spec = crate::parser::parse_spec_content(yaml, &Default::default()).unwrap();
+        let spec = lower_with_options(&spec, &LowerOptions::default()).unwrap();
+        let Some(TypeDef::Alias { target, .. }) = spec.types.iter().find(|t| t.name() == "Point")
+        else {
+            panic!("expected alias Point");
+        };
+        assert_eq!(target.to_rescript(), "(float, float)");
+        assert_eq!(target.to_schema(), "S.tuple(s => (s.item(0, S.float), s.item(1, S.float)))");
+
+        let Some(TypeDef::Record { fields, .. }) = spec.types.iter().find(|t| t.name() == "Shape")
+        else {
+            panic!("expected record Shape");
+        };
+        let types: Vec<String> = fields.iter().map(|f| f.ty.to_rescript()).collect();
+        // A property named like the keyword is still a property
+        assert_eq!(types, vec!["point", "option<(int, point)>", "option<string>"]);
+    }
+
+    #[test]
+    fn test_const_literals() {
+        let yaml = r##"
+openapi: 3.1.0
+info: { title: T, version: "1" }
+paths: {}
+components:
+  schemas:
+    Cat:
+      type: object
+      required: [kind, version]
+      properties:
+        kind: { type: string, const: cat }
+        version: { type: integer, const: 2 }
+    Dog:
+      type: object
+      required: [kind, version]
+      properties:
+        kind: { type: string, const: dog }
+        version: { type: integer, const: 2 }
+    Pet:
+      oneOf:
+        - $ref: "#/components/schemas/Cat"
+        - $ref: "#/components/schemas/Dog"
+"##;
+        let spec = crate::parser::parse_spec_content(yaml, &Default::default()).unwrap();
+        let spec = lower_with_options(&spec, &LowerOptions::default()).unwrap();
+
+        let Some(TypeDef::Record { fields, .. }) = spec.types.iter().find(|t| t.name() == "Cat")
+        else {
+            panic!("expected record Cat");
+        };
+        assert_eq!(fields[0].ty.to_rescript(), "[#\"cat\"]");
+        assert_eq!(fields[0].ty.to_schema(), "S.literal(#\"cat\")");
+        assert_eq!(fields[1].ty.to_rescript(), "catVersion");
+
+        // `kind` tells the members apart; `version` doesn't
+        let Some(TypeDef::Variant { cases, discriminator, .. }) =
+            spec.types.iter().find(|t| t.name() == "Pet")
+        else {
+            panic!("expected variant Pet");
+        };
+        assert_eq!(discriminator.as_deref(), Some("kind"));
+        let tags: Vec<_> = cases.iter().map(|c| (c.name.as_str(), c.tags.clone())).collect();
+        assert_eq!(tags, vec![("Cat", vec!["cat".to_string()]), ("Dog", vec!["dog".to_string()])]);
+    }
 }
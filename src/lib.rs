@@ -6,6 +6,14 @@
 //! This library provides the core functionality for parsing OpenAPI specs
 //! and generating ReScript code including types, validators, and HTTP clients.
 
+pub mod build;
+pub mod bundle;
 pub mod codegen;
+pub mod compat;
+pub mod filter;
 pub mod ir;
+pub mod manifest;
 pub mod parser;
+pub mod remote;
+pub mod resolve;
+pub mod upgrade;
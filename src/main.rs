@@ -2,13 +2,18 @@
 // SPDX-FileCopyrightText: 2025 Hyperpolymath
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use notify::{Config as NotifyConfig, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::PathBuf;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 use std::time::Duration;
 
-use rescript_openapi::{codegen, ir, parser};
+use rescript_openapi::{build, bundle, codegen, compat, filter, ir, manifest, parser, remote};
+
+#[cfg(feature = "tui")]
+mod tui;
 
 /// Command-line interface for rescript-openapi
 #[derive(Parser)]
@@ -24,109 +29,472 @@ struct Cli {
 enum Commands {
     /// Generate ReScript code from an OpenAPI specification
     Generate {
-        /// Path to OpenAPI spec (JSON or YAML)
-        #[arg(short, long)]
-        input: PathBuf,
-
-        /// Output directory for generated code
-        #[arg(short, long, default_value = "src/api")]
-        output: PathBuf,
-
-        /// Module name prefix
-        #[arg(short, long, default_value = "Api")]
-        module: String,
-
-        /// Generate rescript-schema validators
-        #[arg(long, default_value = "true")]
-        with_schema: bool,
-
-        /// Generate HTTP client functions
-        #[arg(long, default_value = "true")]
-        with_client: bool,
+        #[command(flatten)]
+        args: CodegenArgs,
 
         /// Watch input file for changes and regenerate automatically
         #[arg(short, long)]
         watch: bool,
 
+        /// Seconds between polls when watching a spec URL
+        #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+        poll_interval: u64,
+
         /// Print generated code to stdout instead of writing to files
         #[arg(long)]
         dry_run: bool,
+
+        /// Print lowering statistics (type counts, degraded schemas, synthesized names)
+        #[arg(short, long)]
+        verbose: bool,
+
+        /// Generate twice and fail if the outputs differ
+        #[arg(long)]
+        assert_deterministic: bool,
+    },
+
+    /// Package spec, config, tool version and output hashes into a reproducible bundle
+    ExportBundle {
+        #[command(flatten)]
+        args: CodegenArgs,
+
+        /// Bundle file to write
+        #[arg(long, value_name = "FILE", default_value = "codegen-bundle.json")]
+        bundle: PathBuf,
+    },
+
+    /// Show the IR and generated code for one schema or operation
+    #[command(group = clap::ArgGroup::new("element").required(true))]
+    Explain {
+        #[command(flatten)]
+        args: CodegenArgs,
+
+        /// Component schema to explain, e.g. `User`
+        #[arg(long, value_name = "NAME", group = "element")]
+        schema: Option<String>,
+
+        /// Operation to explain, by operationId, e.g. `getUser`
+        #[arg(long, value_name = "ID", group = "element")]
+        operation: Option<String>,
+    },
+
+    /// Report generated symbols a new spec would change or remove
+    Compat {
+        /// Options for the new spec, as for `generate`
+        #[command(flatten)]
+        args: CodegenArgs,
+
+        /// Directory holding the previously generated code
+        #[arg(long, value_name = "DIR")]
+        against: PathBuf,
     },
 
     /// Validate an OpenAPI specification
     Validate {
-        /// Path to OpenAPI spec
+        /// Path or http(s) URL of the OpenAPI spec
         #[arg(short, long)]
-        input: PathBuf,
+        input: SpecInput,
+
+        #[command(flatten)]
+        auth: AuthArgs,
+
+        /// Stop at the first error, or also lower the spec and report every problem
+        #[arg(long, value_enum, default_value = "fail-fast")]
+        error_strategy: parser::ErrorStrategy,
+
+        /// Browse the spec interactively with every problem shown in place
+        #[arg(long)]
+        tui: bool,
     },
 
     /// Print information about an OpenAPI specification
     Info {
-        /// Path to OpenAPI spec
+        /// Path or http(s) URL of the OpenAPI spec
         #[arg(short, long)]
-        input: PathBuf,
+        input: SpecInput,
+
+        #[command(flatten)]
+        auth: AuthArgs,
+
+        /// Browse tags, operations and schemas interactively
+        #[arg(long)]
+        tui: bool,
     },
 }
 
-/// Represents the generated code output for dry-run mode
-struct GeneratedCode {
-    /// Name of the file that would be created
-    filename: String,
-    /// Generated code content
-    content: String,
+/// Inputs that determine generated code, shared by `generate` and `export-bundle`
+#[derive(Args, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct CodegenArgs {
+    /// Path or http(s) URL of the OpenAPI spec (JSON or YAML)
+    #[arg(short, long)]
+    input: SpecInput,
+
+    // Credentials stay out of bundles
+    #[command(flatten)]
+    #[serde(skip)]
+    auth: AuthArgs,
+
+    /// Output directory for generated code
+    #[arg(short, long, default_value = "src/api")]
+    output: PathBuf,
+
+    /// Module name prefix
+    #[arg(short, long, default_value = "Api")]
+    module: String,
+
+    /// Generate rescript-schema validators
+    #[arg(long, default_value = "true")]
+    with_schema: bool,
+
+    /// Export a runtime registry of schemas keyed by type name
+    #[arg(long)]
+    with_schema_registry: bool,
+
+    /// Put field descriptions in constraint error messages, so validation errors are human-readable
+    #[arg(long)]
+    describe_errors: bool,
+
+    /// Generate HTTP client functions
+    #[arg(long, default_value = "true")]
+    with_client: bool,
+
+    /// Generate a recording test double of the client (ClientSpy.res)
+    #[arg(long)]
+    with_spy: bool,
+
+    /// Also generate `*Exn` client functions that raise instead of returning a result
+    #[arg(long)]
+    with_exn: bool,
+
+    /// Retry transient failures (e.g. 502/503) with exponential backoff, set in `makeConfig`
+    #[arg(long)]
+    with_retry: bool,
+
+    /// Don't generate `{operationId}EachPage` / `{operationId}All` for paged list operations
+    #[arg(long)]
+    no_pagination_helpers: bool,
+
+    /// Standard library module opened by generated files
+    #[arg(long, value_name = "MODULE", default_value = "RescriptCore")]
+    core_module: String,
+
+    /// Don't open a standard library module (e.g. ReScript 12, where Core is built in)
+    #[arg(long, conflicts_with = "core_module")]
+    no_core_open: bool,
+
+    /// Module aliased as `S` for rescript-schema
+    #[arg(long, value_name = "MODULE", default_value = "RescriptSchema.S")]
+    schema_module: String,
+
+    /// Namespace qualifying references between generated modules (e.g. `MyApp`)
+    #[arg(long, value_name = "NAME")]
+    namespace: Option<String>,
+
+    /// Extra module to open in every generated file (repeatable)
+    #[arg(long = "open", value_name = "MODULE")]
+    extra_opens: Vec<String>,
+
+    /// Client operation surface: standalone functions, or also a config-bound instance
+    #[arg(long, value_enum, default_value = "functions")]
+    client_surface: codegen::ClientSurface,
+
+    /// Client results: the success body, a variant with a case per documented status, or the
+    /// success body with a typed error per operation
+    #[arg(long, value_enum, default_value = "success")]
+    response_style: codegen::ResponseStyle,
+
+    /// Request body serialization: through the generated schema, or plain JSON.stringify
+    #[arg(long, value_enum, default_value = "schema")]
+    body_serializer: codegen::BodySerializer,
+
+    /// Record field naming: camelCase with @as, preserve JSON names, or camelCase sent as snake_case
+    #[arg(long, value_enum, default_value = "camel")]
+    field_case: ir::FieldCase,
+
+    /// ReScript type for int64 integers
+    #[arg(long, value_enum, default_value = "int")]
+    int64: ir::Int64Mode,
+
+    /// Treat Swagger 2.0 `x-nullable` / `x-isnullable` markers like `nullable: true`
+    #[arg(long)]
+    compat_nullable: bool,
+
+    /// Decode a field from a different JSON type, e.g. `Order.total=string` (repeatable)
+    #[arg(long = "coerce", value_name = "TYPE.FIELD=WIRE")]
+    coercions: Vec<ir::Coercion>,
+
+    /// Type for a string format, e.g. `date-time=date` for `Date.t` (repeatable)
+    #[arg(long = "format", value_name = "FORMAT=TYPE")]
+    formats: Vec<ir::FormatMapping>,
+
+    /// Body of responses with only wildcard media types (`*/*`): text, blob, or ignore
+    #[arg(long, value_enum, default_value = "text")]
+    wildcard_responses: ir::WildcardResponses,
+
+    /// Body of binary responses (octet-stream, images, `format: binary`): blob or array-buffer
+    #[arg(long, value_enum, default_value = "blob")]
+    binary_responses: ir::BinaryResponses,
+
+    /// Turn identical inline string enums on several fields into one shared named type
+    #[arg(long)]
+    promote_enums: bool,
+
+    /// Return `negotiated<'json>` with an `~accept` argument for responses offering
+    /// `application/json` among other media types, instead of only reading JSON
+    #[arg(long)]
+    negotiate_responses: bool,
+
+    /// Undeclared properties of records that also declare some: field (an `extra` dict) or ignore
+    #[arg(long, value_enum, default_value = "field")]
+    extra_properties: ir::ExtraProperties,
+
+    /// Request body records: shared with responses, or split (`{Name}Input` without read-only fields)
+    #[arg(long, value_enum, default_value = "shared")]
+    request_types: ir::RequestTypes,
+
+    /// Only generate operations with this tag (repeatable)
+    #[arg(long = "include-tag", value_name = "TAG")]
+    include_tags: Vec<String>,
+
+    /// Only generate operations under this path prefix (repeatable)
+    #[arg(long = "include-path", value_name = "PREFIX")]
+    include_paths: Vec<String>,
+
+    /// Write the filtered spec (kept operations and reachable components) to this file
+    #[arg(long, value_name = "FILE")]
+    emit_spec: Option<PathBuf>,
+
+    /// Stop at the first error, or collect and report every problem at once
+    #[arg(long, value_enum, default_value = "fail-fast")]
+    error_strategy: parser::ErrorStrategy,
+
+    /// Also write manifest.json (input, option and output hashes) for build tool plugins
+    #[arg(long)]
+    manifest: bool,
 }
 
-/// Generate code and return as a vector of GeneratedCode structs
-fn generate_code(
-    input_path: &PathBuf,
-    config: &codegen::Config,
-) -> Result<Vec<GeneratedCode>> {
-    let spec = parser::parse_spec(input_path)
-        .with_context(|| format!("Failed to parse OpenAPI spec: {:?}", input_path))?;
-    let api_spec = ir::lower(&spec)
-        .context("Failed to lower OpenAPI spec to IR")?;
-
-    let mut generated_files = Vec::new();
-
-    // Generate Types.res - all type definitions
-    let types_code = codegen::types::generate(&api_spec, config)
-        .context("Failed to generate types")?;
-    generated_files.push(GeneratedCode {
-        filename: format!("{}Types.res", config.module_prefix),
-        content: types_code,
-    });
-
-    // Generate Schema.res - rescript-schema validators
-    if config.generate_schema {
-        let schema_code = codegen::schema::generate(&api_spec, config)
-            .context("Failed to generate schema")?;
-        generated_files.push(GeneratedCode {
-            filename: format!("{}Schema.res", config.module_prefix),
-            content: schema_code,
-        });
+impl CodegenArgs {
+    fn config(&self) -> codegen::Config {
+        codegen::Config {
+            output_dir: self.output.clone(),
+            module_prefix: self.module.clone(),
+            imports: codegen::Imports {
+                core_module: (!self.no_core_open).then(|| self.core_module.clone()),
+                schema_module: self.schema_module.clone(),
+                namespace: self.namespace.clone(),
+                extra_opens: self.extra_opens.clone(),
+            },
+            generate_schema: self.with_schema,
+            generate_schema_registry: self.with_schema_registry,
+            describe_errors: self.describe_errors,
+            generate_client: self.with_client,
+            client_surface: self.client_surface,
+            response_style: self.response_style,
+            body_serializer: self.body_serializer,
+            generate_exn: self.with_exn,
+            generate_retry: self.with_retry,
+            generate_spy: self.with_spy,
+            generate_pagination: !self.no_pagination_helpers,
+        }
+    }
+
+    fn options(&self) -> Result<build::Options> {
+        let mut options = build::Options::new(self.input.build_input());
+        options.config = self.config();
+        options.lower = ir::LowerOptions {
+            field_case: self.field_case,
+            int64: self.int64,
+            error_strategy: self.error_strategy,
+            compat_nullable: self.compat_nullable,
+            coercions: self.coercions.clone(),
+            formats: self.formats.clone(),
+            wildcard_responses: self.wildcard_responses,
+            binary_responses: self.binary_responses,
+            promote_enums: self.promote_enums,
+            extra_properties: self.extra_properties,
+            request_types: self.request_types,
+            negotiate_responses: self.negotiate_responses,
+        };
+        options.filter = filter::Filter {
+            tags: self.include_tags.clone(),
+            paths: self.include_paths.clone(),
+        };
+        options.auth = self.auth.auth()?;
+        options.limits = self.auth.limits();
+        options.emit_spec = self.emit_spec.clone();
+        options.manifest = self.manifest;
+        // Downloaded once, for generation, the manifest and bundles alike
+        options.fetch()?;
+        Ok(options)
+    }
+}
+
+/// `--input`: a spec file, or a URL to fetch it from
+#[derive(Debug, Clone)]
+enum SpecInput {
+    Path(PathBuf),
+    Url(String),
+}
+
+impl std::str::FromStr for SpecInput {
+    type Err = std::convert::Infallible;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(match remote::is_url(input) {
+            true => SpecInput::Url(input.to_string()),
+            false => SpecInput::Path(PathBuf::from(input)),
+        })
+    }
+}
+
+impl std::fmt::Display for SpecInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpecInput::Path(path) => write!(f, "{}", path.display()),
+            SpecInput::Url(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+impl Serialize for SpecInput {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl SpecInput {
+    fn build_input(&self) -> build::Input {
+        match self {
+            SpecInput::Path(path) => build::Input::Path(path.clone()),
+            SpecInput::Url(url) => build::Input::Url(url.clone()),
+        }
+    }
+
+    fn parse(&self, args: &AuthArgs) -> Result<openapiv3::OpenAPI> {
+        let limits = args.limits();
+        match self {
+            SpecInput::Path(path) => parser::parse_spec_with_limits(path, &limits),
+            SpecInput::Url(url) => parser::parse_spec_url_with_limits(url, &args.auth()?, &limits),
+        }
     }
+}
 
-    // Generate Client.res - HTTP client functions
-    if config.generate_client {
-        let client_code = codegen::client::generate(&api_spec, config)
-            .context("Failed to generate client")?;
-        generated_files.push(GeneratedCode {
-            filename: format!("{}Client.res", config.module_prefix),
-            content: client_code,
-        });
+/// Password for `--basic-auth USER` without `:PASSWORD`
+const PASSWORD_ENV: &str = "RESCRIPT_OPENAPI_PASSWORD";
+
+/// Headers and credentials sent when `--input` is a URL, and whether the
+/// spec may reference other URLs
+#[derive(Args)]
+struct AuthArgs {
+    /// Extra request header for a spec URL, as `Name: value` (repeatable)
+    #[arg(long = "header", value_name = "NAME: VALUE", value_parser = parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// Environment variable holding a bearer token for a spec URL
+    #[arg(long, value_name = "VAR")]
+    bearer_token_env: Option<String>,
+
+    /// Basic auth for a spec URL; the password defaults to $RESCRIPT_OPENAPI_PASSWORD
+    #[arg(long, value_name = "USER[:PASSWORD]")]
+    basic_auth: Option<String>,
+
+    /// Fetch `$ref`s to http(s) URLs named by the spec (only same-origin references
+    /// of a spec URL are followed otherwise)
+    #[arg(long)]
+    allow_remote_refs: bool,
+}
+
+impl AuthArgs {
+    fn limits(&self) -> parser::Limits {
+        parser::Limits {
+            allow_remote_refs: self.allow_remote_refs,
+            ..parser::Limits::default()
+        }
     }
 
-    Ok(generated_files)
+    fn auth(&self) -> Result<remote::Auth> {
+        let mut auth = remote::Auth::default();
+        for (name, value) in &self.headers {
+            auth.header(name, value);
+        }
+        if let Some(var) = &self.bearer_token_env {
+            let token = std::env::var(var)
+                .with_context(|| format!("--bearer-token-env: ${} is not set", var))?;
+            auth.bearer(&token);
+        }
+        if let Some(credentials) = &self.basic_auth {
+            let (user, password) = match credentials.split_once(':') {
+                Some((user, password)) => (user, password.to_string()),
+                None => (
+                    credentials.as_str(),
+                    std::env::var(PASSWORD_ENV)
+                        .with_context(|| format!("--basic-auth: ${} is not set", PASSWORD_ENV))?,
+                ),
+            };
+            auth.basic(user, &password);
+        }
+        Ok(auth)
+    }
+}
+
+fn parse_header(header: &str) -> Result<(String, String), String> {
+    match header.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("expected `Name: value`, got {:?}", header)),
+    }
+}
+
+/// Parse, filter and lower a spec, reporting diagnostics to stderr
+fn lower_spec(options: &build::Options) -> Result<ir::ApiSpec> {
+    let api_spec = build::lower(options).map_err(report_build_error)?;
+    report_diagnostics(&api_spec.diagnostics);
+    Ok(api_spec)
+}
+
+/// Generate code in memory, reporting diagnostics to stderr
+fn generate_code(options: &build::Options) -> Result<build::Generated> {
+    let generated = build::generate(options).map_err(report_build_error)?;
+    report_diagnostics(&generated.diagnostics);
+    Ok(generated)
+}
+
+/// Generate code in memory twice, failing if the runs disagree
+fn generate_code_deterministic(options: &build::Options) -> Result<build::Generated> {
+    let generated = build::generate_deterministic(options).map_err(report_build_error)?;
+    report_diagnostics(&generated.diagnostics);
+    Ok(generated)
+}
+
+/// Print the diagnostics carried by a failed run before returning it as an error
+fn report_build_error(error: build::Error) -> anyhow::Error {
+    if let build::Error::Invalid { diagnostics } = &error {
+        report_diagnostics(diagnostics);
+    }
+    error.into()
+}
+
+/// Print diagnostics to stderr followed by a count summary
+fn report_diagnostics(diagnostics: &[parser::Diagnostic]) {
+    if diagnostics.is_empty() {
+        return;
+    }
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+    eprintln!("{}", parser::summarize(diagnostics));
 }
 
 /// Write generated code to files in the output directory
-fn write_generated_code(config: &codegen::Config, generated_files: &[GeneratedCode]) -> Result<()> {
+fn write_generated_code(config: &codegen::Config, generated_files: &[build::GeneratedFile]) -> Result<()> {
     std::fs::create_dir_all(&config.output_dir)
         .with_context(|| format!("Failed to create output directory: {:?}", config.output_dir))?;
 
     for generated_file in generated_files {
         let file_path = config.output_dir.join(&generated_file.filename);
-        std::fs::write(&file_path, &generated_file.content)
+        codegen::write_file(&file_path, &generated_file.content)
             .with_context(|| format!("Failed to write file: {:?}", file_path))?;
     }
 
@@ -134,7 +502,7 @@ fn write_generated_code(config: &codegen::Config, generated_files: &[GeneratedCo
 }
 
 /// Print generated code to stdout (dry-run mode)
-fn print_generated_code(generated_files: &[GeneratedCode]) {
+fn print_generated_code(generated_files: &[build::GeneratedFile]) {
     for (index, generated_file) in generated_files.iter().enumerate() {
         if index > 0 {
             println!("\n{}", "=".repeat(80));
@@ -145,36 +513,123 @@ fn print_generated_code(generated_files: &[GeneratedCode]) {
     }
 }
 
+/// Output flags of the generate command
+#[derive(Clone, Copy)]
+struct GenerateMode {
+    dry_run: bool,
+    verbose: bool,
+    assert_deterministic: bool,
+}
+
 /// Run the generate command once
-fn run_generate(
-    input_path: &PathBuf,
-    config: &codegen::Config,
-    dry_run_mode: bool,
-) -> Result<()> {
-    let generated_files = generate_code(input_path, config)?;
+fn run_generate(options: &build::Options, mode: GenerateMode) -> Result<()> {
+    let generated = if mode.assert_deterministic {
+        generate_code_deterministic(options)?
+    } else {
+        generate_code(options)?
+    };
+    if mode.verbose {
+        eprintln!("{}", generated.report);
+    }
+    let generated_files = generated.files;
 
-    if dry_run_mode {
+    if mode.dry_run {
         print_generated_code(&generated_files);
     } else {
-        write_generated_code(config, &generated_files)?;
+        write_generated_code(&options.config, &generated_files)?;
         println!(
             "Generated ReScript code in {:?}",
-            config.output_dir
+            options.config.output_dir
         );
     }
 
     Ok(())
 }
 
-/// Watch the input file for changes and regenerate on modification
+/// Generate in memory and write a bundle describing the run
+fn export_bundle(args: &CodegenArgs, bundle_path: &Path) -> Result<()> {
+    let options = args.options()?;
+
+    // The document as written; a URL was already fetched into text
+    let source = match &options.input {
+        build::Input::Path(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read spec file: {:?}", path))?,
+        build::Input::Text(text) => text.clone(),
+        _ => unreachable!("--input is a path or a fetched URL"),
+    };
+    // Parsed once, for both the outputs and the effective spec
+    let generated = generate_code(&options)?;
+    let outputs: Vec<(String, String)> = generated
+        .files
+        .into_iter()
+        .map(|file| (file.filename, file.content))
+        .collect();
+
+    let bundle = bundle::Bundle::new(
+        &args.input.to_string(),
+        &source,
+        &generated.spec,
+        serde_json::to_value(args)?,
+        &outputs,
+    )?;
+    bundle.write(bundle_path)
+}
+
+/// Print which previously generated symbols the new spec removes, changes or adds
+fn report_compat(args: &CodegenArgs, against: &Path) -> Result<()> {
+    let options = args.options()?;
+    let generated = build::generate(&options).map_err(report_build_error)?;
+    report_diagnostics(&generated.diagnostics);
+
+    let old = compat::read_generated(against)
+        .with_context(|| format!("Failed to read generated code in {:?}", against))?;
+    if old.is_empty() {
+        anyhow::bail!("No generated files found in {:?}", against);
+    }
+    let new: Vec<(String, String)> = generated
+        .files
+        .into_iter()
+        .filter(|file| file.filename.ends_with(".res"))
+        .map(|file| (file.filename, file.content))
+        .collect();
+
+    match compat::previous_spec_hash(against, &options.config.module_prefix) {
+        Some(hash) if hash == generated.spec_hash => println!("Spec unchanged ({})", hash),
+        Some(hash) => println!("Spec changed: {} -> {}", hash, generated.spec_hash),
+        None => println!("Spec hash of existing code unknown; new: {}", generated.spec_hash),
+    }
+
+    let changes = compat::compare(&old, &new);
+    for (change, label) in [
+        (compat::Change::Removed, "Removed"),
+        (compat::Change::Changed, "Changed"),
+        (compat::Change::Added, "Added"),
+    ] {
+        let matching: Vec<_> = changes.iter().filter(|c| c.change == change).collect();
+        if matching.is_empty() {
+            continue;
+        }
+        println!("\n{} ({}):", label, matching.len());
+        for c in matching {
+            println!("  {}: {}", c.file, c.symbol);
+        }
+    }
+    if changes.is_empty() {
+        println!("No generated symbols change");
+    }
+
+    Ok(())
+}
+
+/// Watch the input file, and the files it references, for changes and regenerate on modification
 fn watch_and_regenerate(
     input_path: &PathBuf,
-    config: &codegen::Config,
-    dry_run_mode: bool,
+    options: &build::Options,
+    mode: GenerateMode,
 ) -> Result<()> {
     // Perform initial generation
     println!("Watching {:?} for changes...", input_path);
-    if let Err(error) = run_generate(input_path, config, dry_run_mode) {
+    if let Err(error) = run_generate(options, mode) {
         eprintln!("Error during initial generation: {}", error);
     }
 
@@ -187,14 +642,9 @@ fn watch_and_regenerate(
     let mut watcher: RecommendedWatcher = Watcher::new(sender, notify_config)
         .context("Failed to create file watcher")?;
 
-    // Watch the input file's parent directory to catch file replacements
-    let watch_path = input_path
-        .parent()
-        .unwrap_or(input_path.as_path());
-
-    watcher
-        .watch(watch_path, RecursiveMode::NonRecursive)
-        .with_context(|| format!("Failed to watch path: {:?}", watch_path))?;
+    // Watch the input files' parent directories to catch file replacements
+    let mut watched_dirs = HashSet::new();
+    let mut inputs = watch_inputs(&mut watcher, &mut watched_dirs, input_path)?;
 
     println!("Press Ctrl+C to stop watching.\n");
 
@@ -204,9 +654,9 @@ fn watch_and_regenerate(
             Ok(event_result) => {
                 match event_result {
                     Ok(event) => {
-                        // Check if the event is for our input file
+                        // Check if the event is for one of our input files
                         let is_our_file = event.paths.iter().any(|path| {
-                            path.file_name() == input_path.file_name()
+                            inputs.contains(&canonical(path))
                         });
 
                         if is_our_file {
@@ -215,9 +665,15 @@ fn watch_and_regenerate(
                             match event.kind {
                                 EventKind::Modify(_) | EventKind::Create(_) => {
                                     println!("\nFile changed, regenerating...");
-                                    match run_generate(input_path, config, dry_run_mode) {
+                                    // The changed file may reference different files now
+                                    inputs = watch_inputs(
+                                        &mut watcher,
+                                        &mut watched_dirs,
+                                        input_path,
+                                    )?;
+                                    match run_generate(options, mode) {
                                         Ok(()) => {
-                                            if !dry_run_mode {
+                                            if !mode.dry_run {
                                                 println!("Regeneration complete.");
                                             }
                                         }
@@ -245,49 +701,164 @@ fn watch_and_regenerate(
     Ok(())
 }
 
+/// Poll a spec URL and regenerate whenever the document changes
+///
+/// `options` hold the document already fetched; each changed one replaces it,
+/// so a regeneration uses what the poll downloaded.
+fn poll_and_regenerate(
+    url: &str,
+    interval: Duration,
+    mut options: build::Options,
+    mode: GenerateMode,
+) -> Result<()> {
+    println!("Polling {} for changes every {:?}...", url, interval);
+    if let Err(error) = run_generate(&options, mode) {
+        eprintln!("Error during initial generation: {}", error);
+    }
+    println!("Press Ctrl+C to stop watching.\n");
+
+    let current = match &options.input {
+        build::Input::Text(text) => Some(text.clone()),
+        _ => None,
+    };
+    let (max_bytes, auth) = (options.limits.max_spec_bytes, options.auth.clone());
+    remote::poll(url, interval, max_bytes, &auth, current.as_deref(), |content| {
+        println!("\nSpec changed, regenerating...");
+        options.input = build::Input::Text(content);
+        match run_generate(&options, mode) {
+            Ok(()) if !mode.dry_run => println!("Regeneration complete."),
+            Ok(()) => {}
+            Err(error) => eprintln!("Error during regeneration: {}", error),
+        }
+    })
+}
+
+/// The input file and every local file it references, as canonical paths,
+/// with their directories added to the watcher
+fn watch_inputs(
+    watcher: &mut RecommendedWatcher,
+    watched_dirs: &mut HashSet<PathBuf>,
+    input_path: &Path,
+) -> Result<HashSet<PathBuf>> {
+    let mut inputs: HashSet<PathBuf> = manifest::input_files(input_path)
+        .iter()
+        .map(|path| canonical(path))
+        .collect();
+    // Kept even while the file is being replaced
+    inputs.insert(canonical(input_path));
+
+    for dir in inputs.iter().filter_map(|path| path.parent()) {
+        if watched_dirs.insert(dir.to_path_buf()) {
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .with_context(|| format!("Failed to watch path: {:?}", dir))?;
+        }
+    }
+    Ok(inputs)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Open the interactive spec browser
+#[cfg(feature = "tui")]
+fn browse(spec: &openapiv3::OpenAPI, diagnostics: Vec<parser::Diagnostic>) -> Result<()> {
+    tui::run(spec, diagnostics)
+}
+
+#[cfg(not(feature = "tui"))]
+fn browse(_spec: &openapiv3::OpenAPI, _diagnostics: Vec<parser::Diagnostic>) -> Result<()> {
+    anyhow::bail!("--tui requires building with the `tui` feature")
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
         Commands::Generate {
-            input,
-            output,
-            module,
-            with_schema,
-            with_client,
+            args,
             watch,
+            poll_interval,
             dry_run,
+            verbose,
+            assert_deterministic,
         } => {
-            let config = codegen::Config {
-                output_dir: output,
-                module_prefix: module,
-                generate_schema: with_schema,
-                generate_client: with_client,
+            let options = args.options()?;
+            let mode = GenerateMode {
+                dry_run,
+                verbose,
+                assert_deterministic,
             };
 
-            if watch {
-                watch_and_regenerate(&input, &config, dry_run)?;
-            } else {
-                run_generate(&input, &config, dry_run)?;
+            match (&args.input, watch) {
+                (SpecInput::Path(path), true) => watch_and_regenerate(path, &options, mode)?,
+                (SpecInput::Url(url), true) => {
+                    let interval = Duration::from_secs(poll_interval);
+                    poll_and_regenerate(url, interval, options, mode)?
+                }
+                (_, false) => run_generate(&options, mode)?,
             }
         }
 
-        Commands::Validate { input } => {
-            let spec = parser::parse_spec(&input)?;
-            let diagnostics = parser::validate(&spec);
+        Commands::ExportBundle { args, bundle } => {
+            export_bundle(&args, &bundle)?;
+            println!("Wrote codegen bundle to {:?}", bundle);
+        }
+
+        Commands::Explain {
+            args,
+            schema,
+            operation,
+        } => {
+            let options = args.options()?;
+            let api_spec = lower_spec(&options)?;
+            let explanation = match (schema, operation) {
+                (Some(name), _) => codegen::explain::schema(&api_spec, &options.config, &name)?,
+                (None, Some(operation_id)) => {
+                    codegen::explain::operation(&api_spec, &options.config, &operation_id)?
+                }
+                (None, None) => unreachable!("clap requires --schema or --operation"),
+            };
+            print!("{}", explanation);
+        }
+
+        Commands::Compat { args, against } => {
+            report_compat(&args, &against)?;
+        }
+
+        Commands::Validate {
+            input,
+            auth,
+            error_strategy,
+            tui,
+        } => {
+            let spec = input.parse(&auth)?;
+            let diagnostics = ir::diagnostics(&spec, error_strategy)?;
+            if tui {
+                // Exits as without the browser once it is closed
+                let valid = diagnostics.is_empty();
+                browse(&spec, diagnostics)?;
+                if !valid {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
 
             if diagnostics.is_empty() {
                 println!("OpenAPI spec is valid");
             } else {
-                for diagnostic in &diagnostics {
-                    eprintln!("{}", diagnostic);
-                }
+                report_diagnostics(&diagnostics);
                 std::process::exit(1);
             }
         }
 
-        Commands::Info { input } => {
-            let spec = parser::parse_spec(&input)?;
+        Commands::Info { input, auth, tui } => {
+            let spec = input.parse(&auth)?;
+            if tui {
+                let diagnostics = ir::diagnostics(&spec, parser::ErrorStrategy::Collect)?;
+                return browse(&spec, diagnostics);
+            }
             println!("Title: {}", spec.info.title);
             println!("Version: {}", spec.info.version);
             if let Some(description) = &spec.info.description {
@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Output manifest for build tool integration
+//!
+//! `manifest.json` sits next to the generated files and lists what they were
+//! generated from: every input file with its hash, a hash of the options and
+//! the tool version, plus a hash of each output. A file-watching plugin can
+//! compare it against the current inputs to decide whether to regenerate, and
+//! against the outputs to spot hand edits.
+
+use crate::bundle::{sha256_hex, OutputHash, Tool};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// File name of the manifest in the output directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Bump when the manifest layout changes incompatibly
+pub const MANIFEST_FORMAT: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub tool: Tool,
+    /// The spec file first, then local files it references through `$ref`
+    pub inputs: Vec<InputHash>,
+    /// SHA-256 of the normalized spec, as embedded in the generated Meta module
+    pub spec_hash: String,
+    /// SHA-256 of the generation options
+    pub options_sha256: String,
+    /// Generated files in generation order
+    pub outputs: Vec<OutputHash>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputHash {
+    pub file: String,
+    pub sha256: String,
+}
+
+impl Manifest {
+    /// Describe a run from its hashed inputs, options and `(filename, content)` outputs
+    pub fn new(
+        inputs: Vec<InputHash>,
+        spec_hash: &str,
+        options: &serde_json::Value,
+        outputs: &[(String, String)],
+    ) -> Self {
+        Manifest {
+            format: MANIFEST_FORMAT,
+            tool: Tool::current(),
+            inputs,
+            spec_hash: spec_hash.to_string(),
+            options_sha256: sha256_hex(options.to_string().as_bytes()),
+            outputs: outputs
+                .iter()
+                .map(|(file, content)| OutputHash {
+                    file: file.clone(),
+                    sha256: sha256_hex(content.as_bytes()),
+                })
+                .collect(),
+        }
+    }
+
+    /// Pretty-printed JSON with a trailing newline
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+}
+
+/// Hash a spec file and every existing local file it references
+///
+/// `spec` is not needed any more: references are read from each file, so
+/// files referenced only by other referenced files are hashed too.
+#[deprecated(note = "use `hash_inputs`")]
+pub fn input_hashes(spec_path: &Path, _spec: &serde_json::Value) -> Vec<InputHash> {
+    hash_inputs(spec_path)
+}
+
+/// Hash a spec file and every existing local file it references, directly
+/// or through other referenced files
+pub fn hash_inputs(spec_path: &Path) -> Vec<InputHash> {
+    input_files(spec_path)
+        .into_iter()
+        .filter_map(|path| {
+            let content = std::fs::read(&path).ok()?;
+            Some(InputHash {
+                file: path.display().to_string(),
+                sha256: sha256_hex(&content),
+            })
+        })
+        .collect()
+}
+
+/// A spec file and every existing local file it references, directly or
+/// through other referenced files, each listed once
+pub fn input_files(spec_path: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![spec_path.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        let Ok(content) = std::fs::read(&path) else {
+            continue;
+        };
+        if !seen.insert(std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone())) {
+            continue;
+        }
+
+        // YAML is a superset of JSON; integer keys such as status codes become strings
+        let document = serde_yaml::from_slice::<serde_yaml::Value>(&content)
+            .ok()
+            .and_then(|value| serde_json::to_value(value).ok())
+            .unwrap_or_default();
+        let base = path.parent().unwrap_or(Path::new(""));
+        let mut referenced: Vec<PathBuf> =
+            referenced_files(&document).into_iter().map(|file| base.join(file)).collect();
+        // Popped from the end, so reverse to visit references in sorted order
+        referenced.reverse();
+        pending.extend(referenced);
+        files.push(path);
+    }
+    files
+}
+
+/// Relative file paths named by non-local `$ref`s, e.g. `schemas/user.yaml`
+fn referenced_files(value: &serde_json::Value) -> BTreeSet<PathBuf> {
+    let mut files = BTreeSet::new();
+    collect_file_refs(value, &mut files);
+    files
+}
+
+fn collect_file_refs(value: &serde_json::Value, out: &mut BTreeSet<PathBuf>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("$ref", serde_json::Value::String(reference)) => {
+                        let file = reference.split('#').next().unwrap_or_default();
+                        if !file.is_empty() && !file.contains("://") {
+                            out.insert(PathBuf::from(file));
+                        }
+                    }
+                    _ => collect_file_refs(value, out),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_file_refs(item, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_referenced_files() {
+        let spec = serde_json::json!({
+            "paths": {"/a": {"$ref": "paths/a.yaml"}},
+            "components": {"schemas": {
+                "User": {"$ref": "schemas/user.yaml#/User"},
+                "Local": {"$ref": "#/components/schemas/User"},
+                "Remote": {"$ref": "https://example.com/pet.yaml"},
+                "Again": {"items": [{"$ref": "schemas/user.yaml#/Other"}]}
+            }}
+        });
+        let files: Vec<_> = referenced_files(&spec).into_iter().collect();
+        assert_eq!(files, vec![PathBuf::from("paths/a.yaml"), PathBuf::from("schemas/user.yaml")]);
+    }
+
+    #[test]
+    fn test_input_files_follow_nested_refs() {
+        let files = input_files(Path::new("tests/fixtures/multi-file/openapi.yaml"));
+        let names: Vec<_> = files
+            .iter()
+            .map(|f| f.strip_prefix("tests/fixtures/multi-file").unwrap().display().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "openapi.yaml",
+                "paths/user.yaml",
+                "paths/../schemas/Error.yaml",
+                "paths/../schemas/User.yaml",
+                "paths/../schemas/Address.yaml",
+                "paths/users.yaml",
+                "paths/../parameters/limit.yaml",
+            ]
+        );
+    }
+}
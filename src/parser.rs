@@ -5,45 +5,293 @@
 //!
 //! Handles parsing of OpenAPI 3.x specifications in JSON and YAML formats.
 
-use anyhow::{Context, Result};
-use openapiv3::OpenAPI;
+use crate::resolve::{self, Base};
+use crate::remote::{self, Auth};
+use crate::upgrade;
+use anyhow::{bail, Context, Result};
+use openapiv3::{OpenAPI, ReferenceOr};
+use std::collections::HashSet;
 use std::path::Path;
 
+/// Resource limits applied while parsing, so hostile documents fail with a
+/// clear error instead of exhausting memory or the stack
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Maximum size of the spec document in bytes
+    pub max_spec_bytes: usize,
+    /// Maximum nesting depth of objects/arrays in the document
+    pub max_depth: usize,
+    /// Maximum length of a chain of schemas that are pure `$ref` aliases
+    pub max_ref_depth: usize,
+    /// Fetch `$ref`s to http(s) URLs; off by default, since a spec could
+    /// otherwise make the generator request arbitrary URLs. References from a
+    /// spec URL to documents on its own origin are always followed.
+    pub allow_remote_refs: bool,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_spec_bytes: 32 * 1024 * 1024,
+            max_depth: 96,
+            max_ref_depth: 32,
+            allow_remote_refs: false,
+        }
+    }
+}
+
 /// Parse an OpenAPI specification from a file
 pub fn parse_spec(path: &Path) -> Result<OpenAPI> {
+    parse_spec_with_limits(path, &Limits::default())
+}
+
+/// Parse an OpenAPI specification from a file with explicit resource limits
+pub fn parse_spec_with_limits(path: &Path, limits: &Limits) -> Result<OpenAPI> {
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read OpenAPI spec from {:?}", path))?
+        .len();
+    if size > limits.max_spec_bytes as u64 {
+        bail!(
+            "OpenAPI spec {:?} is {} bytes, exceeding the limit of {} bytes",
+            path,
+            size,
+            limits.max_spec_bytes
+        );
+    }
+
     let content = std::fs::read_to_string(path)
         .with_context(|| format!("Failed to read OpenAPI spec from {:?}", path))?;
 
     let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    parse_content(&content, SpecFormat::from_extension(ext), limits, Base::File(path))
+}
+
+/// Fetch and parse an OpenAPI specification from an `http(s)://` URL
+///
+/// `auth`'s headers are sent for the spec and any `$ref` documents on the same origin.
+pub fn parse_spec_url(url: &str, auth: &Auth) -> Result<OpenAPI> {
+    parse_spec_url_with_limits(url, auth, &Limits::default())
+}
+
+/// Fetch and parse an OpenAPI specification from a URL with explicit resource limits
+pub fn parse_spec_url_with_limits(url: &str, auth: &Auth, limits: &Limits) -> Result<OpenAPI> {
+    let content = remote::fetch(url, limits.max_spec_bytes, auth)?;
+    parse_fetched_spec(url, &content, auth, limits)
+}
+
+/// Parse a document already fetched from `url`, resolving its relative `$ref`s against it
+pub fn parse_fetched_spec(url: &str, content: &str, auth: &Auth, limits: &Limits) -> Result<OpenAPI> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let ext = path.rsplit_once('.').map_or("", |(_, ext)| ext);
+    parse_content(content, SpecFormat::from_extension(ext), limits, Base::Url(url, auth))
+}
+
+/// Serialization of an in-memory spec document
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpecFormat {
+    Json,
+    Yaml,
+    /// Try JSON first, then YAML
+    #[default]
+    Auto,
+}
 
-    match ext {
-        "json" => serde_json::from_str(&content)
-            .with_context(|| "Failed to parse OpenAPI spec as JSON"),
-        "yaml" | "yml" => serde_yaml::from_str(&content)
-            .with_context(|| "Failed to parse OpenAPI spec as YAML"),
-        _ => {
+impl SpecFormat {
+    /// Format implied by a file extension, `Auto` for unknown ones
+    pub fn from_extension(ext: &str) -> Self {
+        match ext {
+            "json" => SpecFormat::Json,
+            "yaml" | "yml" => SpecFormat::Yaml,
+            _ => SpecFormat::Auto,
+        }
+    }
+}
+
+/// Parse an OpenAPI specification held in memory (JSON or YAML, auto-detected)
+pub fn parse_spec_content(content: &str, limits: &Limits) -> Result<OpenAPI> {
+    parse_spec_content_as(content, SpecFormat::Auto, limits)
+}
+
+/// Parse an OpenAPI specification that is already a JSON value, e.g. fetched from a registry
+pub fn parse_spec_value(value: serde_json::Value) -> Result<OpenAPI> {
+    parse_spec_value_with_limits(value, &Limits::default())
+}
+
+/// Parse an OpenAPI specification JSON value with explicit resource limits
+///
+/// The size limit doesn't apply, as there is no document text to measure.
+pub fn parse_spec_value_with_limits(value: serde_json::Value, limits: &Limits) -> Result<OpenAPI> {
+    let spec = from_json_value(value, limits, Base::Memory)
+        .context("Failed to parse OpenAPI spec value")?;
+    check_ref_chains(&spec, limits)?;
+    Ok(spec)
+}
+
+/// Parse an OpenAPI specification held in memory in a known format
+pub fn parse_spec_content_as(content: &str, format: SpecFormat, limits: &Limits) -> Result<OpenAPI> {
+    parse_content(content, format, limits, Base::Memory)
+}
+
+/// Parse a document, resolving relative `$ref`s against where it was read from
+fn parse_content(
+    content: &str,
+    format: SpecFormat,
+    limits: &Limits,
+    base: Base,
+) -> Result<OpenAPI> {
+    if content.len() > limits.max_spec_bytes {
+        bail!(
+            "OpenAPI spec is {} bytes, exceeding the limit of {} bytes",
+            content.len(),
+            limits.max_spec_bytes
+        );
+    }
+
+    let spec: OpenAPI = match format {
+        SpecFormat::Json => from_json(content, limits, base)
+            .with_context(|| "Failed to parse OpenAPI spec as JSON")?,
+        SpecFormat::Yaml => from_yaml(content, limits, base)
+            .with_context(|| "Failed to parse OpenAPI spec as YAML")?,
+        SpecFormat::Auto => {
             // Try JSON first, then YAML
-            serde_json::from_str(&content)
-                .or_else(|_| serde_yaml::from_str(&content))
-                .with_context(|| "Failed to parse OpenAPI spec (tried JSON and YAML)")
+            from_json(content, limits, base)
+                .or_else(|_| from_yaml(content, limits, base))
+                .with_context(|| "Failed to parse OpenAPI spec (tried JSON and YAML)")?
+        }
+    };
+
+    check_ref_chains(&spec, limits)?;
+    Ok(spec)
+}
+
+fn from_json(content: &str, limits: &Limits, base: Base) -> Result<OpenAPI> {
+    from_json_value(serde_json::from_str(content)?, limits, base)
+}
+
+fn from_json_value(value: serde_json::Value, limits: &Limits, base: Base) -> Result<OpenAPI> {
+    check_depth(&value, limits, |v| match v {
+        serde_json::Value::Array(items) => items.iter().collect(),
+        serde_json::Value::Object(map) => map.values().collect(),
+        _ => Vec::new(),
+    })?;
+    // Upgrades work on YAML values, which every JSON document converts to
+    finish(serde_yaml::to_value(value)?, limits, base)
+}
+
+fn from_yaml(content: &str, limits: &Limits, base: Base) -> Result<OpenAPI> {
+    let value: serde_yaml::Value = serde_yaml::from_str(content)?;
+    check_yaml_depth(&value, limits)?;
+    finish(value, limits, base)
+}
+
+/// Resolve external references and upgrade a depth-checked document
+fn finish(mut value: serde_yaml::Value, limits: &Limits, base: Base) -> Result<OpenAPI> {
+    resolve::resolve(&mut value, base, limits)?;
+    upgrade::upgrade(&mut value);
+    Ok(serde_yaml::from_value(value)?)
+}
+
+pub(crate) fn check_yaml_depth(value: &serde_yaml::Value, limits: &Limits) -> Result<()> {
+    check_depth(value, limits, |v| match v {
+        serde_yaml::Value::Sequence(items) => items.iter().collect(),
+        serde_yaml::Value::Mapping(map) => map.values().collect(),
+        serde_yaml::Value::Tagged(tagged) => vec![&tagged.value],
+        _ => Vec::new(),
+    })
+}
+
+/// Iteratively measure document nesting so deep input can't overflow the stack
+fn check_depth<'v, V>(root: &'v V, limits: &Limits, children: impl Fn(&'v V) -> Vec<&'v V>) -> Result<()> {
+    let mut stack = vec![(root, 1usize)];
+    while let Some((value, depth)) = stack.pop() {
+        if depth > limits.max_depth {
+            bail!(
+                "OpenAPI spec nesting exceeds the maximum depth of {}",
+                limits.max_depth
+            );
         }
+        stack.extend(children(value).into_iter().map(|child| (child, depth + 1)));
     }
+    Ok(())
+}
+
+/// Reject cyclic or overly long chains of schemas that only alias another `$ref`
+fn check_ref_chains(spec: &OpenAPI, limits: &Limits) -> Result<()> {
+    let Some(components) = &spec.components else {
+        return Ok(());
+    };
+
+    for start in components.schemas.keys() {
+        let mut seen = HashSet::new();
+        let mut current = start.as_str();
+        while let Some(ReferenceOr::Reference { reference }) = components.schemas.get(current) {
+            if !seen.insert(current) {
+                bail!("Cyclic $ref chain starting at components.schemas.{}", start);
+            }
+            if seen.len() > limits.max_ref_depth {
+                bail!(
+                    "$ref chain starting at components.schemas.{} exceeds the maximum depth of {}",
+                    start,
+                    limits.max_ref_depth
+                );
+            }
+            match reference.strip_prefix("#/components/schemas/") {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write an OpenAPI specification to a file (YAML unless the extension is .json)
+pub fn write_spec(spec: &OpenAPI, path: &Path) -> Result<()> {
+    let content = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::to_string_pretty(spec)
+            .with_context(|| "Failed to serialize OpenAPI spec as JSON")?,
+        _ => serde_yaml::to_string(spec)
+            .with_context(|| "Failed to serialize OpenAPI spec as YAML")?,
+    };
+    std::fs::write(path, content)
+        .with_context(|| format!("Failed to write OpenAPI spec to {:?}", path))
 }
 
 /// Diagnostic message for validation issues
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Diagnostic {
     pub severity: Severity,
     pub message: String,
     pub path: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Severity {
     Error,
     Warning,
 }
 
+/// How validation and lowering react to the first error
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorStrategy {
+    /// Stop at the first error
+    #[default]
+    FailFast,
+    /// Keep going and report every problem at once
+    Collect,
+}
+
+/// Summarize diagnostics as "N error(s), M warning(s)"
+pub fn summarize(diagnostics: &[Diagnostic]) -> String {
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    format!("{} error(s), {} warning(s)", errors, warnings)
+}
+
 impl std::fmt::Display for Diagnostic {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let prefix = match self.severity {
@@ -112,7 +360,7 @@ fn check_schema_compatibility(
             diagnostics.push(Diagnostic {
                 severity: Severity::Warning,
                 message: format!(
-                    "Schema '{}' uses anyOf - support is experimental",
+                    "Schema '{}' uses anyOf - will generate as untagged variant type",
                     name
                 ),
                 path: Some(format!("components.schemas.{}", name)),
@@ -140,4 +388,58 @@ mod tests {
         let spec = parse_spec(temp.path()).unwrap();
         assert_eq!(spec.info.title, "Test");
     }
+
+    #[test]
+    fn test_parse_in_memory_spec() {
+        let value = serde_json::json!({
+            "openapi": "3.0.0",
+            "info": { "title": "Registry", "version": "1.0.0" },
+            "paths": {}
+        });
+        let spec = parse_spec_value(value).unwrap();
+        assert_eq!(spec.info.title, "Registry");
+
+        let error = parse_spec_value(serde_json::json!({ "openapi": 3 })).unwrap_err();
+        assert_eq!(error.to_string(), "Failed to parse OpenAPI spec value");
+
+        let yaml = "openapi: 3.0.0\ninfo: { title: Text, version: '1' }\npaths: {}\n";
+        let spec = parse_spec_content_as(yaml, SpecFormat::Yaml, &Limits::default()).unwrap();
+        assert_eq!(spec.info.title, "Text");
+        let error = parse_spec_content_as(yaml, SpecFormat::Json, &Limits::default()).unwrap_err();
+        assert_eq!(error.to_string(), "Failed to parse OpenAPI spec as JSON");
+    }
+
+    #[test]
+    fn test_limits_reject_hostile_specs() {
+        let limits = Limits {
+            max_spec_bytes: 4096,
+            max_depth: 16,
+            max_ref_depth: 4,
+            allow_remote_refs: false,
+        };
+
+        let deep = format!(
+            r#"{{"openapi": "3.0.0", "info": {{"title": "T", "version": "1"}}, "paths": {{}}, "x-deep": {}{}}}"#,
+            "[".repeat(32),
+            "]".repeat(32)
+        );
+        let error = parse_spec_content(&deep, &limits).unwrap_err();
+        assert!(format!("{:#}", error).contains("maximum depth of 16"));
+
+        let large = " ".repeat(5000);
+        let error = parse_spec_content(&large, &limits).unwrap_err();
+        assert!(format!("{:#}", error).contains("exceeding the limit"));
+
+        let cyclic = r##"
+openapi: "3.0.0"
+info: { title: T, version: "1" }
+paths: {}
+components:
+  schemas:
+    A: { $ref: "#/components/schemas/B" }
+    B: { $ref: "#/components/schemas/A" }
+"##;
+        let error = parse_spec_content(cyclic, &limits).unwrap_err();
+        assert!(format!("{:#}", error).contains("Cyclic $ref chain"));
+    }
 }
@@ -0,0 +1,339 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Fetching and polling remotely hosted specs
+//!
+//! Specs and `$ref` documents named by `http(s)://` URLs are fetched with the
+//! default `remote` feature. Watch mode against a spec URL polls the server
+//! instead of using file events. The schedule honors `Retry-After` on 429/503
+//! responses, backs off exponentially on other failures, and jitters every
+//! delay so many dev sessions don't hit a rate-limited spec endpoint in
+//! lockstep.
+
+use std::time::{Duration, SystemTime};
+
+/// Whether an input names a remote document rather than a file
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// Headers sent when fetching a spec URL, typically credentials
+///
+/// They go to the spec's own origin only, never to other hosts its `$ref`s
+/// name. `Debug` shows header names but not their values.
+#[derive(Clone, Default)]
+pub struct Auth {
+    pub headers: Vec<(String, String)>,
+}
+
+impl Auth {
+    /// Add a header sent with every request
+    pub fn header(&mut self, name: &str, value: &str) {
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    /// Authenticate with a bearer token
+    pub fn bearer(&mut self, token: &str) {
+        self.header("Authorization", &format!("Bearer {}", token));
+    }
+
+    /// Authenticate with HTTP basic auth
+    pub fn basic(&mut self, user: &str, password: &str) {
+        let credentials = base64(format!("{}:{}", user, password).as_bytes());
+        self.header("Authorization", &format!("Basic {}", credentials));
+    }
+}
+
+impl std::fmt::Debug for Auth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.headers.iter().map(|(name, _)| name)).finish()
+    }
+}
+
+/// `scheme://host[:port]` of a URL
+pub fn origin(url: &str) -> &str {
+    let Some(scheme_end) = url.find("://") else {
+        return url;
+    };
+    let host_start = scheme_end + 3;
+    let host_end = url[host_start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |end| host_start + end);
+    &url[..host_end]
+}
+
+/// Standard base64 with padding, as HTTP basic auth expects
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Why fetching a remote document failed
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    /// The server answered with an error status
+    #[error("{url} responded with HTTP {status}")]
+    Status {
+        url: String,
+        status: u16,
+        /// The `Retry-After` header, if any
+        retry_after: Option<String>,
+    },
+    /// No response, or a body that couldn't be read or exceeds the size limit
+    #[error("Failed to fetch {url}")]
+    Transport {
+        url: String,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+/// Fetch a document's text with `auth`'s headers, failing if it is longer than `max_bytes`
+#[cfg(feature = "remote")]
+pub fn fetch(url: &str, max_bytes: usize, auth: &Auth) -> Result<String, FetchError> {
+    use std::io::Read;
+
+    let transport = |source: anyhow::Error| FetchError::Transport {
+        url: url.to_string(),
+        source,
+    };
+    let request = auth
+        .headers
+        .iter()
+        .fold(ureq::get(url), |request, (name, value)| request.set(name, value));
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, response)) => {
+            return Err(FetchError::Status {
+                url: url.to_string(),
+                status,
+                retry_after: response.header("Retry-After").map(str::to_string),
+            })
+        }
+        Err(error) => return Err(transport(error.into())),
+    };
+
+    let mut content = String::new();
+    response
+        .into_reader()
+        .take(max_bytes as u64 + 1)
+        .read_to_string(&mut content)
+        .map_err(|error| transport(error.into()))?;
+    if content.len() > max_bytes {
+        return Err(transport(anyhow::anyhow!(
+            "Document exceeds the limit of {} bytes",
+            max_bytes
+        )));
+    }
+    Ok(content)
+}
+
+#[cfg(not(feature = "remote"))]
+pub fn fetch(url: &str, _max_bytes: usize, _auth: &Auth) -> Result<String, FetchError> {
+    Err(FetchError::Transport {
+        url: url.to_string(),
+        source: anyhow::anyhow!("Fetching over HTTP(S) needs the `remote` feature"),
+    })
+}
+
+/// Upper bound on the exponential backoff between failed polls
+const MAX_DELAY: Duration = Duration::from_secs(15 * 60);
+
+/// Fraction of the delay used as +/- jitter
+const JITTER_RATIO: f64 = 0.1;
+
+/// Computes the delay before the next poll of a remote spec
+#[derive(Debug, Clone)]
+pub struct PollBackoff {
+    interval: Duration,
+    failures: u32,
+}
+
+impl PollBackoff {
+    /// Create a schedule polling every `interval` while the server is healthy
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            failures: 0,
+        }
+    }
+
+    /// Delay before the next poll, given the last response status and its
+    /// `Retry-After` header (if any)
+    pub fn next_delay(&mut self, status: u16, retry_after: Option<&str>) -> Duration {
+        if (200..400).contains(&status) {
+            self.failures = 0;
+            return jitter(self.interval);
+        }
+
+        self.failures = self.failures.saturating_add(1);
+
+        let server_hint = match status {
+            429 | 503 => retry_after.and_then(|v| parse_retry_after(v, SystemTime::now())),
+            _ => None,
+        };
+
+        // Only our own backoff is capped; never poll sooner than the server asked
+        let delay = server_hint.unwrap_or_else(|| {
+            let exponent = self.failures.min(16);
+            self.interval.saturating_mul(1 << exponent).min(MAX_DELAY)
+        });
+
+        // Only add jitter on top of the server's delay
+        if server_hint.is_some() {
+            delay + jitter(delay) / 10
+        } else {
+            jitter(delay)
+        }
+    }
+}
+
+/// Poll `url` every `interval` forever, calling `on_change` with each document
+/// that differs from the one before (starting from `current`, if known)
+///
+/// Failed polls are reported and slow the schedule down per [`PollBackoff`].
+pub fn poll(
+    url: &str,
+    interval: Duration,
+    max_bytes: usize,
+    auth: &Auth,
+    current: Option<&str>,
+    mut on_change: impl FnMut(String),
+) -> ! {
+    let mut backoff = PollBackoff::new(interval);
+    let mut last_hash = current.map(|text| crate::bundle::sha256_hex(text.as_bytes()));
+    loop {
+        let (status, retry_after) = match fetch(url, max_bytes, auth) {
+            Ok(content) => {
+                let hash = crate::bundle::sha256_hex(content.as_bytes());
+                let changed = last_hash.as_ref().is_some_and(|last| *last != hash);
+                last_hash = Some(hash);
+                if changed {
+                    on_change(content);
+                }
+                (200, None)
+            }
+            Err(FetchError::Status { status, retry_after, .. }) => {
+                eprintln!("Polling {} failed with HTTP {}", url, status);
+                (status, retry_after)
+            }
+            Err(error) => {
+                eprintln!("Polling failed: {:#}", anyhow::Error::from(error));
+                (0, None)
+            }
+        };
+        std::thread::sleep(backoff.next_delay(status, retry_after.as_deref()));
+    }
+}
+
+/// Parse a `Retry-After` value: delta-seconds or an HTTP-date
+pub fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+/// Randomize a delay by +/- `JITTER_RATIO`
+fn jitter(delay: Duration) -> Duration {
+    let factor = 1.0 + JITTER_RATIO * (fastrand::f64() * 2.0 - 1.0);
+    delay.mul_f64(factor)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_445_412_480);
+        assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:30:00 GMT", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_backoff_honors_retry_after_and_resets() {
+        let mut backoff = PollBackoff::new(Duration::from_secs(2));
+
+        let delay = backoff.next_delay(429, Some("30"));
+        assert!(delay >= Duration::from_secs(30) && delay <= Duration::from_secs(34));
+
+        let delay = backoff.next_delay(500, None);
+        assert!(delay >= Duration::from_secs(7) && delay <= Duration::from_secs(9));
+
+        let delay = backoff.next_delay(200, None);
+        assert!(delay >= Duration::from_millis(1800) && delay <= Duration::from_millis(2200));
+
+        // A long Retry-After is honored beyond the cap on our own backoff
+        let delay = backoff.next_delay(503, Some("3600"));
+        assert!(delay >= Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_auth_headers() {
+        let mut auth = Auth::default();
+        auth.basic("Aladdin", "open sesame");
+        auth.bearer("t0ken");
+        assert_eq!(auth.headers[0].1, "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+        assert_eq!(auth.headers[1].1, "Bearer t0ken");
+        assert_eq!(format!("{:?}", auth), r#"["Authorization", "Authorization"]"#);
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(
+            origin("https://api.example.com:8443/v1/spec.json"),
+            "https://api.example.com:8443"
+        );
+    }
+
+    /// Serve one canned HTTP response on a local port, returning its URL
+    #[cfg(feature = "remote")]
+    pub(crate) fn serve_once(response: &'static str) -> String {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/openapi.yaml", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        url
+    }
+
+    #[test]
+    #[cfg(feature = "remote")]
+    fn test_fetch() {
+        let url = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 14\r\n\r\nopenapi: 3.0.3");
+        assert_eq!(fetch(&url, 1024, &Auth::default()).unwrap(), "openapi: 3.0.3");
+
+        let url = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 14\r\n\r\nopenapi: 3.0.3");
+        assert!(matches!(fetch(&url, 8, &Auth::default()), Err(FetchError::Transport { .. })));
+
+        let url = serve_once("HTTP/1.1 503 Busy\r\nRetry-After: 30\r\nContent-Length: 0\r\n\r\n");
+        assert!(matches!(
+            fetch(&url, 1024, &Auth::default()),
+            Err(FetchError::Status { status: 503, retry_after: Some(ref after), .. })
+                if after == "30"
+        ));
+    }
+}
@@ -0,0 +1,624 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! External `$ref` resolution
+//!
+//! `openapiv3` and lowering only follow references within the document, so
+//! references into other documents (`./models/user.yaml#/User`,
+//! `https://example.com/common.yaml#/components/schemas/Error`) are resolved
+//! before the document is upgraded and deserialized. Each referenced
+//! document is loaded once, relative references resolving against the
+//! document they appear in. Remote documents are only fetched with
+//! [`Limits::allow_remote_refs`], or from the origin of a spec read from a URL.
+//!
+//! A reference in schema position imports its target into the root
+//! document's schemas, named after the last segment of its JSON pointer (or
+//! the file name) with a numeric suffix if that name is taken, and is
+//! rewritten to point there; recursive schemas and schemas shared by several
+//! references keep one named type. A root schema that only references
+//! another file (`User: {$ref: schemas/User.yaml}`) takes its target's place
+//! instead, so specs split into per-resource files keep their names. Any
+//! other reference (a parameter, response or path item) is replaced by a copy
+//! of its target, and cycles among those are errors. References from another
+//! document back into the root become local references.
+
+use crate::parser::{self, Limits};
+use crate::remote;
+use anyhow::{bail, Context, Result};
+use serde_yaml::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+/// Where a document is loaded from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Source {
+    File(PathBuf),
+    Url(String),
+    /// The root document given as text or a value, which has no location
+    Memory,
+}
+
+impl Source {
+    /// The document a reference's (non-fragment) part names, relative to this one
+    fn join(&self, relative: &str) -> Result<Source> {
+        if relative.contains("://") {
+            return Ok(Source::Url(relative.to_string()));
+        }
+        match self {
+            Source::File(path) => {
+                let dir = path.parent().unwrap_or(Path::new(""));
+                Ok(Source::File(normalize(&dir.join(relative))))
+            }
+            Source::Url(url) => Ok(Source::Url(join_url(url, relative))),
+            Source::Memory => bail!(
+                "$ref {:?} is relative, but the spec was not read from a file",
+                relative
+            ),
+        }
+    }
+
+    /// Schema name implied by the document alone, e.g. `user` for `models/user.yaml`
+    fn stem(&self) -> String {
+        let name = match self {
+            Source::File(path) => path.file_name().and_then(|n| n.to_str()).unwrap_or_default(),
+            Source::Url(url) => url.rsplit('/').next().unwrap_or_default(),
+            Source::Memory => "",
+        };
+        name.split('.').next().unwrap_or_default().to_string()
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Url(url) => write!(f, "{}", url),
+            Source::Memory => write!(f, "<spec>"),
+        }
+    }
+}
+
+/// Where a root document was read from, against which its relative
+/// references resolve
+#[derive(Debug, Clone, Copy)]
+pub enum Base<'a> {
+    File(&'a Path),
+    /// A spec URL, with the headers sent to its origin
+    Url(&'a str, &'a remote::Auth),
+    /// Text or a value in memory, which can only reference absolute URLs
+    Memory,
+}
+
+/// Resolve every external `$ref` in a root document
+pub fn resolve(document: &mut Value, base: Base, limits: &Limits) -> Result<()> {
+    let root = match base {
+        Base::File(path) => Source::File(normalize(&std::path::absolute(path)?)),
+        Base::Url(url, _) => Source::Url(url.to_string()),
+        Base::Memory => Source::Memory,
+    };
+    // Swagger 2.0 keeps schemas in `definitions`; its references are rewritten later
+    let swagger = document.get("swagger").is_some();
+
+    let mut resolver = Resolver {
+        root: root.clone(),
+        limits,
+        auth: match base {
+            Base::Url(url, auth) => Some((remote::origin(url).to_string(), auth)),
+            _ => None,
+        },
+        documents: HashMap::new(),
+        imported: HashMap::new(),
+        names: HashSet::new(),
+        schemas: Mapping::new(),
+        inlining: Vec::new(),
+        schema_prefix: if swagger { "#/definitions/" } else { "#/components/schemas/" },
+    };
+    if let Some(schemas) = schemas_mut(document, swagger) {
+        resolver.adopt(schemas)?;
+    }
+    resolver.resolve_value(document, &root, false)?;
+
+    if resolver.schemas.is_empty() {
+        return Ok(());
+    }
+    let Value::Mapping(map) = document else {
+        return Ok(());
+    };
+    let container = if swagger {
+        map
+    } else {
+        mapping_entry(map, "components")
+    };
+    mapping_entry(container, if swagger { "definitions" } else { "schemas" })
+        .extend(resolver.schemas);
+    Ok(())
+}
+
+struct Resolver<'a> {
+    root: Source,
+    limits: &'a Limits,
+    /// The spec URL's origin and the headers sent to it
+    auth: Option<(String, &'a remote::Auth)>,
+    /// Loaded documents
+    documents: HashMap<Source, Value>,
+    /// Imported schemas by `(document, pointer)`, with the name given to each
+    imported: HashMap<(Source, String), String>,
+    /// Schema names in use
+    names: HashSet<String>,
+    /// Imported schemas, added to the root once every reference is resolved
+    schemas: Mapping,
+    /// References being inlined, outermost first, to report cycles
+    inlining: Vec<String>,
+    schema_prefix: &'static str,
+}
+
+impl Resolver<'_> {
+    /// Resolve the references in a value from `source`
+    ///
+    /// `in_schema` is set once the walk enters a schema; values that are data
+    /// rather than schemas (examples, defaults, enums) are not walked.
+    fn resolve_value(&mut self, value: &mut Value, source: &Source, in_schema: bool) -> Result<()> {
+        match value {
+            Value::Mapping(map) => {
+                if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+                    let reference = reference.to_string();
+                    if let Some(resolved) = self.resolve_ref(&reference, source, in_schema)? {
+                        *value = resolved;
+                    }
+                    return Ok(());
+                }
+                for (key, child) in map.iter_mut() {
+                    match key.as_str() {
+                        Some("example" | "default" | "enum" | "const") => {}
+                        Some("examples") if in_schema => {}
+                        // Named schemas, and property names that could be keywords
+                        Some("schemas" | "definitions") if !in_schema => {
+                            self.resolve_each(child, source)?
+                        }
+                        Some("properties") if in_schema => self.resolve_each(child, source)?,
+                        Some("schema") => self.resolve_value(child, source, true)?,
+                        _ => self.resolve_value(child, source, in_schema)?,
+                    }
+                }
+                Ok(())
+            }
+            Value::Sequence(items) => {
+                for item in items {
+                    self.resolve_value(item, source, in_schema)?;
+                }
+                Ok(())
+            }
+            Value::Tagged(tagged) => self.resolve_value(&mut tagged.value, source, in_schema),
+            _ => Ok(()),
+        }
+    }
+
+    /// Resolve each value of a map of schemas
+    fn resolve_each(&mut self, value: &mut Value, source: &Source) -> Result<()> {
+        if let Value::Mapping(map) = value {
+            for schema in map.values_mut() {
+                self.resolve_value(schema, source, true)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Take over the root's named schemas, which may be kept in other files
+    ///
+    /// A map of schemas that is itself a `$ref` is replaced by its target. An
+    /// entry that only references a schema in another file is replaced by
+    /// that schema, and other references to it use the entry's name, so
+    /// per-file schemas keep the names the root gives them.
+    fn adopt(&mut self, schemas: &mut Value) -> Result<()> {
+        let mut source = self.root.clone();
+        let mut prefix = String::new();
+        if let Some(reference) = schemas.get("$ref").and_then(Value::as_str) {
+            let reference = reference.to_string();
+            let (target, pointer) = self.locate(&reference, &self.root.clone())?;
+            *schemas = self.target(&target, &pointer)?;
+            (source, prefix) = (target, pointer);
+        }
+        let Value::Mapping(schemas) = schemas else {
+            return Ok(());
+        };
+
+        let mut adopted = Vec::new();
+        for (name, schema) in schemas.iter() {
+            let Some(name) = name.as_str() else {
+                continue;
+            };
+            self.names.insert(name.to_string());
+            if source != self.root {
+                let key = (source.clone(), format!("{}/{}", prefix, escape(name)));
+                self.imported.insert(key, name.to_string());
+            }
+            let Some(reference) = schema.get("$ref").and_then(Value::as_str) else {
+                continue;
+            };
+            let key = self.locate(reference, &source)?;
+            if key.0 != self.root && !self.imported.contains_key(&key) {
+                self.imported.insert(key.clone(), name.to_string());
+                adopted.push((name.to_string(), key));
+            }
+        }
+
+        // Entries from another file resolve against it; adopted ones are replaced below
+        if source != self.root {
+            for (name, schema) in schemas.iter_mut() {
+                if !adopted.iter().any(|(adopted, _)| name.as_str() == Some(adopted)) {
+                    self.resolve_value(schema, &source, true)?;
+                }
+            }
+        }
+        for (name, (target, pointer)) in adopted {
+            let mut schema = self.target(&target, &pointer)?;
+            self.resolve_value(&mut schema, &target, true)?;
+            schemas.insert(name.into(), schema);
+        }
+        Ok(())
+    }
+
+    /// The document and JSON pointer a reference from `source` names
+    fn locate(&self, reference: &str, source: &Source) -> Result<(Source, String)> {
+        let (document, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+        let target = if document.is_empty() {
+            source.clone()
+        } else {
+            source.join(document)?
+        };
+        Ok((target, pointer.to_string()))
+    }
+
+    /// What a `$ref` mapping is replaced with, `None` to keep it
+    fn resolve_ref(
+        &mut self,
+        reference: &str,
+        source: &Source,
+        in_schema: bool,
+    ) -> Result<Option<Value>> {
+        let (target, pointer) = self.locate(reference, source)?;
+        let pointer = pointer.as_str();
+
+        if target == self.root {
+            // Local references in the root already resolve
+            return Ok((*source != self.root || !reference.starts_with('#'))
+                .then(|| reference_to(format!("#{}", pointer))));
+        }
+
+        if in_schema {
+            let name = self.import(&target, pointer)?;
+            return Ok(Some(reference_to(format!("{}{}", self.schema_prefix, name))));
+        }
+
+        let key = format!("{}#{}", target, pointer);
+        if self.inlining.contains(&key) {
+            bail!("Cyclic $ref: {} -> {}", self.inlining.join(" -> "), key);
+        }
+        let mut value = self.target(&target, pointer)?;
+        self.inlining.push(key);
+        self.resolve_value(&mut value, &target, false)?;
+        self.inlining.pop();
+        Ok(Some(value))
+    }
+
+    /// Import a schema from another document, returning its name in the root
+    fn import(&mut self, source: &Source, pointer: &str) -> Result<String> {
+        let key = (source.clone(), pointer.to_string());
+        if let Some(name) = self.imported.get(&key) {
+            return Ok(name.clone());
+        }
+
+        let base = match pointer.rsplit('/').next().map(unescape) {
+            Some(segment) if !segment.is_empty() => segment,
+            _ => source.stem(),
+        };
+        let base = if base.is_empty() { "Schema".to_string() } else { base };
+        let name = (1..)
+            .map(|n| if n == 1 { base.clone() } else { format!("{}{}", base, n) })
+            .find(|name| !self.names.contains(name))
+            .unwrap_or(base);
+        self.names.insert(name.clone());
+        // Registered before resolving the schema itself, so recursion ends here
+        self.imported.insert(key, name.clone());
+
+        let mut schema = self.target(source, pointer)?;
+        self.resolve_value(&mut schema, source, true)?;
+        self.schemas.insert(name.as_str().into(), schema);
+        Ok(name)
+    }
+
+    /// A copy of the value a JSON pointer names in a document
+    fn target(&mut self, source: &Source, pointer: &str) -> Result<Value> {
+        let document = self.load(source)?;
+        let mut value = document;
+        for segment in pointer.split('/').skip(1) {
+            let segment = unescape(segment);
+            let next = match value {
+                Value::Mapping(map) => map.get(segment.as_str()),
+                Value::Sequence(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => None,
+            };
+            match next {
+                Some(next) => value = next,
+                None => bail!("$ref target {}#{} does not exist", source, pointer),
+            }
+        }
+        Ok(value.clone())
+    }
+
+    /// A document, loading it on first use
+    fn load(&mut self, source: &Source) -> Result<&Value> {
+        if !self.documents.contains_key(source) {
+            let content = match source {
+                Source::File(path) => read_file(path, self.limits)?,
+                Source::Url(url) => {
+                    let no_auth = remote::Auth::default();
+                    let auth = match &self.auth {
+                        Some((origin, auth)) if remote::origin(url) == origin => auth,
+                        _ if self.limits.allow_remote_refs => &no_auth,
+                        _ => bail!(
+                            "$ref document {} is remote; fetching it needs --allow-remote-refs",
+                            url
+                        ),
+                    };
+                    remote::fetch(url, self.limits.max_spec_bytes, auth)?
+                }
+                Source::Memory => bail!("The in-memory spec can't be referenced"),
+            };
+            // YAML is a superset of JSON, so this reads either
+            let value: Value = serde_yaml::from_str(&content)
+                .with_context(|| format!("Failed to parse $ref document {}", source))?;
+            parser::check_yaml_depth(&value, self.limits)?;
+            self.documents.insert(source.clone(), value);
+        }
+        Ok(&self.documents[source])
+    }
+}
+
+/// A `{$ref: reference}` mapping
+fn reference_to(reference: String) -> Value {
+    let mut map = Mapping::new();
+    map.insert("$ref".into(), reference.into());
+    Value::Mapping(map)
+}
+
+/// The root document's map of named schemas, if it has one
+fn schemas_mut(document: &mut Value, swagger: bool) -> Option<&mut Value> {
+    match swagger {
+        true => document.get_mut("definitions"),
+        false => document.get_mut("components")?.get_mut("schemas"),
+    }
+}
+
+/// The mapping under `key`, created if missing or not a mapping
+fn mapping_entry<'m>(map: &'m mut Mapping, key: &str) -> &'m mut Mapping {
+    if !map.get(key).is_some_and(Value::is_mapping) {
+        map.insert(key.into(), Value::Mapping(Mapping::new()));
+    }
+    match map.get_mut(key) {
+        Some(Value::Mapping(entry)) => entry,
+        _ => unreachable!("inserted above"),
+    }
+}
+
+/// Decode a JSON pointer segment (`~1` is `/`, `~0` is `~`)
+fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+/// Encode a JSON pointer segment
+fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+/// Remove `.` and `..` components without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(normalized.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Resolve a relative reference against a URL
+fn join_url(base: &str, relative: &str) -> String {
+    let base = base.split(['?', '#']).next().unwrap_or(base);
+    let path_start = base
+        .find("://")
+        .and_then(|scheme_end| base[scheme_end + 3..].find('/').map(|i| scheme_end + 3 + i))
+        .unwrap_or(base.len());
+    let (origin, path) = base.split_at(path_start);
+
+    let joined = match relative.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => {
+            let dir = path.rfind('/').map_or("", |i| &path[..i + 1]);
+            format!("{}{}", dir.trim_start_matches('/'), relative)
+        }
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    format!("{}/{}", origin, segments.join("/"))
+}
+
+fn read_file(path: &Path, limits: &Limits) -> Result<String> {
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read $ref document {:?}", path))?
+        .len();
+    if size > limits.max_spec_bytes as u64 {
+        bail!(
+            "$ref document {:?} is {} bytes, exceeding the limit of {} bytes",
+            path,
+            size,
+            limits.max_spec_bytes
+        );
+    }
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read $ref document {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_url() {
+        let base = "https://example.com/specs/v1/openapi.yaml";
+        assert_eq!(join_url(base, "common.yaml"), "https://example.com/specs/v1/common.yaml");
+        assert_eq!(join_url(base, "../shared/a.yaml"), "https://example.com/specs/shared/a.yaml");
+        assert_eq!(join_url(base, "/root.yaml"), "https://example.com/root.yaml");
+        assert_eq!(join_url("https://example.com", "a.yaml"), "https://example.com/a.yaml");
+    }
+
+    #[test]
+    fn test_resolve_file_refs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("models")).unwrap();
+        std::fs::write(
+            dir.path().join("models/user.yaml"),
+            r##"
+User:
+  type: object
+  properties:
+    address: {$ref: "#/Address"}
+    friends: {type: array, items: {$ref: "#/User"}}
+    error: {$ref: "../openapi.yaml#/components/schemas/Error"}
+Address:
+  type: object
+  properties:
+    city: {type: string}
+"##,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("params.yaml"),
+            "in: query\nname: limit\nschema: {type: integer}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("openapi.yaml"),
+            r##"
+openapi: 3.0.3
+info: {title: T, version: "1"}
+paths:
+  /users:
+    get:
+      parameters:
+        - $ref: params.yaml
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema: {$ref: "./models/user.yaml#/User"}
+components:
+  schemas:
+    Error: {type: object}
+    Address: {type: string}
+"##,
+        )
+        .unwrap();
+
+        let spec = parser::parse_spec(&dir.path().join("openapi.yaml")).unwrap();
+        let json = serde_json::to_value(&spec).unwrap();
+        let schemas = &json["components"]["schemas"];
+        assert_eq!(
+            schemas["User"]["properties"]["address"]["$ref"],
+            "#/components/schemas/Address2"
+        );
+        assert_eq!(
+            schemas["User"]["properties"]["friends"]["items"]["$ref"],
+            "#/components/schemas/User"
+        );
+        assert_eq!(
+            schemas["User"]["properties"]["error"]["$ref"],
+            "#/components/schemas/Error"
+        );
+        assert_eq!(schemas["Address2"]["properties"]["city"]["type"], "string");
+        let get = &json["paths"]["/users"]["get"];
+        assert_eq!(get["parameters"][0]["name"], "limit");
+        assert_eq!(
+            get["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/User"
+        );
+    }
+
+    #[test]
+    fn test_resolve_reports_cycles_and_missing_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.yaml"), "$ref: b.yaml\n").unwrap();
+        std::fs::write(dir.path().join("b.yaml"), "$ref: a.yaml\n").unwrap();
+        let spec = |reference: &str| {
+            format!(
+                "openapi: 3.0.3\ninfo: {{title: T, version: '1'}}\npaths:\n  /a: {{$ref: '{}'}}\n",
+                reference
+            )
+        };
+
+        std::fs::write(dir.path().join("cycle.yaml"), spec("a.yaml")).unwrap();
+        let error = parser::parse_spec(&dir.path().join("cycle.yaml")).unwrap_err();
+        assert!(format!("{:#}", error).contains("Cyclic $ref"), "{:#}", error);
+
+        std::fs::write(dir.path().join("missing.yaml"), spec("a.yaml#/nope")).unwrap();
+        let error = parser::parse_spec(&dir.path().join("missing.yaml")).unwrap_err();
+        assert!(format!("{:#}", error).contains("does not exist"), "{:#}", error);
+
+        let error = parser::parse_spec_content(&spec("a.yaml"), &Limits::default()).unwrap_err();
+        assert!(format!("{:#}", error).contains("not read from a file"), "{:#}", error);
+    }
+
+    #[test]
+    fn test_resolve_remote_refs_need_opt_in() {
+        let spec = "openapi: 3.0.3\ninfo: {title: T, version: '1'}\npaths: {}\ncomponents:\n  schemas:\n    User: {$ref: 'http://127.0.0.1:9/user.yaml'}\n";
+
+        let error = parser::parse_spec_content(spec, &Limits::default()).unwrap_err();
+        assert!(format!("{:#}", error).contains("needs --allow-remote-refs"), "{:#}", error);
+
+        // Allowed, the reference is fetched (and fails, as nothing listens there)
+        let limits = Limits {
+            allow_remote_refs: true,
+            ..Limits::default()
+        };
+        let error = parser::parse_spec_content(spec, &limits).unwrap_err();
+        assert!(!format!("{:#}", error).contains("--allow-remote-refs"), "{:#}", error);
+    }
+
+    #[test]
+    fn test_resolve_schema_map_in_another_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("schemas.yaml"),
+            "User:\n  properties:\n    address: {$ref: '#/Address'}\nAddress: {type: object}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("openapi.yaml"),
+            "openapi: 3.0.3\ninfo: {title: T, version: '1'}\npaths: {}\ncomponents:\n  schemas: {$ref: schemas.yaml}\n",
+        )
+        .unwrap();
+
+        let spec = parser::parse_spec(&dir.path().join("openapi.yaml")).unwrap();
+        let json = serde_json::to_value(&spec).unwrap();
+        let schemas = &json["components"]["schemas"];
+        assert_eq!(schemas.as_object().unwrap().len(), 2);
+        assert_eq!(
+            schemas["User"]["properties"]["address"]["$ref"],
+            "#/components/schemas/Address"
+        );
+    }
+}
@@ -0,0 +1,495 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Interactive spec browser for `info --tui` and `validate --tui`
+//!
+//! Shows tags -> operations -> parameters, request body and responses, then
+//! component schemas -> properties, with validation and lowering diagnostics
+//! counted next to the elements they concern and listed in the detail pane.
+
+use anyhow::Result;
+use openapiv3::{OpenAPI, Operation, ReferenceOr, Schema, SchemaKind, Type, VariantOrUnknownOrEmpty};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use rescript_openapi::parser::{self, Diagnostic, Severity};
+
+/// An element of the spec tree
+#[derive(Debug)]
+struct Node {
+    label: String,
+    /// Diagnostic path prefix for this element, e.g. `paths./pets.get`
+    location: Option<String>,
+    /// Lines shown in the detail pane
+    details: Vec<String>,
+    children: Vec<Node>,
+    expanded: bool,
+}
+
+impl Node {
+    fn new(label: impl Into<String>, location: Option<String>) -> Self {
+        Self {
+            label: label.into(),
+            location,
+            details: Vec::new(),
+            children: Vec::new(),
+            expanded: false,
+        }
+    }
+}
+
+struct App {
+    roots: Vec<Node>,
+    diagnostics: Vec<Diagnostic>,
+    list: ListState,
+}
+
+/// Browse a spec until the user quits
+pub fn run(spec: &OpenAPI, diagnostics: Vec<Diagnostic>) -> Result<()> {
+    let mut app = App {
+        roots: build_tree(spec),
+        diagnostics,
+        list: ListState::default().with_selected(Some(0)),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        let rows = visible_rows(&app.roots);
+        let selected = app.list.selected().unwrap_or(0).min(rows.len().saturating_sub(1));
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Down | KeyCode::Char('j') => app.list.select(Some((selected + 1).min(rows.len() - 1))),
+            KeyCode::Up | KeyCode::Char('k') => app.list.select(Some(selected.saturating_sub(1))),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Enter => {
+                let node = node_mut(&mut app.roots, &rows[selected].1);
+                if node.children.is_empty() {
+                    continue;
+                }
+                // Enter toggles; right arrow only expands
+                node.expanded = !(node.expanded && key.code == KeyCode::Enter);
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                let path = &rows[selected].1;
+                let node = node_mut(&mut app.roots, path);
+                if node.expanded {
+                    node.expanded = false;
+                } else if path.len() > 1 {
+                    // Jump to the parent
+                    let parent = &path[..path.len() - 1];
+                    if let Some(index) = rows.iter().position(|(_, p)| p == parent) {
+                        app.list.select(Some(index));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [main, footer] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+    let [tree, detail] =
+        Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)]).areas(main);
+
+    let rows = visible_rows(&app.roots);
+    let items: Vec<ListItem> = rows
+        .iter()
+        .map(|(depth, path)| {
+            let node = node_at(&app.roots, path);
+            let marker = match (node.children.is_empty(), node.expanded) {
+                (true, _) => "  ",
+                (false, true) => "▾ ",
+                (false, false) => "▸ ",
+            };
+            let mut spans = vec![
+                Span::raw("  ".repeat(*depth)),
+                Span::raw(marker),
+                Span::raw(node.label.clone()),
+            ];
+            spans.extend(badge(&matching(&app.diagnostics, node)));
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Spec"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, tree, &mut app.list);
+
+    let selected = app.list.selected().unwrap_or(0);
+    let lines = match rows.get(selected) {
+        Some((_, path)) => detail_lines(node_at(&app.roots, path), &app.diagnostics),
+        None => Vec::new(),
+    };
+    let details = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Details"))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(details, detail);
+
+    let help = format!(
+        " ↑↓ move  →/Enter expand  ← collapse  q quit  |  {}",
+        parser::summarize(&app.diagnostics)
+    );
+    frame.render_widget(Paragraph::new(help), footer);
+}
+
+fn detail_lines<'a>(node: &'a Node, diagnostics: &'a [Diagnostic]) -> Vec<Line<'a>> {
+    let mut lines: Vec<Line> = vec![Line::styled(
+        node.label.as_str(),
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    lines.extend(node.details.iter().map(|d| Line::raw(d.as_str())));
+
+    let found = matching(diagnostics, node);
+    if !found.is_empty() {
+        lines.push(Line::raw(""));
+        for diagnostic in found {
+            let color = match diagnostic.severity {
+                Severity::Error => Color::Red,
+                Severity::Warning => Color::Yellow,
+            };
+            lines.push(Line::styled(diagnostic.to_string(), Style::default().fg(color)));
+        }
+    }
+    lines
+}
+
+/// `✗n ⚠m` counts after a label, if the element has diagnostics
+fn badge(diagnostics: &[&Diagnostic]) -> Vec<Span<'static>> {
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    let warnings = diagnostics.len() - errors;
+    let mut spans = Vec::new();
+    if errors > 0 {
+        spans.push(Span::styled(format!(" ✗{}", errors), Style::default().fg(Color::Red)));
+    }
+    if warnings > 0 {
+        spans.push(Span::styled(format!(" ⚠{}", warnings), Style::default().fg(Color::Yellow)));
+    }
+    spans
+}
+
+/// Diagnostics located at or below a node
+///
+/// Group nodes without a location (tags, the schema list) collect those of
+/// their children.
+fn matching<'a>(diagnostics: &'a [Diagnostic], node: &Node) -> Vec<&'a Diagnostic> {
+    diagnostics
+        .iter()
+        .filter(|d| {
+            let Some(path) = &d.path else {
+                return false;
+            };
+            match &node.location {
+                Some(location) => is_within(path, location),
+                None => node
+                    .children
+                    .iter()
+                    .filter_map(|c| c.location.as_deref())
+                    .any(|location| is_within(path, location)),
+            }
+        })
+        .collect()
+}
+
+fn is_within(path: &str, location: &str) -> bool {
+    path == location
+        || path
+            .strip_prefix(location)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Depth and child-index path of every row currently shown
+fn visible_rows(roots: &[Node]) -> Vec<(usize, Vec<usize>)> {
+    fn walk(nodes: &[Node], prefix: &[usize], rows: &mut Vec<(usize, Vec<usize>)>) {
+        for (index, node) in nodes.iter().enumerate() {
+            let mut path = prefix.to_vec();
+            path.push(index);
+            rows.push((prefix.len(), path.clone()));
+            if node.expanded {
+                walk(&node.children, &path, rows);
+            }
+        }
+    }
+    let mut rows = Vec::new();
+    walk(roots, &[], &mut rows);
+    rows
+}
+
+fn node_at<'a>(roots: &'a [Node], path: &[usize]) -> &'a Node {
+    let mut node = &roots[path[0]];
+    for &index in &path[1..] {
+        node = &node.children[index];
+    }
+    node
+}
+
+fn node_mut<'a>(roots: &'a mut [Node], path: &[usize]) -> &'a mut Node {
+    let mut node = &mut roots[path[0]];
+    for &index in &path[1..] {
+        node = &mut node.children[index];
+    }
+    node
+}
+
+/// Tags with their operations, then component schemas
+fn build_tree(spec: &OpenAPI) -> Vec<Node> {
+    let mut tags: Vec<Node> = spec
+        .tags
+        .iter()
+        .map(|tag| {
+            let mut node = Node::new(format!("tag {}", tag.name), None);
+            node.details.extend(tag.description.clone());
+            node
+        })
+        .collect();
+    let mut untagged = Node::new("untagged", None);
+
+    for (path, method, op) in spec.operations() {
+        let operation = operation_node(path, method, op);
+        let tag = op.tags.first().map(|name| format!("tag {}", name));
+        match tag {
+            Some(label) => match tags.iter_mut().find(|t| t.label == label) {
+                Some(node) => node.children.push(operation),
+                None => {
+                    // Tags used by operations but not declared at the top level
+                    let mut node = Node::new(label, None);
+                    node.children.push(operation);
+                    tags.push(node);
+                }
+            },
+            None => untagged.children.push(operation),
+        }
+    }
+
+    let mut roots: Vec<Node> = tags.into_iter().filter(|t| !t.children.is_empty()).collect();
+    if !untagged.children.is_empty() {
+        roots.push(untagged);
+    }
+    for root in &mut roots {
+        root.label = format!("{} ({})", root.label, root.children.len());
+    }
+
+    let schemas = spec.components.iter().flat_map(|c| c.schemas.iter());
+    let mut schema_list = Node::new("schemas", None);
+    for (name, schema) in schemas {
+        schema_list.children.push(schema_node(name, schema));
+    }
+    schema_list.label = format!("schemas ({})", schema_list.children.len());
+    roots.push(schema_list);
+    roots
+}
+
+fn operation_node(path: &str, method: &str, op: &Operation) -> Node {
+    let mut label = format!("{} {}", method.to_uppercase(), path);
+    if let Some(id) = &op.operation_id {
+        label.push_str(&format!("  {}", id));
+    }
+    let mut node = Node::new(label, Some(format!("paths.{}.{}", path, method)));
+    node.details.extend(op.summary.clone());
+    node.details.extend(op.description.clone());
+    if op.deprecated {
+        node.details.push("Deprecated".to_string());
+    }
+
+    for param in &op.parameters {
+        let ReferenceOr::Item(param) = param else {
+            node.children.push(Node::new(format!("param {}", ref_name(param)), None));
+            continue;
+        };
+        let data = param.parameter_data_ref();
+        let kind = match param {
+            openapiv3::Parameter::Query { .. } => "query",
+            openapiv3::Parameter::Header { .. } => "header",
+            openapiv3::Parameter::Path { .. } => "path",
+            openapiv3::Parameter::Cookie { .. } => "cookie",
+        };
+        let ty = match &data.format {
+            openapiv3::ParameterSchemaOrContent::Schema(schema) => schema_summary(schema),
+            openapiv3::ParameterSchemaOrContent::Content(_) => "content".to_string(),
+        };
+        let required = if data.required { ", required" } else { "" };
+        let mut child = Node::new(
+            format!("{} {}: {}{}", kind, data.name, ty, required),
+            Some(format!("paths.{}.{}.parameters.{}", path, method, data.name)),
+        );
+        child.details.extend(data.description.clone());
+        node.children.push(child);
+    }
+
+    if let Some(ReferenceOr::Item(body)) = &op.request_body {
+        for (media_type, media) in &body.content {
+            let ty = media.schema.as_ref().map(schema_summary).unwrap_or_default();
+            let mut child = Node::new(format!("body {}: {}", media_type, ty), None);
+            child.details.extend(body.description.clone());
+            node.children.push(child);
+        }
+    }
+
+    let responses = op
+        .responses
+        .responses
+        .iter()
+        .map(|(status, response)| (status.to_string(), response))
+        .chain(op.responses.default.iter().map(|r| ("default".to_string(), r)));
+    for (status, response) in responses {
+        let label = match response {
+            ReferenceOr::Item(response) => {
+                let ty = response
+                    .content
+                    .get("application/json")
+                    .and_then(|media| media.schema.as_ref())
+                    .map(|schema| format!(": {}", schema_summary(schema)))
+                    .unwrap_or_default();
+                let mut child = Node::new(format!("{}{}", status, ty), None);
+                child.details.push(response.description.clone());
+                child.details.extend(response.content.keys().cloned());
+                node.children.push(child);
+                continue;
+            }
+            ReferenceOr::Reference { reference } => format!("{}: {}", status, reference),
+        };
+        node.children.push(Node::new(label, None));
+    }
+
+    node
+}
+
+fn schema_node(name: &str, schema: &ReferenceOr<Schema>) -> Node {
+    let location = format!("components.schemas.{}", name);
+    let mut node = Node::new(
+        format!("{}: {}", name, schema_summary(schema)),
+        Some(location.clone()),
+    );
+    let ReferenceOr::Item(schema) = schema else {
+        return node;
+    };
+    node.details.extend(schema.schema_data.description.clone());
+
+    if let SchemaKind::Type(Type::Object(object)) = &schema.schema_kind {
+        for (prop_name, prop) in &object.properties {
+            let required = if object.required.contains(prop_name) { ", required" } else { "" };
+            let ty = match prop {
+                ReferenceOr::Item(schema) => schema_summary(&ReferenceOr::Item((**schema).clone())),
+                ReferenceOr::Reference { reference } => short_ref(reference).to_string(),
+            };
+            let mut child = Node::new(
+                format!("{}: {}{}", prop_name, ty, required),
+                Some(format!("{}.properties.{}", location, prop_name)),
+            );
+            if let Some(description) = prop.as_item().and_then(|s| s.schema_data.description.clone()) {
+                child.details.push(description);
+            }
+            node.children.push(child);
+        }
+    }
+    node
+}
+
+/// Short type description, e.g. `array<Pet>` or `integer (int64)`
+fn schema_summary(schema: &ReferenceOr<Schema>) -> String {
+    let schema = match schema {
+        ReferenceOr::Reference { reference } => return short_ref(reference).to_string(),
+        ReferenceOr::Item(schema) => schema,
+    };
+    match &schema.schema_kind {
+        SchemaKind::Type(Type::String(s)) if !s.enumeration.is_empty() => {
+            let values: Vec<&str> = s.enumeration.iter().flatten().map(String::as_str).collect();
+            format!("enum [{}]", values.join(", "))
+        }
+        SchemaKind::Type(Type::String(s)) => with_format(&s.format, "string"),
+        SchemaKind::Type(Type::Integer(i)) => with_format(&i.format, "integer"),
+        SchemaKind::Type(Type::Number(n)) => with_format(&n.format, "number"),
+        SchemaKind::Type(Type::Boolean(_)) => "boolean".to_string(),
+        SchemaKind::Type(Type::Array(array)) => {
+            let items = match &array.items {
+                Some(ReferenceOr::Item(items)) => schema_summary(&ReferenceOr::Item((**items).clone())),
+                Some(ReferenceOr::Reference { reference }) => short_ref(reference).to_string(),
+                None => "any".to_string(),
+            };
+            format!("array<{}>", items)
+        }
+        SchemaKind::Type(Type::Object(_)) => "object".to_string(),
+        SchemaKind::OneOf { one_of } => format!("oneOf ({})", one_of.len()),
+        SchemaKind::AnyOf { any_of } => format!("anyOf ({})", any_of.len()),
+        SchemaKind::AllOf { all_of } => format!("allOf ({})", all_of.len()),
+        SchemaKind::Not { .. } => "not".to_string(),
+        SchemaKind::Any(any) if !any.enumeration.is_empty() => {
+            let values: Vec<String> = any.enumeration.iter().map(|v| v.to_string()).collect();
+            format!("enum [{}]", values.join(", "))
+        }
+        SchemaKind::Any(_) => "any".to_string(),
+    }
+}
+
+/// `integer (int64)`, or just `integer` without a format
+fn with_format<F: std::fmt::Debug>(format: &VariantOrUnknownOrEmpty<F>, name: &str) -> String {
+    match format {
+        VariantOrUnknownOrEmpty::Empty => name.to_string(),
+        VariantOrUnknownOrEmpty::Unknown(f) => format!("{} ({})", name, f),
+        VariantOrUnknownOrEmpty::Item(f) => format!("{} ({:?})", name, f).to_lowercase(),
+    }
+}
+
+fn ref_name<T>(reference: &ReferenceOr<T>) -> &str {
+    match reference {
+        ReferenceOr::Reference { reference } => short_ref(reference),
+        ReferenceOr::Item(_) => "",
+    }
+}
+
+/// `#/components/schemas/Pet` -> `Pet`
+fn short_ref(reference: &str) -> &str {
+    reference.rsplit('/').next().unwrap_or(reference)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_tree_groups_operations_and_matches_diagnostics() {
+        let spec = parser::parse_spec(Path::new("tests/fixtures/petstore.yaml")).unwrap();
+        let mut roots = build_tree(&spec);
+
+        let labels: Vec<&str> = roots.iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(labels, vec!["tag pet (5)", "tag store (4)", "tag user (3)", "schemas (6)"]);
+
+        // Only roots are shown until a node is expanded
+        assert_eq!(visible_rows(&roots).len(), 4);
+        roots[0].expanded = true;
+        assert_eq!(visible_rows(&roots).len(), 9);
+        assert_eq!(node_at(&roots, &[0, 0]).label, "PUT /pet  updatePet");
+
+        let diagnostic = Diagnostic {
+            severity: Severity::Warning,
+            message: "int64 integer mapped to 32-bit int".to_string(),
+            path: Some("components.schemas.Pet.properties.id".to_string()),
+        };
+        let diagnostics = [diagnostic];
+        let schemas = &roots[3];
+        let pet = schemas.children.iter().find(|n| n.label.starts_with("Pet:")).unwrap();
+        assert_eq!(matching(&diagnostics, pet).len(), 1);
+        assert_eq!(matching(&diagnostics, schemas).len(), 1);
+        assert!(matching(&diagnostics, &roots[0]).is_empty());
+        assert!(!is_within("components.schemas.PetType", "components.schemas.Pet"));
+    }
+}
@@ -0,0 +1,842 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// SPDX-FileCopyrightText: 2025 Hyperpolymath
+
+//! Swagger 2.0 and OpenAPI 3.1 documents rewritten into the 3.0 shape
+//! `openapiv3` models
+//!
+//! Swagger 2.0 documents are converted wholesale: `definitions` become
+//! `components.schemas`, body and form parameters a `requestBody`, response
+//! schemas `content` for each `produces` media type, and so on.
+//!
+//! 3.1 adopts JSON Schema 2020-12, whose keywords `openapiv3` either rejects
+//! (`type: [string, "null"]`, numeric `exclusiveMinimum`) or silently drops
+//! (`const`, `prefixItems`, `webhooks`). Before deserializing, each is turned
+//! into its 3.0 equivalent or, where there is none, moved to an `x-`
+//! extension that lowering reads. 3.0 documents pass through unchanged.
+
+use serde_yaml::{Mapping, Value};
+
+/// Extension holding an OpenAPI 3.1 `prefixItems` list of tuple item schemas
+pub const PREFIX_ITEMS_EXTENSION: &str = "x-prefixItems";
+
+/// Root extension holding the OpenAPI 3.1 `webhooks` map of path items
+pub const WEBHOOKS_EXTENSION: &str = "x-webhooks";
+
+/// Schema keywords without a 3.0 equivalent, with the extension each is moved to
+const LIFTED_KEYWORDS: &[(&str, &str)] = &[("prefixItems", PREFIX_ITEMS_EXTENSION)];
+
+/// Rewrite a parsed document in place
+///
+/// Must run after the document's depth has been checked, which bounds the
+/// recursion.
+pub fn upgrade(document: &mut Value) {
+    let is_version = |document: &Value, key: &str, prefix: &str| {
+        document.get(key).and_then(Value::as_str).is_some_and(|v| v.starts_with(prefix))
+    };
+    if is_version(document, "swagger", "2.") {
+        upgrade_swagger(document);
+    }
+    if !is_version(document, "openapi", "3.1.") {
+        return;
+    }
+    let Value::Mapping(root) = document else { return };
+    if let Some(webhooks) = root.remove("webhooks") {
+        root.insert(WEBHOOKS_EXTENSION.into(), webhooks);
+    }
+    // Only the default dialect is understood, and webhook-only documents have no paths
+    root.remove("jsonSchemaDialect");
+    if !root.contains_key("paths") {
+        root.insert("paths".into(), Value::Mapping(Mapping::new()));
+    }
+
+    for key in ["paths", WEBHOOKS_EXTENSION] {
+        if let Some(Value::Mapping(items)) = root.get_mut(key) {
+            items.values_mut().for_each(upgrade_path_item);
+        }
+    }
+    let Some(Value::Mapping(components)) = root.get_mut("components") else { return };
+    let each = |components: &mut Mapping, key: &str, upgrade: fn(&mut Value)| {
+        if let Some(Value::Mapping(items)) = components.get_mut(key) {
+            items.values_mut().for_each(upgrade);
+        }
+    };
+    each(components, "schemas", upgrade_schemas);
+    each(components, "parameters", upgrade_parameter_schemas);
+    each(components, "headers", upgrade_parameter_schemas);
+    each(components, "requestBodies", upgrade_content);
+    each(components, "responses", upgrade_response_schemas);
+    each(components, "callbacks", upgrade_callback);
+    each(components, "pathItems", upgrade_path_item);
+}
+
+/// Rewrite the schemas of a path item's parameters and operations
+fn upgrade_path_item(item: &mut Value) {
+    let Value::Mapping(item) = item else { return };
+    for (key, child) in item.iter_mut() {
+        match key.as_str() {
+            Some("parameters") => each_item(child, upgrade_parameter_schemas),
+            Some(method) if OPERATION_METHODS.contains(&method) || method == "trace" => {
+                upgrade_operation_schemas(child)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn upgrade_operation_schemas(operation: &mut Value) {
+    if let Some(parameters) = operation.get_mut("parameters") {
+        each_item(parameters, upgrade_parameter_schemas);
+    }
+    if let Some(body) = operation.get_mut("requestBody") {
+        upgrade_content(body);
+    }
+    if let Some(Value::Mapping(responses)) = operation.get_mut("responses") {
+        responses.values_mut().for_each(upgrade_response_schemas);
+    }
+    if let Some(Value::Mapping(callbacks)) = operation.get_mut("callbacks") {
+        callbacks.values_mut().for_each(upgrade_callback);
+    }
+}
+
+/// A callback maps runtime expressions to path items
+fn upgrade_callback(callback: &mut Value) {
+    if let Value::Mapping(items) = callback {
+        items.values_mut().for_each(upgrade_path_item);
+    }
+}
+
+/// A parameter or header: a `schema`, or one in `content`
+fn upgrade_parameter_schemas(parameter: &mut Value) {
+    if let Some(schema) = parameter.get_mut("schema") {
+        upgrade_schemas(schema);
+    }
+    upgrade_content(parameter);
+}
+
+fn upgrade_response_schemas(response: &mut Value) {
+    upgrade_content(response);
+    if let Some(Value::Mapping(headers)) = response.get_mut("headers") {
+        headers.values_mut().for_each(upgrade_parameter_schemas);
+    }
+}
+
+/// The schema of each media type in a request body, response or parameter's `content`
+fn upgrade_content(value: &mut Value) {
+    if let Some(Value::Mapping(content)) = value.get_mut("content") {
+        for media in content.values_mut() {
+            if let Some(schema) = media.get_mut("schema") {
+                upgrade_schemas(schema);
+            }
+        }
+    }
+}
+
+fn each_item(value: &mut Value, upgrade: fn(&mut Value)) {
+    if let Value::Sequence(items) = value {
+        items.iter_mut().for_each(upgrade);
+    }
+}
+
+/// Rewrite a schema and every schema nested in it
+///
+/// Only subschema keywords are followed, so data (examples, defaults, enums)
+/// and names (properties, `$defs`) are left alone.
+fn upgrade_schemas(schema: &mut Value) {
+    let Value::Mapping(map) = schema else { return };
+    upgrade_schema(map);
+    for (key, child) in map.iter_mut() {
+        match key.as_str() {
+            Some(
+                "items" | "additionalProperties" | "not" | "if" | "then" | "else" | "contains"
+                | "propertyNames" | "unevaluatedItems" | "unevaluatedProperties",
+            ) => upgrade_schemas(child),
+            Some("allOf" | "anyOf" | "oneOf" | PREFIX_ITEMS_EXTENSION) => {
+                each_item(child, upgrade_schemas)
+            }
+            Some("properties" | "patternProperties" | "$defs" | "dependentSchemas") => {
+                if let Value::Mapping(schemas) = child {
+                    schemas.values_mut().for_each(upgrade_schemas);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Rewrite the keywords of one schema
+fn upgrade_schema(map: &mut Mapping) {
+    // `const: v` is the one-value `enum: [v]`
+    if !map.contains_key("enum") {
+        if let Some(value) = map.remove("const") {
+            map.insert("enum".into(), Value::Sequence(vec![value]));
+        }
+    }
+
+    for (keyword, extension) in LIFTED_KEYWORDS {
+        if !map.contains_key(*extension) {
+            if let Some(lifted) = map.remove(*keyword) {
+                map.insert((*extension).into(), lifted);
+            }
+        }
+    }
+    // `items: false` closes a tuple; 3.0 only allows a schema there
+    if map.contains_key(PREFIX_ITEMS_EXTENSION) && map.get("items").is_some_and(Value::is_bool) {
+        map.remove("items");
+    }
+
+    upgrade_type(map);
+    upgrade_null_member(map, "anyOf");
+    upgrade_null_member(map, "oneOf");
+
+    // Numeric `exclusiveMinimum: n` is 3.0's `minimum: n, exclusiveMinimum: true`
+    for (exclusive, bound) in [("exclusiveMinimum", "minimum"), ("exclusiveMaximum", "maximum")] {
+        if let Some(value) = map.get(exclusive).filter(|v| v.is_number()).cloned() {
+            map.insert(bound.into(), value);
+            map.insert(exclusive.into(), true.into());
+        }
+    }
+
+    // Schema `examples` is a list; 3.0 schemas have a single `example`
+    if let Some(Value::Sequence(examples)) = map.get("examples") {
+        let first = examples.first().cloned();
+        map.remove("examples");
+        if let Some(first) = first.filter(|_| !map.contains_key("example")) {
+            map.insert("example".into(), first);
+        }
+    }
+}
+
+/// `type: [string, "null"]` as `type: string, nullable: true`
+///
+/// Several non-null types become an `anyOf` with one member per type.
+fn upgrade_type(map: &mut Mapping) {
+    let types: Vec<String> = match map.get("type") {
+        Some(Value::Sequence(types)) => {
+            types.iter().filter_map(Value::as_str).map(str::to_string).collect()
+        }
+        Some(Value::String(ty)) if ty == "null" => vec![ty.clone()],
+        _ => return,
+    };
+    map.remove("type");
+
+    let (nulls, types): (Vec<String>, Vec<String>) = types.into_iter().partition(|t| t == "null");
+    if !nulls.is_empty() {
+        map.insert("nullable".into(), true.into());
+    }
+    match types.as_slice() {
+        [] => {}
+        [ty] => {
+            map.insert("type".into(), ty.as_str().into());
+        }
+        _ if map.contains_key("anyOf") || map.contains_key("oneOf") => {}
+        _ => {
+            let members = types
+                .iter()
+                .map(|ty| {
+                    let mut member = Mapping::new();
+                    member.insert("type".into(), ty.as_str().into());
+                    Value::Mapping(member)
+                })
+                .collect();
+            map.insert("anyOf".into(), Value::Sequence(members));
+        }
+    }
+}
+
+/// `anyOf: [X, {type: "null"}]` as a nullable `allOf: [X]`
+///
+/// That's how 3.1 makes a `$ref` nullable; other unions just lose the member
+/// and become nullable.
+fn upgrade_null_member(map: &mut Mapping, keyword: &str) {
+    let Some(Value::Sequence(members)) = map.get_mut(keyword) else {
+        return;
+    };
+    let is_null = |member: &Value| {
+        let ty = member.get("type");
+        member.as_mapping().is_some_and(|m| m.len() == 1)
+            && (ty.and_then(Value::as_str) == Some("null")
+                || ty.and_then(Value::as_sequence).is_some_and(|t| t.len() == 1 && t[0] == "null"))
+    };
+    let before = members.len();
+    members.retain(|member| !is_null(member));
+    if members.len() == before {
+        return;
+    }
+    if let [member] = members.as_slice() {
+        let member = member.clone();
+        map.remove(keyword);
+        if !map.contains_key("allOf") {
+            map.insert("allOf".into(), Value::Sequence(vec![member]));
+        }
+    }
+    map.insert("nullable".into(), true.into());
+}
+
+/// Schema keywords Swagger 2.0 puts directly on non-body parameters and headers
+const PARAMETER_SCHEMA_KEYWORDS: &[&str] = &[
+    "type",
+    "format",
+    "items",
+    "default",
+    "maximum",
+    "exclusiveMaximum",
+    "minimum",
+    "exclusiveMinimum",
+    "maxLength",
+    "minLength",
+    "pattern",
+    "maxItems",
+    "minItems",
+    "uniqueItems",
+    "enum",
+    "multipleOf",
+];
+
+const OPERATION_METHODS: &[&str] = &["get", "put", "post", "delete", "options", "head", "patch"];
+
+/// Convert a Swagger 2.0 document into OpenAPI 3.0
+fn upgrade_swagger(document: &mut Value) {
+    let Value::Mapping(root) = document else { return };
+    root.remove("swagger");
+    root.insert("openapi".into(), "3.0.3".into());
+
+    let host = root.remove("host");
+    let base_path = root.remove("basePath");
+    let schemes = root.remove("schemes");
+    if let Some(servers) = swagger_servers(host, base_path, schemes) {
+        root.insert("servers".into(), servers);
+    }
+
+    let json = || vec!["application/json".to_string()];
+    let consumes = media_types(root.remove("consumes")).unwrap_or_else(json);
+    let produces = media_types(root.remove("produces")).unwrap_or_else(json);
+    let globals = match root.remove("parameters") {
+        Some(Value::Mapping(parameters)) => parameters,
+        _ => Mapping::new(),
+    };
+
+    let mut components = Mapping::new();
+    if let Some(definitions) = root.remove("definitions") {
+        components.insert("schemas".into(), definitions);
+    }
+    // Body and form parameters are inlined into each operation's `requestBody`
+    let parameters: Mapping = globals
+        .iter()
+        .filter(|(_, parameter)| !is_payload_parameter(parameter))
+        .map(|(name, parameter)| (name.clone(), upgrade_parameter(parameter)))
+        .collect();
+    if !parameters.is_empty() {
+        components.insert("parameters".into(), Value::Mapping(parameters));
+    }
+    if let Some(Value::Mapping(mut responses)) = root.remove("responses") {
+        responses.values_mut().for_each(|response| upgrade_response(response, &produces));
+        components.insert("responses".into(), Value::Mapping(responses));
+    }
+    if let Some(Value::Mapping(mut schemes)) = root.remove("securityDefinitions") {
+        schemes.values_mut().for_each(upgrade_security_scheme);
+        components.insert("securitySchemes".into(), Value::Mapping(schemes));
+    }
+    if !components.is_empty() {
+        root.insert("components".into(), Value::Mapping(components));
+    }
+
+    if let Some(Value::Mapping(paths)) = root.get_mut("paths") {
+        for item in paths.values_mut() {
+            let Value::Mapping(item) = item else { continue };
+            // Path-level parameters are pushed down, as body and form ones must be
+            let inherited = match item.remove("parameters") {
+                Some(Value::Sequence(parameters)) => parameters,
+                _ => Vec::new(),
+            };
+            for method in OPERATION_METHODS {
+                if let Some(Value::Mapping(operation)) = item.get_mut(*method) {
+                    let context = OperationContext {
+                        consumes: &consumes,
+                        produces: &produces,
+                        globals: &globals,
+                        inherited: &inherited,
+                    };
+                    upgrade_operation(operation, &context);
+                }
+            }
+        }
+    }
+
+    upgrade_swagger_schemas(document);
+}
+
+/// `servers` from `host`, `basePath` and `schemes`
+fn swagger_servers(
+    host: Option<Value>,
+    base_path: Option<Value>,
+    schemes: Option<Value>,
+) -> Option<Value> {
+    let base_path = base_path.as_ref().and_then(Value::as_str).unwrap_or_default();
+    let urls: Vec<String> = match host.as_ref().and_then(Value::as_str) {
+        Some(host) => {
+            let schemes = media_types(schemes).unwrap_or_else(|| vec!["https".to_string()]);
+            schemes.iter().map(|scheme| format!("{}://{}{}", scheme, host, base_path)).collect()
+        }
+        None if !base_path.is_empty() => vec![base_path.to_string()],
+        None => return None,
+    };
+    let servers = urls
+        .into_iter()
+        .map(|url| {
+            let mut server = Mapping::new();
+            server.insert("url".into(), url.into());
+            Value::Mapping(server)
+        })
+        .collect();
+    Some(Value::Sequence(servers))
+}
+
+/// A list of strings such as `consumes`, or `None` if absent or empty
+fn media_types(value: Option<Value>) -> Option<Vec<String>> {
+    let Some(Value::Sequence(items)) = value else {
+        return None;
+    };
+    let items: Vec<String> = items.iter().filter_map(Value::as_str).map(str::to_string).collect();
+    (!items.is_empty()).then_some(items)
+}
+
+/// Document-wide settings an operation falls back to
+struct OperationContext<'a> {
+    consumes: &'a [String],
+    produces: &'a [String],
+    /// Root `parameters`, which `$ref`s point into
+    globals: &'a Mapping,
+    /// Parameters declared on the path item
+    inherited: &'a [Value],
+}
+
+fn upgrade_operation(operation: &mut Mapping, context: &OperationContext) {
+    let consumes = media_types(operation.remove("consumes"))
+        .unwrap_or_else(|| context.consumes.to_vec());
+    let produces = media_types(operation.remove("produces"))
+        .unwrap_or_else(|| context.produces.to_vec());
+
+    let parameters = match operation.remove("parameters") {
+        Some(Value::Sequence(parameters)) => parameters,
+        _ => Vec::new(),
+    };
+    // Referenced parameters are inlined; the operation's own override the path's
+    let key = |p: &Value| (p.get("name").cloned(), p.get("in").cloned());
+    let parameters: Vec<&Value> = parameters
+        .iter()
+        .map(|p| resolve_parameter(p, context.globals))
+        .collect();
+    let inherited = context
+        .inherited
+        .iter()
+        .map(|p| resolve_parameter(p, context.globals))
+        .filter(|p| parameters.iter().all(|own| key(own) != key(p)));
+
+    let mut kept = Vec::new();
+    let mut body = None;
+    let mut form_properties = Mapping::new();
+    let mut form_required = Vec::new();
+    for resolved in inherited.chain(parameters.iter().copied()) {
+        match resolved.get("in").and_then(Value::as_str) {
+            Some("body") => body = Some(resolved.clone()),
+            Some("formData") => {
+                let Some(name) = resolved.get("name").cloned() else { continue };
+                if resolved.get("required").and_then(Value::as_bool) == Some(true) {
+                    form_required.push(name.clone());
+                }
+                form_properties.insert(name, parameter_schema(resolved));
+            }
+            _ => kept.push(upgrade_parameter(resolved)),
+        }
+    }
+    if !kept.is_empty() {
+        operation.insert("parameters".into(), Value::Sequence(kept));
+    }
+
+    let request_body = match body {
+        Some(body) => {
+            let schema = body.get("schema").cloned().unwrap_or(Value::Mapping(Mapping::new()));
+            let mut request_body = Mapping::new();
+            if let Some(description) = body.get("description") {
+                request_body.insert("description".into(), description.clone());
+            }
+            request_body.insert("content".into(), content(&consumes, &schema, None));
+            if let Some(required) = body.get("required") {
+                request_body.insert("required".into(), required.clone());
+            }
+            Some(request_body)
+        }
+        None if !form_properties.is_empty() => {
+            let has_file = form_properties
+                .values()
+                .any(|schema| schema.get("format").and_then(Value::as_str) == Some("binary"));
+            let media_type = if has_file || consumes.iter().any(|c| c == "multipart/form-data") {
+                "multipart/form-data"
+            } else {
+                "application/x-www-form-urlencoded"
+            };
+            let mut schema = Mapping::new();
+            schema.insert("type".into(), "object".into());
+            schema.insert("properties".into(), Value::Mapping(form_properties));
+            if !form_required.is_empty() {
+                schema.insert("required".into(), Value::Sequence(form_required));
+            }
+            let mut request_body = Mapping::new();
+            let content = content(&[media_type.to_string()], &Value::Mapping(schema), None);
+            request_body.insert("content".into(), content);
+            Some(request_body)
+        }
+        None => None,
+    };
+    if let Some(request_body) = request_body {
+        operation.insert("requestBody".into(), Value::Mapping(request_body));
+    }
+
+    if let Some(Value::Mapping(responses)) = operation.get_mut("responses") {
+        responses.values_mut().for_each(|response| upgrade_response(response, &produces));
+    }
+}
+
+/// The parameter a `#/parameters/...` reference points to, or the parameter itself
+fn resolve_parameter<'a>(parameter: &'a Value, globals: &'a Mapping) -> &'a Value {
+    parameter
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix("#/parameters/"))
+        .and_then(|name| globals.get(name))
+        .unwrap_or(parameter)
+}
+
+fn is_payload_parameter(parameter: &Value) -> bool {
+    matches!(parameter.get("in").and_then(Value::as_str), Some("body" | "formData"))
+}
+
+/// A path, query or header parameter with its type keywords moved into `schema`
+fn upgrade_parameter(parameter: &Value) -> Value {
+    let Value::Mapping(parameter) = parameter else {
+        return parameter.clone();
+    };
+    if parameter.contains_key("$ref") {
+        return Value::Mapping(parameter.clone());
+    }
+    let mut upgraded = Mapping::new();
+    for (key, value) in parameter {
+        let keyword = key.as_str().unwrap_or_default();
+        if !PARAMETER_SCHEMA_KEYWORDS.contains(&keyword) && keyword != "collectionFormat" {
+            upgraded.insert(key.clone(), value.clone());
+        }
+    }
+    upgraded.insert("schema".into(), parameter_schema(&Value::Mapping(parameter.clone())));
+
+    // Arrays are comma-separated unless `collectionFormat` says otherwise
+    let is_array = parameter.get("type").and_then(Value::as_str) == Some("array");
+    let is_query = parameter.get("in").and_then(Value::as_str) == Some("query");
+    if is_array && is_query {
+        let (style, explode) = match parameter.get("collectionFormat").and_then(Value::as_str) {
+            Some("multi") => ("form", true),
+            Some("ssv") => ("spaceDelimited", false),
+            Some("pipes") => ("pipeDelimited", false),
+            _ => ("form", false),
+        };
+        upgraded.insert("style".into(), style.into());
+        upgraded.insert("explode".into(), explode.into());
+    }
+    Value::Mapping(upgraded)
+}
+
+/// The schema of a non-body parameter or header, from the keywords on it
+fn parameter_schema(parameter: &Value) -> Value {
+    let mut schema = Mapping::new();
+    for keyword in PARAMETER_SCHEMA_KEYWORDS {
+        if let Some(value) = parameter.get(*keyword) {
+            schema.insert((*keyword).into(), value.clone());
+        }
+    }
+    if let Some(Value::Mapping(items)) = schema.get_mut("items") {
+        items.remove("collectionFormat");
+    }
+    Value::Mapping(schema)
+}
+
+/// `content` with the same schema for each media type
+fn content(media_types: &[String], schema: &Value, examples: Option<&Mapping>) -> Value {
+    let content = media_types
+        .iter()
+        .map(|media_type| {
+            let mut media = Mapping::new();
+            media.insert("schema".into(), schema.clone());
+            if let Some(example) = examples.and_then(|e| e.get(media_type.as_str())) {
+                media.insert("example".into(), example.clone());
+            }
+            (media_type.as_str().into(), Value::Mapping(media))
+        })
+        .collect();
+    Value::Mapping(content)
+}
+
+fn upgrade_response(response: &mut Value, produces: &[String]) {
+    let Value::Mapping(response) = response else { return };
+    let examples = match response.remove("examples") {
+        Some(Value::Mapping(examples)) => Some(examples),
+        _ => None,
+    };
+    if let Some(schema) = response.remove("schema") {
+        response.insert("content".into(), content(produces, &schema, examples.as_ref()));
+    }
+    if let Some(Value::Mapping(headers)) = response.get_mut("headers") {
+        for header in headers.values_mut() {
+            let mut upgraded = Mapping::new();
+            if let Some(description) = header.get("description") {
+                upgraded.insert("description".into(), description.clone());
+            }
+            upgraded.insert("schema".into(), parameter_schema(header));
+            *header = Value::Mapping(upgraded);
+        }
+    }
+}
+
+fn upgrade_security_scheme(scheme: &mut Value) {
+    let Value::Mapping(scheme) = scheme else { return };
+    match scheme.get("type").and_then(Value::as_str) {
+        Some("basic") => {
+            scheme.insert("type".into(), "http".into());
+            scheme.insert("scheme".into(), "basic".into());
+        }
+        Some("oauth2") => {
+            let flow = match scheme.remove("flow").as_ref().and_then(Value::as_str) {
+                Some("implicit") => "implicit",
+                Some("password") => "password",
+                Some("application") => "clientCredentials",
+                _ => "authorizationCode",
+            };
+            let mut settings = Mapping::new();
+            for key in ["authorizationUrl", "tokenUrl", "scopes"] {
+                if let Some(value) = scheme.remove(key) {
+                    settings.insert(key.into(), value);
+                }
+            }
+            if !settings.contains_key("scopes") {
+                settings.insert("scopes".into(), Value::Mapping(Mapping::new()));
+            }
+            let mut flows = Mapping::new();
+            flows.insert(flow.into(), Value::Mapping(settings));
+            scheme.insert("flows".into(), Value::Mapping(flows));
+        }
+        _ => {}
+    }
+}
+
+/// Point `$ref`s at components, and rewrite schema keywords that changed shape
+fn upgrade_swagger_schemas(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            if let Some(Value::String(reference)) = map.get_mut("$ref") {
+                for (old, new) in [
+                    ("#/definitions/", "#/components/schemas/"),
+                    ("#/parameters/", "#/components/parameters/"),
+                    ("#/responses/", "#/components/responses/"),
+                ] {
+                    if reference.contains(old) {
+                        *reference = reference.replacen(old, new, 1);
+                    }
+                }
+            }
+            // A 2.0 discriminator is just the property name
+            if let Some(Value::String(property)) = map.get("discriminator") {
+                let mut discriminator = Mapping::new();
+                discriminator.insert("propertyName".into(), property.as_str().into());
+                map.insert("discriminator".into(), Value::Mapping(discriminator));
+            }
+            if map.get("type").and_then(Value::as_str) == Some("file") {
+                map.insert("type".into(), "string".into());
+                map.insert("format".into(), "binary".into());
+            }
+            for (key, child) in map.iter_mut() {
+                if !matches!(key.as_str(), Some("example" | "examples" | "default" | "enum")) {
+                    upgrade_swagger_schemas(child);
+                }
+            }
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(upgrade_swagger_schemas),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upgraded(yaml: &str) -> Value {
+        let mut value: Value = serde_yaml::from_str(yaml).unwrap();
+        upgrade(&mut value);
+        value
+    }
+
+    #[test]
+    fn test_upgrade_schema_keywords() {
+        let value = upgraded(
+            r##"
+openapi: 3.1.0
+jsonSchemaDialect: https://spec.openapis.org/oas/3.1/dialect/base
+webhooks: { newPet: {} }
+components:
+  schemas:
+    A: { type: [string, "null"], examples: [a, b] }
+    B: { type: [string, integer] }
+    C: { anyOf: [{ $ref: "#/components/schemas/A" }, { type: "null" }] }
+    D: { type: integer, exclusiveMinimum: 0, const: 1 }
+    E:
+      type: object
+      properties:
+        const: { type: string, example: { type: [a, "null"] } }
+"##,
+        );
+        let expected: Value = serde_yaml::from_str(
+            r##"
+openapi: 3.1.0
+components:
+  schemas:
+    A: { nullable: true, type: string, example: a }
+    B: { anyOf: [{ type: string }, { type: integer }] }
+    C: { allOf: [{ $ref: "#/components/schemas/A" }], nullable: true }
+    D: { type: integer, exclusiveMinimum: true, enum: [1], minimum: 0 }
+    E:
+      type: object
+      properties:
+        const: { type: string, example: { type: [a, "null"] } }
+x-webhooks: { newPet: {} }
+paths: {}
+"##,
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn test_upgrade_schema_positions_only() {
+        let value = upgraded(
+            r##"
+openapi: 3.1.0
+paths:
+  /things:
+    get:
+      parameters:
+        - { name: id, in: query, schema: { type: [integer, "null"] } }
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema: { type: array, items: { const: 1 } }
+              examples: { one: { value: [1] } }
+      x-meta: { const: 1, type: [a, b] }
+components:
+  schemas:
+    const: { type: object, properties: { type: { type: [string, "null"] } } }
+"##,
+        );
+        let expected: Value = serde_yaml::from_str(
+            r##"
+openapi: 3.1.0
+paths:
+  /things:
+    get:
+      parameters:
+        - { name: id, in: query, schema: { type: integer, nullable: true } }
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema: { type: array, items: { enum: [1] } }
+              examples: { one: { value: [1] } }
+      x-meta: { const: 1, type: [a, b] }
+components:
+  schemas:
+    const: { type: object, properties: { type: { type: string, nullable: true } } }
+"##,
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn test_upgrade_skips_openapi_3_0() {
+        let yaml = r##"
+openapi: 3.0.3
+webhooks: { newPet: {} }
+components:
+  schemas:
+    A: { const: 1, examples: [a] }
+"##;
+        let original: Value = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(upgraded(yaml), original);
+    }
+
+    #[test]
+    fn test_upgrade_swagger() {
+        let value = upgraded(
+            r##"
+swagger: "2.0"
+info: { title: T, version: "1" }
+paths:
+  /pets:
+    post:
+      parameters:
+        - { name: pet, in: body, required: true, schema: { $ref: "#/definitions/Pet" } }
+        - { name: ids, in: query, type: array, items: { type: integer }, collectionFormat: pipes }
+      responses:
+        "200": { description: OK, schema: { type: file }, examples: { application/json: 1 } }
+  /photos:
+    post:
+      consumes: [multipart/form-data]
+      parameters:
+        - { name: file, in: formData, type: file, required: true }
+      responses: {}
+definitions:
+  Pet: { type: object, discriminator: kind, properties: { kind: { type: string } } }
+"##,
+        );
+        let expected: Value = serde_yaml::from_str(
+            r##"
+openapi: 3.0.3
+info: { title: T, version: "1" }
+paths:
+  /pets:
+    post:
+      parameters:
+        - name: ids
+          in: query
+          schema: { type: array, items: { type: integer } }
+          style: pipeDelimited
+          explode: false
+      requestBody:
+        content: { application/json: { schema: { $ref: "#/components/schemas/Pet" } } }
+        required: true
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json: { schema: { type: string, format: binary }, example: 1 }
+  /photos:
+    post:
+      requestBody:
+        content:
+          multipart/form-data:
+            schema:
+              type: object
+              properties: { file: { type: string, format: binary } }
+              required: [file]
+      responses: {}
+components:
+  schemas:
+    Pet:
+      type: object
+      discriminator: { propertyName: kind }
+      properties: { kind: { type: string } }
+"##,
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+    }
+}
@@ -3,19 +3,21 @@
 
 //! Snapshot tests for code generation
 
-use rescript_openapi::{codegen, ir, parser};
+use rescript_openapi::{build, bundle, codegen, filter, ir, manifest, parser};
 use std::path::{Path, PathBuf};
 
+fn test_config() -> codegen::Config {
+    codegen::Config {
+        output_dir: PathBuf::from("/tmp"),
+        ..Default::default()
+    }
+}
+
 fn generate_from_spec(spec_path: &str) -> (String, String, String) {
     let spec = parser::parse_spec(Path::new(spec_path)).expect("Failed to parse spec");
     let api = ir::lower(&spec).expect("Failed to lower spec");
 
-    let config = codegen::Config {
-        output_dir: PathBuf::from("/tmp"),
-        module_prefix: "Api".to_string(),
-        generate_schema: true,
-        generate_client: true,
-    };
+    let config = test_config();
 
     let types = codegen::types::generate(&api, &config).expect("Failed to generate types");
     let schema = codegen::schema::generate(&api, &config).expect("Failed to generate schema");
@@ -42,6 +44,602 @@ fn test_petstore_client() {
     insta::assert_snapshot!("petstore_client", client);
 }
 
+#[test]
+fn test_petstore_auth() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/petstore.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let auth = codegen::auth::generate(&api, &test_config()).expect("Failed to generate auth");
+    insta::assert_snapshot!("petstore_auth", auth);
+
+    // makeConfig takes a token store and asks it for a bearer token
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+    assert!(client.contains("  ~tokenStore: option<Auth.tokenStore>=?,\n"));
+    assert!(client.contains(
+        "  let getBearerToken = switch tokenStore {\n  | Some(store) => getBearerToken->Option.orElse(Some(() => store->Auth.getToken))\n"
+    ));
+    assert!(client.contains("    bearerToken: switch (getBearerToken, bearerToken) {\n    | (Some(getToken), _) => Some(getToken)\n"));
+}
+
+#[test]
+fn test_petstore_spy() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/petstore.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let spy = codegen::spy::generate(&api, &test_config()).expect("Failed to generate spy");
+    insta::assert_snapshot!("petstore_spy", spy);
+}
+
+#[test]
+fn test_spy_mock_responses() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/complex.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let spy = codegen::spy::generate(&api, &test_config()).expect("Failed to generate spy");
+
+    assert!(spy.contains("let setLatency = (~minMs: int, ~maxMs: int=minMs, ()): unit =>"));
+    assert!(spy.contains("let setErrorRate = (rate: float): unit =>"));
+    assert!(spy.contains(
+        r#"  let response: result<JSON.t, apiError> = await respond("getUser", ~example=JSON.parseExn("{\"email\":\"ada@example.com\",\"id\":\"7f3c9a52-1c1e-4f3b-9d4e-2a1b5c6d7e8f\",\"profile\":{\"displayName\":\"Ada\"}}"), ~errors=[{status: 404, example: Some(JSON.parseExn("{\"code\":\"not_found\",\"message\":\"User not found\"}"))}], ())"#
+    ));
+    // Examples and stubs are parsed like real responses, not cast
+    assert!(spy.contains("  | Ok(res) =>\n    try {\n      Ok(parseUser(res.body))\n"));
+    assert!(!spy.contains("Obj.magic)\n  | Some(Error"));
+}
+
+#[test]
+fn test_petstore_meta() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/petstore.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let meta = codegen::meta::generate(&api, &test_config()).expect("Failed to generate meta");
+    insta::assert_snapshot!("petstore_meta", meta);
+}
+
+#[test]
+fn test_server_variables() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Servers, version: "1.0.0" }
+servers:
+  - url: https://{region}.api.example.com/{basePath}
+    description: Production server
+    variables:
+      region:
+        default: us-east
+        enum: [us-east, eu-west]
+        description: Data centre, see */regions
+      basePath:
+        default: v1
+  - url: http://localhost:{port}
+    description: Local
+    variables:
+      port: { default: "8080" }
+  - url: /fallback
+paths: {}
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let meta = codegen::meta::generate(&api, &test_config()).expect("Failed to generate meta");
+
+    assert!(meta.contains("  type t = [#Production | #Local | #Server3]"));
+    assert!(meta.contains(
+        r#"  let production = (~region: [#"us-east" | #"eu-west"]=#"us-east", ~basePath: string="v1", ()): string => `https://${(region :> string)}.api.example.com/${basePath}`"#
+    ));
+    assert!(meta.contains(
+        "  /**\n   * Production server\n   *\n   * `https://{region}.api.example.com/{basePath}`\n   * - `~region`: Data centre, see * /regions\n   */\n"
+    ));
+    assert!(meta.contains(
+        r#"  let local = (~port: string="8080", ()): string => `http://localhost:${port}`"#
+    ));
+    assert!(meta.contains("  let server3 = (): string => `/fallback`"));
+    assert!(meta.contains("    | #Local => local()"));
+    assert!(meta.contains("  let all: array<t> = [#Production, #Local, #Server3]"));
+}
+
+#[test]
+fn test_request_scope_cancellation() {
+    let (_, _, client) = generate_from_spec("tests/fixtures/petstore.yaml");
+
+    // Requests sent with a scope run on a signal of the scope's, chained to the caller's
+    assert!(client.contains(
+        "      | Some(scope) =>\n        await scope->RequestScope.track(~signal=?req.signal, signal =>\n          send({...req, signal: Some(signal)})\n        )\n"
+    ));
+    assert!(client.contains("    | Some(signal) if signal->aborted => controller->abort\n    | Some(signal) => signal->onAbort(() => controller->abort)\n"));
+    // Settled requests are released, and abortAll only aborts those still running
+    assert!(client.contains("    switch await run(controller->abortSignal) {\n    | result =>\n      release()\n"));
+    assert!(client.contains(
+        "    let running = scope.controllers->Array.copy\n    scope.controllers->Array.splice(~start=0, ~remove=running->Array.length, ~insert=[])\n    running->Array.forEach(abort)\n"
+    ));
+    // fetch gets the signal, and its AbortError becomes `kind: Aborted`
+    assert!(client.contains("      | Some(signal) => {...init, signal: signal->Obj.magic}\n"));
+    assert!(client.contains(
+        "    | Exn.Error(e) if Exn.name(e) == Some(\"AbortError\") =>\n      Error({status: 0, message: \"Request aborted\", body: None, kind: Aborted})\n"
+    ));
+    assert!(client.contains("  ~scope: option<RequestScope.t>=?,\n"));
+}
+
+#[test]
+fn test_petstore_client_instance() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/petstore.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let config = codegen::Config {
+        client_surface: codegen::ClientSurface::Instance,
+        ..test_config()
+    };
+    let client = codegen::client::generate(&api, &config).expect("Failed to generate client");
+    insta::assert_snapshot!("petstore_client_instance", client);
+}
+
+#[test]
+fn test_client_instance_group_names() {
+    let yaml = r#"
+openapi: 3.0.3
+info: {title: Groups, version: "1.0"}
+paths:
+  /types:
+    get:
+      tags: [type]
+      operationId: listTypes
+      responses:
+        '204': {description: Listed}
+  /codes:
+    get:
+      tags: [2fa]
+      operationId: listCodes
+      responses:
+        '204': {description: Listed}
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let config = codegen::Config {
+        client_surface: codegen::ClientSurface::Instance,
+        ..test_config()
+    };
+    let client = codegen::client::generate(&api, &config).unwrap();
+
+    assert!(client.contains("  type type_Api = {\n"));
+    assert!(client.contains("    type_: type_Api,\n"));
+    assert!(client.contains("  type _2faApi = {\n"));
+    assert!(client.contains("    _2fa: _2faApi,\n"));
+}
+
+#[test]
+fn test_custom_imports() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/petstore.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let config = codegen::Config {
+        imports: codegen::Imports {
+            core_module: None,
+            schema_module: "Vendor.Schema.S".to_string(),
+            namespace: Some("Shop".to_string()),
+            extra_opens: vec!["Prelude".to_string()],
+        },
+        ..test_config()
+    };
+
+    let schema = codegen::schema::generate(&api, &config).unwrap();
+    assert!(schema.contains("open Prelude\nopen Shop.ApiTypes\n\nmodule S = Vendor.Schema.S\n"));
+
+    let client = codegen::client::generate(&api, &config).unwrap();
+    assert!(!client.contains("open RescriptCore"));
+    assert!(client.contains("open Shop.ApiSchema\n\nmodule Meta = Shop.ApiMeta\n"));
+}
+
+#[test]
+fn test_bundle_is_reproducible() {
+    let path = "tests/fixtures/petstore.yaml";
+    let source = std::fs::read_to_string(path).unwrap();
+    // The same document reformatted, with its top-level keys in reverse order
+    let value: serde_yaml::Mapping = serde_yaml::from_str(&source).unwrap();
+    let reordered: serde_yaml::Mapping =
+        value.into_iter().collect::<Vec<_>>().into_iter().rev().collect();
+    let reordered = serde_yaml::to_string(&reordered).unwrap();
+    let config = serde_json::json!({ "module": "Api" });
+
+    let bundle = |source: &str| {
+        let generated = build::generate(&build::Options::new(build::Input::Text(source.to_string()))).unwrap();
+        let outputs: Vec<(String, String)> =
+            generated.files.into_iter().map(|f| (f.filename, f.content)).collect();
+        let bundle = bundle::Bundle::new(path, source, &generated.spec, config.clone(), &outputs).unwrap();
+        (bundle, outputs)
+    };
+    let (first, outputs) = bundle(&source);
+    let (second, _) = bundle(&reordered);
+
+    // Only the source text and its hash tell the two apart
+    assert_ne!(first.spec.sha256, second.spec.sha256);
+    assert_eq!(first.spec.sha256, bundle::sha256_hex(source.as_bytes()));
+    assert_eq!(first.spec.effective, second.spec.effective);
+    assert_eq!(
+        serde_json::to_string(&first.outputs).unwrap(),
+        serde_json::to_string(&second.outputs).unwrap()
+    );
+    assert_eq!(first.outputs[0].sha256, bundle::sha256_hex(outputs[0].1.as_bytes()));
+}
+
+#[test]
+fn test_swagger_conversion() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/swagger.yaml")).unwrap();
+    assert_eq!(spec.openapi, "3.0.3");
+    assert_eq!(spec.servers[0].url, "https://petstore.example.com/v1");
+    let schemes = &spec.components.as_ref().unwrap().security_schemes;
+    assert!(matches!(
+        schemes["basic"].as_item(),
+        Some(openapiv3::SecurityScheme::HTTP { scheme, .. }) if scheme == "basic"
+    ));
+
+    let (types, _, client) = generate_from_spec("tests/fixtures/swagger.yaml");
+    insta::assert_snapshot!("swagger_types", types);
+    // Referenced and path-level parameters are inlined into each operation
+    assert!(client.contains("let listPets = async (config: config, ~limit: option<int>=?, ~tags: option<array<string>>=?, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ())"));
+    assert!(client.contains("let createPet = async (config: config, ~body: newPet, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ())"));
+    assert!(client.contains("let uploadPhoto = async (config: config, ~petId: string, ~body: uploadPhotoForm, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ())"));
+}
+
+#[test]
+fn test_binary_download() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Files, version: "1.0.0" }
+paths:
+  /files/{id}:
+    get:
+      operationId: downloadFile
+      parameters:
+        - { name: id, in: path, required: true, schema: { type: string } }
+      responses:
+        '200':
+          description: The file
+          content:
+            application/octet-stream:
+              schema: { type: string, format: binary }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let options = ir::LowerOptions {
+        binary_responses: ir::BinaryResponses::ArrayBuffer,
+        ..Default::default()
+    };
+    let api = ir::lower_with_options(&spec, &options).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains("): result<rawBody<ArrayBuffer.t>, apiError> => {"));
+    // Requested as a Blob, which any transport can return, and read into an ArrayBuffer
+    assert!(client.contains("      responseType: #blob,"));
+    assert!(client.contains("let readArrayBuffer = async (response: promise<result<httpResponse, apiError>>) =>"));
+    assert!(client.contains("    switch await readArrayBuffer(sendRequest(config, ~timeoutMs?, req)) {"));
+    assert!(client.contains(
+        r#"      Ok({contentType: res.headers->headerValue("Content-Type"), body: res.body->Obj.magic})"#
+    ));
+}
+
+#[test]
+fn test_streaming_variant() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Exports, version: "1.0.0" }
+paths:
+  /exports/{id}:
+    get:
+      operationId: downloadExport
+      x-rescript-stream: true
+      parameters:
+        - { name: id, in: path, required: true, schema: { type: string } }
+      responses:
+        '200':
+          description: The export
+          content:
+            text/csv:
+              schema: { type: string }
+  /exports:
+    get:
+      operationId: listExports
+      responses:
+        '200':
+          description: Exports
+          content:
+            application/json:
+              schema: { type: array, items: { type: string } }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains(
+        "let forEachChunk = async (\n  stream: option<readableStream>,\n  onChunk: Uint8Array.t => unit,\n): result<unit, apiError> =>"
+    ));
+    // No body (a 204) reads as empty; a dropped connection is an `Error`, not an exception
+    assert!(client.contains("  switch stream {\n  | None => Ok()\n"));
+    assert!(client.contains("body == null ? undefined : "));
+    assert!(client.contains(
+        "      | exception Exn.Error(e) =>\n        finished := Some(Error({status: 0, message: Exn.message(e)->Option.getOr(\"Network error\"), body: None}))\n"
+    ));
+    assert!(client.contains(
+        "  let downloadExportStream = async (config: config, ~id: string, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ~onChunk: Uint8Array.t => unit, ()): result<unit, apiError> => {"
+    ));
+    assert!(client.contains(
+        "    let req = {...req, responseType: #blob, stream: true}\n\n    switch await sendRequest(config, ~timeoutMs?, req) {"
+    ));
+    assert!(client.contains("      checkServerVersion(config, res)\n      await forEachChunk(bodyStream(res.body), onChunk)\n    | Error(e) =>"));
+    // A transport that reads the body anyway hands back a Blob, streamed from memory
+    assert!(client.contains("body instanceof Blob ? body.stream() : body"));
+    // The regular function is generated as well, and unflagged operations get no variant
+    assert!(client.contains("  let downloadExport = async (config: config, ~id: string, "));
+    assert!(!client.contains("listExportsStream"));
+}
+
+#[test]
+fn test_server_sent_events() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Events, version: "1.0.0" }
+paths:
+  /orders/events:
+    get:
+      operationId: watchOrders
+      parameters:
+        - { name: since, in: query, schema: { type: string } }
+      responses:
+        '200':
+          description: Order updates
+          content:
+            text/event-stream:
+              schema: { $ref: '#/components/schemas/OrderUpdate' }
+  /logs:
+    get:
+      operationId: tailLogs
+      responses:
+        '200':
+          description: Log lines
+          content:
+            text/event-stream: {}
+components:
+  schemas:
+    OrderUpdate:
+      type: object
+      required: [id]
+      properties:
+        id: { type: string }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains("let forEachServerEvent = async ("));
+    assert!(client.contains(
+        "  let watchOrdersEvents = async (config: config, ~since: option<string>=?, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ~onEvent: serverEvent<orderUpdate> => unit, ()): result<unit, apiError> => {"
+    ));
+    assert!(client.contains(
+        r#"    req.headers->Dict.set("Accept", "text/event-stream")
+    let req = {...req, responseType: #blob, stream: true}"#
+    ));
+    // Only decoding is guarded: exceptions from `onEvent` are not turned into errors
+    assert!(client.contains(
+        r#"      let failure = ref(None)
+      let read = await forEachServerEvent(bodyStream(res.body), e =>
+        if failure.contents->Option.isNone {
+          switch parseOrderUpdate(JSON.parseExn(e.data)) {
+          | data => onEvent({event: e.event, id: e.id, data})
+          | exception Exn.Error(e) =>
+            failure := Some({status: 0, message: Exn.message(e)->Option.getOr("Parse error"), body: None})
+          }
+        }
+      )
+      switch failure.contents {
+      | Some(e) => Error(e)
+      | None => read
+      }"#
+    ));
+    // Without a schema the data is passed on as text
+    assert!(client.contains("~onEvent: serverEvent<string> => unit, ()): result<unit, apiError>"));
+    assert!(client.contains("      await forEachServerEvent(bodyStream(res.body), onEvent)\n"));
+}
+
+#[test]
+fn test_ndjson_items() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Export, version: "1.0.0" }
+paths:
+  /orders/export:
+    get:
+      operationId: exportOrders
+      responses:
+        '200':
+          description: One order per line
+          content:
+            application/x-ndjson:
+              schema:
+                type: array
+                items: { $ref: '#/components/schemas/Order' }
+components:
+  schemas:
+    Order:
+      type: object
+      required: [id]
+      properties:
+        id: { type: string }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains(
+        "let forEachLine = async (\n  stream: option<readableStream>,\n  onLine: string => unit,\n): result<unit, apiError> => {"
+    ));
+    assert!(!client.contains("forEachServerEvent"));
+    assert!(client.contains(
+        "  let exportOrdersItems = async (config: config, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ~onItem: order => unit, ()): result<unit, apiError> => {"
+    ));
+    assert!(client.contains(
+        r#"      let read = await forEachLine(bodyStream(res.body), line =>
+        if failure.contents->Option.isNone && line->String.trim != "" {
+          switch parseOrder(JSON.parseExn(line)) {
+          | data => onItem(data)
+          | exception Exn.Error(e) =>"#
+    ));
+}
+
+#[test]
+fn test_json_or_multipart_body() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Pets, version: "1.0.0" }
+paths:
+  /pets:
+    post:
+      operationId: createPet
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema: { $ref: '#/components/schemas/NewPet' }
+          multipart/form-data:
+            schema:
+              type: object
+              required: [name]
+              properties:
+                name: { type: string }
+                photo: { type: string, format: binary }
+      responses:
+        '204':
+          description: Created
+components:
+  schemas:
+    NewPet:
+      type: object
+      required: [name]
+      properties:
+        name: { type: string }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+    let types = codegen::types::generate(&api, &test_config()).unwrap();
+
+    assert!(types.contains("type createPetForm = {\n  name: string,\n  photo?: Fetch.Blob.t,\n}"));
+    assert!(types.contains("type createPetBody = Json(newPet) | Multipart(createPetForm)"));
+    assert!(client.contains("~body: createPetBody, "));
+    assert!(client.contains(
+        r#"    let (body, form) = switch body {
+    | Json(body) =>
+      headers->Dict.set("Content-Type", "application/json")
+      (Some(serializeNewPet(body)), None)
+    | Multipart(body) =>
+      let form = makeFormData()
+      form->appendFormValue("name", body.name)
+      switch body.photo { | Some(v) => form->appendFormFile("photo", v) | None => () }
+      (None, Some(form))
+    }
+"#
+    ));
+    assert!(client.contains("      body: body,\n"));
+    assert!(client.contains("      form: ?form,\n    }"));
+
+    // The JSON record keeps its constructor
+    assert!(types.contains("let makeNewPet = "));
+}
+
+#[test]
+fn test_multipart_body() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Uploads, version: "1.0.0" }
+paths:
+  /documents:
+    post:
+      operationId: uploadDocument
+      requestBody:
+        required: true
+        content:
+          multipart/form-data:
+            schema:
+              type: object
+              required: [file, pageCount]
+              properties:
+                file:
+                  type: string
+                  format: binary
+                  description: The scanned document
+                attachments:
+                  type: array
+                  items: { type: string, format: binary }
+                pageCount: { type: integer }
+                kind: { type: string, enum: [invoice, receipt] }
+                labels:
+                  type: array
+                  items: { type: string }
+                metadata:
+                  type: object
+                  properties:
+                    source: { type: string }
+                page_count: { type: integer }
+                signature: { $ref: '#/components/schemas/Binary' }
+                scans:
+                  type: array
+                  items: { $ref: '#/components/schemas/Binary' }
+      responses:
+        '204':
+          description: Uploaded
+components:
+  schemas:
+    Binary: { type: string, format: binary }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+    let types = codegen::types::generate(&api, &test_config()).unwrap();
+
+    // Form records live with the other types; nested objects keep a type, files
+    // behind a `$ref` are files, and colliding field names are numbered
+    assert!(!client.contains("type uploadDocumentForm"));
+    assert!(types.contains(
+        r#"type uploadDocumentForm = {
+  /** The scanned document */
+  file: Fetch.Blob.t,
+  attachments?: array<Fetch.Blob.t>,
+  pageCount: int,
+  kind?: [#"invoice" | #"receipt"],
+  labels?: array<string>,
+  metadata?: uploadDocumentFormMetadata,
+  pageCount2?: int,
+  signature?: Fetch.Blob.t,
+  scans?: array<Fetch.Blob.t>,
+}"#
+    ));
+    assert!(types.contains("type uploadDocumentFormMetadata = {\n  source: option<string>,\n}"));
+    assert!(api.diagnostics.iter().any(|d| d.message.contains("'pageCount' and 'page_count'")));
+    assert!(client.contains("~body: uploadDocumentForm, "));
+    assert!(client.contains(
+        r#"    let form = makeFormData()
+    form->appendFormFile("file", body.file)
+    switch body.attachments { | Some(v) => v->Array.forEach(file => form->appendFormFile("attachments", file)) | None => () }
+    form->appendFormValue("pageCount", body.pageCount->Wire.int)
+    switch body.kind { | Some(v) => form->appendFormValue("kind", (v :> string)) | None => () }
+    switch body.labels { | Some(v) => v->Array.forEach(item => form->appendFormValue("labels", item)) | None => () }
+    switch body.metadata { | Some(v) => form->appendFormValue("metadata", v->S.reverseConvertToJsonOrThrow(uploadDocumentFormMetadataSchema)->JSON.stringify) | None => () }
+    switch body.pageCount2 { | Some(v) => form->appendFormValue("page_count", v->Wire.int) | None => () }
+    switch body.signature { | Some(v) => form->appendFormFile("signature", v) | None => () }
+"#
+    ));
+    assert!(client.contains("      form,\n    }"));
+    assert!(client.contains("  form?: formData,"));
+    // The browser sets the multipart Content-Type with its boundary
+    assert!(!client.contains(r#"headers->Dict.set("Content-Type", "application/json")"#));
+}
+
+#[test]
+fn test_multi_file_project() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/multi-file/openapi.yaml")).unwrap();
+    let schemas = &spec.components.as_ref().unwrap().schemas;
+    // Named in the root, or after the file for schemas only referenced elsewhere
+    assert_eq!(
+        schemas.keys().collect::<Vec<_>>(),
+        vec!["User", "Error", "Address"]
+    );
+
+    let (types, _, client) = generate_from_spec("tests/fixtures/multi-file/openapi.yaml");
+    insta::assert_snapshot!("multi_file_types", types);
+    assert!(client.contains("let listUsers = async (config: config, ~limit: option<int>=?, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ())"));
+    assert!(client.contains("let getUser = async (config: config, ~userId: string, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ())"));
+}
+
 #[test]
 fn test_complex_types() {
     let (types, _, _) = generate_from_spec("tests/fixtures/complex.yaml");
@@ -59,3 +657,1404 @@ fn test_complex_client() {
     let (_, _, client) = generate_from_spec("tests/fixtures/complex.yaml");
     insta::assert_snapshot!("complex_client", client);
 }
+
+#[test]
+fn test_filter_prunes_unreachable_schemas() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/petstore.yaml")).unwrap();
+    let filter = filter::Filter {
+        tags: vec!["store".to_string()],
+        paths: vec![],
+    };
+    let filtered = filter::apply(&spec, &filter);
+
+    let paths: Vec<_> = filtered.paths.paths.keys().cloned().collect();
+    assert_eq!(paths, vec!["/store/inventory", "/store/order", "/store/order/{orderId}"]);
+
+    let schemas: Vec<_> = filtered
+        .components
+        .as_ref()
+        .unwrap()
+        .schemas
+        .keys()
+        .cloned()
+        .collect();
+    assert_eq!(schemas, vec!["Order"]);
+    assert_eq!(filtered.tags.len(), 1);
+
+    // Shared components only a dropped operation used go too, with their schemas
+    let spec = parser::parse_spec_content(
+        r#"
+openapi: 3.0.3
+info: {title: Shared, version: "1.0"}
+paths:
+  /orders:
+    get:
+      tags: [orders]
+      operationId: listOrders
+      parameters: [{$ref: '#/components/parameters/Page'}]
+      responses:
+        '200': {$ref: '#/components/responses/Orders'}
+  /users:
+    get:
+      tags: [users]
+      operationId: listUsers
+      parameters: [{$ref: '#/components/parameters/Page'}]
+      responses:
+        '200': {$ref: '#/components/responses/Users'}
+components:
+  parameters:
+    Page: {name: page, in: query, schema: {type: integer}}
+  responses:
+    Orders:
+      description: Orders
+      content:
+        application/json:
+          schema: {type: array, items: {$ref: '#/components/schemas/Order'}}
+    Users:
+      description: Users
+      content:
+        application/json:
+          schema: {type: array, items: {$ref: '#/components/schemas/User'}}
+  schemas:
+    Order: {type: object, properties: {id: {type: string}}}
+    User: {type: object, properties: {id: {type: string}}}
+"#,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let filter = filter::Filter {
+        tags: vec!["orders".to_string()],
+        paths: vec![],
+    };
+    let components = filter::apply(&spec, &filter).components.unwrap();
+    assert_eq!(components.responses.keys().collect::<Vec<_>>(), vec!["Orders"]);
+    assert_eq!(components.parameters.keys().collect::<Vec<_>>(), vec!["Page"]);
+    assert_eq!(components.schemas.keys().collect::<Vec<_>>(), vec!["Order"]);
+}
+
+#[test]
+fn test_schema_registry() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/petstore.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let config = codegen::Config {
+        generate_schema_registry: true,
+        ..test_config()
+    };
+    let schema = codegen::schema::generate(&api, &config).unwrap();
+
+    assert!(schema.contains("type anySchema = AnySchema(S.t<'a>): anySchema"));
+    assert!(schema.contains("  (\"Pet\", AnySchema(petSchema)),\n"));
+    assert!(schema.contains("  (\"ApiResponse\", AnySchema(apiResponseSchema)),\n"));
+}
+
+#[test]
+fn test_unused_helpers_are_omitted() {
+    let spec = parser::parse_spec_content(
+        r#"
+openapi: 3.0.3
+info: {title: Minimal, version: "1.0"}
+paths:
+  /health:
+    get:
+      operationId: getHealth
+      responses:
+        '200': {description: OK}
+"#,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(!client.contains("authConfig"));
+    assert!(!client.contains("applyAuth"));
+    assert!(!client.contains("~bearerToken"));
+    assert!(!client.contains("module Auth"));
+    assert!(!client.contains("module Wire"));
+    assert!(!client.contains("type rawBody"));
+    assert!(!client.contains("warnDeprecatedParam"));
+    assert!(!client.contains("type rec page"));
+    assert!(!client.contains("problem"));
+    assert!(client.contains("/** Create client configuration\n *\n * Status handlers"));
+    assert!(client.contains("  {\n    baseUrl,\n    headers,\n    on401,\n"));
+
+    // The fixture with auth, raw bodies and deprecated parameters keeps them all
+    let (_, _, client) = generate_from_spec("tests/fixtures/complex.yaml");
+    assert!(client.contains("await applyAuth(headers, query, config.auth, [[Bearer], [ApiKey("));
+    assert!(client.contains("module Wire"));
+    assert!(client.contains("type rawBody"));
+    assert!(client.contains("let warnDeprecatedParam"));
+    // Only the list declaring a `Link` header gets a paged variant
+    assert!(client.contains("let rec fetchPage"));
+    assert!(client.contains("let listEventsPage = async (config: config, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ()): result<page<array<event>>, apiError>"));
+    assert!(!client.contains("listUsersPage"));
+}
+
+#[test]
+fn test_body_serializer() {
+    let spec = parser::parse_spec_content(
+        r##"
+openapi: 3.0.3
+info: {title: Bodies, version: "1.0"}
+paths:
+  /pets:
+    post:
+      operationId: addPets
+      requestBody:
+        content:
+          application/json:
+            schema: {type: array, items: {$ref: "#/components/schemas/Pet"}}
+      responses:
+        '204': {description: Added}
+components:
+  schemas:
+    Pet:
+      type: object
+      properties:
+        pet_name: {type: string}
+"##,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let api = ir::lower(&spec).unwrap();
+
+    // Inline body types are serialized through their schema too
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+    assert!(client.contains("Some(body->S.reverseConvertToJsonOrThrow(S.array(petSchema)))"));
+
+    let config = codegen::Config {
+        body_serializer: codegen::BodySerializer::Stringify,
+        ..test_config()
+    };
+    let client = codegen::client::generate(&api, &config).unwrap();
+    assert!(client.contains("    body: Some(body->Obj.magic),"));
+}
+
+#[test]
+fn test_constraints() {
+    let spec = parser::parse_spec_content(
+        r##"
+openapi: 3.0.3
+info: {title: Constraints, version: "1.0"}
+paths: {}
+components:
+  schemas:
+    Slug: {type: string, pattern: '^[a-z0-9"-]+$', maxLength: 40}
+    Tag:
+      type: object
+      required: [label]
+      properties:
+        label: {type: string, minLength: 1, maxLength: 20}
+        weight: {type: integer, minimum: 0, exclusiveMinimum: true, maximum: 100, multipleOf: 5}
+        score: {type: number, maximum: 1.5}
+        aliases: {type: array, maxItems: 3, items: {type: string}}
+"##,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let schema = codegen::schema::generate(&api, &test_config()).unwrap();
+
+    assert!(schema.contains(
+        r#"S.string->S.String.max(40)->S.String.pattern(RegExp.fromString("^[a-z0-9\"-]+$"))->S.setName("Slug")"#
+    ));
+    assert!(schema.contains(r#"s.field("label", S.string->S.String.min(1)->S.String.max(20)"#));
+    // Exclusive integer bounds become the next integer in
+    assert!(schema.contains(
+        r#"S.option(S.int->S.Int.min(1)->S.Int.max(100)->S.refine(s => v => if mod(v, 5) != 0 {s.fail("Must be a multiple of 5")}))"#
+    ));
+    assert!(schema.contains("S.option(S.float->S.Float.max(1.5))"));
+    assert!(schema.contains("S.option(S.array(S.string)->S.Array.max(3))"));
+}
+
+#[test]
+fn test_openapi_31() {
+    let spec = parser::parse_spec_content(
+        r##"{
+  "openapi": "3.1.0",
+  "info": {"title": "Pets", "version": "1"},
+  "webhooks": {
+    "newPet": {
+      "post": {
+        "summary": "A pet was added",
+        "requestBody": {
+          "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+        },
+        "responses": {"200": {"description": "Received"}}
+      }
+    }
+  },
+  "components": {
+    "schemas": {
+      "Pet": {
+        "type": "object",
+        "required": ["tag", "owner"],
+        "properties": {
+          "tag": {"type": ["string", "null"], "examples": ["good"]},
+          "owner": {"anyOf": [{"$ref": "#/components/schemas/Owner"}, {"type": "null"}]},
+          "age": {"type": "integer", "exclusiveMinimum": 0}
+        }
+      },
+      "Owner": {"type": "object", "properties": {"id": {"type": ["string", "integer"]}}}
+    }
+  }
+}"##,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let api = ir::lower(&spec).unwrap();
+
+    let types = codegen::types::generate(&api, &test_config()).unwrap();
+    assert!(types.contains("  owner: option<owner>,\n"));
+    assert!(types.contains("  tag: option<string>,\n"));
+    assert!(types.contains("  id: option<stringOrInt>,\n"));
+
+    let schema = codegen::schema::generate(&api, &test_config()).unwrap();
+    assert!(schema.contains(r#"s.field("owner", S.nullable(ownerSchema)"#));
+    assert!(schema.contains("S.option(S.int->S.Int.min(1))"));
+    // String-or-number ids can also be read as plain strings
+    assert!(schema.contains(
+        "let stringOrIntStringSchema: S.t<string> = stringOrIntSchema->S.transform(_ => {\n  parser: (value: stringOrInt) => switch value { | S(v) => v | I(v) => v->Int.toString },\n  serializer: (value): stringOrInt => S(value),\n})\n"
+    ));
+    assert!(schema.contains(
+        "/** A pet was added */\nlet parseNewPetWebhook = (json: Js.Json.t): pet => {\n  S.parseJsonOrThrow(json, petSchema)\n}\n"
+    ));
+}
+
+#[test]
+fn test_exn_wrappers() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/complex.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+    assert!(!client.contains("exception ApiError"));
+
+    let config = codegen::Config {
+        generate_exn: true,
+        ..test_config()
+    };
+    let client = codegen::client::generate(&api, &config).unwrap();
+    assert!(client.contains("exception ApiError(apiError)\n"));
+    // Optional arguments are passed on as options, spec defaults are kept
+    assert!(client.contains(
+        "  let getUserExn = async (config: config, ~userId: string, ~fields: option<array<Fields.User.field>>=?, ~timeoutMs: int=5000, ~retries: int=2, ~signal: option<abortSignal>=?, ()): user =>\n    switch await getUser(config, ~userId, ~fields?, ~timeoutMs, ~retries, ~signal?, ()) {\n    | Ok(value) => value\n    | Error(e) => raise(ApiError(e))\n    }\n"
+    ));
+    assert!(client.contains("switch await listUsers(config, ~limit?, ~offset?, ~status?, ~filter?, ~timeoutMs?, ~signal?, ())"));
+    assert!(client.contains("  let listEventsPageExn = async (config: config, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ()): page<array<event>> =>\n"));
+}
+
+#[test]
+fn test_response_variants() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/complex.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let config = codegen::Config {
+        response_style: codegen::ResponseStyle::Variant,
+        generate_exn: true,
+        ..test_config()
+    };
+
+    let client = codegen::client::generate(&api, &config).unwrap();
+    assert!(client.contains(
+        "type getUserOutcome =\n  /** Success */\n  | Ok200(user)\n  /** Not found */\n  | NotFound404(error)\n"
+    ));
+    assert!(client.contains(
+        "let decodeGetUserOutcome = (res: receivedResponse): getUserOutcome =>\n  switch (res.status, res.body) {\n  | (200, Some(json)) => Ok200(parseUser(json))\n  | (404, Some(json)) => NotFound404(parseError(json))\n  | (status, _) => UnexpectedStatus(status, res.text)\n  }\n"
+    ));
+    assert!(client.contains(
+        "): result<getUserOutcome, apiError> => {\n"
+    ));
+    assert!(client.contains(
+        "    decodeOutcome(config, await sendWithPolicy(config, req, ~timeoutMs, ~retries, ()), decodeGetUserOutcome)\n"
+    ));
+    assert!(client.contains("~retries: int=2, ~signal: option<abortSignal>=?, ()): getUserOutcome =>\n"));
+    // Pages keep decoding the success body
+    assert!(client.contains("): result<page<array<event>>, apiError> => {\n"));
+
+    let spy = codegen::spy::generate(&api, &config).unwrap();
+    assert!(spy.contains(
+        "  let response = response->Result.map((body): httpResponse => {status: 200, headers: Dict.make(), body})\n  decodeOutcome(config, response, decodeGetUserOutcome)\n"
+    ));
+
+    let operation = codegen::explain::operation(&api, &config, "getUser").unwrap();
+    assert!(operation.contains("== ApiClient.res ==\n\n/** Documented outcomes of `getUser`"));
+}
+
+#[test]
+fn test_typed_errors() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/complex.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let config = codegen::Config {
+        response_style: codegen::ResponseStyle::TypedErrors,
+        client_surface: codegen::ClientSurface::Instance,
+        ..test_config()
+    };
+
+    let client = codegen::client::generate(&api, &config).unwrap();
+    assert!(client.contains(
+        "type getUserFailure =\n  /** Not found */\n  | NotFound404(error)\n  /** A status the spec doesn't document, with the response text */\n  | UnexpectedStatus(int, string)\n  /** No response to decode: network failure, timeout, abort or size limit */\n  | NetworkError(apiError)\n"
+    ));
+    assert!(client.contains("  | (404, Some(json)) => NotFound404(parseError(json))\n"));
+    assert!(client.contains("~retries: int=2, ~signal: option<abortSignal>=?, ()): result<user, getUserFailure> => {\n"));
+    assert!(client.contains(
+        "    decodeTyped(\n      config,\n      await sendWithPolicy(config, req, ~timeoutMs, ~retries, ()),\n      ~ok=res => parseUser(res.body),\n      ~failure=decodeGetUserFailure,\n      ~network=(e): getUserFailure => NetworkError(e),\n      ~invalid=(message, body): getUserFailure => DecodeError(message, body),\n    )\n"
+    ));
+    assert!(client.contains("~retries: int=?, ~signal: abortSignal=?, unit) => promise<result<user, getUserFailure>>,\n"));
+
+    let spy = codegen::spy::generate(&api, &config).unwrap();
+    assert!(spy.contains("()): result<user, getUserFailure> => {\n"));
+    assert!(spy.contains("  decodeTyped(\n    config,\n    response,\n    ~ok=res => parseUser(res.body),\n"));
+
+    // `*Exn` wrappers raise the untyped error
+    let config = codegen::Config {
+        generate_exn: true,
+        ..config
+    };
+    let error = codegen::client::generate(&api, &config).unwrap_err();
+    assert!(error.to_string().contains("typed errors"));
+}
+
+#[test]
+fn test_problem_details() {
+    let spec = parser::parse_spec_content(
+        r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /items/{id}:
+    get:
+      operationId: getItem
+      parameters:
+        - { name: id, in: path, required: true, schema: { type: string } }
+      responses:
+        "200":
+          description: Item
+          content:
+            application/json: { schema: { type: string } }
+        "404":
+          description: Not found
+          content:
+            application/problem+json: {}
+        "409":
+          description: Conflict
+          content:
+            application/problem+json: { schema: { $ref: "#/components/schemas/Conflict" } }
+components:
+  schemas:
+    Conflict: { type: object, properties: { title: { type: string } } }
+"##,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let config = codegen::Config {
+        response_style: codegen::ResponseStyle::TypedErrors,
+        ..test_config()
+    };
+
+    let types = codegen::types::generate(&api, &config).unwrap();
+    assert!(types.contains(
+        "/** Machine-readable details of an error (RFC 7807) */\ntype problemDetails = {\n  /** URI reference identifying the problem type */\n  @as(\"type\") type_: option<string>,\n"
+    ));
+
+    let client = codegen::client::generate(&api, &config).unwrap();
+    assert!(client.contains("  kind?: errorKind,\n  /** RFC 7807 details, when the body is `application/problem+json` */\n  problem?: problemDetails,\n}\n"));
+    assert!(client.contains("        Error({status, message, body, ?problem: decodeProblem(headers, body)})\n"));
+    // A schema-less problem body decodes as `ProblemDetails`, a declared one as its schema
+    assert!(client.contains("  | (404, Some(json)) => NotFound404(parseProblemDetails(json))\n"));
+    assert!(client.contains("  | (409, Some(json)) => Conflict409(parseConflict(json))\n"));
+}
+
+#[test]
+fn test_client_config() {
+    let (_, _, client) = generate_from_spec("tests/fixtures/petstore.yaml");
+
+    assert!(client.contains("  ~timeoutMs: option<int>=?,\n  ~credentials: option<credentialsMode>=?,\n"));
+    assert!(client.contains("    timeoutMs,\n    credentials,\n    transport,\n    requestInterceptors,\n    responseInterceptors,\n    onRequest,\n    onResponse,\n  }\n}\n"));
+    // Every request carries the credentials mode and goes through the config's transport
+    assert!(client.contains("      signal,\n      credentials: config.credentials,\n    }\n"));
+    assert!(client.contains("    let send = config.transport->Option.getOr(Http.request)\n"));
+    // Interceptors wrap every transport call
+    assert!(client.contains("    let req = await req->intercept(config.requestInterceptors)\n"));
+    assert!(client.contains("    await result->intercept(config.responseInterceptors)\n  }\n"));
+    // Observation hooks see every request and how it ended
+    assert!(client.contains("    config.onRequest->Option.forEach(f => f({method: req.method, url: req.url}))\n"));
+    assert!(client.contains("        durationMs: Date.now() -. startedAt,\n"));
+    // Without `x-timeout-ms` the config's default timeout still applies
+    assert!(client.contains("  | Timeout({timeoutMs: int})\n"));
+    assert!(client.contains("      switch timeoutMs->Option.orElse(config.timeoutMs) {\n"));
+}
+
+#[test]
+fn test_retry_policy() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/complex.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+    assert!(!client.contains("retryPolicy"));
+    assert!(!client.contains("sendRetrying"));
+
+    let config = codegen::Config { generate_retry: true, ..test_config() };
+    let client = codegen::client::generate(&api, &config).unwrap();
+    assert!(client.contains("  ~retry: retryPolicy=defaultRetryPolicy,\n"));
+    assert!(client.contains("    retry,\n  }\n}\n"));
+    assert!(client.contains("  let sendRetrying = async (\n    config: config,\n    ~timeoutMs: option<int>=?,\n    req: httpRequest,\n  )"));
+    assert!(client.contains("    switch await sendRetrying(config, ~timeoutMs?, req) {\n"));
+    // Operations with `x-retries` keep their own policy
+    assert!(client.contains("    switch await sendWithPolicy(config, req, ~timeoutMs, ~retries, ()) {\n"));
+}
+
+#[test]
+fn test_bearer_security() {
+    let spec = parser::parse_spec_content(
+        r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+security:
+  - bearerAuth: []
+paths:
+  /items:
+    get:
+      operationId: listItems
+      responses:
+        "204": { description: Done }
+    post:
+      operationId: createItem
+      security:
+        - bearerAuth: []
+        - {}
+      responses:
+        "204": { description: Done }
+  /health:
+    get:
+      operationId: getHealth
+      security: []
+      responses:
+        "204": { description: Done }
+  /admin:
+    get:
+      operationId: getAdmin
+      security:
+        - basicAuth: []
+      responses:
+        "204": { description: Done }
+components:
+  securitySchemes:
+    bearerAuth: { type: http, scheme: bearer, bearerFormat: JWT }
+    basicAuth: { type: http, scheme: basic }
+"##,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains("  ~getBearerToken: option<unit => promise<option<string>>>=?,\n"));
+    // Global security applies unless the operation declares its own
+    assert!(client.contains(
+        "  let listItems = async (config: config, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ()): result<unit, apiError> => {\n    let path = \"/items\"\n    let query = []\n    let headers = Dict.fromArray(config.headers->Dict.toArray)\n    headers->Dict.set(\"Content-Type\", \"application/json\")\n    await applyAuth(headers, query, config.auth, [[Bearer]])\n"
+    ));
+    // Optional auth sends the token when there is one
+    assert!(client.contains("    await applyAuth(headers, query, config.auth, [[Bearer], []])\n"));
+    // Public operations and those needing schemes the client can't send get no credentials
+    assert_eq!(client.matches("    await applyAuth(headers, query, config.auth").count(), 2);
+
+    // Without any declared requirements, whatever the config holds is sent
+    let spec = parser::parse_spec_content(
+        r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /items:
+    get:
+      operationId: listItems
+      responses:
+        "204": { description: Done }
+components:
+  securitySchemes:
+    bearerAuth: { type: http, scheme: bearer }
+    oauth: { type: openIdConnect, openIdConnectUrl: "https://example.com/.well-known/openid-configuration" }
+    key: { type: apiKey, in: header, name: X-Key }
+"##,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+    assert!(client.contains(
+        r#"    await applyConfiguredAuth(headers, query, config.auth, [Bearer, ApiKey({scheme: "key", name: "X-Key", location: #header})])"#
+    ));
+}
+
+#[test]
+fn test_api_key_security() {
+    let spec = parser::parse_spec_content(
+        r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /items:
+    get:
+      operationId: listItems
+      security:
+        - queryKey: []
+        - sessionKey: []
+      responses:
+        "204": { description: Done }
+    post:
+      operationId: createItem
+      security:
+        - headerKey: []
+          queryKey: []
+      responses:
+        "204": { description: Done }
+components:
+  securitySchemes:
+    headerKey: { type: apiKey, in: header, name: X-API-Key }
+    queryKey: { type: apiKey, in: query, name: api_key }
+    sessionKey: { type: apiKey, in: cookie, name: session }
+"##,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains("  ~apiKey: option<string>=?,\n  ~apiKeys=Dict.make(),\n"));
+    // The old `~apiKeyHeader` is still accepted, and used where nothing is declared
+    assert!(client.contains("  ~apiKeyHeader: option<string>=?,\n"));
+    assert!(client.contains(
+        "  | (Some(name), Some(key)) if headers->Dict.get(name)->Option.isNone => headers->Dict.set(name, key)\n"
+    ));
+    // Each key goes where its scheme declares it, alternatives in order
+    assert!(client.contains(
+        "    await applyAuth(headers, query, config.auth, [[ApiKey({scheme: \"queryKey\", name: \"api_key\", location: #query})], [ApiKey({scheme: \"sessionKey\", name: \"session\", location: #cookie})]])\n"
+    ));
+    // Schemes required together form one requirement
+    assert!(client.contains(
+        "[[ApiKey({scheme: \"headerKey\", name: \"X-API-Key\", location: #header}), ApiKey({scheme: \"queryKey\", name: \"api_key\", location: #query})]]"
+    ));
+}
+
+#[test]
+fn test_range_and_default_responses() {
+    let spec = parser::parse_spec_content(
+        r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /items:
+    get:
+      operationId: listItems
+      responses:
+        "2XX":
+          description: Items
+          content:
+            application/json: { schema: { type: array, items: { type: string } } }
+        "4XX":
+          description: Client error
+          content:
+            application/json: { schema: { $ref: "#/components/schemas/Error" } }
+        "404": { description: Not found }
+        default:
+          description: Unexpected error
+          content:
+            application/json: { schema: { $ref: "#/components/schemas/Error" } }
+components:
+  schemas:
+    Error: { type: object, properties: { message: { type: string } } }
+"##,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let config = codegen::Config {
+        response_style: codegen::ResponseStyle::Variant,
+        ..test_config()
+    };
+
+    let client = codegen::client::generate(&api, &config).unwrap();
+    assert!(client.contains(
+        "  /** Client error */\n  | ClientError4XX(int, error)\n  /** Not found */\n  | NotFound404\n  /** Unexpected error */\n  | Default(int, error)\n"
+    ));
+    // Exact codes are matched before the range and `default` covering them
+    assert!(client.contains(
+        "  | (404, _) => NotFound404\n  | (status, Some(json)) if status >= 200 && status < 300 => Success2XX(status, S.parseJsonOrThrow(json, S.array(S.string)))\n  | (status, Some(json)) if status >= 400 && status < 500 => ClientError4XX(status, parseError(json))\n  | (status, Some(json)) => Default(status, parseError(json))\n  | (status, _) => UnexpectedStatus(status, res.text)\n"
+    ));
+    // The `2XX` range is the success body in the default style
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+    assert!(client.contains("  let listItems = async (config: config, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ()): result<array<string>, apiError> => {\n"));
+    assert!(client.contains("   * - 4XX: Client error\n   * - 404: Not found\n"));
+}
+
+#[test]
+fn test_empty_responses() {
+    let spec = parser::parse_spec_content(
+        r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths:
+  /items/{id}:
+    delete:
+      operationId: deleteItem
+      parameters:
+        - { name: id, in: path, required: true, schema: { type: string } }
+      responses:
+        "204": { description: Deleted }
+    put:
+      operationId: touchItem
+      parameters:
+        - { name: id, in: path, required: true, schema: { type: string } }
+      responses:
+        "200":
+          description: Touched
+          content:
+            application/json: {}
+"##,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let api = ir::lower(&spec).unwrap();
+
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+    // Both return unit, ignoring whatever body the transport read
+    assert_eq!(client.matches("): result<unit, apiError> => {\n").count(), 2);
+    assert_eq!(client.matches("      responseType: #json,\n").count(), 2);
+    assert!(client.contains("        | (_, _) if status == 204 => Ok({status, headers, body: JSON.Encode.null})"));
+    assert_eq!(client.matches("      checkServerVersion(config, res)\n      Ok()\n").count(), 2);
+
+    let config = codegen::Config {
+        response_style: codegen::ResponseStyle::Variant,
+        ..test_config()
+    };
+    let client = codegen::client::generate(&api, &config).unwrap();
+    assert!(client.contains("  /** Deleted */\n  | NoContent204\n"));
+    assert!(client.contains("  | (204, _) => NoContent204\n"));
+}
+
+#[test]
+fn test_recursive_types() {
+    // A -> B -> C -> A, plus C -> B: B and C stay a cycle once A is taken out
+    let spec = parser::parse_spec_content(
+        r##"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    A: { type: object, properties: { b: { $ref: "#/components/schemas/B" } } }
+    B: { type: object, properties: { c: { $ref: "#/components/schemas/C" } } }
+    C:
+      type: object
+      properties:
+        a: { $ref: "#/components/schemas/A" }
+        b: { $ref: "#/components/schemas/B" }
+"##,
+        &parser::Limits::default(),
+    )
+    .unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let config = test_config();
+
+    let types = codegen::types::generate(&api, &config).unwrap();
+    assert!(types.contains("type rec a = {\n  b: option<b>,\n}\nand b = {\n  c: option<c>,\n}\nand c = {\n"));
+
+    let schema = codegen::schema::generate(&api, &config).unwrap();
+    // B and C are defined once, in a function of A's schema
+    assert!(schema.contains("let aGroupSchemas = (aSchema: S.t<a>) => {\n"));
+    assert!(schema.contains(
+        "  let bGroupSchemas = (bSchema: S.t<b>) => {\n    let cSchema: S.t<c> = S.object("
+    ));
+    assert_eq!(schema.matches("let cSchema: S.t<c> = ").count(), 1);
+    assert_eq!(schema.matches("let bSchema: S.t<b> = ").count(), 1);
+    // A binds only the members it refers to
+    assert!(schema.contains(
+        "let aSchema: S.t<a> = S.recursive(aSchema => {\n  let (bSchema, _) = aGroupSchemas(aSchema)\n"
+    ));
+    assert!(schema.contains("})\n\nlet parseA = "));
+    assert!(schema.contains("\nlet (bSchema, cSchema) = aGroupSchemas(aSchema)\n\nlet parseB = "));
+    assert!(schema.contains("\nlet parseC = "));
+
+    #[allow(deprecated)]
+    let sorted = codegen::schema::topological_sort(&api.types);
+    let names: Vec<&str> = sorted.iter().map(|t| t.name()).collect();
+    assert_eq!(names, ["A", "B", "C"]);
+}
+
+#[test]
+fn test_describe_errors() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Users, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    User:
+      type: object
+      required: [email, age]
+      properties:
+        email:
+          type: string
+          maxLength: 254
+          description: |
+            Primary email address.
+            Used for sign-in.
+        age: { type: integer, multipleOf: 2, description: 'Age in "years"' }
+        nickname: { type: string, minLength: 2 }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let config = codegen::Config {
+        describe_errors: true,
+        ..test_config()
+    };
+    let schema = codegen::schema::generate(&api, &config).unwrap();
+
+    // The description leads the message; the name still gives the path
+    assert!(schema.contains(
+        r#"s.field("email", S.string->S.String.max(254, ~message="Primary email address: Must be at most 254 characters long")->S.setName("User.email"))"#
+    ));
+    assert!(schema.contains(
+        r#"S.int->S.refine(s => v => if mod(v, 2) != 0 {s.fail("Age in \"years\": Must be a multiple of 2")})->S.setName("User.age")"#
+    ));
+    // Fields without a description keep the default message
+    assert!(schema.contains(r#"S.option(S.string->S.String.min(2))->S.setName("User.nickname")"#));
+
+    let schema = codegen::schema::generate(&api, &test_config()).unwrap();
+    assert!(schema.contains(r#"s.field("email", S.string->S.String.max(254)->S.setName("User.email"))"#));
+}
+
+#[test]
+fn test_explain() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/petstore.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let config = test_config();
+
+    let schema = codegen::explain::schema(&api, &config, "pet").unwrap();
+    assert!(schema.starts_with("== IR ==\n\nRecord {\n    name: \"Pet\","));
+    assert!(schema.contains("== ApiTypes.res ==\n\ntype pet = {\n"));
+    assert!(schema.contains("let makePet = (\n"));
+    assert!(schema.contains("== ApiSchema.res ==\n\nlet petSchema: S.t<pet>"));
+
+    let operation = codegen::explain::operation(&api, &config, "getPetById").unwrap();
+    assert!(operation.contains("operation_id: \"getPetById\""));
+    assert!(operation.contains("== ApiClient.res ==\n"));
+    assert!(operation.contains("  let getPetById = async (config: config, ~petId: int, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ())"));
+
+    let error = codegen::explain::operation(&api, &config, "getPet").unwrap_err();
+    assert!(error.to_string().starts_with("Unknown operation 'getPet'; available: "));
+}
+
+#[test]
+fn test_build_api() {
+    let source = std::fs::read_to_string("tests/fixtures/petstore.yaml").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut options = build::Options::new(build::Input::Text(source));
+    options.config.output_dir = dir.path().to_path_buf();
+    let generated = build::generate(&options).unwrap();
+
+    let names: Vec<_> = generated.files.iter().map(|f| f.filename.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["ApiTypes.res", "ApiMeta.res", "ApiAuth.res", "ApiSchema.res", "ApiClient.res"]
+    );
+    assert!(!generated.diagnostics.is_empty());
+    // In-memory output leaves the directory alone
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    options.output = build::Output::Directory;
+    let generated = build::generate(&options).unwrap();
+    let written = std::fs::read_to_string(dir.path().join("ApiTypes.res")).unwrap();
+    assert_eq!(written, generated.files[0].content);
+
+    let options = build::Options::new(build::Input::Text("openapi: [".to_string()));
+    let error = build::generate(&options).unwrap_err();
+    assert!(matches!(error, build::Error::Parse { .. }));
+    assert_eq!(error.to_string(), "Failed to parse OpenAPI spec: <text>");
+}
+
+#[test]
+fn test_generate_deterministic() {
+    let options = build::Options::new(build::Input::Path("tests/fixtures/complex.yaml".into()));
+    let checked = build::generate_deterministic(&options).unwrap();
+    let single = build::generate(&options).unwrap();
+    assert_eq!(checked.files, single.files);
+
+    // Colliding names are suffixed the same way whichever is declared first
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Users, version: "1.0.0" }
+paths:
+  /user-items:
+    get:
+      responses:
+        '200': { description: Items, content: { application/json: { schema: { $ref: '#/components/schemas/User' } } } }
+  /user_items:
+    get:
+      responses:
+        '200': { description: Items, content: { application/json: { schema: { $ref: '#/components/schemas/UserHome' } } } }
+components:
+  schemas:
+    UserHome:
+      type: object
+      properties:
+        address: { type: object, properties: { street: { type: string } } }
+    User:
+      type: object
+      properties:
+        home_address: { type: object, properties: { zip: { type: string } } }
+"#;
+    let options = build::Options::new(build::Input::Text(yaml.to_string()));
+    let checked = build::generate_deterministic(&options).unwrap();
+    let client = &checked.files.iter().find(|f| f.filename == "ApiClient.res").unwrap().content;
+    assert!(client.find("let getUserItems =").unwrap() < client.find("let getUserItems2 =").unwrap());
+    let types = &checked.files[0].content;
+    assert!(types.contains("type userHomeAddress = {\n  zip:"));
+    assert!(types.contains("type userHomeAddress2 = {\n  street:"));
+
+    let error = build::Error::Nondeterministic {
+        files: vec!["ApiTypes.res".to_string(), "ApiSchema.res".to_string()],
+    };
+    assert_eq!(
+        error.to_string(),
+        "Generation is not deterministic; output differs in ApiTypes.res, ApiSchema.res"
+    );
+}
+
+#[test]
+fn test_manifest() {
+    let mut options = build::Options::new(build::Input::Path("tests/fixtures/petstore.yaml".into()));
+    options.manifest = true;
+    let generated = build::generate(&options).unwrap();
+
+    let last = generated.files.last().unwrap();
+    assert_eq!(last.filename, "manifest.json");
+    let manifest: manifest::Manifest = serde_json::from_str(&last.content).unwrap();
+    assert_eq!(manifest.format, manifest::MANIFEST_FORMAT);
+    assert_eq!(manifest.inputs.len(), 1);
+    assert_eq!(manifest.inputs[0].file, "tests/fixtures/petstore.yaml");
+    let source = std::fs::read("tests/fixtures/petstore.yaml").unwrap();
+    assert_eq!(manifest.inputs[0].sha256, bundle::sha256_hex(&source));
+    assert_eq!(manifest.outputs.len(), generated.files.len() - 1);
+    assert!(generated.files[1].content.contains(&manifest.spec_hash));
+
+    // Changing an option changes the options hash
+    options.config.module_prefix = "Shop".to_string();
+    let other = build::generate(&options).unwrap();
+    let other: manifest::Manifest = serde_json::from_str(&other.files.last().unwrap().content).unwrap();
+    assert_ne!(other.options_sha256, manifest.options_sha256);
+    assert_eq!(other.spec_hash, manifest.spec_hash);
+}
+
+#[test]
+fn test_negotiated_response() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Reports, version: "1.0.0" }
+paths:
+  /reports/{id}:
+    get:
+      operationId: getReport
+      parameters:
+        - { name: id, in: path, required: true, schema: { type: string } }
+      responses:
+        '200':
+          description: The report
+          content:
+            text/csv:
+              schema: { type: string }
+            application/json:
+              schema: { $ref: '#/components/schemas/Report' }
+            application/pdf:
+              schema: { type: string, format: binary }
+components:
+  schemas:
+    Report:
+      type: object
+      required: [title]
+      properties:
+        title: { type: string }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+
+    // JSON alone is read unless negotiation is asked for
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+    assert!(!client.contains("negotiated<"));
+    assert!(client.contains(
+        r#"  let getReport = async (config: config, ~id: string, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ()): result<report, apiError> => {"#
+    ));
+
+    let options = ir::LowerOptions { negotiate_responses: true, ..Default::default() };
+    let api = ir::lower_with_options(&spec, &options).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains("type negotiated<'json> =\n  | Json('json)\n"));
+    assert!(client.contains(
+        r#"  let getReport = async (config: config, ~id: string, ~accept: [#"application/json" | #"text/csv" | #"application/pdf"]=#"application/json", ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ()): result<negotiated<report>, apiError> => {"#
+    ));
+    assert!(client.contains(r#"    headers->Dict.set("Accept", (accept :> string))"#));
+    assert!(client.contains(
+        r#"      responseType: switch accept { | #"application/json" => #json | #"text/csv" => #text | #"application/pdf" => #blob },"#
+    ));
+    assert!(client.contains(
+        r#"        Ok(switch res.headers->mediaType { | Some("text/csv") => Text({contentType: res.headers->headerValue("Content-Type"), body: res.body->JSON.Decode.string->Option.getOr("")}) | Some("application/pdf") => Blob({contentType: res.headers->headerValue("Content-Type"), body: res.body->Obj.magic}) | _ => Json(parseReport(res.body)) })"#
+    ));
+
+    let config = codegen::Config { client_surface: codegen::ClientSurface::Instance, ..test_config() };
+    let client = codegen::client::generate(&api, &config).unwrap();
+    assert!(client.contains(
+        r#"      getReport: (~id, ~accept=?, ~timeoutMs=?, ~signal=?, ()) => getReport(config, ~id, ~accept?, ~timeoutMs?, ~signal?, ()),"#
+    ));
+}
+
+#[test]
+fn test_negotiated_response_without_json() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Reports, version: "1.0.0" }
+paths:
+  /reports/{id}:
+    get:
+      operationId: getReport
+      parameters:
+        - { name: id, in: path, required: true, schema: { type: string } }
+      responses:
+        '200':
+          description: The report
+          content:
+            Text/CSV; charset=utf-8:
+              schema: { type: string }
+            text/csv:
+              schema: { type: string }
+            application/pdf:
+              schema: { type: string, format: binary }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains(
+        r#"~accept: [#"text/csv" | #"application/pdf"]=#"text/csv", ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ()): result<negotiated<unit>, apiError> => {"#
+    ));
+    assert!(client.contains(r#"| Some("application/pdf") => Blob("#));
+}
+
+#[test]
+fn test_query_styles() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Orders, version: "1.0.0" }
+paths:
+  /orders:
+    get:
+      operationId: listOrders
+      parameters:
+        - name: filter
+          in: query
+          style: deepObject
+          explode: true
+          schema: { $ref: '#/components/schemas/OrderFilter' }
+        - name: ids
+          in: query
+          style: pipeDelimited
+          explode: false
+          schema: { type: array, items: { type: integer } }
+        - name: sort_by
+          in: query
+          required: true
+          schema: { type: string }
+        - name: range
+          in: query
+          explode: false
+          schema:
+            type: object
+            additionalProperties: { type: integer }
+      responses:
+        '204': { description: Listed }
+components:
+  schemas:
+    OrderFilter:
+      type: object
+      properties:
+        status: { type: string }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains("module QueryStyle = {"));
+    assert!(client.contains(
+        r#"    switch filter { | Some(v) => QueryStyle.deepObject(query, "filter", v->serializeOrderFilter) | None => () }"#
+    ));
+    assert!(client.contains(
+        r#"    switch ids { | Some(v) => query->Array.push(("ids", v->Array.map(item => item->Wire.int)->Array.join("|"))) | None => () }"#
+    ));
+    assert!(client.contains(r#"    query->Array.push(("sort_by", sortBy))"#));
+    assert!(client.contains(
+        r#"    switch range { | Some(v) => QueryStyle.form(query, "range", v->S.reverseConvertToJsonOrThrow(S.dict(S.int)), ~explode=false) | None => () }"#
+    ));
+}
+
+#[test]
+fn test_array_query_params() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Pets, version: "1.0.0" }
+paths:
+  /pets:
+    get:
+      operationId: findPets
+      parameters:
+        - name: tags
+          in: query
+          required: true
+          schema: { type: array, items: { type: string } }
+        - name: ids
+          in: query
+          explode: false
+          schema: { type: array, items: { type: integer } }
+        - name: owners
+          in: query
+          schema: { type: array, items: { $ref: '#/components/schemas/Owner' } }
+      responses:
+        '204': { description: Found }
+components:
+  schemas:
+    Owner:
+      type: object
+      properties:
+        name: { type: string }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    // Exploded `form` (the default) repeats the name per item
+    assert!(client.contains(r#"    tags->Array.forEach(item => query->Array.push(("tags", item)))"#));
+    assert!(client.contains(
+        r#"    switch ids { | Some(v) => query->Array.push(("ids", v->Array.map(item => item->Wire.int)->Array.join(","))) | None => () }"#
+    ));
+    assert!(client.contains(
+        r#"    switch owners { | Some(v) => QueryStyle.form(query, "owners", v->S.reverseConvertToJsonOrThrow(S.array(ownerSchema)), ~explode=true) | None => () }"#
+    ));
+    assert!(client.contains("query: array<(string, string)>): string => {"));
+}
+
+#[test]
+fn test_path_param_styles() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Files, version: "1.0.0" }
+paths:
+  /files/{file_name}/versions{version}/shards{shards}/tags{tags}:
+    get:
+      operationId: getShards
+      parameters:
+        - { name: file_name, in: path, required: true, schema: { type: string } }
+        - { name: version, in: path, required: true, style: label, schema: { type: integer } }
+        - name: shards
+          in: path
+          required: true
+          style: matrix
+          explode: true
+          schema: { type: array, items: { type: integer } }
+        - name: tags
+          in: path
+          required: true
+          style: label
+          schema: { type: array, items: { type: string } }
+      responses:
+        '204': { description: Found }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains(
+        r#"    let path = `/files/${encodeURIComponent(fileName)}/versions.${version->Wire.int}/shards;shards=${shards->Array.map(item => item->Wire.int)->Array.join(";shards=")}/tags.${tags->Array.map(item => encodeURIComponent(item))->Array.join(",")}`"#
+    ));
+}
+
+#[test]
+fn test_cookie_params() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Prefs, version: "1.0.0" }
+paths:
+  /prefs:
+    get:
+      operationId: getPrefs
+      parameters:
+        - { name: session_id, in: cookie, required: true, schema: { type: string } }
+        - { name: ab, in: cookie, schema: { type: array, items: { type: integer } } }
+      responses:
+        '204': { description: Found }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains("let addCookie = (headers: Dict.t<string>, name: string, value: string): unit => {"));
+    assert!(client.contains(
+        "  let getPrefs = async (config: config, ~sessionId: string, ~ab: option<array<int>>=?, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ()): result<unit, apiError> => {"
+    ));
+    assert!(client.contains(r#"    headers->addCookie("session_id", sessionId)"#));
+    assert!(client.contains(
+        r#"    switch ab { | Some(v) => headers->addCookie("ab", v->Array.map(item => item->Wire.int)->Array.join(",")) | None => () }"#
+    ));
+}
+
+#[test]
+fn test_request_types() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Users, version: "1.0.0" }
+paths:
+  /users:
+    post:
+      operationId: createUser
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema: { $ref: '#/components/schemas/User' }
+      responses:
+        '201':
+          description: Created
+          content:
+            application/json:
+              schema: { $ref: '#/components/schemas/User' }
+components:
+  schemas:
+    User:
+      type: object
+      required: [id, name, password]
+      properties:
+        id: { type: string, readOnly: true }
+        createdAt: { type: string, readOnly: true }
+        name: { type: string }
+        password: { type: string, writeOnly: true }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+
+    // Shared: one record, fields as required as the spec says
+    let api = ir::lower(&spec).unwrap();
+    let types = codegen::types::generate(&api, &test_config()).unwrap();
+    assert!(types.contains("  id: string,\n"));
+    assert!(types.contains("  password: string,\n"));
+    assert!(types.contains("let makeUser = (\n  ~id: string,\n  ~name: string,\n  ~password: string,\n  (),\n"));
+    assert!(types.contains("  createdAt: None,\n"));
+
+    // Split: the input record leaves out read-only fields, the response write-only ones
+    let options = ir::LowerOptions { request_types: ir::RequestTypes::Split, ..Default::default() };
+    let api = ir::lower_with_options(&spec, &options).unwrap();
+    let types = codegen::types::generate(&api, &test_config()).unwrap();
+    assert!(types.contains("  createdAt: option<string>,\n  name: string,\n}\n"));
+    assert!(types.contains("type userInput = {\n  name: string,\n"));
+    assert!(types.contains("let makeUserInput = (\n  ~name: string,\n  ~password: string,\n  (),\n"));
+    assert!(!types.contains("makeUser ="));
+}
+
+#[test]
+fn test_header_params() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Docs, version: "1.0.0" }
+paths:
+  /docs/{id}:
+    put:
+      operationId: updateDoc
+      parameters:
+        - { name: id, in: path, required: true, schema: { type: integer } }
+        - { name: If-Match, in: header, required: true, schema: { type: string } }
+        - { name: X-Request-Id, in: header, schema: { type: string, format: uuid } }
+        - { name: X-Features, in: header, schema: { type: array, items: { type: string } } }
+        - { name: Accept, in: header, schema: { type: string } }
+      responses:
+        '204': { description: Updated }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    // `Accept` is the client's to set, so the parameter is ignored
+    assert!(client.contains(
+        "  let updateDoc = async (config: config, ~id: int, ~ifMatch: string, ~xRequestId: option<string>=?, ~xFeatures: option<array<string>>=?, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ()): result<unit, apiError> => {"
+    ));
+    assert!(client.contains(r#"    headers->Dict.set("If-Match", ifMatch)"#));
+    assert!(client.contains(
+        r#"    switch xRequestId { | Some(v) => headers->Dict.set("X-Request-Id", v) | None => () }"#
+    ));
+    assert!(client.contains(
+        r#"    switch xFeatures { | Some(v) => headers->Dict.set("X-Features", v->Array.join(",")) | None => () }"#
+    ));
+}
+
+#[test]
+fn test_pagination_iterators() {
+    let yaml = r#"
+openapi: 3.0.3
+info: { title: Pets, version: "1.0.0" }
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      parameters:
+        - { name: page, in: query, schema: { type: integer } }
+        - { name: per_page, in: query, schema: { type: integer } }
+      responses:
+        '200':
+          description: A page of pets
+          content:
+            application/json:
+              schema: { type: array, items: { $ref: '#/components/schemas/Pet' } }
+  /owners:
+    get:
+      operationId: listOwners
+      parameters:
+        - { name: cursor, in: query, schema: { type: string } }
+      responses:
+        '200':
+          description: A page of owners
+          content:
+            application/json:
+              schema: { $ref: '#/components/schemas/OwnerList' }
+  /owners/{id}:
+    get:
+      operationId: getOwner
+      parameters:
+        - { name: id, in: path, required: true, schema: { type: string } }
+      responses:
+        '200':
+          description: An owner
+          content:
+            application/json:
+              schema: { type: string }
+components:
+  schemas:
+    Pet:
+      type: object
+      required: [name]
+      properties:
+        name: { type: string }
+    OwnerList:
+      type: object
+      required: [data]
+      properties:
+        data: { type: array, items: { type: string } }
+        next_cursor: { type: string }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    assert!(client.contains(
+        "  let listPetsEachPage = async (config: config, ~page: option<int>=?, ~perPage: option<int>=?, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ~maxPages: option<int>=?, ~onPage: array<pet> => unit, ()): result<unit, apiError> => {"
+    ));
+    // A page repeating the previous one ends paging before it is handed on
+    assert!(client.contains(
+        "          if lastBody.contents == Some(res.body->JSON.stringify) {\n            Ok()\n          } else {\n            lastBody := Some(res.body->JSON.stringify)\n            pagesFetched := pagesFetched.contents + 1\n            onPage(received)\n            if received->Array.length == 0 || perPage->Option.mapOr(false, size => received->Array.length < size) || maxPages->Option.mapOr(false, max => pagesFetched.contents >= max) {\n              Ok()\n            } else {\n              await fetchFrom(Some(page->Option.getOr(1) + 1))\n            }\n          }\n"
+    ));
+    assert!(client.contains("    await fetchFrom(Some(page->Option.getOr(1)))\n"));
+    // `All` only collects what `EachPage` hands on
+    assert!(client.contains(
+        "  let listPetsAll = async (config: config, ~page: option<int>=?, ~perPage: option<int>=?, ~timeoutMs: option<int>=?, ~signal: option<abortSignal>=?, ~maxPages: option<int>=?, ()): result<array<pet>, apiError> => {\n    let collected = []\n    (await listPetsEachPage(config, ~page?, ~perPage?, ~timeoutMs?, ~signal?, ~maxPages?, ~onPage=received => collected->Array.pushMany(received), ()))->Result.map(() => collected)\n  }\n"
+    ));
+
+    assert!(client.contains("    let rec fetchFrom = async (cursor): result<unit, apiError> => {"));
+    assert!(client.contains(
+        "          let received = data.data\n          pagesFetched := pagesFetched.contents + 1\n          onPage(received)\n          switch data.nextCursor {\n          | Some(next) if next != \"\" && Some(next) != cursor && maxPages->Option.mapOr(true, max => pagesFetched.contents < max) => await fetchFrom(Some(next))\n          | _ => Ok()\n          }\n"
+    ));
+    assert!(!client.contains("getOwnerAll"));
+
+    let config = codegen::Config { generate_pagination: false, ..test_config() };
+    let client = codegen::client::generate(&api, &config).unwrap();
+    assert!(!client.contains("listPetsEachPage"));
+    assert!(!client.contains("listPetsAll"));
+}
+
+#[test]
+fn test_field_case_preserve_wire_names() {
+    let yaml = r#"
+openapi: "3.0.0"
+info: { title: Test, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Trace:
+      type: object
+      required: [x-request-id]
+      properties:
+        x-request-id: { type: string }
+        account_id: { type: string }
+"#;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let options = ir::LowerOptions { field_case: ir::FieldCase::Preserve, ..Default::default() };
+    let api = ir::lower_with_options(&spec, &options).unwrap();
+    let types = codegen::types::generate(&api, &test_config()).unwrap();
+    let schema = codegen::schema::generate(&api, &test_config()).unwrap();
+
+    assert!(types.contains(r#"@as("x-request-id") x_request_id: string,"#));
+    assert!(types.contains("  account_id: option<string>,"));
+    assert!(schema.contains(r#"x_request_id: s.field("x-request-id", "#));
+}
+
+#[test]
+fn test_deprecations() {
+    let spec = parser::parse_spec(Path::new("tests/fixtures/deprecated.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let types = codegen::types::generate(&api, &test_config()).unwrap();
+    let schema = codegen::schema::generate(&api, &test_config()).unwrap();
+    let client = codegen::client::generate(&api, &test_config()).unwrap();
+
+    // Generated code refers to what it deprecates, so only application code warns
+    for file in [&types, &schema, &client] {
+        assert!(file.contains("@@warning(\"-3\")\n"));
+    }
+    assert!(types.contains("@deprecated(\"`Gadget` is deprecated in the API\")\ntype gadget = {\n"));
+    assert!(types.contains(
+        "  @deprecated(\"`Widget.legacyCode` is deprecated in the API\") legacyCode: option<string>,\n"
+    ));
+    assert!(client.contains(
+        "  @deprecated(\"`GET /gadgets` is deprecated in the API\")\n  let listGadgets = async ("
+    ));
+    assert!(client.contains("  /** @deprecated ~sort: Use `order` instead */\n  let listWidgets = async ("));
+    assert!(client.contains("      warnDeprecatedParam(config, \"listWidgets\", \"sort\")\n"));
+
+    // Specs without deprecations keep deprecation warnings on
+    let spec = parser::parse_spec(Path::new("tests/fixtures/petstore.yaml")).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    assert!(!codegen::types::generate(&api, &test_config()).unwrap().contains("@@warning"));
+}
+
+#[test]
+fn test_discriminator_round_trip() {
+    let yaml = r##"
+openapi: 3.0.3
+info: { title: Pets, version: "1.0.0" }
+paths: {}
+components:
+  schemas:
+    Cat: { type: object, properties: { name: { type: string } } }
+    Dog: { type: object, properties: { name: { type: string } } }
+    Pet:
+      oneOf:
+        - $ref: "#/components/schemas/Cat"
+        - $ref: "#/components/schemas/Dog"
+      discriminator:
+        propertyName: kind
+        mapping: { cat: Cat, kitty: Cat, dog: Dog }
+"##;
+    let spec = parser::parse_spec_content(yaml, &parser::Limits::default()).unwrap();
+    let api = ir::lower(&spec).unwrap();
+    let types = codegen::types::generate(&api, &test_config()).unwrap();
+    let schema = codegen::schema::generate(&api, &test_config()).unwrap();
+
+    // Tags pointing at the same schema select one case
+    assert!(types.contains("type pet =\n  | Cat(cat)\n  | Dog(dog)\n"));
+    assert!(schema.contains(r#"    | Some("cat" | "kitty") => (Cat(S.parseJsonOrThrow(json, catSchema)): pet)"#));
+    // Neither member declares `kind`, so the serializer writes it
+    assert!(schema.contains(
+        "    | Cat(x) =>\n      let o = S.reverseConvertToJsonOrThrow(x, catSchema)->JSON.Decode.object->Option.getOr(Dict.make())\n      o->Dict.set(\"kind\", JSON.Encode.string(\"cat\"))\n      JSON.Encode.object(o)\n"
+    ));
+}